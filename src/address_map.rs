@@ -0,0 +1,94 @@
+//! Mapping the offsets of a file to the addresses that its segments are loaded at, for firmware
+//! images.
+
+use serde_derive::{Deserialize, Serialize};
+
+/// A range of bytes in the file, that is loaded at an address of its own.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub offset: u64,
+    pub length: u64,
+    pub address: u64
+}
+
+impl Segment {
+    fn contains_offset(&self, offset: u64) -> bool {
+        offset >= self.offset && offset - self.offset < self.length
+    }
+
+    fn contains_address(&self, address: u64) -> bool {
+        address >= self.address && address - self.address < self.length
+    }
+}
+
+/// Maps the offsets in a file to the addresses that its bytes are loaded at, like the flash and
+/// RAM addresses of the segments of a firmware image. The bytes that are not in a segment are at
+/// the base address plus their offset.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AddressMap {
+    pub base_address: u64,
+    segments: Vec<Segment>
+}
+
+impl AddressMap {
+    pub fn new(base_address: u64, segments: Vec<Segment>) -> AddressMap {
+        let mut map = AddressMap { base_address, segments: Vec::new() };
+        map.set_segments(segments);
+        map
+    }
+
+    /// The segments, in the order of their offsets.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn set_segments(&mut self, mut segments: Vec<Segment>) {
+        segments.retain(|segment| segment.length > 0);
+        segments.sort_by_key(|segment| segment.offset);
+        self.segments = segments;
+    }
+
+    pub fn add_segment(&mut self, segment: Segment) {
+        let mut segments = self.segments.clone();
+        segments.push(segment);
+        self.set_segments(segments);
+    }
+
+    /// The address of the byte at the given offset. Where segments overlap, the first one wins.
+    pub fn address_of(&self, offset: u64) -> u64 {
+        match self.segments.iter().find(|segment| segment.contains_offset(offset)) {
+            Some(segment) => segment.address.wrapping_add(offset - segment.offset),
+            None => self.base_address.wrapping_add(offset)
+        }
+    }
+
+    /// The offset of the byte that is loaded at the given address, if any byte is.
+    pub fn offset_of(&self, address: u64) -> Option<u64> {
+        if let Some(segment) = self.segments.iter().find(|segment| segment.contains_address(address)) {
+            return Some(segment.offset + (address - segment.address));
+        }
+        address.checked_sub(self.base_address)
+            .filter(|offset| !self.segments.iter().any(|segment| segment.contains_offset(*offset)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapping_offsets_and_addresses() {
+        let map = AddressMap::new(0x0800_0000, vec![
+            Segment { offset: 0x200, length: 0x100, address: 0x2000_0000 },
+            Segment { offset: 0x100, length: 0, address: 0x3000_0000 }
+        ]);
+        assert_eq!(map.segments().len(), 1);
+        assert_eq!(map.address_of(0x10), 0x0800_0010);
+        assert_eq!(map.address_of(0x210), 0x2000_0010);
+        assert_eq!(map.address_of(0x300), 0x0800_0300);
+        assert_eq!(map.offset_of(0x2000_00FF), Some(0x2FF));
+        assert_eq!(map.offset_of(0x0800_0300), Some(0x300));
+        assert_eq!(map.offset_of(0x0800_0210), None);
+        assert_eq!(map.offset_of(0x10), None);
+    }
+}
@@ -0,0 +1,121 @@
+//! The dialog that adds, imports and clears the segments of the address map.
+
+use std::fs;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, ScrollView, TextView};
+use xv::address_map::Segment;
+use xv::error::Result;
+
+use crate::hex_view::HexView;
+use crate::map_files::parse_segments;
+use crate::utilities::{expand_path, get_content, parse_number};
+use crate::xv_state::XvState;
+use crate::xv_tui::ShowError;
+
+pub fn open_address_map_dialog(s: &mut Cursive) {
+    let current_dir = s.with_user_data(|state: &mut XvState| {
+        state.current_directory().to_path_buf()
+    }).unwrap();
+    let address_map = s.call_on_id("hex_view", |v: &mut HexView| v.get_address_map().clone()).unwrap();
+
+    let mut segment_lines: Vec<String> = address_map.segments().iter()
+        .map(|segment| format!("0x{:08X}  {:>10}  0x{:08X}", segment.offset, segment.length, segment.address))
+        .collect();
+    segment_lines.insert(0, format!("{:<10}  {:>10}  {}", "Offset", "Length", "Address"));
+    segment_lines.push(format!("The other bytes are at 0x{:X} plus their offset.", address_map.base_address));
+
+    let editors = LinearLayout::vertical()
+        .child(EditView::new().with_id("segment_offset").min_width(20))
+        .child(EditView::new().with_id("segment_length").min_width(20))
+        .child(EditView::new().with_id("segment_address").min_width(20));
+
+    let layout = LinearLayout::vertical()
+        .child(ScrollView::new(TextView::new(segment_lines.join("\n"))).max_height(12))
+        .child(TextView::new("\nAdd a segment:"))
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Offset:  \nLength:  \nAddress:  "))
+            .child(editors))
+        .child(TextView::new("\nOr import the segments from:"))
+        .child(EditView::new()
+            .content(format!("{}", current_dir.display()))
+            .with_id("address_map_path")
+            .min_width(40))
+        .child(TextView::new(
+            "\nA GNU ld map file, or lines with a file offset,\n\
+             a length and an address."));
+
+    let dialog = Dialog::around(layout)
+        .title("Address Map")
+        .dismiss_button("Close")
+        .button("Clear", do_clear_segments)
+        .button("Add", do_add_segment)
+        .button("Import", do_import_segments);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+fn do_add_segment(s: &mut Cursive) {
+    let offset_str = s.call_on_id("segment_offset", get_content).unwrap();
+    let length_str = s.call_on_id("segment_length", get_content).unwrap();
+    let address_str = s.call_on_id("segment_address", get_content).unwrap();
+
+    s.pop_layer();
+
+    match parse_segment(&offset_str, &length_str, &address_str) {
+        Ok(segment) => {
+            s.call_on_id("hex_view", |v: &mut HexView| {
+                let mut address_map = v.get_address_map().clone();
+                address_map.add_segment(segment);
+                v.set_address_map(address_map);
+            });
+            open_address_map_dialog(s);
+        },
+        Err(error) => s.show_error(error)
+    }
+}
+
+fn parse_segment(offset_str: &str, length_str: &str, address_str: &str) -> Result<Segment> {
+    Ok(Segment {
+        offset: parse_number(offset_str)?,
+        length: parse_number(length_str)?,
+        address: parse_number(address_str)?
+    })
+}
+
+fn do_import_segments(s: &mut Cursive) {
+    let path_str = s.call_on_id("address_map_path", get_content).unwrap();
+
+    s.pop_layer();
+
+    let segments = fs::read_to_string(expand_path(&path_str))
+        .map_err(|error| error.into())
+        .and_then(|text| parse_segments(&text));
+    match segments {
+        Ok(segments) => {
+            s.call_on_id("hex_view", |v: &mut HexView| {
+                let mut address_map = v.get_address_map().clone();
+                address_map.set_segments(segments);
+                v.set_address_map(address_map);
+            });
+            open_address_map_dialog(s);
+        },
+        Err(error) => s.show_error(error)
+    }
+}
+
+fn do_clear_segments(s: &mut Cursive) {
+    s.pop_layer();
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        let mut address_map = v.get_address_map().clone();
+        address_map.set_segments(Vec::new());
+        v.set_address_map(address_map);
+    });
+}
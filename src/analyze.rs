@@ -0,0 +1,197 @@
+//! The `analyze` command, that runs the analyses without the text user interface, so that scripts
+//! can use them. The results are printed as text, or as JSON.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::path::PathBuf;
+
+use serde_derive::Serialize;
+use xv::byte_reader::ByteSource;
+use xv::byte_stats::ByteStats;
+use xv::checksum::{compute_checksums, to_hex, CHECKSUM_KINDS};
+use xv::error::{Result, XvError};
+use xv::magic::{mime_type, MAGIC_LENGTH};
+use xv::progress::Progress;
+use xv::strings::{find_strings, FoundString, DEFAULT_MIN_LENGTH};
+
+use crate::utilities::parse_number;
+
+const DEFAULT_BLOCK_SIZE: u64 = 4096;
+
+#[derive(Default)]
+struct Options {
+    file: Option<PathBuf>,
+    strings: bool,
+    entropy: bool,
+    hashes: bool,
+    json: bool,
+    min_length: Option<usize>,
+    block_size: Option<u64>
+}
+
+#[derive(Serialize)]
+struct Analysis {
+    path: String,
+    size: u64,
+    mime_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hashes: Option<BTreeMap<&'static str, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entropy: Option<Entropy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strings: Option<Vec<FoundString>>
+}
+
+/// The entropy of the whole file, and of every block of it, in bits per byte.
+#[derive(Serialize)]
+struct Entropy {
+    file: f64,
+    block_size: u64,
+    blocks: Vec<f64>
+}
+
+/// Run the `analyze` command with the arguments that follow it, and return the exit code.
+pub fn run_analyze<I: Iterator<Item = OsString>>(args: I) -> i32 {
+    let result = parse_options(args).and_then(|options| {
+        let analysis = analyze(&options)?;
+        if options.json {
+            println!("{}", serde_json::to_string_pretty(&analysis).unwrap());
+        } else {
+            print_analysis(&analysis);
+        }
+        Ok(())
+    });
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            1
+        }
+    }
+}
+
+fn parse_options<I: Iterator<Item = OsString>>(mut args: I) -> Result<Options> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--strings") => options.strings = true,
+            Some("--entropy") => options.entropy = true,
+            Some("--hashes") => options.hashes = true,
+            Some("--json") => options.json = true,
+            Some("--min-length") => options.min_length = Some(option_number(&mut args, "--min-length")? as usize),
+            Some("--block-size") => options.block_size = Some(option_number(&mut args, "--block-size")?),
+            _ if options.file.is_none() => options.file = Some(PathBuf::from(arg)),
+            _ => return Err(XvError::Input(format!("Unexpected argument: {}", arg.to_string_lossy())))
+        }
+    }
+    if options.file.is_none() {
+        return Err(XvError::Input(String::from("The 'file' argument is required.")));
+    }
+    if !(options.strings || options.entropy || options.hashes) {
+        options.strings = true;
+        options.entropy = true;
+        options.hashes = true;
+    }
+    Ok(options)
+}
+
+fn option_number<I: Iterator<Item = OsString>>(args: &mut I, option: &str) -> Result<u64> {
+    let value = args.next().and_then(|value| value.into_string().ok())
+        .ok_or_else(|| XvError::Input(format!("The {} option needs a number.", option)))?;
+    match parse_number(&value)? {
+        0 => Err(XvError::Input(format!("The {} option cannot be zero.", option))),
+        number => Ok(number)
+    }
+}
+
+fn analyze(options: &Options) -> Result<Analysis> {
+    let path = options.file.as_ref().unwrap();
+    let mut file = File::open(path)?;
+    let size = file.length()?;
+    let mut start = vec![0; MAGIC_LENGTH.min(size as usize)];
+    file.read_at(0, &mut start)?;
+
+    let hashes = if options.hashes {
+        let checksums = compute_checksums(&CHECKSUM_KINDS, &mut file, 0, size, &Progress::new(size))?;
+        Some(CHECKSUM_KINDS.iter().zip(checksums).map(|(kind, checksum)| (kind.name(), to_hex(&checksum))).collect())
+    } else {
+        None
+    };
+    let entropy = if options.entropy {
+        Some(compute_entropy(&mut file, size, options.block_size.unwrap_or(DEFAULT_BLOCK_SIZE))?)
+    } else {
+        None
+    };
+    let strings = if options.strings {
+        let min_length = options.min_length.unwrap_or(DEFAULT_MIN_LENGTH);
+        Some(find_strings(&mut file, 0, size, min_length, &Progress::new(size))?)
+    } else {
+        None
+    };
+
+    Ok(Analysis {
+        path: path.to_string_lossy().into_owned(),
+        size,
+        mime_type: mime_type(&start),
+        hashes,
+        entropy,
+        strings
+    })
+}
+
+fn compute_entropy(source: &mut ByteSource, size: u64, block_size: u64) -> Result<Entropy> {
+    let mut file_stats = ByteStats::new();
+    let mut blocks = Vec::new();
+    let mut block = vec![0; block_size as usize];
+    let mut offset = 0;
+    while offset < size {
+        let len = source.read_at(offset, &mut block)?;
+        if len == 0 {
+            break;
+        }
+        let block_stats = ByteStats::from_bytes(&block[0..len]);
+        blocks.push(block_stats.entropy());
+        file_stats.add(&block[0..len]);
+        offset += len as u64;
+    }
+    Ok(Entropy { file: file_stats.entropy(), block_size, blocks })
+}
+
+fn print_analysis(analysis: &Analysis) {
+    println!("Path:       {}", analysis.path);
+    println!("Size:       {} bytes", analysis.size);
+    println!("Type:       {}", analysis.mime_type);
+    if let Some(ref hashes) = analysis.hashes {
+        println!();
+        for (name, hash) in hashes {
+            println!("{:<11} {}", format!("{}:", name), hash);
+        }
+    }
+    if let Some(ref entropy) = analysis.entropy {
+        println!();
+        println!("Entropy:    {:.3} bits per byte", entropy.file);
+        for (i, block_entropy) in entropy.blocks.iter().enumerate() {
+            println!("0x{:08X}  {:.3}", i as u64 * entropy.block_size, block_entropy);
+        }
+    }
+    if let Some(ref strings) = analysis.strings {
+        println!();
+        for string in strings {
+            println!("0x{:08X}  {}", string.offset, string.text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computing_block_entropy() {
+        let mut bytes: Vec<u8> = (0..=255).chain(vec![0; 256]).chain(0..=127).collect();
+        let entropy = compute_entropy(&mut bytes, 640, 256).unwrap();
+        assert_eq!(entropy.blocks, vec![8.0, 0.0, 7.0]);
+        assert!(entropy.file > 0.0 && entropy.file < 8.0);
+    }
+}
@@ -0,0 +1,173 @@
+//! Labelled ranges of bytes, imported from the JSON or CSV files that other tools, like
+//! disassemblers and fuzzers, write.
+
+use cursive::theme::Color;
+use serde_json::Value;
+use xv::error::{Result, XvError};
+
+use crate::utilities::parse_number;
+
+/// A range of bytes, with a label, that is shown in a color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub offset: u64,
+    pub length: u64,
+    pub label: String,
+    /// The background color, or `None` to use the highlight color of the theme.
+    pub color: Option<Color>
+}
+
+impl Annotation {
+    pub fn contains(&self, offset: u64) -> bool {
+        offset >= self.offset && offset - self.offset < self.length
+    }
+
+    fn end(&self) -> u64 {
+        self.offset.saturating_add(self.length)
+    }
+}
+
+/// Parse annotations from a JSON array of objects with `offset`, `length`, `label` and `color`
+/// fields, or from CSV with those columns in that order. Only the offset is required, and the
+/// length is 1 by default. Numbers can be written in hex with a `0x` prefix, and colors are names
+/// like `red` or `light blue`, or RGB like `#FF8000`. The annotations are sorted by offset.
+pub fn parse_annotations(text: &str) -> Result<Vec<Annotation>> {
+    let mut annotations = if text.trim_start().starts_with('[') {
+        parse_json(text)?
+    } else {
+        parse_csv(text)?
+    };
+    annotations.sort_by_key(|annotation| annotation.offset);
+    Ok(annotations)
+}
+
+/// The annotations that overlap the range from `start` to `end`, in the order of their offsets.
+/// The annotations that start too far before the range for even the longest of them, which is
+/// `longest` bytes long, to reach it are skipped with a binary search.
+pub fn overlapping(annotations: &[Annotation], longest: u64, start: u64, end: u64) -> Vec<&Annotation> {
+    let first = annotations
+        .partition_point(|annotation| annotation.offset.saturating_add(longest) <= start);
+    annotations[first..].iter()
+        .take_while(|annotation| annotation.offset < end)
+        .filter(|annotation| annotation.end() > start)
+        .collect()
+}
+
+fn parse_json(text: &str) -> Result<Vec<Annotation>> {
+    let values: Vec<Value> = serde_json::from_str(text)
+        .map_err(|error| XvError::Parse(format!("Not a JSON array of annotations: {}.", error)))?;
+    values.iter().enumerate().map(|(i, value)| {
+        let field = |name: &str| match value.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::Number(number)) => Ok(Some(number.to_string())),
+            Some(Value::String(text)) => Ok(Some(text.clone())),
+            Some(_) => Err(XvError::Parse(format!("Annotation {}: The {} is not a number or a string.", i + 1, name)))
+        };
+        new_annotation(field("offset")?, field("length")?, field("label")?, field("color")?)
+            .map_err(|error| XvError::Parse(format!("Annotation {}: {}", i + 1, error)))
+    }).collect()
+}
+
+fn parse_csv(text: &str) -> Result<Vec<Annotation>> {
+    let mut annotations = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_csv_line(line);
+        let field = |index: usize| fields.get(index).filter(|field| !field.is_empty()).cloned();
+        // A first line that does not start with a number is a header.
+        if annotations.is_empty() && field(0).is_some_and(|offset| parse_number(&offset).is_err()) {
+            continue;
+        }
+        let annotation = new_annotation(field(0), field(1), field(2), field(3))
+            .map_err(|error| XvError::Parse(format!("Line {}: {}", i + 1, error)))?;
+        annotations.push(annotation);
+    }
+    Ok(annotations)
+}
+
+/// Split a line of CSV at the commas that are not in double quotes.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c)
+        }
+    }
+    fields.iter().map(|field| String::from(field.trim())).collect()
+}
+
+fn new_annotation(offset: Option<String>, length: Option<String>, label: Option<String>, color: Option<String>)
+    -> Result<Annotation> {
+    let offset = parse_number(&offset.ok_or_else(|| XvError::Parse(String::from("The offset is missing.")))?)?;
+    let length = match length {
+        Some(length) => parse_number(&length)?,
+        None => 1
+    };
+    let color = match color {
+        Some(color) => Some(Color::parse(&color.to_lowercase())
+            .ok_or_else(|| XvError::Parse(format!("Not a color: {}.", color)))?),
+        None => None
+    };
+    Ok(Annotation { offset, length, label: label.unwrap_or_default(), color })
+}
+
+#[cfg(test)]
+mod tests {
+    use cursive::theme::BaseColor;
+
+    use super::*;
+
+    fn annotation(offset: u64, length: u64, label: &str, color: Option<Color>) -> Annotation {
+        Annotation { offset, length, label: String::from(label), color }
+    }
+
+    #[test]
+    fn parsing_json_annotations() {
+        let json = r#"[{"offset": "0x40", "length": 16, "label": "header", "color": "Light Blue"},
+                       {"offset": 4, "label": "magic"}]"#;
+        assert_eq!(parse_annotations(json).unwrap(), vec![
+            annotation(4, 1, "magic", None),
+            annotation(0x40, 16, "header", Some(Color::Light(BaseColor::Blue)))
+        ]);
+        assert!(parse_annotations(r#"[{"length": 4}]"#).is_err());
+        assert!(parse_annotations(r#"[{"offset": 1, "color": "plaid"}]"#).is_err());
+    }
+
+    #[test]
+    fn parsing_csv_annotations() {
+        let csv = "offset,length,label,color\n\
+                   # A comment\n\
+                   0x10, 8, \"crash, \"\"here\"\"\", red\n\
+                   \n\
+                   2,4\n";
+        assert_eq!(parse_annotations(csv).unwrap(), vec![
+            annotation(2, 4, "", None),
+            annotation(0x10, 8, "crash, \"here\"", Some(Color::Dark(BaseColor::Red)))
+        ]);
+        assert_eq!(format!("{}", parse_annotations("1,2\nx,3").unwrap_err()), "Line 2: Not a number: invalid digit found in string.");
+    }
+
+    #[test]
+    fn finding_overlapping_annotations() {
+        let annotations = vec![annotation(0, 4, "a", None), annotation(2, 10, "b", None), annotation(20, 1, "c", None)];
+        let labels = |start, end| -> Vec<String> {
+            overlapping(&annotations, 10, start, end).iter().map(|a| a.label.clone()).collect()
+        };
+        assert_eq!(labels(4, 8), vec!["b"]);
+        assert_eq!(labels(0, 21), vec!["a", "b", "c"]);
+        assert!(labels(12, 20).is_empty());
+        assert_eq!(labels(20, 21), vec!["c"]);
+        assert!(annotations[1].contains(11) && !annotations[1].contains(12));
+    }
+}
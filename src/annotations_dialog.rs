@@ -0,0 +1,86 @@
+//! The dialog that lists the annotations of the file, and imports or clears them.
+
+use std::fs;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, ScrollView, SelectView, TextView};
+
+use crate::annotations::parse_annotations;
+use crate::hex_view::HexView;
+use crate::utilities::{expand_path, get_content};
+use crate::xv_state::XvState;
+use crate::xv_tui::ShowError;
+
+pub fn open_annotations_dialog(s: &mut Cursive) {
+    let current_dir = s.with_user_data(|state: &mut XvState| {
+        state.current_directory().to_path_buf()
+    }).unwrap();
+    let annotations = s.call_on_id("hex_view", |v: &mut HexView| v.get_annotations().to_vec()).unwrap();
+
+    let mut annotation_list = SelectView::new().on_submit(|s, offset: &u64| {
+        let offset = *offset;
+        s.pop_layer();
+        s.call_on_id("hex_view", |v: &mut HexView| v.go_to_offset(offset));
+    });
+    for annotation in &annotations {
+        annotation_list.add_item(format!("0x{:08X}  {:>8}  {}", annotation.offset, annotation.length, annotation.label),
+                                 annotation.offset);
+    }
+
+    let summary = match annotations.len() {
+        0 => String::from("There are no annotations."),
+        1 => String::from("1 annotation. Select it to go to it."),
+        n => format!("{} annotations. Select one to go to it.", n)
+    };
+
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Import from:  "))
+            .child(EditView::new()
+                .content(format!("{}", current_dir.display()))
+                .with_id("annotations_path")
+                .min_width(40)))
+        .child(TextView::new(
+            "\nA JSON array of objects with offset, length, label\n\
+             and color fields, or CSV with those columns.\n"))
+        .child(TextView::new(summary))
+        .child(ScrollView::new(annotation_list).max_height(12));
+
+    let dialog = Dialog::around(layout)
+        .title("Annotations")
+        .dismiss_button("Close")
+        .button("Clear", do_clear_annotations)
+        .button("Import", do_import_annotations);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+fn do_import_annotations(s: &mut Cursive) {
+    let path_str = s.call_on_id("annotations_path", get_content).unwrap();
+
+    s.pop_layer();
+
+    let annotations = fs::read_to_string(expand_path(&path_str))
+        .map_err(|error| error.into())
+        .and_then(|text| parse_annotations(&text));
+    match annotations {
+        Ok(annotations) => {
+            let count = annotations.len();
+            s.call_on_id("hex_view", |v: &mut HexView| v.set_annotations(annotations));
+            s.add_layer(Dialog::info(format!("Imported {} annotations.", count)));
+        },
+        Err(error) => s.show_error(error)
+    }
+}
+
+fn do_clear_annotations(s: &mut Cursive) {
+    s.pop_layer();
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_annotations(Vec::new()));
+}
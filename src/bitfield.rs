@@ -0,0 +1,115 @@
+//! Splitting integers into named ranges of bits, like the flags and fields of a register.
+
+use xv::error::{Result, XvError};
+
+use crate::utilities::parse_number;
+
+#[derive(Debug, PartialEq)]
+pub struct BitField {
+    pub name: String,
+    pub low: u32,
+    pub high: u32
+}
+
+impl BitField {
+    /// Extract the bits of this field from the given value, shifted down to start at bit 0.
+    pub fn extract(&self, value: u64) -> u64 {
+        let width = self.high - self.low + 1;
+        let mask = if width >= 64 { u64::MAX } else { (1 << width) - 1 };
+        (value >> self.low) & mask
+    }
+
+    pub fn width(&self) -> u32 {
+        self.high - self.low + 1
+    }
+}
+
+/// Parse a comma separated list of bit fields. Each field is a bit number, or an inclusive range
+/// of bit numbers, optionally preceded by a name and a colon. For example: `flags:0..2, mode:3..7`.
+/// Unnamed fields are named after their bit range.
+pub fn parse_layout(spec: &str, bit_count: u32) -> Result<Vec<BitField>> {
+    let mut fields = Vec::new();
+    for part in spec.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        let (name, range) = match part.find(':') {
+            Some(i) => (part[0..i].trim(), part[i + 1..].trim()),
+            None => ("", part)
+        };
+        let (low, high) = match range.find("..") {
+            Some(i) => (parse_bit(&range[0..i])?, parse_bit(&range[i + 2..])?),
+            None => {
+                let bit = parse_bit(range)?;
+                (bit, bit)
+            }
+        };
+        if low > high {
+            return Err(XvError::Parse(format!("The bit range '{}' is backwards.", range)));
+        }
+        if high >= bit_count {
+            return Err(XvError::Parse(format!("Bit {} is outside the {} bit integer.", high, bit_count)));
+        }
+        let name = if name.is_empty() { String::from(range) } else { String::from(name) };
+        fields.push(BitField { name, low, high });
+    }
+    Ok(fields)
+}
+
+fn parse_bit(bit: &str) -> Result<u32> {
+    match parse_number(bit.trim()) {
+        Ok(n) if n < 64 => Ok(n as u32),
+        _ => Err(XvError::Parse(format!("'{}' is not a bit number.", bit.trim())))
+    }
+}
+
+/// Read an unsigned integer from up to 8 bytes.
+pub fn read_integer(bytes: &[u8], big_endian: bool) -> u64 {
+    let mut value = 0;
+    if big_endian {
+        for b in bytes {
+            value = (value << 8) | u64::from(*b);
+        }
+    } else {
+        for b in bytes.iter().rev() {
+            value = (value << 8) | u64::from(*b);
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_layouts() {
+        let fields = parse_layout("flags:0..2, mode: 3..7 ,15", 16).unwrap();
+        assert_eq!(fields, vec![
+            BitField { name: String::from("flags"), low: 0, high: 2 },
+            BitField { name: String::from("mode"), low: 3, high: 7 },
+            BitField { name: String::from("15"), low: 15, high: 15 }]);
+        assert!(parse_layout("a:7..3", 16).is_err());
+        assert!(parse_layout("a:8..16", 16).is_err());
+        assert!(parse_layout("a:x", 16).is_err());
+        assert_eq!(parse_layout("", 16).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn extracting_fields() {
+        let value = read_integer(&[0xB5, 0x01], false);
+        assert_eq!(value, 0x01B5);
+        let fields = parse_layout("0..2, 3..7, 8, 0..63", 64).unwrap();
+        assert_eq!(fields[0].extract(value), 0b101);
+        assert_eq!(fields[1].extract(value), 0b10110);
+        assert_eq!(fields[2].extract(value), 1);
+        assert_eq!(fields[3].extract(value), value);
+    }
+
+    #[test]
+    fn reading_big_endian_integers() {
+        assert_eq!(read_integer(&[0x12, 0x34, 0x56, 0x78], true), 0x1234_5678);
+        assert_eq!(read_integer(&[0x12, 0x34, 0x56, 0x78], false), 0x7856_3412);
+    }
+}
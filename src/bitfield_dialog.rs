@@ -0,0 +1,163 @@
+//! The dialog that shows the integer at the current position as bit fields, with layouts that can
+//! be saved and loaded by name.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Checkbox, Dialog, DummyView, EditView, LinearLayout, OnEventView, SelectView, TextView};
+
+use crate::bitfield::{parse_layout, read_integer};
+use crate::hex_view::HexView;
+use crate::utilities::{get_content, parse_number, parse_number_or_zero, parse_offset_or_zero};
+use crate::xv_state::{BitfieldLayout, XvState};
+
+pub fn open_bitfield_dialog(s: &mut Cursive) {
+    let offset = s.call_on_id("hex_view", |v: &mut HexView| v.get_offset()).unwrap();
+
+    let editors = LinearLayout::vertical()
+        .child(EditView::new()
+            .content(format!("0x{:X}", offset))
+            .on_edit(|s, _, _| update_bitfields(s))
+            .with_id("bitfield_offset")
+            .min_width(18))
+        .child(EditView::new()
+            .content("2")
+            .on_edit(|s, _, _| update_bitfields(s))
+            .with_id("bitfield_size")
+            .min_width(18))
+        .child(Checkbox::new()
+            .on_change(|s, _| update_bitfields(s))
+            .with_id("bitfield_big_endian"))
+        .child(EditView::new()
+            .content("0..7, 8..15")
+            .on_edit(|s, _, _| update_bitfields(s))
+            .with_id("bitfield_fields")
+            .min_width(40));
+
+    let form = LinearLayout::horizontal()
+        .child(TextView::new("Offset:  \nSize in bytes:  \nBig endian:  \nFields:  "))
+        .child(editors);
+
+    let layout = LinearLayout::vertical()
+        .child(form)
+        .child(DummyView)
+        .child(TextView::new("").with_id("bitfield_result"));
+
+    let dialog = Dialog::around(layout)
+        .title("Bitfields")
+        .button("Load layout", open_load_layout_dialog)
+        .button("Save layout", open_save_layout_dialog)
+        .dismiss_button("Close");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view);
+    update_bitfields(s);
+}
+
+fn update_bitfields(s: &mut Cursive) {
+    let offset_str = s.call_on_id("bitfield_offset", get_content).unwrap();
+    let size_str = s.call_on_id("bitfield_size", get_content).unwrap();
+    let fields_str = s.call_on_id("bitfield_fields", get_content).unwrap();
+    let big_endian = s.call_on_id("bitfield_big_endian", |c: &mut Checkbox| c.is_checked()).unwrap();
+
+    let length = s.call_on_id("hex_view", |v: &mut HexView| v.get_length()).unwrap();
+    let offset = parse_offset_or_zero(&offset_str, length);
+    let result = match parse_number(&size_str) {
+        Ok(size) if size > 0 && size <= 8 => {
+            let bytes = s.call_on_id("hex_view", |v: &mut HexView| {
+                v.read_bytes(offset, size as usize)
+            }).unwrap();
+            match (bytes, parse_layout(&fields_str, size as u32 * 8)) {
+                (Ok(ref bytes), _) if bytes.len() < size as usize =>
+                    String::from("The integer extends beyond the end of the file."),
+                (Ok(bytes), Ok(fields)) => {
+                    let value = read_integer(&bytes, big_endian);
+                    let mut text = format!("Integer value: {} (0x{:X})\n", value, value);
+                    for field in fields {
+                        let v = field.extract(value);
+                        text.push_str(&format!("\n{}: {} (0x{:X}, 0b{:0width$b})",
+                                               field.name, v, v, v, width = field.width() as usize));
+                    }
+                    text
+                },
+                (Err(error), _) | (_, Err(error)) => format!("{}", error)
+            }
+        },
+        _ => String::from("The size must be 1 to 8 bytes.")
+    };
+
+    s.call_on_id("bitfield_result", |t: &mut TextView| t.set_content(result));
+}
+
+fn open_save_layout_dialog(s: &mut Cursive) {
+    let dialog = Dialog::around(LinearLayout::horizontal()
+        .child(TextView::new("Layout name:  "))
+        .child(EditView::new().with_id("bitfield_layout_name").min_width(24)))
+        .title("Save layout")
+        .dismiss_button("Cancel")
+        .button("Save", do_save_layout);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_save_layout);
+
+    s.add_layer(event_view);
+}
+
+fn do_save_layout(s: &mut Cursive) {
+    let name = s.call_on_id("bitfield_layout_name", get_content).unwrap();
+    s.pop_layer();
+    if name.is_empty() {
+        return;
+    }
+
+    let size = parse_number_or_zero(&s.call_on_id("bitfield_size", get_content).unwrap());
+    let fields = s.call_on_id("bitfield_fields", get_content).unwrap();
+    let big_endian = s.call_on_id("bitfield_big_endian", |c: &mut Checkbox| c.is_checked()).unwrap();
+
+    s.with_user_data(|state: &mut XvState| {
+        state.save_bitfield_layout(BitfieldLayout {
+            name: name.to_string(),
+            size,
+            big_endian,
+            fields: fields.to_string()
+        });
+    });
+}
+
+fn open_load_layout_dialog(s: &mut Cursive) {
+    let mut layout_selector: SelectView<BitfieldLayout> = SelectView::new()
+        .on_submit(do_load_layout)
+        .autojump();
+
+    s.with_user_data(|state: &mut XvState| {
+        for layout in state.bitfield_layouts() {
+            layout_selector.add_item(layout.name.clone(), layout.clone());
+        }
+    });
+
+    let dialog = Dialog::around(layout_selector.min_width(24))
+        .title("Load layout")
+        .dismiss_button("Cancel");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view);
+}
+
+fn do_load_layout(s: &mut Cursive, layout: &BitfieldLayout) {
+    s.pop_layer();
+    s.call_on_id("bitfield_size", |e: &mut EditView| e.set_content(format!("{}", layout.size)));
+    s.call_on_id("bitfield_fields", |e: &mut EditView| e.set_content(layout.fields.clone()));
+    s.call_on_id("bitfield_big_endian", |c: &mut Checkbox| c.set_checked(layout.big_endian));
+    update_bitfields(s);
+}
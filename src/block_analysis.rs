@@ -0,0 +1,149 @@
+//! Finding the blocks of a file that repeat an earlier block, and the long runs of a single byte,
+//! like the padding and copies in disk and firmware images.
+
+use std::collections::HashMap;
+
+use xv::byte_reader::ByteSource;
+use xv::error::{Result, XvError};
+use xv::memory::{Reservation, Subsystem};
+use xv::progress::Progress;
+
+use crate::utilities::fnv_hash;
+
+/// About how much memory a block that has been seen, or an item of the report, takes up.
+const ITEM_SIZE: u64 = 32;
+
+#[derive(Debug, PartialEq)]
+pub struct DuplicateBlock {
+    pub offset: u64,
+    pub original: u64
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ByteRun {
+    pub offset: u64,
+    pub length: u64,
+    pub byte: u8
+}
+
+#[derive(Debug, Default)]
+pub struct BlockReport {
+    pub duplicates: Vec<DuplicateBlock>,
+    pub runs: Vec<ByteRun>
+}
+
+/// Hash every whole `block_size` block of the first `length` bytes of the source, and report the
+/// blocks that have the same contents as an earlier block. Blocks with the same hash are compared
+/// byte by byte, so that a hash collision is never reported as a duplicate. Runs of a single
+/// repeated byte that are at least `min_run` bytes long are reported separately, and blocks that
+/// consist entirely of one byte value are left out of the duplicates, since those are already
+/// covered by the runs. The analysis stops when what it keeps track of no longer fits in the memory
+/// budget.
+pub fn analyse_blocks(source: &mut ByteSource, length: u64, block_size: usize, min_run: u64, progress: &Progress)
+    -> Result<BlockReport> {
+    let mut report = BlockReport::default();
+    // The offsets of the first blocks with every hash, which are different from each other.
+    let mut seen: HashMap<u64, Vec<u64>> = HashMap::new();
+    let mut block = vec![0; block_size];
+    let mut earlier_block = vec![0; block_size];
+    let mut offset = 0;
+    let mut run = ByteRun { offset: 0, length: 0, byte: 0 };
+    let mut memory = Reservation::new(Subsystem::Duplicates, 0);
+    let mut keep_item = || if memory.try_grow(ITEM_SIZE) {
+        Ok(())
+    } else {
+        Err(XvError::Input(String::from("Finding the duplicates would use more than the memory budget. \
+                                         Try larger blocks, or a larger budget.")))
+    };
+
+    while offset < length {
+        let len = source.read_at(offset, &mut block)?.min((length - offset) as usize);
+        if len == 0 {
+            break;
+        }
+        let bytes = &block[0..len];
+
+        for (i, b) in bytes.iter().enumerate() {
+            if run.length > 0 && run.byte == *b {
+                run.length += 1;
+            } else {
+                if run.length >= min_run && run.length > 0 {
+                    keep_item()?;
+                    report.runs.push(run);
+                }
+                run = ByteRun { offset: offset + i as u64, length: 1, byte: *b };
+            }
+        }
+
+        let uniform = bytes.iter().all(|b| *b == bytes[0]);
+        if len == block_size && !uniform {
+            let earlier_offsets = seen.entry(fnv_hash(bytes)).or_default();
+            let mut original = None;
+            for &earlier in earlier_offsets.iter() {
+                if source.read_at(earlier, &mut earlier_block)? == block_size && earlier_block[..] == *bytes {
+                    original = Some(earlier);
+                    break;
+                }
+            }
+            keep_item()?;
+            match original {
+                Some(original) => report.duplicates.push(DuplicateBlock { offset, original }),
+                None => earlier_offsets.push(offset)
+            }
+        }
+        offset += len as u64;
+        progress.advance(len as u64)?;
+    }
+
+    if run.length >= min_run && run.length > 0 {
+        keep_item()?;
+        report.runs.push(run);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyse(mut data: &[u8], block_size: usize, min_run: u64) -> BlockReport {
+        let length = data.len() as u64;
+        analyse_blocks(&mut data, length, block_size, min_run, &Progress::new(length)).unwrap()
+    }
+
+    #[test]
+    fn finding_duplicate_blocks() {
+        let data: &[u8] = b"abcdefghabcdxxxxefghabcd";
+        let report = analyse(data, 4, 16);
+        assert_eq!(report.duplicates, vec![
+            DuplicateBlock { offset: 8, original: 0 },
+            DuplicateBlock { offset: 16, original: 4 },
+            DuplicateBlock { offset: 20, original: 0 }]);
+        assert!(report.runs.is_empty());
+    }
+
+    #[test]
+    fn uniform_blocks_are_reported_as_runs() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"abcd");
+        data.extend_from_slice(&[0xFF; 10]);
+        data.extend_from_slice(b"efgh");
+        let report = analyse(&data, 4, 8);
+        assert!(report.duplicates.is_empty());
+        assert_eq!(report.runs, vec![ByteRun { offset: 4, length: 10, byte: 0xFF }]);
+    }
+
+    #[test]
+    fn run_at_end_of_input() {
+        let data: &[u8] = b"ab\0\0\0\0\0";
+        let report = analyse(data, 4, 5);
+        assert_eq!(report.runs, vec![ByteRun { offset: 2, length: 5, byte: 0 }]);
+    }
+
+    #[test]
+    fn partial_last_block_is_not_hashed() {
+        let data: &[u8] = b"abcdabc";
+        let report = analyse(data, 4, 16);
+        assert!(report.duplicates.is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+//! A coarse index of a file, with the entropy and the kinds of bytes of every block, and the
+//! magic numbers that blocks start with, that is built once, in the background, and kept, so that
+//! the overview, entropy navigation and statistics of large files do not have to read them again.
+
+use std::fs;
+use std::path::Path;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::byte_reader::ByteSource;
+use crate::byte_stats::ByteStats;
+use crate::error::{Result, XvError};
+use crate::hex_reader::{HIGH_ENTROPY, LOW_ENTROPY};
+use crate::magic::magic_type;
+use crate::progress::Progress;
+
+/// The number of bytes that every block of the index describes.
+pub const INDEX_BLOCK_SIZE: u64 = 4096;
+
+/// Magic numbers are looked for at the start of every sector, where disk images and archives
+/// place the files in them.
+const SECTOR_SIZE: usize = 512;
+
+/// What the bytes of a block are like.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BlockSummary {
+    /// The entropy of the block, in bits per byte.
+    pub entropy: f32,
+    /// The fractions of the bytes, from 0.0 to 1.0, that are zero, that are 0xFF, and that are
+    /// printable.
+    pub zeros: f32,
+    pub full: f32,
+    pub printable: f32
+}
+
+/// The index of a file, which is stored as a msgpack array.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockIndex {
+    /// The length and modification time, in seconds, of the file that was indexed, to tell
+    /// whether the index is still of the same bytes.
+    length: u64,
+    modified: Option<u64>,
+    blocks: Vec<BlockSummary>,
+    /// The offsets of the sectors that start with a known magic number, and the MIME types that
+    /// the magic numbers are of.
+    magic_hits: Vec<(u64, String)>,
+    histogram: Vec<u64>
+}
+
+impl BlockIndex {
+    /// Index `length` bytes of the source, which were last modified at the given time.
+    pub fn build(source: &mut ByteSource, length: u64, modified: Option<u64>, progress: &Progress)
+        -> Result<BlockIndex> {
+        let mut index = BlockIndex {
+            length,
+            modified,
+            blocks: Vec::with_capacity((length / INDEX_BLOCK_SIZE + 1) as usize),
+            magic_hits: Vec::new(),
+            histogram: vec![0; 256]
+        };
+        let mut block = vec![0; INDEX_BLOCK_SIZE as usize];
+        let mut offset = 0;
+        while offset < length {
+            let len = source.read_at(offset, &mut block)?;
+            if len == 0 {
+                break;
+            }
+            let bytes = &block[0..len];
+            let stats = ByteStats::from_bytes(bytes);
+            let fraction = |count: u64| (count as f64 / len as f64) as f32;
+            index.blocks.push(BlockSummary {
+                entropy: stats.entropy() as f32,
+                zeros: fraction(stats.histogram()[0]),
+                full: fraction(stats.histogram()[0xFF]),
+                printable: stats.printable_ratio() as f32
+            });
+            for (count, n) in index.histogram.iter_mut().zip(stats.histogram().iter()) {
+                *count += n;
+            }
+            for (i, sector) in bytes.chunks(SECTOR_SIZE).enumerate() {
+                if let Some(mime_type) = magic_type(sector) {
+                    index.magic_hits.push((offset + (i * SECTOR_SIZE) as u64, String::from(mime_type)));
+                }
+            }
+            offset += len as u64;
+            progress.advance(len as u64)?;
+        }
+        Ok(index)
+    }
+
+    /// Whether the index is of a file with the given length and modification time.
+    pub fn is_of(&self, length: u64, modified: Option<u64>) -> bool {
+        self.length == length && self.modified == modified
+    }
+
+    /// The length of the file that was indexed.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn blocks(&self) -> &[BlockSummary] {
+        &self.blocks
+    }
+
+    pub fn magic_hits(&self) -> &[(u64, String)] {
+        &self.magic_hits
+    }
+
+    /// About how many bytes of memory the index takes up.
+    pub fn memory_size(&self) -> u64 {
+        let hits = self.magic_hits.iter().map(|(_, mime_type)| 32 + mime_type.len() as u64).sum::<u64>();
+        (self.blocks.len() * std::mem::size_of::<BlockSummary>()) as u64 + hits + self.histogram.len() as u64 * 8
+    }
+
+    /// The statistics of all the bytes of the file.
+    pub fn stats(&self) -> ByteStats {
+        ByteStats::from_histogram(&self.histogram)
+    }
+
+    /// The start of the next block after the offset with high entropy (if `high` is true) or low
+    /// entropy. The region that the offset is already in is skipped, like
+    /// `hex_reader::find_entropy_region` does.
+    pub fn find_entropy_block(&self, from: u64, high: bool) -> Option<u64> {
+        let in_region = |summary: &BlockSummary| {
+            let entropy = f64::from(summary.entropy);
+            if high { entropy >= HIGH_ENTROPY } else { entropy <= LOW_ENTROPY }
+        };
+        let first = (from / INDEX_BLOCK_SIZE) as usize;
+        let left = self.blocks.iter().skip(first).position(|summary| !in_region(summary))? + first;
+        self.blocks.iter().skip(left).position(in_region)
+            .map(|i| (left + i) as u64 * INDEX_BLOCK_SIZE)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<BlockIndex> {
+        let bytes = fs::read(path)?;
+        rmp_serde::from_slice(&bytes).map_err(|e| XvError::Decode(format!("The index could not be read: {}", e)))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let bytes = rmp_serde::to_vec(self)
+            .map_err(|e| XvError::Parse(format!("The index could not be written: {}", e)))?;
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexing_blocks() {
+        let mut file = vec![0; INDEX_BLOCK_SIZE as usize];
+        file.extend((0..INDEX_BLOCK_SIZE).map(|i| (i * 97 % 256) as u8));
+        file.extend(b"\x7FELF\x02\x01\x01");
+        file.resize(INDEX_BLOCK_SIZE as usize * 3, b'a');
+        let length = file.len() as u64;
+        let index = BlockIndex::build(&mut file, length, Some(7), &Progress::new(length)).unwrap();
+
+        assert_eq!(index.blocks().len(), 3);
+        assert_eq!(index.blocks()[0].zeros, 1.0);
+        assert!(index.blocks()[1].entropy > 7.99);
+        assert!(index.blocks()[2].printable > 0.99);
+        assert_eq!(index.magic_hits(), [(INDEX_BLOCK_SIZE * 2, String::from("application/x-executable"))]);
+        assert_eq!(index.stats().count(), length);
+        assert!(index.is_of(length, Some(7)));
+        assert!(!index.is_of(length, Some(8)));
+
+        assert_eq!(index.find_entropy_block(0, true), Some(INDEX_BLOCK_SIZE));
+        assert_eq!(index.find_entropy_block(INDEX_BLOCK_SIZE, true), None);
+        assert_eq!(index.find_entropy_block(0, false), Some(INDEX_BLOCK_SIZE * 2));
+        assert_eq!(index.find_entropy_block(INDEX_BLOCK_SIZE * 2, false), None);
+
+        let saved = rmp_serde::to_vec(&index).unwrap();
+        assert_eq!(rmp_serde::from_slice::<BlockIndex>(&saved).unwrap(), index);
+    }
+}
@@ -0,0 +1,150 @@
+//! Building the block index and the search index of the file that is viewed in the background,
+//! and keeping them in sidecar files in the cache directory, so that they are there again when
+//! the file is reopened.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use cursive::Cursive;
+use xv::block_index::BlockIndex;
+use xv::byte_reader::ByteSource;
+use xv::error::{Result, XvError};
+use xv::memory;
+use xv::search_index::SearchIndex;
+
+use crate::hex_view::HexView;
+use crate::path_serde::path_to_bytes;
+use crate::progress_dialog::run_with_progress;
+use crate::utilities::{fnv_hash, project_dirs};
+use crate::xv_tui::ShowError;
+
+/// The sidecar file that an index of the file at the path is kept in, with the extension of the
+/// kind of index.
+fn index_path(path: &Path, extension: &str) -> Option<PathBuf> {
+    let dirs = project_dirs()?;
+    Some(dirs.cache_dir().join("index").join(format!("{:016x}.{}", path_hash(path), extension)))
+}
+
+/// The hash of the path, which stays the same from one Rust release to the next, so the sidecar
+/// files are found again after xv is rebuilt.
+fn path_hash(path: &Path) -> u64 {
+    fnv_hash(&path_to_bytes(path))
+}
+
+/// When the file at the path was last modified, in seconds.
+fn modified_time(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|duration| duration.as_secs())
+}
+
+/// The index of the file that was kept from before, if there is one, the file has not changed
+/// since, and it fits in the memory budget.
+pub fn load_index(path: &Path, length: u64) -> Option<BlockIndex> {
+    let index_path = index_path(path, "xvi")?;
+    if !fits_in_memory(&index_path) {
+        return None;
+    }
+    let index = BlockIndex::load(index_path).ok()?;
+    if index.is_of(length, modified_time(path)) { Some(index) } else { None }
+}
+
+/// The search index of the file that was kept from before, if there is one, the file has not
+/// changed since, and it fits in the memory budget.
+pub fn load_search_index(path: &Path, length: u64) -> Option<SearchIndex> {
+    let index_path = index_path(path, "xvs")?;
+    if !fits_in_memory(&index_path) {
+        return None;
+    }
+    let index = SearchIndex::load(index_path).ok()?;
+    if index.is_of(length, modified_time(path)) { Some(index) } else { None }
+}
+
+/// Whether the index that is kept in the file fits in what is left of the memory budget, going by
+/// the size of the file.
+fn fits_in_memory(index_path: &Path) -> bool {
+    fs::metadata(index_path).is_ok_and(|metadata| metadata.len() <= memory::available())
+}
+
+/// The path and the length of the file that is viewed, and a source to read it in the
+/// background.
+fn open_file(s: &mut Cursive) -> Option<(PathBuf, u64, Box<ByteSource + Send>)> {
+    let opened = s.call_on_id("hex_view", |v: &mut HexView| {
+        if !v.is_file() {
+            return Err(XvError::Input(String::from("Only files can be indexed.")));
+        }
+        let length = v.get_length();
+        Ok((v.get_path(), length, v.open_background_source(0, length)?.0))
+    }).unwrap();
+    match opened {
+        Ok(opened) => Some(opened),
+        Err(error) => {
+            s.show_error(error);
+            None
+        }
+    }
+}
+
+/// Index the file that is viewed, and keep the index for when the file is opened again.
+pub fn build_index(s: &mut Cursive) {
+    let (path, length, mut source) = match open_file(s) {
+        Some(opened) => opened,
+        None => return
+    };
+    let modified = modified_time(&path);
+    let index_path = index_path(&path, "xvi");
+    run_with_progress(s, "Indexing", length, move |progress| {
+        let index = BlockIndex::build(&mut *source, length, modified, progress)?;
+        if let Some(index_path) = index_path {
+            make_index_dir(&index_path)?;
+            index.save(index_path)?;
+        }
+        Ok(index)
+    }, move |s, index| {
+        s.call_on_id("hex_view", |v: &mut HexView| if v.get_path() == path {
+            v.set_block_index(Some(index));
+        });
+    });
+}
+
+/// Build the search index of the file that is viewed, that searches use from then on, and keep
+/// it for when the file is opened again.
+pub fn build_search_index(s: &mut Cursive) {
+    let (path, length, mut source) = match open_file(s) {
+        Some(opened) => opened,
+        None => return
+    };
+    let modified = modified_time(&path);
+    let index_path = index_path(&path, "xvs");
+    run_with_progress(s, "Indexing for search", length, move |progress| {
+        let index = SearchIndex::build(&mut *source, length, modified, progress)?;
+        if let Some(index_path) = index_path {
+            make_index_dir(&index_path)?;
+            index.save(index_path)?;
+        }
+        Ok(index)
+    }, move |s, index| {
+        s.call_on_id("hex_view", |v: &mut HexView| if v.get_path() == path {
+            v.set_search_index(Some(Arc::new(index)));
+        });
+    });
+}
+
+fn make_index_dir(index_path: &Path) -> Result<()> {
+    if let Some(dir) = index_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashing_paths_the_same_way_every_time() {
+        assert_eq!(path_hash(Path::new("")), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(path_hash(Path::new("a")), 0xaf63_dc4c_8601_ec8c);
+    }
+}
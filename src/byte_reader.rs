@@ -1,40 +1,408 @@
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::fs::{File, FileType};
+use std::io::Cursor;
+use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Result;
 use std::io::Seek;
 use std::io::SeekFrom;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::XvError;
+use crate::memory::{self, Reservation, Subsystem};
+use crate::object_store::{ObjectSource, ObjectUrl};
+use crate::sparse::SparseFile;
+use crate::spool::Spool;
+
+/// Where the bytes that are viewed come from.
+///
+/// Files are the usual source, but byte vectors, byte slices and cursors work as well, so that
+/// data that is already in memory can be viewed without going through the file system.
+pub trait ByteSource: Debug {
+    /// Read bytes starting at the given offset, until the buffer is full or the end of the
+    /// source is reached. Returns the number of bytes read.
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize>;
+
+    /// The current length of the source in bytes.
+    fn length(&mut self) -> Result<u64>;
+
+    /// When the source was last changed, for sources that can be changed in place, like files.
+    fn modified(&mut self) -> Result<Option<SystemTime>> {
+        Ok(None)
+    }
+
+    /// Another source with the same bytes, that can be read from another thread. Sources that
+    /// cannot be cloned cheaply return `None`.
+    fn try_clone(&self) -> Option<Box<ByteSource + Send>> {
+        None
+    }
+
+    /// How the source is best read in tiles. In-memory sources are read directly.
+    fn tile_config(&self) -> TileConfig {
+        TileConfig::UNTILED
+    }
+
+    /// The ranges that read as zeros without being stored, like the holes of a sparse file, in
+    /// order.
+    fn holes(&self) -> &[Range<u64>] {
+        &[]
+    }
+}
+
+/// How a source is read in tiles, which are kept, so that reading the same bytes again does not
+/// go back to the source.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TileConfig {
+    /// The number of bytes in a tile, or 0 to read straight from the source.
+    pub tile_size: u64,
+    /// How many of the tiles after one that had to be read are read along with it.
+    pub read_ahead: u64
+}
+
+impl TileConfig {
+    pub const UNTILED: TileConfig = TileConfig { tile_size: 0, read_ahead: 0 };
+}
+
+/// The smallest tiles that a source is read in, so that the tiles that are kept stay few enough
+/// to look up and evict quickly.
+pub const MIN_TILE_SIZE: u64 = 4096;
+
+/// How the tiles have been used, for tuning the tile configuration.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TileStats {
+    /// The tiles that were read from the source because they were needed.
+    pub fetched: u64,
+    /// The reads of tiles that were already kept.
+    pub hits: u64,
+    /// The tiles that were read from the source ahead of being needed.
+    pub read_ahead: u64
+}
+
+/// How many bytes of tiles are kept, at most, while they fit in the memory budget.
+const TILE_CACHE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The tiles that have been read, by their start offsets, with when they were last used.
+#[derive(Debug)]
+struct Tiles {
+    tiles: HashMap<u64, (u64, Vec<u8>)>,
+    /// The start offsets of the tiles by when they were last used, from the least recently used.
+    uses: BTreeMap<u64, u64>,
+    /// The number of times that tiles have been used, which orders the uses.
+    clock: u64,
+    stats: TileStats,
+    memory: Reservation
+}
+
+impl Default for Tiles {
+    fn default() -> Self {
+        Tiles {
+            tiles: HashMap::new(),
+            uses: BTreeMap::new(),
+            clock: 0,
+            stats: TileStats::default(),
+            memory: Reservation::new(Subsystem::Tiles, 0)
+        }
+    }
+}
+
+impl Tiles {
+    fn read_at(&mut self, source: &mut ByteSource, config: TileConfig, offset: u64, buf: &mut [u8])
+        -> Result<usize> {
+        if config.tile_size == 0 {
+            return source.read_at(offset, buf);
+        }
+        let mut len = 0;
+        while len < buf.len() {
+            let position = offset + len as u64;
+            let tile_start = position - position % config.tile_size;
+            let tile = self.tile(source, config, tile_start)?;
+            let from = (position - tile_start) as usize;
+            if from >= tile.len() {
+                break;
+            }
+            let count = (buf.len() - len).min(tile.len() - from);
+            buf[len..len + count].copy_from_slice(&tile[from..from + count]);
+            len += count;
+            if (tile.len() as u64) < config.tile_size {
+                // The source ends in this tile.
+                break;
+            }
+        }
+        Ok(len)
+    }
+
+    /// The tile that starts at the offset, read from the source if it is not kept, along with
+    /// the tiles after it that are read ahead.
+    fn tile(&mut self, source: &mut ByteSource, config: TileConfig, start: u64) -> Result<&[u8]> {
+        if self.tiles.contains_key(&start) {
+            self.stats.hits += 1;
+            self.clock += 1;
+            let (used, _) = self.tiles.get_mut(&start).unwrap();
+            self.uses.remove(used);
+            *used = self.clock;
+            self.uses.insert(self.clock, start);
+        } else {
+            let tile = read_tile(source, config, start)?;
+            self.stats.fetched += 1;
+            let mut ahead_start = start + config.tile_size;
+            for _ in 0..config.read_ahead {
+                if tile.len() as u64 != config.tile_size {
+                    break;
+                }
+                if !self.tiles.contains_key(&ahead_start) {
+                    // The tile that is needed was read, so a tile that cannot be read yet, like
+                    // one that is still being fetched, is read when it is needed instead.
+                    let ahead = match read_tile(source, config, ahead_start) {
+                        Ok(ahead) if !ahead.is_empty() => ahead,
+                        _ => break
+                    };
+                    self.stats.read_ahead += 1;
+                    self.keep(config, ahead_start, ahead);
+                }
+                ahead_start += config.tile_size;
+            }
+            self.keep(config, start, tile);
+        }
+        Ok(&self.tiles[&start].1)
+    }
+
+    fn keep(&mut self, config: TileConfig, start: u64, tile: Vec<u8>) {
+        let max_tiles = (TILE_CACHE_SIZE / config.tile_size).max(1) as usize;
+        while self.tiles.len() >= max_tiles || (!self.tiles.is_empty() && memory::available() < tile.len() as u64) {
+            let (&used, &evicted_start) = self.uses.iter().next().unwrap();
+            self.uses.remove(&used);
+            let (_, evicted) = self.tiles.remove(&evicted_start).unwrap();
+            let bytes = self.memory.bytes() - evicted.len() as u64;
+            self.memory.resize(bytes);
+        }
+        let bytes = self.memory.bytes() + tile.len() as u64;
+        self.memory.resize(bytes);
+        self.clock += 1;
+        self.uses.insert(self.clock, start);
+        self.tiles.insert(start, (self.clock, tile));
+    }
+
+    /// Forget the tiles, since the source may have changed.
+    fn clear(&mut self) {
+        self.tiles.clear();
+        self.uses.clear();
+        self.memory.resize(0);
+    }
+}
+
+fn read_tile(source: &mut ByteSource, config: TileConfig, start: u64) -> Result<Vec<u8>> {
+    let mut tile = vec![0; config.tile_size as usize];
+    let len = source.read_at(start, &mut tile)?;
+    tile.truncate(len);
+    Ok(tile)
+}
+
+impl ByteSource for File {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        self.seek(SeekFrom::Start(offset))?;
+        let mut len = 0;
+        while len < buf.len() {
+            match self.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(len)
+    }
+
+    fn length(&mut self) -> Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    fn modified(&mut self) -> Result<Option<SystemTime>> {
+        Ok(self.metadata()?.modified().ok())
+    }
+
+    fn tile_config(&self) -> TileConfig {
+        TileConfig { tile_size: 256 * 1024, read_ahead: 1 }
+    }
+}
+
+impl ByteSource for Vec<u8> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        Ok(copy_from_slice(self, offset, buf))
+    }
+
+    fn length(&mut self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl ByteSource for &[u8] {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        Ok(copy_from_slice(self, offset, buf))
+    }
+
+    fn length(&mut self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+/// Reads from the whole of the underlying bytes. The position of the cursor is not used.
+impl<T: AsRef<[u8]> + Debug> ByteSource for Cursor<T> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        Ok(copy_from_slice(self.get_ref().as_ref(), offset, buf))
+    }
+
+    fn length(&mut self) -> Result<u64> {
+        Ok(self.get_ref().as_ref().len() as u64)
+    }
+}
+
+fn copy_from_slice(bytes: &[u8], offset: u64, buf: &mut [u8]) -> usize {
+    if offset >= bytes.len() as u64 {
+        return 0;
+    }
+    let start = offset as usize;
+    let len = buf.len().min(bytes.len() - start);
+    buf[0..len].copy_from_slice(&bytes[start..start + len]);
+    len
+}
+
+/// Describe a kind of file, like "directory" or "named pipe".
+pub fn describe_file_type(file_type: &FileType) -> &'static str {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return "named pipe";
+        } else if file_type.is_socket() {
+            return "socket";
+        } else if file_type.is_char_device() {
+            return "character device";
+        } else if file_type.is_block_device() {
+            return "block device";
+        }
+    }
+    if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_symlink() {
+        "symbolic link"
+    } else if file_type.is_file() {
+        "file"
+    } else {
+        "special file"
+    }
+}
+
+/// Whether a file can only be read as a stream, from the start to the end, like a named pipe.
+fn is_stream(file_type: &FileType) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        file_type.is_fifo()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = file_type;
+        false
+    }
+}
 
 #[derive(Debug)]
 pub struct TilingByteReader {
-    file: File,
+    source: Box<ByteSource>,
+    /// The path of the file that is read, or just the name of an in-memory source.
     path: PathBuf,
+    is_file: bool,
+    /// The spool that the source is, when its bytes are still arriving.
+    spool: Option<Spool>,
+    tiles: Tiles,
+    /// The tile configuration that was chosen instead of the one of the source.
+    tile_config: Option<TileConfig>,
     length: u64,
-    use_large_addresses: bool,
+    /// When the source was last changed, as of when the length was last looked up.
+    modified: Option<SystemTime>,
     display_name: String
 }
 
 pub type Window = (u64, u64, u16, u16);
 
 impl TilingByteReader {
-    pub fn new<P: AsRef<Path>>(file_name: P) -> Result<TilingByteReader> {
+    /// Read from a file. Symbolic links are followed. Named pipes can only be read from the start,
+    /// so they are spooled to a temporary file, that grows while the bytes arrive. Sockets and
+    /// devices cannot be read, since they either have no length or no end. Objects in cloud
+    /// storage, like `s3://bucket/key`, are read in blocks as they are viewed.
+    pub fn new<P: AsRef<Path>>(file_name: P) -> crate::error::Result<TilingByteReader> {
+        if let Some(url) = file_name.as_ref().to_str().and_then(ObjectUrl::parse) {
+            let display_name = url.file_name().to_string();
+            let mut reader = TilingByteReader::from_source(ObjectSource::open(url)?, display_name)?;
+            reader.path = PathBuf::from(file_name.as_ref());
+            return Ok(reader);
+        }
         let path_buf = file_name.as_ref().canonicalize()?;
+        let file_type = path_buf.metadata()?.file_type();
         let display_name: String = path_buf.file_name().unwrap().to_string_lossy().into();
-        let file = File::open(file_name)?;
-        let file_len = file.metadata()?.len();
+        if is_stream(&file_type) {
+            // Opening a named pipe waits for something to write to it, so it is opened along
+            // with the spooling.
+            let pipe_path = path_buf.clone();
+            let spool = Spool::start_in_temp_file(move |_: &Fn() -> bool| File::open(pipe_path))?;
+            let mut reader = TilingByteReader::from_spool(spool, display_name)?;
+            reader.path = path_buf;
+            return Ok(reader);
+        }
+        if !file_type.is_file() {
+            return Err(XvError::Unsupported(format!(
+                "Only regular files and named pipes can be viewed, and this is a {}.",
+                describe_file_type(&file_type))));
+        }
+        let file = SparseFile::open(File::open(file_name)?)?;
+        let mut reader = TilingByteReader::from_source(file, display_name)?;
+        reader.path = path_buf;
+        reader.is_file = true;
+        Ok(reader)
+    }
+
+    /// Read from the given source, such as a byte vector, instead of a file.
+    pub fn from_source<S, N>(source: S, name: N) -> Result<TilingByteReader>
+        where S: ByteSource + 'static,
+              N: Into<String> {
+        let mut source = Box::new(source);
+        let length = source.length()?;
+        let modified = source.modified()?;
+        let display_name = name.into();
 
         Ok(TilingByteReader {
-            file,
-            path: path_buf,
-            length: file_len,
-            use_large_addresses: file_len > u64::from(std::u32::MAX),
+            source,
+            path: PathBuf::from(&display_name),
+            is_file: false,
+            spool: None,
+            tiles: Tiles::default(),
+            tile_config: None,
+            length,
+            modified,
             display_name
         })
     }
+
+    /// Read from a spool, whose bytes are still arriving.
+    pub fn from_spool<N: Into<String>>(spool: Spool, name: N) -> Result<TilingByteReader> {
+        let mut reader = TilingByteReader::from_source(spool.clone(), name)?;
+        reader.spool = Some(spool);
+        Ok(reader)
+    }
     
+    /// Open the file again, or look up the length of an in-memory source again.
     pub fn reopen(&mut self) -> Result<()> {
-        self.file = File::open(self.path.as_path())?;
-        self.length = self.file.metadata()?.len();
+        if self.is_file {
+            self.source = Box::new(SparseFile::open(File::open(self.path.as_path())?)?);
+        }
+        self.tiles.clear();
+        self.length = self.source.length()?;
+        self.modified = self.source.modified()?;
         Ok(())
     }
     
@@ -46,6 +414,39 @@ impl TilingByteReader {
         self.path.clone()
     }
 
+    /// Whether the bytes are read from a file, rather than from an in-memory source.
+    pub fn is_file(&self) -> bool {
+        self.is_file
+    }
+
+    /// The spool that the bytes are read from, when they are still arriving.
+    pub fn get_spool(&self) -> Option<&Spool> {
+        self.spool.as_ref()
+    }
+
+    pub fn get_tile_config(&self) -> TileConfig {
+        self.tile_config.unwrap_or_else(|| self.source.tile_config())
+    }
+
+    /// The tile configuration that was chosen instead of the one of the source, if one was.
+    pub fn get_chosen_tile_config(&self) -> Option<TileConfig> {
+        self.tile_config
+    }
+
+    /// Read in tiles of another size, or go back to the tiles of the source with `None`. Tiles
+    /// smaller than `MIN_TILE_SIZE` are made that size.
+    pub fn set_tile_config(&mut self, config: Option<TileConfig>) {
+        self.tile_config = config.map(|config| match config.tile_size {
+            0 => config,
+            tile_size => TileConfig { tile_size: tile_size.max(MIN_TILE_SIZE), ..config }
+        });
+        self.tiles.clear();
+    }
+
+    pub fn get_tile_stats(&self) -> TileStats {
+        self.tiles.stats
+    }
+
     pub fn get_window(&mut self, window: Window, line_length: u64, buf: &mut Vec<u8>) -> Result<()> {
         // The binary file is viewed in terms of lines.
         // The lines turn the linear byte sequence into a 2D byte grid.
@@ -57,24 +458,59 @@ impl TilingByteReader {
         // The 'h' height is the number of lines in the window,
         // and 'w' is the width of each window line.
         let (x, y, w, h) = window;
-        let mut read_buf = vec![0; usize::from(w)];
-
+        // The lines are read straight into the end of the buffer, which is grown to fit a whole
+        // line and cut back to what was read, so a buffer that is reused is not allocated again.
+        buf.reserve(usize::from(w) * usize::from(h));
         for i in y..(y + (u64::from(h))) {
             let offset = line_length * i + x;
-            self.file.seek(SeekFrom::Start(offset))?;
-            let bytes_read = self.file.read(&mut read_buf)?;
-            buf.extend(&read_buf[0..bytes_read]);
+            let start = buf.len();
+            buf.resize(start + usize::from(w), 0);
+            let bytes_read = self.read_at(offset, &mut buf[start..])?;
+            buf.truncate(start + bytes_read);
         }
         Ok(())
     }
     
+    /// Read bytes starting at the given file offset, until the buffer is full or the end of the
+    /// file is reached. Returns the number of bytes read.
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let config = self.get_tile_config();
+        self.tiles.read_at(&mut *self.source, config, offset, buf)
+    }
+    
     pub fn get_length(&self) -> u64 {
         self.length
     }
+
+    /// The holes of the source, which read as zeros without being read.
+    pub fn get_holes(&self) -> &[Range<u64>] {
+        self.source.holes()
+    }
+
+    /// A clone of the source, if it can be cloned cheaply.
+    pub fn try_clone_source(&self) -> Option<Box<ByteSource + Send>> {
+        self.source.try_clone()
+    }
     
-    pub fn use_large_addresses(&self) -> bool {
-        self.use_large_addresses
+    /// When the source was last changed, as of when the length was last looked up, if it can be
+    /// changed in place.
+    pub fn get_modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Look up the length of the file again, since it may have changed since it was opened. The
+    /// tiles are forgotten if it has changed, in length or in place.
+    pub fn update_length(&mut self) -> Result<u64> {
+        let length = self.source.length()?;
+        let modified = self.source.modified()?;
+        if length != self.length || modified != self.modified {
+            self.tiles.clear();
+        }
+        self.length = length;
+        self.modified = modified;
+        Ok(self.length)
     }
+
 }
 
 #[cfg(test)]
@@ -118,6 +554,50 @@ mod tests {
         assert_eq!(buf, b"4567cdef")
     }
     
+    #[test]
+    fn reading_at_offset() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(b"0123456789abcdef").unwrap();
+
+        let mut reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(reader.read_at(4, &mut buf).unwrap(), 8);
+        assert_eq!(&buf, b"456789ab");
+        assert_eq!(reader.read_at(12, &mut buf).unwrap(), 4);
+        assert_eq!(&buf[0..4], b"cdef");
+    }
+    
+    #[test]
+    fn updating_length_of_shrunk_file() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(b"0123456789abcdef").unwrap();
+
+        let mut reader = TilingByteReader::new(tmpf.path()).unwrap();
+        assert_eq!(reader.get_length(), 16);
+        tmpf.set_len(4).unwrap();
+        assert_eq!(reader.get_length(), 16);
+        assert_eq!(reader.update_length().unwrap(), 4);
+        assert_eq!(reader.get_length(), 4);
+    }
+    
+    #[test]
+    fn reading_from_memory() {
+        let mut reader = TilingByteReader::from_source(b"0123456789abcdef".to_vec(), "vec").unwrap();
+        let mut buf = Vec::new();
+        reader.get_window((4,0,4,2), 8, &mut buf).unwrap();
+        assert_eq!(buf, b"4567cdef");
+        assert_eq!(reader.get_length(), 16);
+        assert_eq!(reader.file_name(), "vec");
+
+        let mut reader = TilingByteReader::from_source(Cursor::new(&b"0123456789"[..]), "cursor").unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(reader.read_at(6, &mut buf).unwrap(), 4);
+        assert_eq!(&buf[0..4], b"6789");
+        assert_eq!(reader.read_at(20, &mut buf).unwrap(), 0);
+        reader.reopen().unwrap();
+        assert_eq!(reader.get_length(), 10);
+    }
+    
     #[test]
     fn getting_multi_line_string_bottom_left() {
         let mut tmpf = tempfile::NamedTempFile::new().unwrap();
@@ -128,4 +608,65 @@ mod tests {
         reader.get_window((0,1,4,2), 8, &mut buf).unwrap();
         assert_eq!(buf, b"89ab")
     }
+    
+    #[test]
+    fn reading_in_tiles() {
+        let mut source: Vec<u8> = (0..100).collect();
+        let config = TileConfig { tile_size: 16, read_ahead: 1 };
+        let mut tiles = Tiles::default();
+        let mut buf = [0; 4];
+        assert_eq!(tiles.read_at(&mut source, config, 14, &mut buf).unwrap(), 4);
+        assert_eq!(buf, [14, 15, 16, 17]);
+        assert_eq!(tiles.stats, TileStats { fetched: 1, hits: 1, read_ahead: 1 });
+        assert_eq!(tiles.read_at(&mut source, config, 98, &mut buf).unwrap(), 2);
+        assert_eq!(&buf[0..2], [98, 99]);
+        assert_eq!(tiles.read_at(&mut source, config, 200, &mut buf).unwrap(), 0);
+        assert_eq!(tiles.stats, TileStats { fetched: 3, hits: 1, read_ahead: 1 });
+    }
+
+    #[test]
+    fn rereading_a_file_that_was_rewritten() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(b"0123456789abcdef").unwrap();
+
+        let mut reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let mut buf = [0; 4];
+        reader.read_at(0, &mut buf).unwrap();
+        assert_eq!(&buf, b"0123");
+
+        std::fs::write(tmpf.path(), b"fedcba9876543210").unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(10);
+        std::fs::File::options().write(true).open(tmpf.path()).unwrap().set_modified(later).unwrap();
+        assert_eq!(reader.update_length().unwrap(), 16);
+        reader.read_at(0, &mut buf).unwrap();
+        assert_eq!(&buf, b"fedc");
+    }
+
+    #[test]
+    fn refusing_to_read_directories() {
+        match TilingByteReader::new(std::env::temp_dir()).unwrap_err() {
+            XvError::Unsupported(message) =>
+                assert_eq!(message, "Only regular files and named pipes can be viewed, and this is a directory."),
+            error => panic!("{:?}", error)
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn spooling_named_pipes() {
+        let pipe_path = std::env::temp_dir().join(format!("xv-test-pipe-{}", std::process::id()));
+        assert!(std::process::Command::new("mkfifo").arg(&pipe_path).status().unwrap().success());
+
+        let mut reader = TilingByteReader::new(&pipe_path).unwrap();
+        std::fs::OpenOptions::new().write(true).open(&pipe_path).unwrap().write_all(b"01234567").unwrap();
+        let spool = reader.get_spool().unwrap().clone();
+        while !spool.is_finished() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(reader.update_length().unwrap(), 8);
+        let mut buf = Vec::new();
+        reader.get_window((0,0,16,16), 16, &mut buf).unwrap();
+        std::fs::remove_file(&pipe_path).unwrap();
+        assert_eq!(buf, b"01234567")
+    }
 }
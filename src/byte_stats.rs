@@ -0,0 +1,168 @@
+//! Statistics of a range of bytes: the smallest, largest and mean value, the entropy, and how many
+//! are printable.
+
+use crate::hex_tables::{ByteCategory, BYTE_CATEGORY};
+
+/// A histogram of byte values, and the statistics that can be derived from it.
+pub struct ByteStats {
+    histogram: [u64; 256],
+    count: u64
+}
+
+impl Default for ByteStats {
+    fn default() -> Self {
+        ByteStats::new()
+    }
+}
+
+impl ByteStats {
+    pub fn new() -> ByteStats {
+        ByteStats {
+            histogram: [0; 256],
+            count: 0
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> ByteStats {
+        let mut stats = ByteStats::new();
+        stats.add(bytes);
+        stats
+    }
+
+    /// Statistics from the counts of every byte value, from 0 to 255.
+    pub fn from_histogram(histogram: &[u64]) -> ByteStats {
+        let mut stats = ByteStats::new();
+        for (count, n) in stats.histogram.iter_mut().zip(histogram) {
+            *count = *n;
+        }
+        stats.count = stats.histogram.iter().sum();
+        stats
+    }
+
+    /// The counts of every byte value, from 0 to 255.
+    pub fn histogram(&self) -> &[u64; 256] {
+        &self.histogram
+    }
+
+    pub fn add(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.histogram[usize::from(*b)] += 1;
+        }
+        self.count += bytes.len() as u64;
+    }
+
+    pub fn add_byte(&mut self, byte: u8) {
+        self.histogram[usize::from(byte)] += 1;
+        self.count += 1;
+    }
+
+    /// Remove a byte that was previously added, for keeping statistics over a sliding window.
+    pub fn remove_byte(&mut self, byte: u8) {
+        self.histogram[usize::from(byte)] -= 1;
+        self.count -= 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<u8> {
+        (0..=255u8).find(|b| self.histogram[usize::from(*b)] > 0)
+    }
+
+    pub fn max(&self) -> Option<u8> {
+        (0..=255u8).rev().find(|b| self.histogram[usize::from(*b)] > 0)
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let sum: u64 = self.histogram.iter().enumerate().map(|(b, n)| b as u64 * n).sum();
+        sum as f64 / self.count as f64
+    }
+
+    /// The Shannon entropy of the bytes, in bits per byte, from 0.0 to 8.0.
+    pub fn entropy(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let total = self.count as f64;
+        self.histogram.iter()
+            .filter(|n| **n > 0)
+            .map(|n| {
+                let p = *n as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    /// The fraction of the bytes, from 0.0 to 1.0, that are printable ASCII characters or spaces.
+    pub fn printable_ratio(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let printable: u64 = self.histogram.iter().enumerate()
+            .filter(|(b, _)| matches!(BYTE_CATEGORY[*b], ByteCategory::AsciiPrintable | ByteCategory::AsciiWhitespace))
+            .map(|(_, n)| *n)
+            .sum();
+        printable as f64 / self.count as f64
+    }
+
+    /// The `n` most frequent byte values and their counts, most frequent first.
+    pub fn most_common(&self, n: usize) -> Vec<(u8, u64)> {
+        let mut counts: Vec<(u8, u64)> = (0..=255u8)
+            .map(|b| (b, self.histogram[usize::from(b)]))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_of_empty_input() {
+        let stats = ByteStats::from_bytes(b"");
+        assert_eq!(stats.count(), 0);
+        assert_eq!(stats.min(), None);
+        assert_eq!(stats.max(), None);
+        assert_eq!(stats.mean(), 0.0);
+        assert_eq!(stats.entropy(), 0.0);
+        assert!(stats.most_common(3).is_empty());
+    }
+
+    #[test]
+    fn stats_of_text() {
+        let stats = ByteStats::from_bytes(b"aab\x00");
+        assert_eq!(stats.count(), 4);
+        assert_eq!(stats.min(), Some(0));
+        assert_eq!(stats.max(), Some(b'b'));
+        assert_eq!(stats.mean(), 73.0);
+        assert_eq!(stats.entropy(), 1.5);
+        assert_eq!(stats.printable_ratio(), 0.75);
+        assert_eq!(stats.most_common(2), vec![(b'a', 2), (0, 1)]);
+    }
+
+    #[test]
+    fn removing_bytes() {
+        let mut stats = ByteStats::from_bytes(b"aab");
+        stats.remove_byte(b'a');
+        stats.add_byte(b'c');
+        assert_eq!(stats.count(), 3);
+        assert_eq!(stats.min(), Some(b'a'));
+        assert_eq!(stats.max(), Some(b'c'));
+        assert_eq!(stats.most_common(3), vec![(b'a', 1), (b'b', 1), (b'c', 1)]);
+    }
+
+    #[test]
+    fn entropy_of_all_byte_values() {
+        let all: Vec<u8> = (0..=255u8).collect();
+        let stats = ByteStats::from_bytes(&all);
+        assert_eq!(stats.entropy(), 8.0);
+    }
+}
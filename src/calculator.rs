@@ -0,0 +1,261 @@
+//! Integer expressions over offsets and marks, written the way C writes them.
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use xv::error::{Result, XvError};
+
+/// The values that can be used by name in a calculation.
+#[derive(Clone, Copy)]
+pub struct Variables {
+    pub offset: u64,
+    pub size: u64,
+    pub width: u64,
+    pub mark_a: Option<u64>,
+    pub mark_b: Option<u64>
+}
+
+/// Evaluate an integer expression, such as `offset + 4 * width` or `(0x1F00 >> 8) & 0b111`.
+/// The variables `a` and `b` are the offsets of the marks, and can only be used when they are set.
+///
+/// Numbers are decimal, or hexadecimal with a `0x` prefix, binary with `0b`, and octal with `0o`
+/// or a leading zero. The operators are those of C, with the same precedence: `|`, `^`, `&`,
+/// `<<` and `>>`, `+` and `-`, `*`, `/` and `%`, and the unary `-` and `~`. The arithmetic is
+/// done on unsigned 64 bit integers, and it is an error to overflow or divide by zero.
+pub fn evaluate(expression: &str, variables: &Variables) -> Result<u64> {
+    let mut parser = Parser { tokens: tokenize(expression)?, pos: 0, variables };
+    let value = parser.bit_or()?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(value),
+        Some(token) => Err(XvError::Parse(format!("Did not expect '{}' here.", token.text())))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(u64),
+    Name(String),
+    Operator(&'static str)
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Number(n) => format!("{}", n),
+            Token::Name(name) => name.clone(),
+            Token::Operator(op) => String::from(*op)
+        }
+    }
+}
+
+const OPERATORS: [&str; 13] = ["<<", ">>", "|", "^", "&", "+", "-", "*", "/", "%", "~", "(", ")"];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = expression.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let word = &expression[start..end];
+            tokens.push(if c.is_ascii_digit() {
+                Token::Number(parse_literal(word)?)
+            } else {
+                Token::Name(String::from(word))
+            });
+        } else {
+            let rest = &expression[start..];
+            match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                Some(op) => {
+                    for _ in 0..op.len() {
+                        chars.next();
+                    }
+                    tokens.push(Token::Operator(op));
+                },
+                None => return Err(XvError::Parse(format!("Unknown operator '{}'.", c)))
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_literal(word: &str) -> Result<u64> {
+    let digits = word.replace('_', "");
+    let (digits, radix) = if let Some(hex) = digits.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        (binary, 2)
+    } else if let Some(octal) = digits.strip_prefix("0o") {
+        (octal, 8)
+    } else if digits.starts_with('0') && digits.len() > 1 {
+        (&digits[1..], 8)
+    } else {
+        (&digits[..], 10)
+    };
+    u64::from_str_radix(digits, radix)
+        .map_err(|_| XvError::Parse(format!("Not a number: {}.", word)))
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    variables: &'a Variables
+}
+
+impl<'a> Parser<'a> {
+    fn next_operator(&mut self, operators: &[&str]) -> Option<&'static str> {
+        if let Some(Token::Operator(op)) = self.tokens.get(self.pos) {
+            if operators.contains(op) {
+                self.pos += 1;
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    fn bit_or(&mut self) -> Result<u64> {
+        let mut value = self.bit_xor()?;
+        while self.next_operator(&["|"]).is_some() {
+            value |= self.bit_xor()?;
+        }
+        Ok(value)
+    }
+
+    fn bit_xor(&mut self) -> Result<u64> {
+        let mut value = self.bit_and()?;
+        while self.next_operator(&["^"]).is_some() {
+            value ^= self.bit_and()?;
+        }
+        Ok(value)
+    }
+
+    fn bit_and(&mut self) -> Result<u64> {
+        let mut value = self.shift()?;
+        while self.next_operator(&["&"]).is_some() {
+            value &= self.shift()?;
+        }
+        Ok(value)
+    }
+
+    fn shift(&mut self) -> Result<u64> {
+        let mut value = self.sum()?;
+        while let Some(op) = self.next_operator(&["<<", ">>"]) {
+            let bits = self.sum()?;
+            if bits >= 64 {
+                return Err(XvError::Input(format!("Cannot shift by {} bits.", bits)));
+            }
+            value = if op == "<<" { value << bits } else { value >> bits };
+        }
+        Ok(value)
+    }
+
+    fn sum(&mut self) -> Result<u64> {
+        let mut value = self.product()?;
+        while let Some(op) = self.next_operator(&["+", "-"]) {
+            let rhs = self.product()?;
+            let result = if op == "+" { value.checked_add(rhs) } else { value.checked_sub(rhs) };
+            value = result.ok_or_else(overflow)?;
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<u64> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.next_operator(&["*", "/", "%"]) {
+            let rhs = self.unary()?;
+            value = match op {
+                "*" => value.checked_mul(rhs).ok_or_else(overflow)?,
+                _ if rhs == 0 => return Err(XvError::Input(String::from("Division by zero."))),
+                "/" => value / rhs,
+                _ => value % rhs
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<u64> {
+        match self.next_operator(&["-", "~"]) {
+            Some("-") => Ok(self.unary()?.wrapping_neg()),
+            Some(_) => Ok(!self.unary()?),
+            None => self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<u64> {
+        if self.next_operator(&["("]).is_some() {
+            let value = self.bit_or()?;
+            if self.next_operator(&[")"]).is_none() {
+                return Err(XvError::Parse(String::from("A ')' is missing.")));
+            }
+            return Ok(value);
+        }
+        let value = match self.tokens.get(self.pos) {
+            Some(Token::Number(n)) => *n,
+            Some(Token::Name(name)) => match name.as_str() {
+                "offset" => self.variables.offset,
+                "size" => self.variables.size,
+                "width" => self.variables.width,
+                "a" => self.variables.mark_a.ok_or_else(|| unset_mark("A"))?,
+                "b" => self.variables.mark_b.ok_or_else(|| unset_mark("B"))?,
+                _ => return Err(XvError::Parse(format!("Unknown variable '{}'.", name)))
+            },
+            Some(token) => return Err(XvError::Parse(format!("Did not expect '{}' here.", token.text()))),
+            None => return Err(XvError::Parse(String::from("The expression ended too soon.")))
+        };
+        self.pos += 1;
+        Ok(value)
+    }
+}
+
+fn unset_mark(mark: &str) -> XvError {
+    XvError::Input(format!("Mark {} is not set.", mark))
+}
+
+fn overflow() -> XvError {
+    XvError::Input(String::from("The result does not fit in 64 bits."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VARIABLES: Variables = Variables {
+        offset: 0x100, size: 1000, width: 16, mark_a: Some(0x40), mark_b: None };
+
+    fn eval(expression: &str) -> Result<u64> {
+        evaluate(expression, &VARIABLES)
+    }
+
+    #[test]
+    fn evaluating_numbers_and_operators() {
+        assert_eq!(eval("0x10 + 0b11 * 0o10 + 010 + 1_000").unwrap(), 16 + 24 + 8 + 1000);
+        assert_eq!(eval("(1 + 2) * 3 - 10 / 3 % 2").unwrap(), 8);
+        assert_eq!(eval("1 << 4 | 0xF0 & 0x3C ^ 1").unwrap(), 16 | ((0xF0 & 0x3C) ^ 1));
+        assert_eq!(eval("~0 >> 60").unwrap(), 15);
+        assert_eq!(eval("-1").unwrap(), u64::MAX);
+        assert_eq!(eval("size - offset - 2 * width").unwrap(), 712);
+        assert_eq!(eval("offset - a").unwrap(), 0xC0);
+    }
+
+    #[test]
+    fn reporting_errors() {
+        assert!(eval("offset - size").is_err());
+        assert!(eval("1 / (width - 16)").is_err());
+        assert!(eval("1 << 64").is_err());
+        assert!(eval("(1 + 2").is_err());
+        assert!(eval("1 +").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("0xZZ").is_err());
+        assert!(eval("cursor").is_err());
+        assert!(eval("b - a").is_err());
+        assert!(eval("1 $ 2").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+//! The calculator, that evaluates an expression as it is typed, and can go to the result.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, DummyView, EditView, LinearLayout, OnEventView, TextView};
+
+use crate::calculator::{evaluate, Variables};
+use crate::hex_view::HexView;
+use crate::utilities::get_content;
+use crate::xv_tui::ShowError;
+
+pub fn open_calculator_dialog(s: &mut Cursive) {
+    let variables = get_variables(s);
+
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Expression:  "))
+            .child(EditView::new()
+                .content("offset")
+                .on_edit(move |s, expression, _| update_result(s, expression, &variables))
+                .with_id("calculator_expression")
+                .min_width(40)))
+        .child(DummyView)
+        .child(TextView::new(
+            "Variables: offset, size, width, a, b.\nOperators: + - * / % << >> & | ^ ~ ( )"))
+        .child(DummyView)
+        .child(TextView::new("").with_id("calculator_result"));
+
+    let dialog = Dialog::around(layout)
+        .title("Calculator")
+        .button("Go to", do_go_to_result)
+        .dismiss_button("Close");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_go_to_result);
+
+    s.add_layer(event_view);
+    update_result(s, "offset", &variables);
+}
+
+fn get_variables(s: &mut Cursive) -> Variables {
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        let (mark_a, mark_b) = v.get_marks();
+        Variables {
+            offset: v.get_offset(),
+            size: v.get_length(),
+            width: v.get_line_width(),
+            mark_a,
+            mark_b
+        }
+    }).unwrap()
+}
+
+fn update_result(s: &mut Cursive, expression: &str, variables: &Variables) {
+    let text = match evaluate(expression, variables) {
+        Ok(value) => format!("Decimal:  {}\nHex:      0x{:X}\nOctal:    0o{:o}\nBinary:   0b{:b}",
+                             value, value, value, value),
+        Err(error) => format!("{}", error)
+    };
+    s.call_on_id("calculator_result", |v: &mut TextView| v.set_content(text));
+}
+
+fn do_go_to_result(s: &mut Cursive) {
+    let expression = s.call_on_id("calculator_expression", get_content).unwrap();
+    let variables = get_variables(s);
+    match evaluate(&expression, &variables) {
+        Ok(offset) => {
+            s.pop_layer();
+            s.call_on_id("hex_view", |view: &mut HexView| {
+                view.go_to_offset(offset);
+            });
+        },
+        Err(error) => s.show_error(error)
+    }
+}
@@ -0,0 +1,235 @@
+//! Finding the packets in pcap and pcapng network captures, so that their bytes can be looked at
+//! without exporting them from a packet analyzer first.
+
+use xv::byte_reader::ByteSource;
+use xv::error::{Result, XvError};
+use xv::progress::Progress;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_OBSOLETE_PACKET: u32 = 2;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+/// The option of an interface description block with the resolution of its timestamps.
+const IF_TSRESOL: u64 = 9;
+
+/// A captured packet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packet {
+    /// When the packet was captured, in nanoseconds since the epoch, if the capture has the time.
+    pub timestamp: Option<u64>,
+    /// The offset of the captured bytes of the packet in the file.
+    pub offset: u64,
+    /// The number of captured bytes, which can be fewer than the packet had.
+    pub length: u64,
+    pub original_length: u64
+}
+
+/// Find the packets of a pcap or pcapng capture. Returns `None` when the source is neither.
+pub fn find_packets(source: &mut ByteSource, progress: &Progress) -> Result<Option<Vec<Packet>>> {
+    let mut magic = [0; 4];
+    if source.read_at(0, &mut magic)? < 4 {
+        return Ok(None);
+    }
+    let big = u32::from_be_bytes(magic);
+    let little = u32::from_le_bytes(magic);
+    match (big, little) {
+        (0xA1B2_C3D4, _) => find_pcap_packets(source, true, 1000, progress).map(Some),
+        (_, 0xA1B2_C3D4) => find_pcap_packets(source, false, 1000, progress).map(Some),
+        (0xA1B2_3C4D, _) => find_pcap_packets(source, true, 1, progress).map(Some),
+        (_, 0xA1B2_3C4D) => find_pcap_packets(source, false, 1, progress).map(Some),
+        (PCAPNG_SECTION_HEADER, _) => find_pcapng_packets(source, progress).map(Some),
+        _ => Ok(None)
+    }
+}
+
+fn number(bytes: &[u8], big_endian: bool) -> u64 {
+    if big_endian {
+        bytes.iter().fold(0, |n, b| n << 8 | u64::from(*b))
+    } else {
+        bytes.iter().rev().fold(0, |n, b| n << 8 | u64::from(*b))
+    }
+}
+
+fn damaged() -> XvError {
+    XvError::Decode(String::from("The capture is damaged."))
+}
+
+/// Read a header, and tell whether all of it was there. A capture that ends in the middle of a
+/// header was cut short while it was written, and the packets before it are kept.
+fn read_header(source: &mut ByteSource, offset: u64, header: &mut [u8]) -> Result<bool> {
+    Ok(source.read_at(offset, header)? == header.len())
+}
+
+/// Find the packets of a classic pcap file, whose fractions of seconds are in the given number of
+/// nanoseconds.
+fn find_pcap_packets(source: &mut ByteSource, big_endian: bool, fraction_nanos: u64, progress: &Progress)
+    -> Result<Vec<Packet>> {
+    let mut packets = Vec::new();
+    let mut offset = 24;
+    let mut header = [0; 16];
+    while read_header(source, offset, &mut header)? {
+        let seconds = number(&header[0..4], big_endian);
+        let fraction = number(&header[4..8], big_endian);
+        let length = number(&header[8..12], big_endian);
+        let original_length = number(&header[12..16], big_endian);
+        packets.push(Packet {
+            timestamp: Some(seconds * 1_000_000_000 + fraction * fraction_nanos),
+            offset: offset + 16,
+            length,
+            original_length
+        });
+        offset += 16 + length;
+        progress.advance(16 + length)?;
+    }
+    Ok(packets)
+}
+
+/// The resolution of the timestamps of an interface, in nanoseconds per tick, from its
+/// description block.
+fn interface_resolution(body: &[u8], big_endian: bool) -> f64 {
+    let mut pos = 8;
+    while pos + 4 <= body.len() {
+        let code = number(&body[pos..pos + 2], big_endian);
+        let length = number(&body[pos + 2..pos + 4], big_endian) as usize;
+        if code == 0 || pos + 4 + length > body.len() {
+            break;
+        }
+        if code == IF_TSRESOL && length == 1 {
+            let value = body[pos + 4];
+            return if value & 0x80 == 0 {
+                1e9 / 10f64.powi(i32::from(value))
+            } else {
+                1e9 / 2f64.powi(i32::from(value & 0x7F))
+            };
+        }
+        pos += 4 + ((length + 3) & !3);
+    }
+    // Microseconds, unless the interface says otherwise.
+    1000.0
+}
+
+fn find_pcapng_packets(source: &mut ByteSource, progress: &Progress) -> Result<Vec<Packet>> {
+    let mut packets = Vec::new();
+    let mut resolutions = Vec::new();
+    let mut big_endian = false;
+    let mut offset = 0;
+    let mut header = [0; 12];
+    while read_header(source, offset, &mut header)? {
+        let block_type = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        if block_type == PCAPNG_SECTION_HEADER {
+            // Every section has its own byte order and interfaces.
+            big_endian = number(&header[8..12], true) == u64::from(PCAPNG_BYTE_ORDER_MAGIC);
+            resolutions.clear();
+        }
+        let block_type = number(&header[0..4], big_endian) as u32;
+        let block_length = number(&header[4..8], big_endian);
+        if block_length < 12 {
+            return Err(damaged());
+        }
+        let body_length = block_length - 12;
+        let mut body = vec![0; body_length.min(64) as usize];
+        if !read_header(source, offset + 8, &mut body)? {
+            break;
+        }
+        match block_type {
+            PCAPNG_INTERFACE_DESCRIPTION => {
+                // The options are usually short, so read all of them.
+                let mut body = vec![0; body_length.min(4096) as usize];
+                source.read_at(offset + 8, &mut body)?;
+                resolutions.push(interface_resolution(&body, big_endian));
+            },
+            PCAPNG_ENHANCED_PACKET | PCAPNG_OBSOLETE_PACKET if body.len() >= 20 => {
+                let interface = if block_type == PCAPNG_ENHANCED_PACKET {
+                    number(&body[0..4], big_endian)
+                } else {
+                    number(&body[0..2], big_endian)
+                } as usize;
+                let ticks = number(&body[4..8], big_endian) << 32 | number(&body[8..12], big_endian);
+                let timestamp = resolutions.get(interface).map(|resolution| (ticks as f64 * resolution) as u64);
+                packets.push(Packet {
+                    timestamp,
+                    offset: offset + 28,
+                    length: number(&body[12..16], big_endian).min(body_length.saturating_sub(20)),
+                    original_length: number(&body[16..20], big_endian)
+                });
+            },
+            PCAPNG_SIMPLE_PACKET if body.len() >= 4 => {
+                let original_length = number(&body[0..4], big_endian);
+                packets.push(Packet {
+                    timestamp: None,
+                    offset: offset + 12,
+                    length: original_length.min(body_length - 4),
+                    original_length
+                });
+            },
+            _ => {}
+        }
+        offset += block_length;
+        progress.advance(block_length)?;
+    }
+    Ok(packets)
+}
+
+/// Describe the time of a packet, in seconds since the first packet of the capture.
+pub fn describe_time(packet: &Packet, first: &Packet) -> String {
+    match (packet.timestamp, first.timestamp) {
+        (Some(time), Some(start)) => {
+            let nanos = time.saturating_sub(start);
+            format!("{}.{:06}", nanos / 1_000_000_000, nanos % 1_000_000_000 / 1000)
+        },
+        _ => String::from("-")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(bytes: Vec<u8>) -> Option<Vec<Packet>> {
+        find_packets(&mut bytes.clone(), &Progress::new(bytes.len() as u64)).unwrap()
+    }
+
+    #[test]
+    fn finding_pcap_packets() {
+        let mut capture = b"\xD4\xC3\xB2\xA1".to_vec();
+        capture.resize(24, 0);
+        capture.extend_from_slice(&[10, 0, 0, 0, 0x20, 0xA1, 0x07, 0, 3, 0, 0, 0, 60, 0, 0, 0]);
+        capture.extend_from_slice(b"abc");
+        capture.extend_from_slice(&[11, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0]);
+        capture.extend_from_slice(b"de");
+        // A header that was cut short.
+        capture.extend_from_slice(&[12, 0, 0]);
+
+        let packets = find(capture).unwrap();
+        assert_eq!(packets, vec![
+            Packet { timestamp: Some(10_500_000_000), offset: 40, length: 3, original_length: 60 },
+            Packet { timestamp: Some(11_000_000_000), offset: 59, length: 2, original_length: 2 }
+        ]);
+        assert_eq!(describe_time(&packets[1], &packets[0]), "0.500000");
+        assert_eq!(find(b"Hello, World!".to_vec()), None);
+    }
+
+    #[test]
+    fn finding_pcapng_packets() {
+        let mut capture = Vec::new();
+        // The section header, with the byte order magic and the version.
+        capture.extend_from_slice(&[0x0A, 0x0D, 0x0D, 0x0A, 28, 0, 0, 0, 0x4D, 0x3C, 0x2B, 0x1A, 1, 0, 0, 0]);
+        capture.extend_from_slice(&[0xFF; 8]);
+        capture.extend_from_slice(&[28, 0, 0, 0]);
+        // An interface with timestamps in milliseconds.
+        capture.extend_from_slice(&[1, 0, 0, 0, 32, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        capture.extend_from_slice(&[9, 0, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 32, 0, 0, 0]);
+        // An enhanced packet of 3 bytes, at 1500 milliseconds.
+        capture.extend_from_slice(&[6, 0, 0, 0, 36, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xDC, 5, 0, 0]);
+        capture.extend_from_slice(&[3, 0, 0, 0, 3, 0, 0, 0, b'a', b'b', b'c', 0, 36, 0, 0, 0]);
+        // A simple packet of 2 bytes.
+        capture.extend_from_slice(&[3, 0, 0, 0, 20, 0, 0, 0, 2, 0, 0, 0, b'd', b'e', 0, 0, 20, 0, 0, 0]);
+
+        assert_eq!(find(capture).unwrap(), vec![
+            Packet { timestamp: Some(1_500_000_000), offset: 88, length: 3, original_length: 3 },
+            Packet { timestamp: None, offset: 108, length: 2, original_length: 2 }
+        ]);
+    }
+}
@@ -0,0 +1,153 @@
+//! CRC-32, MD5 and SHA-256 checksums of any `ByteSource`, computed in one pass.
+
+use std::fmt::Write;
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use crate::byte_reader::ByteSource;
+use crate::error::{Result, XvError};
+use crate::progress::Progress;
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The checksums that files can be verified with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChecksumKind {
+    Crc32,
+    Md5,
+    Sha256
+}
+
+pub const CHECKSUM_KINDS: [ChecksumKind; 3] = [ChecksumKind::Crc32, ChecksumKind::Md5, ChecksumKind::Sha256];
+
+impl ChecksumKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ChecksumKind::Crc32 => "CRC-32",
+            ChecksumKind::Md5 => "MD5",
+            ChecksumKind::Sha256 => "SHA-256"
+        }
+    }
+
+    /// The kind of checksum that has the given number of bytes.
+    pub fn from_size(size: usize) -> Result<ChecksumKind> {
+        CHECKSUM_KINDS.iter().cloned()
+            .find(|kind| kind.size() == size)
+            .ok_or_else(|| XvError::Input(String::from("This is not a CRC-32, MD5 or SHA-256 checksum.")))
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            ChecksumKind::Crc32 => 4,
+            ChecksumKind::Md5 => 16,
+            ChecksumKind::Sha256 => 32
+        }
+    }
+
+    /// Start computing a checksum of this kind.
+    pub fn checksum(self) -> Checksum {
+        match self {
+            ChecksumKind::Crc32 => Checksum::Crc32(crc32fast::Hasher::new()),
+            ChecksumKind::Md5 => Checksum::Md5(Md5::new()),
+            ChecksumKind::Sha256 => Checksum::Sha256(Sha256::new())
+        }
+    }
+}
+
+/// A checksum that is being computed. Feed it all the bytes with `update`, and get the result with
+/// `finish`.
+#[derive(Clone, Debug)]
+pub enum Checksum {
+    Crc32(crc32fast::Hasher),
+    Md5(Md5),
+    Sha256(Sha256)
+}
+
+impl Checksum {
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Checksum::Crc32(crc) => crc.update(bytes),
+            Checksum::Md5(hash) => hash.update(bytes),
+            Checksum::Sha256(hash) => hash.update(bytes)
+        }
+    }
+
+    /// The checksum as bytes, in the order they are usually written in.
+    pub fn finish(self) -> Vec<u8> {
+        match self {
+            Checksum::Crc32(crc) => crc.finalize().to_be_bytes().to_vec(),
+            Checksum::Md5(hash) => hash.finalize().to_vec(),
+            Checksum::Sha256(hash) => hash.finalize().to_vec()
+        }
+    }
+}
+
+/// Compute checksums of `length` bytes of the source, starting at `offset`. The bytes are only
+/// read once, however many kinds of checksums are computed. Fewer bytes are checksummed if the
+/// source ends before the range does.
+pub fn compute_checksums(kinds: &[ChecksumKind], source: &mut ByteSource, offset: u64, length: u64,
+                         progress: &Progress) -> Result<Vec<Vec<u8>>> {
+    let mut checksums: Vec<Checksum> = kinds.iter().map(|kind| kind.checksum()).collect();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut done = 0;
+    while done < length {
+        let len = (length - done).min(CHUNK_SIZE as u64) as usize;
+        let read = source.read_at(offset + done, &mut buf[0..len])?;
+        if read == 0 {
+            break;
+        }
+        for checksum in &mut checksums {
+            checksum.update(&buf[0..read]);
+        }
+        done += read as u64;
+        progress.advance(read as u64)?;
+    }
+    Ok(checksums.into_iter().map(Checksum::finish).collect())
+}
+
+/// Write bytes as lowercase hex digits, the way checksums are usually written.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        write!(hex, "{:02x}", b).unwrap();
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex_checksum(kind: ChecksumKind, parts: &[&[u8]]) -> String {
+        let mut checksum = kind.checksum();
+        for part in parts {
+            checksum.update(part);
+        }
+        to_hex(&checksum.finish())
+    }
+
+    #[test]
+    fn computing_checksums() {
+        assert_eq!(hex_checksum(ChecksumKind::Crc32, &[b"1234", b"56789"]), "cbf43926");
+        assert_eq!(hex_checksum(ChecksumKind::Md5, &[]), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex_checksum(ChecksumKind::Md5, &[b"The quick brown fox ", b"jumps over the lazy dog"]),
+                   "9e107d9d372bb6826bd81d3542a419d6");
+        assert_eq!(hex_checksum(ChecksumKind::Sha256, &[b"abc"]),
+                   "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(hex_checksum(ChecksumKind::Sha256,
+                                &[b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"]),
+                   "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    }
+
+    #[test]
+    fn checksumming_part_of_a_source() {
+        let mut source: Vec<u8> = b"xx123456789".to_vec();
+        let kinds = [ChecksumKind::Crc32, ChecksumKind::Md5];
+        let checksums = compute_checksums(&kinds, &mut source, 2, 100, &Progress::new(100)).unwrap();
+        assert_eq!(to_hex(&checksums[0]), "cbf43926");
+        assert_eq!(to_hex(&checksums[1]), "25f9e794323b453885f5181f1b624d0b");
+        assert_eq!(ChecksumKind::from_size(checksums[1].len()).unwrap(), ChecksumKind::Md5);
+        assert!(ChecksumKind::from_size(5).is_err());
+    }
+}
@@ -0,0 +1,747 @@
+//! The command line, that gives access to every feature by name, including the ones that have no
+//! key. Several commands can be given at once, separated by `;`.
+
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+use cursive::Cursive;
+use cursive::event::{Event, EventResult, Key};
+use cursive::traits::{Boxable, Identifiable};
+use cursive::view::View;
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
+use xv::byte_reader::{TileConfig, MIN_TILE_SIZE};
+use xv::error::{Result, XvError};
+use xv::hex_reader::{DisplayTransform, SearchOptions, TextRendering};
+
+use crate::address_map_dialog::open_address_map_dialog;
+use crate::annotations_dialog::open_annotations_dialog;
+use crate::block_indexing::{build_index, build_search_index};
+use crate::bitfield_dialog::open_bitfield_dialog;
+use crate::calculator_dialog::open_calculator_dialog;
+use crate::compare_dialog::open_compare_dialog;
+use crate::core_dump_dialog::open_core_dump_dialog;
+use crate::disassembler_dialog::open_disassembler_dialog;
+use crate::duplicates_dialog::open_duplicates_dialog;
+use crate::export_dialog::{import_records, open_export_dialog};
+use crate::file_info_dialog::open_file_info_dialog;
+use crate::memory_dialog::open_memory_dialog;
+use crate::filter_dialog::open_filter_dialog;
+use crate::goto_dialog::open_goto_dialog;
+use crate::group_separators::SEPARATORS;
+use crate::help_text::show_help;
+use crate::hex_view::{HexView, PANES};
+use crate::inspector_dialog::open_inspector_dialog;
+use crate::legend_dialog::open_legend_dialog;
+use crate::open_file_dialog::{open_file_dialog, open_path};
+use crate::overview_view::open_overview;
+use crate::packets_dialog::open_packets_dialog;
+use crate::peek_dialog::{open_peek_dialog, peek, DEFAULT_PEEK_LENGTH};
+use crate::points_dialog::open_points_dialog;
+use crate::record_dialog::open_record_dialog;
+use crate::scripts::{run_script_file, SCRIPT_KEYS};
+use crate::search_dialog::{open_search_dialog, search_again};
+use crate::set_width_dialog::open_set_width_dialog;
+use crate::stats_dialog::open_stats_dialog;
+use crate::split_view::{close_split_view, lock_split_view, open_split_view, unlock_split_view};
+use crate::status_bar::{speak_line, update_measure};
+use crate::strings_panel::toggle_strings_panel;
+use crate::switch_file_dialog::switch_file_dialog;
+use crate::symbols_dialog::{open_jump_to_symbol_dialog, open_symbols_dialog};
+use crate::typed_array_dialog::open_typed_array_dialog;
+use crate::utilities::{expand_path, get_content, parse_address, parse_hex_bytes, parse_number};
+use crate::verify_dialog::open_verify_dialog;
+use crate::workspace_dialog::{open_load_workspace_dialog, open_save_workspace_dialog};
+use crate::xv_state::{XvState, DISPLAY_PROFILES};
+use crate::xv_tui::{change_group_separators, change_theme, quit, set_column_guides, set_display_profile,
+                    set_max_fps, set_memory_budget, set_pages, set_panes, set_screen_reader, set_stripes,
+                    toggle_full_screen, toggle_presentation, ShowError};
+
+/// What a command does.
+enum Action {
+    /// Send a key to the hex view. The command can be given a repeat count.
+    View(Event),
+    /// Run a callback, that usually opens a dialog.
+    Callback(fn(&mut Cursive)),
+    /// Run a function with the arguments of the command.
+    Run(fn(&mut Cursive, &[String]) -> Result<()>)
+}
+
+/// All the commands, in the order they are completed in.
+const COMMANDS: &[(&str, Action)] = &[
+    ("goto", Action::Run(goto)),
+    ("peek", Action::Run(peek_at)),
+    ("set", Action::Run(set)),
+    ("search", Action::Run(search)),
+    ("find", Action::Run(find)),
+    ("open", Action::Run(open)),
+    ("mark", Action::Run(mark)),
+    ("next", Action::View(Event::Char('n'))),
+    ("previous", Action::View(Event::Char('N'))),
+    ("up", Action::View(Event::Key(Key::Up))),
+    ("down", Action::View(Event::Key(Key::Down))),
+    ("left", Action::View(Event::Key(Key::Left))),
+    ("right", Action::View(Event::Key(Key::Right))),
+    ("page-up", Action::View(Event::Key(Key::PageUp))),
+    ("page-down", Action::View(Event::Key(Key::PageDown))),
+    ("line-start", Action::View(Event::Key(Key::Home))),
+    ("line-end", Action::View(Event::Key(Key::End))),
+    ("scroll-left", Action::View(Event::Char('<'))),
+    ("scroll-right", Action::View(Event::Char('>'))),
+    ("start", Action::View(Event::Ctrl(Key::Home))),
+    ("end", Action::View(Event::Ctrl(Key::End))),
+    ("select", Action::View(Event::CtrlChar(' '))),
+    ("exchange-mark", Action::View(Event::CtrlChar('x'))),
+    ("clear-selection", Action::View(Event::CtrlChar('g'))),
+    ("select-columns", Action::View(Event::CtrlChar('r'))),
+    ("visual", Action::View(Event::Char('v'))),
+    ("bits", Action::View(Event::Char('B'))),
+    ("reload", Action::View(Event::Char('r'))),
+    ("reopen", Action::View(Event::Char('R'))),
+    ("high-entropy", Action::View(Event::Char('e'))),
+    ("low-entropy", Action::View(Event::Char('E'))),
+    ("skip-run", Action::View(Event::Char('x'))),
+    ("skip-zeros", Action::View(Event::Char('z'))),
+    ("skip-to-text", Action::View(Event::Char('p'))),
+    ("next-point", Action::View(Event::Char('}'))),
+    ("previous-point", Action::View(Event::Char('{'))),
+    ("width", Action::Callback(open_set_width_dialog)),
+    ("records", Action::Callback(open_record_dialog)),
+    ("switch", Action::Callback(switch_file_dialog)),
+    ("duplicates", Action::Callback(open_duplicates_dialog)),
+    ("stats", Action::Callback(open_stats_dialog)),
+    ("overview", Action::Callback(open_overview)),
+    ("index", Action::Callback(build_index)),
+    ("search-index", Action::Callback(build_search_index)),
+    ("bitfield", Action::Callback(open_bitfield_dialog)),
+    ("array", Action::Callback(open_typed_array_dialog)),
+    ("inspector", Action::Callback(open_inspector_dialog)),
+    ("filter", Action::Callback(open_filter_dialog)),
+    ("calculator", Action::Callback(open_calculator_dialog)),
+    ("compare", Action::Callback(open_compare_dialog)),
+    ("checksum", Action::Callback(open_verify_dialog)),
+    ("export", Action::Callback(open_export_dialog)),
+    ("import-records", Action::Run(import)),
+    ("script", Action::Run(script)),
+    ("bind-script", Action::Run(bind_script)),
+    ("annotations", Action::Callback(open_annotations_dialog)),
+    ("address-map", Action::Callback(open_address_map_dialog)),
+    ("symbols", Action::Callback(open_symbols_dialog)),
+    ("jump-to-symbol", Action::Callback(open_jump_to_symbol_dialog)),
+    ("decode", Action::Callback(open_disassembler_dialog)),
+    ("core-dump", Action::Callback(open_core_dump_dialog)),
+    ("packets", Action::Callback(open_packets_dialog)),
+    ("points", Action::Callback(open_points_dialog)),
+    ("strings", Action::Callback(toggle_strings_panel)),
+    ("split", Action::Run(split)),
+    ("scroll-lock", Action::Run(scroll_lock)),
+    ("info", Action::Callback(open_file_info_dialog)),
+    ("memory", Action::Callback(open_memory_dialog)),
+    ("save-workspace", Action::Callback(open_save_workspace_dialog)),
+    ("load-workspace", Action::Callback(open_load_workspace_dialog)),
+    ("theme", Action::Callback(change_theme)),
+    ("legend", Action::Callback(open_legend_dialog)),
+    ("profile", Action::Run(profile)),
+    ("follow", Action::Run(follow)),
+    ("separator", Action::Run(separator)),
+    ("transform", Action::Run(transform)),
+    ("text", Action::Run(text)),
+    ("pane", Action::Run(pane)),
+    ("full-screen", Action::Callback(toggle_full_screen)),
+    ("presentation", Action::Callback(toggle_presentation)),
+    ("screen-reader", Action::Run(screen_reader)),
+    ("speak-line", Action::Callback(speak_line)),
+    ("help", Action::Callback(show_help)),
+    ("quit", Action::Callback(quit))
+];
+
+const TRANSFORMS: [&str; 3] = ["swap-nibbles", "reverse-bits", "reverse-groups"];
+
+/// The options of the `text` command.
+const TEXT_RENDERINGS: [&str; 4] = ["caret", "distinct", "fold", "utf8"];
+
+/// The settings of the `set` command.
+const SETTINGS: [&str; 13] = ["width", "group", "skip", "show", "digits", "base", "stripes", "guides", "tile-size",
+                              "read-ahead", "memory", "fps", "pages"];
+
+/// The highest frame rate that can be set, which is beyond what terminals keep up with.
+const MAX_FPS: u64 = 240;
+
+/// The most pages that can be shown side by side.
+const MAX_PAGES: u64 = 8;
+
+/// The largest tiles, and the most tiles read ahead, that can be set, which keep a read from
+/// taking too long. Tiles can be no smaller than `MIN_TILE_SIZE`, or 0 to read without tiles.
+const MAX_TILE_SIZE: u64 = 16 * 1024 * 1024;
+const MAX_READ_AHEAD: u64 = 64;
+
+pub fn open_command_line(s: &mut Cursive) {
+    let command_edit = OnEventView::new(EditView::new()
+        .on_submit(do_commands)
+        .with_id("command_line"))
+        .on_pre_event(Key::Tab, complete_command_line);
+
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new(":"))
+            .child(command_edit.min_width(50)))
+        .child(TextView::new("").with_id("command_completions"));
+
+    let dialog = Dialog::around(layout)
+        .title("Command");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+fn do_commands(s: &mut Cursive, line: &str) {
+    let line = line.to_string();
+    s.pop_layer();
+    if let Err(error) = run_commands(s, &line) {
+        s.show_error(error);
+    }
+}
+
+/// Run the commands in the line, one after the other, until one of them fails.
+pub fn run_commands(s: &mut Cursive, line: &str) -> Result<()> {
+    for words in parse_commands(line)? {
+        let (name, args) = words.split_first().unwrap();
+        let (_, action) = COMMANDS.iter().find(|(command, _)| command == name)
+            .ok_or_else(|| XvError::Input(format!("There is no {} command.", name)))?;
+        match action {
+            Action::View(ref event) => {
+                let count = match args {
+                    [] => 1,
+                    [count] => parse_number(count)?,
+                    _ => return Err(too_many_arguments(name))
+                };
+                send_to_view(s, count, event)
+            },
+            Action::Callback(callback) if args.is_empty() => callback(s),
+            Action::Callback(_) => return Err(too_many_arguments(name)),
+            Action::Run(run) => run(s, args)?
+        }
+    }
+    Ok(())
+}
+
+fn too_many_arguments(name: &str) -> XvError {
+    XvError::Input(format!("Too many arguments for the {} command.", name))
+}
+
+fn send_to_view(s: &mut Cursive, count: u64, event: &Event) {
+    let result = s.call_on_id("hex_view", |v: &mut HexView| {
+        if count != 1 {
+            for c in count.to_string().chars() {
+                v.on_event(Event::Char(c));
+            }
+        }
+        v.on_event(event.clone())
+    }).unwrap();
+    if let EventResult::Consumed(Some(callback)) = result {
+        callback(s);
+    }
+}
+
+fn goto(s: &mut Cursive, args: &[String]) -> Result<()> {
+    match args {
+        [] => open_goto_dialog(s),
+        [offset] => s.call_on_id("hex_view", |v: &mut HexView| -> Result<()> {
+            let offset = parse_address(offset, v.get_length(), v.get_address_map())?;
+            v.go_to_offset(offset);
+            Ok(())
+        }).unwrap()?,
+        _ => return Err(too_many_arguments("goto"))
+    }
+    Ok(())
+}
+
+fn set(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let (setting, value) = match args {
+        [] => {
+            open_set_width_dialog(s);
+            return Ok(());
+        },
+        [setting, value] => (setting.as_str(), parse_number(value)?),
+        _ => return Err(XvError::Input(format!("Give a setting and a value, like \"set width 32\". \
+                                                The settings are {}.", SETTINGS.join(", "))))
+    };
+    // These are not settings of the file, and are remembered for all files.
+    match setting {
+        "stripes" if value <= 1 => {
+            set_stripes(s, value == 1);
+            return Ok(());
+        },
+        "stripes" => return Err(XvError::Input(String::from("Set the stripes to 1 to shade every other row, or 0."))),
+        "guides" => {
+            set_column_guides(s, value);
+            return Ok(());
+        },
+        "memory" => {
+            let budget = value.checked_mul(1024 * 1024).filter(|&budget| budget > 0)
+                .ok_or_else(|| XvError::Input(format!("The memory budget cannot be {} MiB.", value)))?;
+            set_memory_budget(s, budget);
+            return Ok(());
+        },
+        "fps" if value <= MAX_FPS => {
+            set_max_fps(s, value);
+            return Ok(());
+        },
+        "fps" => return Err(XvError::Input(format!("The frame rate can be at most {}, or 0 for no limit.", MAX_FPS))),
+        "pages" if value > 0 && value <= MAX_PAGES => {
+            set_pages(s, value as u16);
+            return Ok(());
+        },
+        "pages" => return Err(XvError::Input(format!("Show from 1 to {} pages side by side.", MAX_PAGES))),
+        _ => {}
+    }
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        let (skip, shown) = v.get_line_columns();
+        match setting {
+            "width" if value > 0 => v.set_line_width(value),
+            "group" if value > 0 && value <= u64::from(u16::MAX) => v.set_group(u16::try_from(value).unwrap()),
+            "skip" if value < v.get_line_width() => v.set_line_columns(value, shown),
+            "show" => v.set_line_columns(skip, if value > 0 { Some(value) } else { None }),
+            "digits" if value == 0 => v.set_offset_digits(None),
+            "digits" if value == 8 || value == 16 => v.set_offset_digits(Some(value as u8)),
+            "base" => v.set_base_address(value),
+            "tile-size" if value == 0 || (MIN_TILE_SIZE..=MAX_TILE_SIZE).contains(&value) => {
+                let config = TileConfig { tile_size: value, ..v.get_tile_config() };
+                v.set_tile_config(Some(config));
+            },
+            "read-ahead" if value <= MAX_READ_AHEAD => {
+                let config = TileConfig { read_ahead: value, ..v.get_tile_config() };
+                v.set_tile_config(Some(config));
+            },
+            "width" | "group" | "skip" | "digits" | "tile-size" | "read-ahead" => {
+                return Err(XvError::Input(format!("The {} cannot be {}.", setting, value)));
+            },
+            _ => return Err(XvError::Input(format!("There is no {} setting.", setting)))
+        }
+        Ok(())
+    }).unwrap()
+}
+
+/// Search for hex bytes.
+fn search(s: &mut Cursive, args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        open_search_dialog(s);
+        return Ok(());
+    }
+    let pattern = parse_hex_bytes(&args.join(""))?;
+    start_search(s, pattern);
+    Ok(())
+}
+
+/// Search for text.
+fn find(s: &mut Cursive, args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        open_search_dialog(s);
+        return Ok(());
+    }
+    start_search(s, args.join(" ").into_bytes());
+    Ok(())
+}
+
+fn start_search(s: &mut Cursive, pattern: Vec<u8>) {
+    let options = s.with_user_data(|state: &mut XvState| state.search_settings().options).unwrap();
+    let options = SearchOptions { backwards: false, ..options };
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_search(pattern, options));
+    search_again(s, false);
+}
+
+fn open(s: &mut Cursive, args: &[String]) -> Result<()> {
+    match args {
+        [] => open_file_dialog(s),
+        [path] => open_path(s, expand_path(path).as_os_str()),
+        _ => return Err(too_many_arguments("open"))
+    }
+    Ok(())
+}
+
+/// Convert Intel HEX or S-records to a flat binary, and open it.
+fn import(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let (path, fill) = match args {
+        [path] => (path, 0xFF),
+        [path, fill] => (path, u8::try_from(parse_number(fill)?)
+            .map_err(|_| XvError::Input(String::from("The fill must be a byte.")))?),
+        _ => return Err(XvError::Input(String::from("Give the file to import, and an optional fill byte.")))
+    };
+    import_records(s, &expand_path(path), fill)
+}
+
+/// Show another file below the hex view, or hide it when no file is given.
+fn split(s: &mut Cursive, args: &[String]) -> Result<()> {
+    match args {
+        [] => {
+            close_split_view(s);
+            Ok(())
+        },
+        [path] => open_split_view(s, &expand_path(path)),
+        _ => Err(XvError::Input(String::from("Give the file to show below, or nothing to hide it.")))
+    }
+}
+
+/// Scroll the split view along with the hex view, at the distance it is now or at the given
+/// distance, or stop scrolling it along.
+fn scroll_lock(s: &mut Cursive, args: &[String]) -> Result<()> {
+    match args {
+        [] => lock_split_view(s, None),
+        [off] if off == "off" => unlock_split_view(s),
+        [delta] => {
+            let (negative, delta) = match delta.strip_prefix('-') {
+                Some(delta) => (true, delta),
+                None => (false, delta.as_str())
+            };
+            let delta = i64::try_from(parse_number(delta)?)
+                .map_err(|_| XvError::Input(String::from("The distance is too far.")))?;
+            lock_split_view(s, Some(if negative { -delta } else { delta }))
+        },
+        _ => Err(too_many_arguments("scroll-lock"))
+    }
+}
+
+fn script(s: &mut Cursive, args: &[String]) -> Result<()> {
+    match args {
+        [path] => run_script_file(s, &expand_path(path)),
+        _ => Err(XvError::Input(String::from("Give the script to run.")))
+    }
+}
+
+/// Bind a script to a function key, or remove the binding when no script is given.
+fn bind_script(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let keys: Vec<&str> = SCRIPT_KEYS.iter().map(|(name, _)| *name).collect();
+    let (key, path) = match args {
+        [key] => (key, None),
+        [key, path] => (key, Some(expand_path(path))),
+        _ => return Err(XvError::Input(format!("Give one of the keys {}, and a script.", keys.join(", "))))
+    };
+    let key = key.to_uppercase();
+    if !keys.contains(&key.as_str()) {
+        return Err(XvError::Input(format!("Scripts can only be bound to {}.", keys.join(", "))));
+    }
+    if let Some(path) = &path {
+        fs::metadata(path)?;
+    }
+    s.with_user_data(|state: &mut XvState| state.bind_script(&key, path));
+    Ok(())
+}
+
+/// Choose how the dialogs and the bytes are told apart: by colors, by colors that are easy to tell
+/// apart with color blindness, or without colors.
+fn profile(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let names: Vec<&str> = DISPLAY_PROFILES.iter().map(|profile| profile.name()).collect();
+    let profile = match args {
+        [name] => DISPLAY_PROFILES.iter().find(|profile| profile.name() == name.as_str()).cloned(),
+        _ => None
+    };
+    match profile {
+        Some(profile) => {
+            set_display_profile(s, profile);
+            Ok(())
+        },
+        None => Err(XvError::Input(format!("Give one of the display profiles: {}.", names.join(", "))))
+    }
+}
+
+/// Choose what the group boundaries are shown as, or, with a number of bytes, what the boundaries
+/// of larger groups of that many bytes are shown as.
+fn separator(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let names: Vec<&str> = SEPARATORS.iter().map(|separator| separator.name()).collect();
+    let (name, every) = match args {
+        [name] => (name.as_str(), None),
+        [name, every] => (name.as_str(), Some(parse_number(every)?)),
+        _ => return Err(XvError::Input(format!("Give one of the separators, {}, and an optional number of bytes \
+                                                for larger groups.", names.join(", "))))
+    };
+    let separator = SEPARATORS.iter().find(|separator| separator.name() == name).cloned();
+    match (separator, every) {
+        (Some(separator), None) => change_group_separators(s, |separators| separators.set_groups(separator)),
+        (None, Some(every)) if name == "off" => {
+            change_group_separators(s, |separators| separators.set_level(every, None));
+        },
+        (_, Some(every)) if every == 0 || every % u64::from(get_group(s)) != 0 => {
+            return Err(XvError::Input(String::from("The larger groups must be a whole number of groups.")));
+        },
+        (Some(separator), Some(every)) => {
+            change_group_separators(s, |separators| separators.set_level(every, Some(separator)));
+        },
+        (None, _) => return Err(XvError::Input(format!("There is no {} separator. The separators are {}.",
+                                                       name, names.join(", "))))
+    }
+    Ok(())
+}
+
+fn get_group(s: &mut Cursive) -> u16 {
+    s.call_on_id("hex_view", |v: &mut HexView| v.get_group()).unwrap()
+}
+
+/// Go to the end, and stay at the end as bytes are added to a growing source, or stop.
+fn peek_at(s: &mut Cursive, args: &[String]) -> Result<()> {
+    match args {
+        [] => {
+            open_peek_dialog(s);
+            Ok(())
+        },
+        [offset] => peek(s, offset, DEFAULT_PEEK_LENGTH),
+        [offset, length] => peek(s, offset, parse_number(length)?),
+        _ => Err(too_many_arguments("peek"))
+    }
+}
+
+fn follow(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let following = match args {
+        [] => !s.call_on_id("hex_view", |v: &mut HexView| v.is_following()).unwrap(),
+        [value] if value == "on" => true,
+        [value] if value == "off" => false,
+        _ => return Err(XvError::Input(String::from("Give on or off, or nothing to toggle following the end.")))
+    };
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_following(following));
+    Ok(())
+}
+
+/// Show the bytes transformed, or as they are when no transforms are given.
+fn transform(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let mut transform = DisplayTransform::default();
+    for arg in args {
+        match arg.as_str() {
+            "swap-nibbles" => transform.swap_nibbles = true,
+            "reverse-bits" => transform.reverse_bits = true,
+            "reverse-groups" => transform.reverse_groups = true,
+            _ => return Err(XvError::Input(format!("There is no {} transform. The transforms are {}.",
+                                                   arg, TRANSFORMS.join(", "))))
+        }
+    }
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_transform(transform));
+    Ok(())
+}
+
+/// Render the text of the visual column with the given options, in the visual mode it is in, or
+/// plainly without any.
+fn text(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let mut rendering = TextRendering::default();
+    for arg in args {
+        match arg.as_str() {
+            "caret" => rendering.caret = true,
+            "distinct" => rendering.distinct = true,
+            "fold" => rendering.fold = true,
+            "utf8" => rendering.utf8 = true,
+            _ => return Err(XvError::Input(format!("There is no {} text option. The options are {}.",
+                                                   arg, TEXT_RENDERINGS.join(", "))))
+        }
+    }
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_text_rendering(rendering));
+    Ok(())
+}
+
+/// Show or hide the offsets, hex or visual column, or toggle it.
+fn pane(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let mut panes = s.call_on_id("hex_view", |v: &mut HexView| v.get_panes()).unwrap();
+    let (name, on) = match args {
+        [name] => (name.as_str(), None),
+        [name, value] if value == "on" => (name.as_str(), Some(true)),
+        [name, value] if value == "off" => (name.as_str(), Some(false)),
+        _ => return Err(XvError::Input(format!("Give a column, and on or off, or nothing to toggle it. \
+                                                The columns are {}.", PANES.join(", "))))
+    };
+    let shown = match name {
+        "offsets" => &mut panes.offsets,
+        "hex" => &mut panes.hex,
+        "visual" => &mut panes.visual,
+        _ => return Err(XvError::Input(format!("There is no {} column. The columns are {}.", name, PANES.join(", "))))
+    };
+    *shown = on.unwrap_or(!*shown);
+    set_panes(s, panes);
+    Ok(())
+}
+
+/// Turn the screen reader mode on or off, or toggle it.
+fn screen_reader(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let on = match args {
+        [] => !s.call_on_id("hex_view", |v: &mut HexView| v.get_screen_reader()).unwrap(),
+        [value] if value == "on" => true,
+        [value] if value == "off" => false,
+        _ => return Err(XvError::Input(String::from("Give on or off, or nothing to toggle the screen reader mode.")))
+    };
+    set_screen_reader(s, on);
+    Ok(())
+}
+
+/// Set mark A or B at an offset, or at the current position.
+fn mark(s: &mut Cursive, args: &[String]) -> Result<()> {
+    let (which, offset) = match args {
+        [which] => (which.as_str(), None),
+        [which, offset] => (which.as_str(), Some(offset)),
+        _ => return Err(XvError::Input(String::from("Give the mark to set, a or b, and an optional offset.")))
+    };
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        let offset = match offset {
+            Some(offset) => parse_address(offset, v.get_length(), v.get_address_map())?,
+            None => v.get_offset()
+        };
+        let (a, b) = v.get_marks();
+        match which {
+            "a" => v.set_marks((Some(offset), b)),
+            "b" => v.set_marks((a, Some(offset))),
+            _ => return Err(XvError::Input(format!("There is no mark {}, only a and b.", which)))
+        }
+        Ok(())
+    }).unwrap()?;
+    update_measure(s);
+    Ok(())
+}
+
+/// Split a line into commands at the `;` characters, and the commands into words. Words that
+/// contain spaces or `;` can be put in double quotes.
+fn parse_commands(line: &str) -> Result<Vec<Vec<String>>> {
+    let mut commands = Vec::new();
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            },
+            c if quoted => word.get_or_insert_with(String::new).push(c),
+            ';' => {
+                words.extend(word.take());
+                if !words.is_empty() {
+                    commands.push(words);
+                    words = Vec::new();
+                }
+            },
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c)
+        }
+    }
+    if quoted {
+        return Err(XvError::Parse(String::from("A quote is not closed.")));
+    }
+    words.extend(word.take());
+    if !words.is_empty() {
+        commands.push(words);
+    }
+    Ok(commands)
+}
+
+fn complete_command_line(s: &mut Cursive) {
+    let line = s.call_on_id("command_line", get_content).unwrap();
+    let base = s.with_user_data(|state: &mut XvState| state.current_directory().to_path_buf()).unwrap();
+    let (start, candidates) = completions(&line, &base);
+    let completion = match candidates.len() {
+        0 => return,
+        1 if candidates[0].ends_with('/') => candidates[0].clone(),
+        1 => format!("{} ", candidates[0]),
+        _ => common_prefix(&candidates)
+    };
+    let shown = if candidates.len() > 1 { candidates.join("  ") } else { String::new() };
+    let completed = format!("{}{}", &line[0..start], completion);
+    s.call_on_id("command_line", |v: &mut EditView| v.set_content(completed));
+    s.call_on_id("command_completions", |v: &mut TextView| v.set_content(shown));
+}
+
+/// The ways to finish the last word of the line, and where that word starts. Command names,
+/// settings and marks are completed, and so are file names for the `open` command.
+fn completions(line: &str, base: &Path) -> (usize, Vec<String>) {
+    let command_start = line.rfind(';').map_or(0, |i| i + 1);
+    let start = line.char_indices().rev().find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8())
+        .max(command_start);
+    let word = &line[start..];
+    let previous: Vec<&str> = line[command_start..start].split_whitespace().collect();
+    let choices: Vec<&str> = match previous.as_slice() {
+        [] => COMMANDS.iter().map(|(command, _)| *command).collect(),
+        ["set"] => SETTINGS.to_vec(),
+        ["mark"] => vec!["a", "b"],
+        ["screen-reader"] | ["follow"] | ["pane", _] => vec!["on", "off"],
+        ["pane"] => PANES.to_vec(),
+        ["separator"] => SEPARATORS.iter().map(|separator| separator.name()).chain(Some("off")).collect(),
+        ["profile"] => DISPLAY_PROFILES.iter().map(|profile| profile.name()).collect(),
+        ["open"] | ["import-records"] => return (start, complete_path(word, base)),
+        _ if previous.first() == Some(&"transform") => TRANSFORMS.to_vec(),
+        _ if previous.first() == Some(&"text") => TEXT_RENDERINGS.to_vec(),
+        _ => Vec::new()
+    };
+    let candidates = choices.into_iter()
+        .filter(|choice| choice.starts_with(word))
+        .map(String::from)
+        .collect();
+    (start, candidates)
+}
+
+/// The files and directories that a partly typed path could be. Directories end with a `/`.
+fn complete_path(word: &str, base: &Path) -> Vec<String> {
+    let (dir, prefix) = match word.rfind('/') {
+        Some(i) => (&word[0..=i], &word[i + 1..]),
+        None => ("", word)
+    };
+    let entries = match fs::read_dir(base.join(expand_path(dir))) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new()
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let mut prefix = words[0].as_str();
+    for word in &words[1..] {
+        while !word.starts_with(prefix) {
+            prefix = &prefix[0..prefix.char_indices().last().map_or(0, |(i, _)| i)];
+        }
+    }
+    String::from(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(commands: &[&[&str]]) -> Vec<Vec<String>> {
+        commands.iter().map(|words| words.iter().map(|word| String::from(*word)).collect()).collect()
+    }
+
+    #[test]
+    fn parsing_commands() {
+        assert_eq!(parse_commands("goto 0x400; set width 32").unwrap(),
+                   words(&[&["goto", "0x400"], &["set", "width", "32"]]));
+        assert_eq!(parse_commands("  find \"a; b\"  ;; next ").unwrap(),
+                   words(&[&["find", "a; b"], &["next"]]));
+        assert_eq!(parse_commands("open \"\"").unwrap(), words(&[&["open", ""]]));
+        assert!(parse_commands("").unwrap().is_empty());
+        assert!(parse_commands("find \"abc").is_err());
+    }
+
+    #[test]
+    fn completing_commands() {
+        let base = Path::new("/");
+        assert_eq!(completions("go", base), (0, vec![String::from("goto")]));
+        assert_eq!(completions("next; s", base).0, 6);
+        assert_eq!(completions("next; s", base).1,
+                   vec!["set", "search", "scroll-left", "scroll-right", "start", "select", "select-columns",
+                        "skip-run", "skip-zeros", "skip-to-text", "switch", "stats", "search-index", "script",
+                        "symbols", "strings", "split", "scroll-lock", "save-workspace", "separator",
+                        "screen-reader", "speak-line"]);
+        assert_eq!(completions("set w", base), (4, vec![String::from("width")]));
+        assert_eq!(completions("set\u{3000}w", base), (6, vec![String::from("width")]));
+        assert_eq!(completions("goto 0x", base), (5, Vec::new()));
+        assert_eq!(common_prefix(&[String::from("skip-run"), String::from("skip-zeros")]), "skip-");
+    }
+}
@@ -0,0 +1,257 @@
+//! Comparing the file with another file, or two files with a common base, and listing the
+//! differences.
+
+use std::fs;
+use std::fs::File;
+use std::ops::Range;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::theme::{BaseColor, Color};
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, ScrollView, SelectView, TextView};
+use xv::diff::{diff, diff_three_way, Difference, DifferenceKind, ThreeWayDifference, ThreeWayKind};
+use xv::byte_reader::ByteSource;
+use xv::error::{Result, XvError};
+use xv::progress::Progress;
+
+use crate::annotations::Annotation;
+use crate::hex_view::HexView;
+use crate::progress_dialog::run_with_progress;
+use crate::utilities::{expand_path, get_content, height_left, parse_number};
+use crate::xv_state::XvState;
+use crate::xv_tui::ShowError;
+
+const MAX_COMPARED_SIZE: u64 = 256 * 1024 * 1024;
+const MAX_REPORTED_ITEMS: usize = 1000;
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
+pub fn open_compare_dialog(s: &mut Cursive) {
+    let current_dir = s.with_user_data(|state: &mut XvState| {
+        state.current_directory().to_path_buf()
+    }).unwrap();
+
+    let editors = LinearLayout::vertical()
+        .child(EditView::new()
+            .content(format!("{}", current_dir.display()))
+            .with_id("compare_path")
+            .min_width(40))
+        .child(EditView::new()
+            .with_id("compare_base_path")
+            .min_width(40))
+        .child(EditView::new()
+            .content("32")
+            .with_id("compare_block_size")
+            .min_width(8));
+
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Compare with:  \nCommon base:  \nBlock size:  "))
+            .child(editors))
+        .child(TextView::new("\nWith a common base, the changes that this file and the other\n\
+                              file made to it are compared, and conflicts are shown."));
+
+    let dialog = Dialog::around(layout)
+        .title("Compare with file")
+        .dismiss_button("Cancel")
+        .button("Compare", do_compare);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_compare);
+
+    s.add_layer(event_view)
+}
+
+fn do_compare(s: &mut Cursive) {
+    let path_str = s.call_on_id("compare_path", get_content).unwrap();
+    let base_path_str = s.call_on_id("compare_base_path", get_content).unwrap();
+    let block_size_str = s.call_on_id("compare_block_size", get_content).unwrap();
+
+    s.pop_layer();
+
+    let block_size = match parse_number(&block_size_str) {
+        Ok(size) if size > 0 => size as usize,
+        Ok(_) => return s.show_error(XvError::Input(String::from("The block size must be more than zero."))),
+        Err(error) => return s.show_error(error)
+    };
+    let other_path = expand_path(&path_str);
+    let other_length = match fs::metadata(&other_path) {
+        Ok(metadata) => metadata.len(),
+        Err(error) => return s.show_error(error)
+    };
+    let base = if base_path_str.trim().is_empty() {
+        None
+    } else {
+        let base_path = expand_path(&base_path_str);
+        match fs::metadata(&base_path) {
+            Ok(metadata) => Some((base_path, metadata.len())),
+            Err(error) => return s.show_error(error)
+        }
+    };
+    let base_length = base.as_ref().map_or(0, |(_, length)| *length);
+    let length = s.call_on_id("hex_view", |v: &mut HexView| v.get_length()).unwrap();
+    if length.max(other_length).max(base_length) > MAX_COMPARED_SIZE {
+        return s.show_error(XvError::Input(
+            format!("Only files of up to {} bytes can be compared.", MAX_COMPARED_SIZE)));
+    }
+
+    let source = s.call_on_id("hex_view", |v: &mut HexView| {
+        v.open_background_source(0, length)
+    }).unwrap();
+    let mut current_source = match source {
+        Ok((source, _)) => source,
+        Err(error) => return s.show_error(error)
+    };
+
+    if let Some((base_path, base_length)) = base {
+        // The three files are read, and then this file and the other file are gone through again,
+        // to find their differences from the base file.
+        let total = base_length + 2 * length + 2 * other_length;
+        let compare = move |progress: &Progress| {
+            let mine = read_all(current_source.as_mut(), length, progress)?;
+            let theirs = read_all(&mut File::open(&other_path)?, other_length, progress)?;
+            let base = read_all(&mut File::open(&base_path)?, base_length, progress)?;
+            diff_three_way(&base, &mine, &theirs, block_size, progress)
+        };
+        return run_with_progress(s, "Comparing", total, compare, show_three_way_differences);
+    }
+
+    // Both files are read, and then the other file is gone through again to find the differences.
+    let total = length + 2 * other_length;
+    let compare = move |progress: &Progress| {
+        let current = read_all(current_source.as_mut(), length, progress)?;
+        let other = read_all(&mut File::open(&other_path)?, other_length, progress)?;
+        diff(&current, &other, block_size, progress)
+    };
+    run_with_progress(s, "Comparing", total, compare, |s, differences| show_differences(s, &differences));
+}
+
+fn read_all(source: &mut ByteSource, length: u64, progress: &Progress) -> Result<Vec<u8>> {
+    let mut bytes = vec![0; length as usize];
+    let mut done = 0;
+    while done < bytes.len() {
+        let end = (done + READ_CHUNK_SIZE).min(bytes.len());
+        let read = source.read_at(done as u64, &mut bytes[done..end])?;
+        if read == 0 {
+            break;
+        }
+        done += read;
+        progress.advance(read as u64)?;
+    }
+    bytes.truncate(done);
+    Ok(bytes)
+}
+
+fn show_differences(s: &mut Cursive, differences: &[Difference]) {
+    let mut result_selector: SelectView<u64> = SelectView::new().on_submit(go_to_difference);
+
+    for difference in differences.iter().take(MAX_REPORTED_ITEMS) {
+        let kind = match difference.kind {
+            DifferenceKind::Changed => "changed",
+            DifferenceKind::Inserted => "inserted",
+            DifferenceKind::Deleted => "deleted"
+        };
+        result_selector.add_item(
+            format!("{:<8}  {}  ->  {}", kind, describe_range(&difference.first),
+                    describe_range(&difference.second)),
+            difference.first.start);
+    }
+
+    let summary = if differences.is_empty() {
+        String::from("The files are the same.")
+    } else {
+        format!("{} differences. Offsets in this file  ->  offsets in the other file.", differences.len())
+    };
+
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(summary))
+        .child(ScrollView::new(result_selector))
+        .max_height(height_left(s, 11).min(50));
+
+    let dialog = Dialog::around(layout)
+        .title("Differences")
+        .dismiss_button("Close");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+/// List the changes that this file and the other file made to the base file, and show them in this
+/// file as annotations, with the conflicts in red.
+fn show_three_way_differences(s: &mut Cursive, differences: Vec<ThreeWayDifference>) {
+    let mut result_selector: SelectView<u64> = SelectView::new().on_submit(go_to_difference);
+    let mut annotations = Vec::new();
+    let mut conflicts = 0;
+
+    for difference in &differences {
+        let (kind, color) = match difference.kind {
+            ThreeWayKind::Mine => ("mine", Color::Dark(BaseColor::Green)),
+            ThreeWayKind::Theirs => ("theirs", Color::Dark(BaseColor::Blue)),
+            ThreeWayKind::Both => ("both", Color::Dark(BaseColor::Yellow)),
+            ThreeWayKind::Conflict => ("conflict", Color::Dark(BaseColor::Red))
+        };
+        if difference.kind == ThreeWayKind::Conflict {
+            conflicts += 1;
+        }
+        if result_selector.len() < MAX_REPORTED_ITEMS {
+            result_selector.add_item(
+                format!("{:<8}  base {}  mine {}  theirs {}", kind, describe_range(&difference.base),
+                        describe_range(&difference.mine), describe_range(&difference.theirs)),
+                difference.mine.start);
+        }
+        annotations.push(Annotation {
+            offset: difference.mine.start,
+            length: difference.mine.end - difference.mine.start,
+            label: format!("Changed by {}", kind),
+            color: Some(color)
+        });
+    }
+
+    let summary = if differences.is_empty() {
+        String::from("Neither file changed the base file.")
+    } else {
+        format!("{} changes, {} of them conflicting. They are shown in this file as annotations:
+                 mine in green, theirs in blue, both in yellow, and conflicts in red.",
+                differences.len(), conflicts)
+    };
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_annotations(annotations));
+
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(summary))
+        .child(ScrollView::new(result_selector))
+        .max_height(height_left(s, 11).min(50));
+
+    let dialog = Dialog::around(layout)
+        .title("Three-way differences")
+        .dismiss_button("Close");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+fn describe_range(range: &Range<u64>) -> String {
+    if range.start == range.end {
+        format!("at 0x{:X}", range.start)
+    } else {
+        format!("0x{:X}..0x{:X} ({} bytes)", range.start, range.end, range.end - range.start)
+    }
+}
+
+fn go_to_difference(s: &mut Cursive, offset: &u64) {
+    let target = *offset;
+    s.pop_layer();
+    s.call_on_id("hex_view", |view: &mut HexView| {
+        view.go_to_offset(target);
+    });
+}
@@ -0,0 +1,95 @@
+//! The dialog that lists the memory segments and the threads of a core dump, and maps the offsets
+//! to their addresses.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, ScrollView, SelectView, TextView};
+use xv::error::XvError;
+
+use crate::core_dumps::{parse_core_dump, CoreDump};
+use crate::hex_view::HexView;
+use crate::utilities::{get_content, height_left, parse_number};
+use crate::xv_tui::ShowError;
+
+pub fn open_core_dump_dialog(s: &mut Cursive) {
+    let result = s.call_on_id("hex_view", |v: &mut HexView| {
+        let length = v.get_length();
+        parse_core_dump(length, |offset, len| v.read_bytes(offset, len))
+    }).unwrap();
+    let dump = match result {
+        Ok(Some(dump)) => dump,
+        Ok(None) => return s.show_error(XvError::Input(String::from(
+            "This is not an ELF core dump or a Windows minidump."))),
+        Err(error) => return s.show_error(error)
+    };
+
+    let mut item_selector = SelectView::<u64>::new()
+        .on_submit(|s, offset: &u64| {
+            let offset = *offset;
+            s.pop_layer();
+            s.call_on_id("hex_view", |v: &mut HexView| v.go_to_offset(offset));
+        });
+    for segment in &dump.segments {
+        item_selector.add_item(format!("Memory  0x{:016X}  {:>10} bytes  at 0x{:X}",
+                                       segment.address, segment.length, segment.offset), segment.offset);
+    }
+    for thread in &dump.threads {
+        item_selector.add_item(format!("Thread  {:<18}  registers at 0x{:X}", thread.id, thread.offset), thread.offset);
+    }
+
+    let summary = format!("{} memory segments and {} threads. Select one to go to it,\n\
+                           or go to the byte at a virtual address:", dump.segments.len(), dump.threads.len());
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(summary))
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Address:  "))
+            .child(EditView::new()
+                .with_id("core_dump_address")
+                .min_width(20)))
+        .child(TextView::new(""))
+        .child(ScrollView::new(item_selector).max_height(height_left(s, 14).min(20)));
+
+    let title = dump.kind;
+    let map_dump = dump.clone();
+    let dialog = Dialog::around(layout)
+        .title(title)
+        .dismiss_button("Close")
+        .button("Use addresses", move |s| do_use_addresses(s, &map_dump))
+        .button("Go", move |s| do_go_to_address(s, &dump));
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+/// Show the virtual addresses of the memory of the dump in the offsets column, and go to
+/// addresses instead of offsets.
+fn do_use_addresses(s: &mut Cursive, dump: &CoreDump) {
+    s.pop_layer();
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        let mut address_map = v.get_address_map().clone();
+        address_map.set_segments(dump.segments.clone());
+        v.set_address_map(address_map);
+    });
+}
+
+fn do_go_to_address(s: &mut Cursive, dump: &CoreDump) {
+    let address_str = s.call_on_id("core_dump_address", get_content).unwrap();
+
+    let address = match parse_number(&address_str) {
+        Ok(address) => address,
+        Err(error) => return s.show_error(error)
+    };
+    let segment = match dump.segment_at(address) {
+        Some(segment) => segment,
+        None => return s.show_error(XvError::Input(format!(
+            "The dump has no memory at the address 0x{:X}.", address)))
+    };
+    let offset = segment.offset + (address - segment.address);
+    s.pop_layer();
+    s.call_on_id("hex_view", |v: &mut HexView| v.go_to_offset(offset));
+}
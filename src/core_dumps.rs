@@ -0,0 +1,335 @@
+//! Finding the memory segments and the threads in ELF core files and Windows minidumps, so that
+//! the memory of a crashed process can be looked at by its addresses.
+
+use std::convert::TryFrom;
+
+use xv::address_map::Segment;
+use xv::error::{Result, XvError};
+
+/// The most program headers, memory ranges or threads that are read from a dump. Real dumps have
+/// far fewer, so more means that the dump is damaged.
+const MAX_ENTRIES: u64 = 65536;
+
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const NT_PRSTATUS: u32 = 1;
+
+const THREAD_LIST_STREAM: u32 = 3;
+const MEMORY_LIST_STREAM: u32 = 5;
+const MEMORY64_LIST_STREAM: u32 = 9;
+
+/// A thread of the dumped process, with the offset of its registers in the dump.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Thread {
+    pub id: u64,
+    pub offset: u64
+}
+
+/// The memory and the threads of a dumped process.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoreDump {
+    /// What kind of dump it is, like "ELF core dump".
+    pub kind: &'static str,
+    /// Where the memory of the process is in the dump, sorted by address.
+    pub segments: Vec<Segment>,
+    pub threads: Vec<Thread>
+}
+
+impl CoreDump {
+    /// The segment that has the byte at the given address.
+    pub fn segment_at(&self, address: u64) -> Option<&Segment> {
+        self.segments.iter().find(|segment| address >= segment.address && address - segment.address < segment.length)
+    }
+}
+
+/// Read the memory segments and the threads of an ELF core file or a Windows minidump, that is
+/// `length` bytes long. The bytes are read with the given function, that returns fewer bytes than
+/// asked for at the end of the file. Returns `None` when the file is neither.
+pub fn parse_core_dump<F>(length: u64, read: F) -> Result<Option<CoreDump>>
+    where F: FnMut(u64, usize) -> Result<Vec<u8>> {
+    let mut read = DumpReader { read, length };
+    let magic = (read.read)(0, 4)?;
+    let dump = if magic == b"\x7FELF" {
+        parse_elf_core(&mut read)?
+    } else if magic == b"MDMP" {
+        Some(parse_minidump(&mut read)?)
+    } else {
+        None
+    };
+    Ok(dump.map(|mut dump| {
+        dump.segments.sort_by_key(|segment| segment.address);
+        dump
+    }))
+}
+
+/// Reads numbers from a header, in the byte order of the file.
+struct Fields<'a> {
+    bytes: &'a [u8],
+    big_endian: bool
+}
+
+impl<'a> Fields<'a> {
+    fn number(&self, offset: usize, size: usize) -> u64 {
+        let bytes = &self.bytes[offset..offset + size];
+        if self.big_endian {
+            bytes.iter().fold(0, |n, b| n << 8 | u64::from(*b))
+        } else {
+            bytes.iter().rev().fold(0, |n, b| n << 8 | u64::from(*b))
+        }
+    }
+
+    fn u16(&self, offset: usize) -> u64 {
+        self.number(offset, 2)
+    }
+
+    fn u32(&self, offset: usize) -> u64 {
+        self.number(offset, 4)
+    }
+
+    fn u64(&self, offset: usize) -> u64 {
+        self.number(offset, 8)
+    }
+}
+
+/// Reads the bytes of a dump that is `length` bytes long.
+struct DumpReader<F> {
+    read: F,
+    length: u64
+}
+
+impl<F: FnMut(u64, usize) -> Result<Vec<u8>>> DumpReader<F> {
+    /// Read `len` bytes from the offset. Lengths from a damaged dump can be far beyond its end, so
+    /// they are checked before anything is allocated for them.
+    fn read_exactly(&mut self, offset: u64, len: u64) -> Result<Vec<u8>> {
+        if offset.checked_add(len).is_none_or(|end| end > self.length) {
+            return Err(cut_short());
+        }
+        let len = usize::try_from(len).map_err(|_| cut_short())?;
+        let bytes = (self.read)(offset, len)?;
+        if bytes.len() < len {
+            return Err(cut_short());
+        }
+        Ok(bytes)
+    }
+}
+
+fn cut_short() -> XvError {
+    XvError::Decode(String::from("The dump is cut short, or damaged."))
+}
+
+fn check_count(count: u64) -> Result<u64> {
+    if count > MAX_ENTRIES {
+        return Err(cut_short());
+    }
+    Ok(count)
+}
+
+fn parse_elf_core<F>(read: &mut DumpReader<F>) -> Result<Option<CoreDump>>
+    where F: FnMut(u64, usize) -> Result<Vec<u8>> {
+    let header = read.read_exactly(0, 52)?;
+    let wide = match header[4] {
+        1 => false,
+        2 => true,
+        _ => return Err(XvError::Decode(String::from("Unknown ELF class.")))
+    };
+    let header = if wide { read.read_exactly(0, 64)? } else { header };
+    let fields = Fields { bytes: &header, big_endian: header[5] == 2 };
+    if fields.u16(16) != 4 {
+        // Executables and libraries are not dumps.
+        return Ok(None);
+    }
+    let (phoff, phentsize, phnum) = if wide {
+        (fields.u64(32), fields.u16(54), fields.u16(56))
+    } else {
+        (fields.u32(28), fields.u16(42), fields.u16(44))
+    };
+    let min_phentsize = if wide { 56 } else { 32 };
+    if phentsize < min_phentsize {
+        return Err(cut_short());
+    }
+    let table = read.read_exactly(phoff, phentsize * check_count(phnum)?)?;
+
+    let mut segments = Vec::new();
+    let mut threads = Vec::new();
+    for entry in table.chunks(phentsize as usize) {
+        let entry = Fields { bytes: entry, big_endian: fields.big_endian };
+        let (p_type, offset, address, length) = if wide {
+            (entry.u32(0), entry.u64(8), entry.u64(16), entry.u64(32))
+        } else {
+            (entry.u32(0), entry.u32(4), entry.u32(8), entry.u32(16))
+        };
+        match p_type as u32 {
+            PT_LOAD if length > 0 => segments.push(Segment { offset, length, address }),
+            PT_NOTE => {
+                let notes = read.read_exactly(offset, length)?;
+                threads.extend(parse_prstatus_notes(&notes, offset, wide, fields.big_endian));
+            },
+            _ => {}
+        }
+    }
+    Ok(Some(CoreDump { kind: "ELF core dump", segments, threads }))
+}
+
+/// Find the threads in the notes of a core file. Every thread has an `NT_PRSTATUS` note, with its
+/// id and its registers.
+fn parse_prstatus_notes(notes: &[u8], offset: u64, wide: bool, big_endian: bool) -> Vec<Thread> {
+    let align = |n: usize| (n + 3) & !3;
+    // The pid comes after the signal info, the current signal and two signal masks.
+    let pid_offset = if wide { 32 } else { 24 };
+    let mut threads = Vec::new();
+    let mut pos = 0;
+    while pos + 12 <= notes.len() {
+        let fields = Fields { bytes: &notes[pos..], big_endian };
+        let name_size = fields.u32(0) as usize;
+        let desc_size = fields.u32(4) as usize;
+        let note_type = fields.u32(8) as u32;
+        let desc = pos + 12 + align(name_size);
+        if desc + desc_size > notes.len() {
+            break;
+        }
+        if note_type == NT_PRSTATUS && desc_size >= pid_offset + 4 {
+            let desc_fields = Fields { bytes: &notes[desc..desc + desc_size], big_endian };
+            threads.push(Thread { id: desc_fields.u32(pid_offset), offset: offset + desc as u64 });
+        }
+        pos = desc + align(desc_size);
+    }
+    threads
+}
+
+fn parse_minidump<F>(read: &mut DumpReader<F>) -> Result<CoreDump>
+    where F: FnMut(u64, usize) -> Result<Vec<u8>> {
+    let header = read.read_exactly(0, 16)?;
+    let header = Fields { bytes: &header, big_endian: false };
+    let stream_count = check_count(header.u32(8))?;
+    let directory = read.read_exactly(header.u32(12), stream_count * 12)?;
+
+    let mut segments = Vec::new();
+    let mut threads = Vec::new();
+    for entry in directory.chunks(12) {
+        let entry = Fields { bytes: entry, big_endian: false };
+        let rva = entry.u32(8);
+        match entry.u32(0) as u32 {
+            THREAD_LIST_STREAM => {
+                let count = check_count(Fields { bytes: &read.read_exactly(rva, 4)?, big_endian: false }.u32(0))?;
+                let list = read.read_exactly(rva + 4, count * 48)?;
+                for thread in list.chunks(48) {
+                    let thread = Fields { bytes: thread, big_endian: false };
+                    // The location of the context, that has the registers, is at the end.
+                    threads.push(Thread { id: thread.u32(0), offset: thread.u32(44) });
+                }
+            },
+            MEMORY_LIST_STREAM => {
+                let count = check_count(Fields { bytes: &read.read_exactly(rva, 4)?, big_endian: false }.u32(0))?;
+                let list = read.read_exactly(rva + 4, count * 16)?;
+                for range in list.chunks(16) {
+                    let range = Fields { bytes: range, big_endian: false };
+                    segments.push(Segment { offset: range.u32(12), length: range.u32(8), address: range.u64(0) });
+                }
+            },
+            MEMORY64_LIST_STREAM => {
+                let list_header = read.read_exactly(rva, 16)?;
+                let list_header = Fields { bytes: &list_header, big_endian: false };
+                let count = check_count(list_header.u64(0))?;
+                // The memory of all the ranges follows each other, from the base offset on.
+                let mut offset = list_header.u64(8);
+                let list = read.read_exactly(rva + 16, count * 16)?;
+                for range in list.chunks(16) {
+                    let range = Fields { bytes: range, big_endian: false };
+                    let length = range.u64(8);
+                    segments.push(Segment { offset, length, address: range.u64(0) });
+                    offset = offset.saturating_add(length);
+                }
+            },
+            _ => {}
+        }
+    }
+    Ok(CoreDump { kind: "Minidump", segments, threads })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Result<Option<CoreDump>> {
+        parse_core_dump(bytes.len() as u64, |offset, len| {
+            let start = (offset as usize).min(bytes.len());
+            Ok(bytes[start..(start + len).min(bytes.len())].to_vec())
+        })
+    }
+
+    fn put(bytes: &mut Vec<u8>, offset: usize, value: u64, size: usize) {
+        if bytes.len() < offset + size {
+            bytes.resize(offset + size, 0);
+        }
+        for i in 0..size {
+            bytes[offset + i] = (value >> (8 * i)) as u8;
+        }
+    }
+
+    #[test]
+    fn parsing_elf_core_dumps() {
+        let mut elf = b"\x7FELF\x02\x01\x01".to_vec();
+        elf.resize(0x110, 0);
+        put(&mut elf, 16, 4, 2);
+        put(&mut elf, 32, 64, 8);
+        put(&mut elf, 54, 56, 2);
+        put(&mut elf, 56, 2, 2);
+        // A note segment with one thread, and a load segment.
+        put(&mut elf, 64, u64::from(PT_NOTE), 4);
+        put(&mut elf, 72, 0xB0, 8);
+        put(&mut elf, 96, 12 + 8 + 40, 8);
+        put(&mut elf, 120, u64::from(PT_LOAD), 4);
+        put(&mut elf, 128, 0x100, 8);
+        put(&mut elf, 136, 0x7FFF_0000, 8);
+        put(&mut elf, 152, 0x10, 8);
+        put(&mut elf, 0xB0, 5, 4);
+        put(&mut elf, 0xB4, 40, 4);
+        put(&mut elf, 0xB8, u64::from(NT_PRSTATUS), 4);
+        elf[0xBC..0xC1].copy_from_slice(b"CORE\0");
+        put(&mut elf, 0xC4 + 32, 4242, 4);
+
+        let dump = parse(&elf).unwrap().unwrap();
+        assert_eq!(dump.kind, "ELF core dump");
+        assert_eq!(dump.segments, vec![Segment { offset: 0x100, length: 0x10, address: 0x7FFF_0000 }]);
+        assert_eq!(dump.threads, vec![Thread { id: 4242, offset: 0xC4 }]);
+        assert_eq!(dump.segment_at(0x7FFF_000F).map(|segment| segment.offset), Some(0x100));
+        assert_eq!(dump.segment_at(0x7FFF_0010), None);
+
+        put(&mut elf, 16, 2, 2);
+        assert_eq!(parse(&elf).unwrap(), None);
+        assert!(parse(b"\x7FELF\x02\x01\x01").is_err());
+        // A note segment that claims to be far longer than the dump is not read.
+        put(&mut elf, 16, 4, 2);
+        put(&mut elf, 96, u64::MAX - 0x10, 8);
+        assert!(parse(&elf).is_err());
+        assert_eq!(parse(b"Hello, World!").unwrap(), None);
+    }
+
+    #[test]
+    fn parsing_minidumps() {
+        let mut dump = b"MDMP".to_vec();
+        put(&mut dump, 8, 2, 4);
+        put(&mut dump, 12, 0x20, 4);
+        put(&mut dump, 0x20, u64::from(THREAD_LIST_STREAM), 4);
+        put(&mut dump, 0x28, 0x40, 4);
+        put(&mut dump, 0x2C, u64::from(MEMORY64_LIST_STREAM), 4);
+        put(&mut dump, 0x34, 0x80, 4);
+        put(&mut dump, 0x40, 1, 4);
+        put(&mut dump, 0x44, 77, 4);
+        put(&mut dump, 0x44 + 44, 0x400, 4);
+        put(&mut dump, 0x80, 2, 8);
+        put(&mut dump, 0x88, 0x1000, 8);
+        put(&mut dump, 0x90, 0x2000_0000, 8);
+        put(&mut dump, 0x98, 0x100, 8);
+        put(&mut dump, 0xA0, 0x1000_0000, 8);
+        put(&mut dump, 0xA8, 0x200, 8);
+
+        let dump = parse(&dump).unwrap().unwrap();
+        assert_eq!(dump.kind, "Minidump");
+        assert_eq!(dump.segments, vec![
+            Segment { offset: 0x1100, length: 0x200, address: 0x1000_0000 },
+            Segment { offset: 0x1000, length: 0x100, address: 0x2000_0000 }
+        ]);
+        assert_eq!(dump.threads, vec![Thread { id: 77, offset: 0x400 }]);
+    }
+}
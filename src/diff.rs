@@ -0,0 +1,280 @@
+//! Finding the inserted, deleted and changed ranges of bytes between files, even when bytes were
+//! inserted or deleted near the start.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::error::Result;
+use crate::progress::Progress;
+
+const HASH_BASE: u64 = 0x100_0000_01B3;
+const PROGRESS_STEP: usize = 64 * 1024;
+
+/// How a range of one file differs from the other file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DifferenceKind {
+    /// The bytes of the first file were replaced by other bytes in the second file.
+    Changed,
+    /// The bytes are only in the second file.
+    Inserted,
+    /// The bytes are only in the first file.
+    Deleted
+}
+
+/// A range of bytes in the first file, and the range in the second file that it corresponds to.
+/// One of the ranges is empty for insertions and deletions, and then it tells where the bytes are
+/// missing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    pub kind: DifferenceKind,
+    pub first: Range<u64>,
+    pub second: Range<u64>
+}
+
+/// Find the differences between two files.
+///
+/// The files are aligned the way rsync does it. The first file is split into blocks of
+/// `block_size` bytes, and a rolling hash over the second file finds where those blocks appear in
+/// it. Each block that is found is extended in both directions to the full run of equal bytes, and
+/// the bytes between the runs are the differences. This way an insertion near the start of a file
+/// shows up as an insertion, instead of as a change to everything after it.
+///
+/// Runs of equal bytes that are shorter than the block size may be reported as changes. The
+/// progress goes up by the length of the second file.
+pub fn diff(first: &[u8], second: &[u8], block_size: usize, progress: &Progress) -> Result<Vec<Difference>> {
+    let block_size = block_size.max(1);
+    let mut differences = Vec::new();
+    let blocks = index_blocks(first, block_size);
+    let top_power = (1..block_size).fold(1u64, |p, _| p.wrapping_mul(HASH_BASE));
+    let (mut first_pos, mut second_pos) = (0, 0);
+    let mut j = 0;
+    let mut hash = None;
+    let mut reported = 0;
+
+    while j + block_size <= second.len() {
+        if j - reported >= PROGRESS_STEP {
+            progress.advance((j - reported) as u64)?;
+            reported = j;
+        }
+        let h = match hash {
+            Some(h) => h,
+            None => hash_block(&second[j..j + block_size])
+        };
+        let window = &second[j..j + block_size];
+        // The offsets of every hash are in order, so those before the position are skipped.
+        let found = blocks.get(&h).and_then(|offsets| {
+            let ahead = &offsets[offsets.partition_point(|&i| i < first_pos)..];
+            ahead.iter().find(|&&i| &first[i..i + block_size] == window)
+        });
+        if let Some(&i) = found {
+            let (mut start_first, mut start_second) = (i, j);
+            while start_first > first_pos && start_second > second_pos
+                && first[start_first - 1] == second[start_second - 1] {
+                start_first -= 1;
+                start_second -= 1;
+            }
+            let mut len = block_size;
+            while i + len < first.len() && j + len < second.len() && first[i + len] == second[j + len] {
+                len += 1;
+            }
+            push_difference(&mut differences, first_pos..start_first, second_pos..start_second);
+            first_pos = i + len;
+            second_pos = j + len;
+            j = second_pos;
+            hash = None;
+        } else {
+            if j + block_size < second.len() {
+                let removed = u64::from(second[j]).wrapping_mul(top_power);
+                hash = Some(h.wrapping_sub(removed).wrapping_mul(HASH_BASE)
+                    .wrapping_add(u64::from(second[j + block_size])));
+            }
+            j += 1;
+        }
+    }
+    push_difference(&mut differences, first_pos..first.len(), second_pos..second.len());
+    progress.advance((second.len() - reported) as u64)?;
+    Ok(differences)
+}
+
+/// Who changed a range of the base file, of the two files that were made from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThreeWayKind {
+    /// Only the first of the two files, which is "mine".
+    Mine,
+    /// Only the second of the two files, which is "theirs".
+    Theirs,
+    /// Both files, to the same bytes.
+    Both,
+    /// Both files, to different bytes.
+    Conflict
+}
+
+/// A range of the base file that was changed in one or both of the other files, and the ranges
+/// of those files that it corresponds to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThreeWayDifference {
+    pub kind: ThreeWayKind,
+    pub base: Range<u64>,
+    pub mine: Range<u64>,
+    pub theirs: Range<u64>
+}
+
+/// Find the ranges of a base file that were changed in two files that were made from it, like
+/// two patched firmware images, and whether the changes conflict.
+///
+/// The base file is compared with each of the other files, like `diff` does. Changes of the two
+/// files to ranges of the base file that overlap or touch are taken together, and they conflict
+/// unless both files have the same bytes there. The progress goes up by the lengths of both
+/// files.
+pub fn diff_three_way(base: &[u8], mine: &[u8], theirs: &[u8], block_size: usize, progress: &Progress)
+    -> Result<Vec<ThreeWayDifference>> {
+    let mut changes: Vec<(bool, Difference)> = Vec::new();
+    changes.extend(diff(base, mine, block_size, progress)?.into_iter().map(|d| (true, d)));
+    changes.extend(diff(base, theirs, block_size, progress)?.into_iter().map(|d| (false, d)));
+    changes.sort_by_key(|(_, d)| (d.first.start, d.first.end));
+
+    let mut differences = Vec::new();
+    // How much further on the bytes after the changes that were gone through are in each file,
+    // than in the base file.
+    let (mut mine_shift, mut theirs_shift) = (0i64, 0i64);
+    let mut changes = changes.into_iter().peekable();
+    while let Some((is_mine, first)) = changes.next() {
+        let base_range = first.first.clone();
+        let (mine_before, theirs_before) = (mine_shift, theirs_shift);
+        let (mut in_mine, mut in_theirs) = (false, false);
+        let mut end = base_range.end;
+        let mut next = Some((is_mine, first));
+        while let Some((is_mine, change)) = next {
+            end = end.max(change.first.end);
+            let shift = change.second.end as i64 - change.first.end as i64;
+            if is_mine {
+                in_mine = true;
+                mine_shift = shift;
+            } else {
+                in_theirs = true;
+                theirs_shift = shift;
+            }
+            next = match changes.peek() {
+                Some((_, change)) if change.first.start <= end => changes.next(),
+                _ => None
+            };
+        }
+        let shifted = |offset: u64, shift: i64| (offset as i64 + shift) as u64;
+        let mine_range = shifted(base_range.start, mine_before)..shifted(end, mine_shift);
+        let theirs_range = shifted(base_range.start, theirs_before)..shifted(end, theirs_shift);
+        let kind = match (in_mine, in_theirs) {
+            (true, false) => ThreeWayKind::Mine,
+            (false, true) => ThreeWayKind::Theirs,
+            _ if mine[mine_range.start as usize..mine_range.end as usize]
+                == theirs[theirs_range.start as usize..theirs_range.end as usize] => ThreeWayKind::Both,
+            _ => ThreeWayKind::Conflict
+        };
+        differences.push(ThreeWayDifference {
+            kind,
+            base: base_range.start..end,
+            mine: mine_range,
+            theirs: theirs_range
+        });
+    }
+    Ok(differences)
+}
+
+fn index_blocks(bytes: &[u8], block_size: usize) -> HashMap<u64, Vec<usize>> {
+    let mut blocks: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (n, block) in bytes.chunks(block_size).enumerate() {
+        if block.len() == block_size {
+            blocks.entry(hash_block(block)).or_default().push(n * block_size);
+        }
+    }
+    blocks
+}
+
+fn hash_block(block: &[u8]) -> u64 {
+    block.iter().fold(0u64, |h, b| h.wrapping_mul(HASH_BASE).wrapping_add(u64::from(*b)))
+}
+
+fn push_difference(differences: &mut Vec<Difference>, first: Range<usize>, second: Range<usize>) {
+    let kind = match (first.start == first.end, second.start == second.end) {
+        (true, true) => return,
+        (false, false) => DifferenceKind::Changed,
+        (true, false) => DifferenceKind::Inserted,
+        (false, true) => DifferenceKind::Deleted
+    };
+    differences.push(Difference {
+        kind,
+        first: first.start as u64..first.end as u64,
+        second: second.start as u64..second.end as u64
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn difference(kind: DifferenceKind, first: Range<u64>, second: Range<u64>) -> Difference {
+        Difference { kind, first, second }
+    }
+
+    fn diff(first: &[u8], second: &[u8], block_size: usize) -> Vec<Difference> {
+        super::diff(first, second, block_size, &Progress::new(second.len() as u64)).unwrap()
+    }
+
+    #[test]
+    fn finding_shifted_differences() {
+        let first: Vec<u8> = (0..200).collect();
+        assert_eq!(diff(&first, &first, 8), vec![]);
+
+        let mut inserted = first.clone();
+        inserted.splice(10..10, vec![0xFF; 3]);
+        assert_eq!(diff(&first, &inserted, 8),
+                   vec![difference(DifferenceKind::Inserted, 10..10, 10..13)]);
+        assert_eq!(diff(&inserted, &first, 8),
+                   vec![difference(DifferenceKind::Deleted, 10..13, 10..10)]);
+
+        let mut changed = inserted.clone();
+        changed[100] = 0;
+        changed.truncate(190);
+        assert_eq!(diff(&first, &changed, 8), vec![
+            difference(DifferenceKind::Inserted, 10..10, 10..13),
+            difference(DifferenceKind::Changed, 97..98, 100..101),
+            difference(DifferenceKind::Deleted, 187..200, 190..190)]);
+    }
+
+    #[test]
+    fn finding_three_way_conflicts() {
+        let base: Vec<u8> = (0..200).collect();
+        let mut mine = base.clone();
+        mine[20] = 0xFF;
+        mine.splice(100..100, vec![0xEE; 2]);
+        // Byte 148 of the base file is byte 150 of this file, after the insertion.
+        mine[150] = 0xAA;
+        let mut theirs = base.clone();
+        theirs[60] = 0xFF;
+        theirs[148] = 0xBB;
+        theirs[180] = 0xCC;
+        mine[180 + 2] = 0xCC;
+
+        let progress = Progress::new((mine.len() + theirs.len()) as u64);
+        let differences = diff_three_way(&base, &mine, &theirs, 8, &progress).unwrap();
+        let kinds: Vec<ThreeWayKind> = differences.iter().map(|d| d.kind).collect();
+        assert_eq!(kinds, vec![ThreeWayKind::Mine, ThreeWayKind::Theirs, ThreeWayKind::Mine,
+                               ThreeWayKind::Conflict, ThreeWayKind::Both]);
+        assert_eq!(differences[0], ThreeWayDifference {
+            kind: ThreeWayKind::Mine, base: 20..21, mine: 20..21, theirs: 20..21 });
+        assert_eq!(differences[1].mine, 60..61);
+        assert_eq!(differences[2].mine, 100..102);
+        assert_eq!(differences[2].theirs, 100..100);
+        assert_eq!(differences[3], ThreeWayDifference {
+            kind: ThreeWayKind::Conflict, base: 148..149, mine: 150..151, theirs: 148..149 });
+        assert_eq!(differences[4].base, 180..181);
+        assert_eq!(differences[4].mine, 182..183);
+    }
+
+    #[test]
+    fn comparing_unrelated_files() {
+        assert_eq!(diff(b"abcdefgh", b"12345", 4),
+                   vec![difference(DifferenceKind::Changed, 0..8, 0..5)]);
+        assert_eq!(diff(b"", b"12345", 4),
+                   vec![difference(DifferenceKind::Inserted, 0..0, 0..5)]);
+    }
+}
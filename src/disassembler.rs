@@ -0,0 +1,208 @@
+//! Decoding single machine instructions, to check what the bytes at a position would do.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Architecture {
+    RiscV32,
+    RiscV64
+}
+
+pub const ARCHITECTURES: [Architecture; 2] = [Architecture::RiscV32, Architecture::RiscV64];
+
+/// An instruction, with its length in bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    pub length: usize,
+    pub text: String
+}
+
+const REGISTERS: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6"];
+
+impl Architecture {
+    pub fn name(self) -> &'static str {
+        match self {
+            Architecture::RiscV32 => "RISC-V RV32IM",
+            Architecture::RiscV64 => "RISC-V RV64IM"
+        }
+    }
+
+    /// Decode the instruction at the start of the bytes, which is at the given address. Branch
+    /// targets are shown as addresses. Returns `None` when the bytes are too few, or are not an
+    /// instruction.
+    pub fn decode(self, bytes: &[u8], address: u64) -> Option<Instruction> {
+        if bytes.len() < 2 {
+            return None;
+        }
+        if bytes[0] & 0b11 != 0b11 {
+            // The compressed instructions are 16 bits.
+            return Some(Instruction { length: 2, text: String::from("(compressed instruction)") });
+        }
+        if bytes.len() < 4 {
+            return None;
+        }
+        let inst = u32::from(bytes[0]) | u32::from(bytes[1]) << 8 | u32::from(bytes[2]) << 16 | u32::from(bytes[3]) << 24;
+        let rv64 = self == Architecture::RiscV64;
+        decode_riscv(inst, address, rv64).map(|text| Instruction { length: 4, text })
+    }
+}
+
+fn decode_riscv(inst: u32, address: u64, rv64: bool) -> Option<String> {
+    let rd = REGISTERS[(inst >> 7 & 31) as usize];
+    let rs1 = REGISTERS[(inst >> 15 & 31) as usize];
+    let rs2 = REGISTERS[(inst >> 20 & 31) as usize];
+    let funct3 = inst >> 12 & 7;
+    let funct7 = inst >> 25;
+    let imm_i = (inst as i32) >> 20;
+    let imm_s = (inst as i32) >> 25 << 5 | (inst >> 7 & 31) as i32;
+    let imm_b = (inst as i32) >> 31 << 12 | ((inst >> 7 & 1) << 11 | (inst >> 25 & 0x3F) << 5 | (inst >> 8 & 0xF) << 1) as i32;
+    let imm_j = (inst as i32) >> 31 << 20 | ((inst >> 12 & 0xFF) << 12 | (inst >> 20 & 1) << 11 | (inst >> 21 & 0x3FF) << 1) as i32;
+    let target = |offset: i32| address.wrapping_add(offset as i64 as u64);
+    // The shift amounts are 6 bits on RV64, and 5 bits on RV32.
+    let shamt_bits = if rv64 { 6 } else { 5 };
+    let shamt = inst >> 20 & ((1 << shamt_bits) - 1);
+    let shift_kind = inst >> (20 + shamt_bits);
+
+    let text = match inst & 0x7F {
+        0x37 => format!("lui {}, 0x{:X}", rd, inst >> 12),
+        0x17 => format!("auipc {}, 0x{:X}", rd, inst >> 12),
+        0x6F => format!("jal {}, 0x{:X}", rd, target(imm_j)),
+        0x67 if funct3 == 0 => {
+            if inst == 0x0000_8067 {
+                String::from("ret")
+            } else {
+                format!("jalr {}, {}({})", rd, imm_i, rs1)
+            }
+        },
+        0x63 => {
+            let name = match funct3 {
+                0 => "beq",
+                1 => "bne",
+                4 => "blt",
+                5 => "bge",
+                6 => "bltu",
+                7 => "bgeu",
+                _ => return None
+            };
+            format!("{} {}, {}, 0x{:X}", name, rs1, rs2, target(imm_b))
+        },
+        0x03 => {
+            let name = match funct3 {
+                0 => "lb",
+                1 => "lh",
+                2 => "lw",
+                3 if rv64 => "ld",
+                4 => "lbu",
+                5 => "lhu",
+                6 if rv64 => "lwu",
+                _ => return None
+            };
+            format!("{} {}, {}({})", name, rd, imm_i, rs1)
+        },
+        0x23 => {
+            let name = match funct3 {
+                0 => "sb",
+                1 => "sh",
+                2 => "sw",
+                3 if rv64 => "sd",
+                _ => return None
+            };
+            format!("{} {}, {}({})", name, rs2, imm_s, rs1)
+        },
+        0x13 => match funct3 {
+            0 if inst == 0x0000_0013 => String::from("nop"),
+            1 if shift_kind == 0 => format!("slli {}, {}, {}", rd, rs1, shamt),
+            5 if shift_kind == 0 => format!("srli {}, {}, {}", rd, rs1, shamt),
+            5 if shift_kind == 0x20 >> (shamt_bits - 5) => format!("srai {}, {}, {}", rd, rs1, shamt),
+            1 | 5 => return None,
+            _ => {
+                let name = ["addi", "", "slti", "sltiu", "xori", "", "ori", "andi"][funct3 as usize];
+                format!("{} {}, {}, {}", name, rd, rs1, imm_i)
+            }
+        },
+        0x1B if rv64 => match (funct3, funct7) {
+            (0, _) => format!("addiw {}, {}, {}", rd, rs1, imm_i),
+            (1, 0) => format!("slliw {}, {}, {}", rd, rs1, inst >> 20 & 31),
+            (5, 0) => format!("srliw {}, {}, {}", rd, rs1, inst >> 20 & 31),
+            (5, 0x20) => format!("sraiw {}, {}, {}", rd, rs1, inst >> 20 & 31),
+            _ => return None
+        },
+        0x33 => {
+            let name = match (funct7, funct3) {
+                (0, 0) => "add",
+                (0x20, 0) => "sub",
+                (0, 1) => "sll",
+                (0, 2) => "slt",
+                (0, 3) => "sltu",
+                (0, 4) => "xor",
+                (0, 5) => "srl",
+                (0x20, 5) => "sra",
+                (0, 6) => "or",
+                (0, 7) => "and",
+                (1, _) => ["mul", "mulh", "mulhsu", "mulhu", "div", "divu", "rem", "remu"][funct3 as usize],
+                _ => return None
+            };
+            format!("{} {}, {}, {}", name, rd, rs1, rs2)
+        },
+        0x3B if rv64 => {
+            let name = match (funct7, funct3) {
+                (0, 0) => "addw",
+                (0x20, 0) => "subw",
+                (0, 1) => "sllw",
+                (0, 5) => "srlw",
+                (0x20, 5) => "sraw",
+                (1, 0) => "mulw",
+                (1, 4) => "divw",
+                (1, 5) => "divuw",
+                (1, 6) => "remw",
+                (1, 7) => "remuw",
+                _ => return None
+            };
+            format!("{} {}, {}, {}", name, rd, rs1, rs2)
+        },
+        0x0F => match funct3 {
+            0 => String::from("fence"),
+            1 => String::from("fence.i"),
+            _ => return None
+        },
+        0x73 => match funct3 {
+            0 if inst == 0x0000_0073 => String::from("ecall"),
+            0 if inst == 0x0010_0073 => String::from("ebreak"),
+            1..=3 => format!("{} {}, 0x{:X}, {}", ["", "csrrw", "csrrs", "csrrc"][funct3 as usize], rd, inst >> 20, rs1),
+            5..=7 => format!("{} {}, 0x{:X}, {}", ["", "", "", "", "", "csrrwi", "csrrsi", "csrrci"][funct3 as usize],
+                             rd, inst >> 20, inst >> 15 & 31),
+            _ => return None
+        },
+        _ => return None
+    };
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(architecture: Architecture, bytes: &[u8]) -> String {
+        architecture.decode(bytes, 0x8000_0000).map_or(String::from("?"), |instruction| instruction.text)
+    }
+
+    #[test]
+    fn decoding_riscv_instructions() {
+        let rv32 = Architecture::RiscV32;
+        assert_eq!(decode(rv32, &[0x13, 0x05, 0x10, 0x00]), "addi a0, zero, 1");
+        assert_eq!(decode(rv32, &[0x13, 0x00, 0x00, 0x00]), "nop");
+        assert_eq!(decode(rv32, &[0x67, 0x80, 0x00, 0x00]), "ret");
+        assert_eq!(decode(rv32, &[0x6F, 0xF0, 0xDF, 0xFF]), "jal zero, 0x7FFFFFFC");
+        assert_eq!(decode(rv32, &[0x63, 0x04, 0xB5, 0x00]), "beq a0, a1, 0x80000008");
+        assert_eq!(decode(rv32, &[0x83, 0x25, 0xC5, 0xFF]), "lw a1, -4(a0)");
+        assert_eq!(decode(rv32, &[0x23, 0x20, 0xB5, 0x00]), "sw a1, 0(a0)");
+        assert_eq!(decode(rv32, &[0x33, 0x05, 0xB5, 0x02]), "mul a0, a0, a1");
+        assert_eq!(decode(rv32, &[0x13, 0x55, 0xF5, 0x41]), "srai a0, a0, 31");
+        assert_eq!(decode(rv32, &[0x73, 0x00, 0x10, 0x00]), "ebreak");
+        assert_eq!(decode(rv32, &[0x03, 0x35, 0x05, 0x00]), "?");
+        assert_eq!(decode(Architecture::RiscV64, &[0x03, 0x35, 0x05, 0x00]), "ld a0, 0(a0)");
+        assert_eq!(decode(Architecture::RiscV64, &[0x1B, 0x05, 0xF5, 0xFF]), "addiw a0, a0, -1");
+        assert_eq!(rv32.decode(&[0x01, 0x00], 0).map(|instruction| instruction.length), Some(2));
+        assert_eq!(rv32.decode(&[0x13, 0x00], 0), None);
+    }
+}
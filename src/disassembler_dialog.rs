@@ -0,0 +1,62 @@
+//! The dialog that decodes the instruction at the current position.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::Identifiable;
+use cursive::views::{Dialog, LinearLayout, OnEventView, SelectView, TextView};
+
+use crate::disassembler::{Architecture, ARCHITECTURES};
+use crate::hex_view::HexView;
+use crate::xv_tui::ShowError;
+
+/// The most bytes that an instruction of any of the architectures takes.
+const MAX_INSTRUCTION_LENGTH: usize = 4;
+
+pub fn open_disassembler_dialog(s: &mut Cursive) {
+    let result = s.call_on_id("hex_view", |v: &mut HexView| {
+        let offset = v.get_offset();
+        let address = v.get_address_map().address_of(offset);
+        v.read_bytes(offset, MAX_INSTRUCTION_LENGTH).map(|bytes| (address, bytes))
+    }).unwrap();
+    let (address, bytes) = match result {
+        Ok(found) => found,
+        Err(error) => return s.show_error(error)
+    };
+
+    let mut architecture_selector: SelectView<Architecture> = SelectView::new().popup();
+    for architecture in ARCHITECTURES.iter() {
+        architecture_selector.add_item(architecture.name(), *architecture);
+    }
+    let decoded = describe_instruction(ARCHITECTURES[0], &bytes, address);
+    let architecture_selector = architecture_selector.on_select(move |s, architecture: &Architecture| {
+        let decoded = describe_instruction(*architecture, &bytes, address);
+        s.call_on_id("decoded_instruction", |v: &mut TextView| v.set_content(decoded));
+    });
+
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Architecture:  "))
+            .child(architecture_selector))
+        .child(TextView::new(decoded).with_id("decoded_instruction"));
+
+    let dialog = Dialog::around(layout)
+        .title(format!("Decode 0x{:X}", address))
+        .dismiss_button("Ok");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+fn describe_instruction(architecture: Architecture, bytes: &[u8], address: u64) -> String {
+    match architecture.decode(bytes, address) {
+        Some(instruction) => {
+            let hex: Vec<String> = bytes[0..instruction.length].iter().map(|b| format!("{:02X}", b)).collect();
+            format!("\n{}\n\n{}\nLength: {} bytes", instruction.text, hex.join(" "), instruction.length)
+        },
+        None => String::from("\nThe bytes here are not an instruction.")
+    }
+}
@@ -0,0 +1,118 @@
+//! The dialog that finds duplicate blocks and runs of a single byte, and lists them.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, ScrollView, SelectView, TextView};
+use xv::error::XvError;
+
+use crate::block_analysis::{analyse_blocks, BlockReport};
+use crate::hex_view::HexView;
+use crate::progress_dialog::run_with_progress;
+use crate::utilities::{get_content, height_left, parse_number};
+use crate::xv_tui::ShowError;
+
+const MAX_REPORTED_ITEMS: usize = 1000;
+
+pub fn open_duplicates_dialog(s: &mut Cursive) {
+    let block_size_edit = EditView::new()
+        .content("4096")
+        .with_id("block_size")
+        .min_width(8);
+
+    let min_run_edit = EditView::new()
+        .content("4096")
+        .with_id("min_run")
+        .min_width(8);
+
+    let editors = LinearLayout::vertical()
+        .child(block_size_edit)
+        .child(min_run_edit);
+
+    let layout = LinearLayout::horizontal()
+        .child(TextView::new("Block size:  \nMinimum run:  "))
+        .child(editors);
+
+    let dialog = Dialog::around(layout)
+        .title("Find duplicate blocks")
+        .dismiss_button("Cancel")
+        .button("Analyse", do_analyse);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_analyse);
+
+    s.add_layer(event_view)
+}
+
+fn do_analyse(s: &mut Cursive) {
+    let block_size_str = s.call_on_id("block_size", get_content).unwrap();
+    let min_run_str = s.call_on_id("min_run", get_content).unwrap();
+
+    s.pop_layer();
+
+    let block_size = match parse_number(&block_size_str) {
+        Ok(size) if size > 0 => size as usize,
+        Ok(_) => return s.show_error(XvError::Input(String::from("The block size must be more than zero."))),
+        Err(error) => return s.show_error(error)
+    };
+    let min_run = match parse_number(&min_run_str) {
+        Ok(min_run) => min_run,
+        Err(error) => return s.show_error(error)
+    };
+
+    let length = s.call_on_id("hex_view", |v: &mut HexView| v.get_length()).unwrap();
+    let source = s.call_on_id("hex_view", |v: &mut HexView| {
+        v.open_background_source(0, length)
+    }).unwrap();
+    let mut source = match source {
+        Ok((source, _)) => source,
+        Err(error) => return s.show_error(error)
+    };
+
+    run_with_progress(s, "Finding duplicates", length, move |progress| {
+        analyse_blocks(source.as_mut(), length, block_size, min_run, progress)
+    }, |s, report| show_report(s, &report));
+}
+
+fn show_report(s: &mut Cursive, report: &BlockReport) {
+    let mut result_selector: SelectView<u64> = SelectView::new().on_submit(go_to_result);
+
+    for dup in report.duplicates.iter().take(MAX_REPORTED_ITEMS) {
+        result_selector.add_item(
+            format!("0x{:016X}  duplicate of 0x{:016X}", dup.offset, dup.original), dup.offset);
+    }
+    for run in report.runs.iter().take(MAX_REPORTED_ITEMS) {
+        result_selector.add_item(
+            format!("0x{:016X}  run of {} x 0x{:02x}", run.offset, run.length, run.byte), run.offset);
+    }
+
+    let summary = format!("{} duplicate blocks, {} runs of repeated bytes.",
+                          report.duplicates.len(), report.runs.len());
+
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(summary))
+        .child(ScrollView::new(result_selector))
+        .max_height(height_left(s, 11).min(50));
+
+    let dialog = Dialog::around(layout)
+        .title("Duplicate blocks")
+        .dismiss_button("Close");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+fn go_to_result(s: &mut Cursive, offset: &u64) {
+    let target = *offset;
+    s.pop_layer();
+    s.call_on_id("hex_view", |view: &mut HexView| {
+        view.go_to_offset(target);
+    });
+}
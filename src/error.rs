@@ -0,0 +1,95 @@
+//! The errors of xv, and the messages that they are shown with.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::ErrorKind;
+use std::num::ParseIntError;
+
+pub type Result<T> = std::result::Result<T, XvError>;
+
+/// The errors that can happen while viewing a file.
+#[derive(Debug)]
+pub enum XvError {
+    /// Reading or opening a file failed.
+    Io(io::Error),
+    /// Some bytes are not in the binary format they were read as, like a damaged capture.
+    Decode(String),
+    /// Some text, such as a number or a bit field layout, could not be parsed.
+    Parse(String),
+    /// The source cannot be viewed, like a directory, or an object in cloud storage without the
+    /// tool that reads it.
+    Unsupported(String),
+    /// The user asked for something that cannot be done.
+    Input(String),
+    /// The user stopped a long running operation before it was done.
+    Cancelled
+}
+
+impl fmt::Display for XvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XvError::Io(error) => match error.kind() {
+                ErrorKind::NotFound => write!(f, "The file or directory does not exist."),
+                ErrorKind::PermissionDenied => write!(f, "Permission denied. You are not allowed to read this."),
+                ErrorKind::UnexpectedEof => write!(f, "The file ended before all of it could be read."),
+                _ => write!(f, "{}", error)
+            },
+            XvError::Decode(message) => write!(f, "{}", message),
+            XvError::Parse(message) => write!(f, "{}", message),
+            XvError::Unsupported(message) => write!(f, "{}", message),
+            XvError::Input(message) => write!(f, "{}", message),
+            XvError::Cancelled => write!(f, "Cancelled.")
+        }
+    }
+}
+
+impl Error for XvError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            XvError::Io(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for XvError {
+    fn from(error: io::Error) -> Self {
+        XvError::Io(error)
+    }
+}
+
+/// For the sources that are read through `io::Result`, like objects in cloud storage.
+impl From<XvError> for io::Error {
+    fn from(error: XvError) -> Self {
+        match error {
+            XvError::Io(error) => error,
+            error => io::Error::other(error)
+        }
+    }
+}
+
+impl From<ParseIntError> for XvError {
+    fn from(error: ParseIntError) -> Self {
+        XvError::Parse(format!("Not a number: {}.", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describing_errors() {
+        let not_found = XvError::from(io::Error::new(ErrorKind::NotFound, "os error 2"));
+        assert_eq!(format!("{}", not_found), "The file or directory does not exist.");
+        assert!(not_found.source().is_some());
+        let other = XvError::from(io::Error::new(ErrorKind::Other, "disk on fire"));
+        assert_eq!(format!("{}", other), "disk on fire");
+        let parse = XvError::from("x".parse::<u64>().unwrap_err());
+        assert_eq!(format!("{}", parse), "Not a number: invalid digit found in string.");
+        assert!(parse.source().is_none());
+        let unsupported = io::Error::from(XvError::Unsupported(String::from("No tool.")));
+        assert_eq!(format!("{}", XvError::from(unsupported)), "No tool.");
+    }
+}
@@ -0,0 +1,282 @@
+//! Exporting bytes, or the selected columns of every row, as hex dumps, JSON or firmware records,
+//! and importing records into a flat binary.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, SelectView, TextView};
+use xv::byte_reader::ByteSource;
+use xv::error::{Result, XvError};
+use xv::hex_dump::HexDump;
+use xv::hex_records::{parse_records, write_records, RecordFormat, SparseImage};
+use xv::progress::Progress;
+
+use crate::hex_view::{ColumnSelection, HexView};
+use crate::open_file_dialog::open_path;
+use crate::progress_dialog::run_with_progress;
+use crate::utilities::{expand_path, get_content, parse_number, parse_offset};
+use crate::xv_state::XvState;
+use crate::xv_tui::ShowError;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ExportFormat {
+    HexDump,
+    Json,
+    Records(RecordFormat)
+}
+
+const EXPORT_FORMATS: [(&str, ExportFormat, &str); 4] = [
+    ("Hex dump", ExportFormat::HexDump, "txt"),
+    ("JSON", ExportFormat::Json, "json"),
+    ("Intel HEX", ExportFormat::Records(RecordFormat::IntelHex), "hex"),
+    ("Motorola S-record", ExportFormat::Records(RecordFormat::SRecord), "srec")
+];
+
+pub fn open_export_dialog(s: &mut Cursive) {
+    let (selection, columns, length, path, address_map) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_selection(), v.get_column_selection(), v.get_length(), v.get_path(), v.get_address_map().clone())
+    }).unwrap();
+    // Export the selected bytes, if there are any, and otherwise the whole file.
+    let (start, end) = match columns {
+        Some(columns) => (columns.start, columns.start + columns.length()),
+        None => selection.unwrap_or((0, length))
+    };
+    let current_dir = s.with_user_data(|state: &mut XvState| {
+        state.current_directory().to_path_buf()
+    }).unwrap();
+    let file_name = path.file_name().map_or(String::from("export"), |name| name.to_string_lossy().into_owned());
+    let export_path = current_dir.join(format!("{}.txt", file_name));
+
+    let mut format_selector = SelectView::new().popup();
+    for (name, format, _) in EXPORT_FORMATS.iter() {
+        format_selector.add_item(*name, *format);
+    }
+    let format_selector = format_selector.on_select(|s, format: &ExportFormat| {
+        // Give the export file the extension of the format.
+        let extension = EXPORT_FORMATS.iter().find(|(_, f, _)| f == format).unwrap().2;
+        s.call_on_id("export_path", |v: &mut EditView| {
+            let path = expand_path(&v.get_content()).with_extension(extension);
+            v.set_content(format!("{}", path.display()));
+        });
+    });
+
+    let editors = LinearLayout::vertical()
+        .child(EditView::new()
+            .content(format!("0x{:X}", start))
+            .with_id("export_offset")
+            .min_width(18))
+        .child(EditView::new()
+            .content(format!("{}", end - start))
+            .with_id("export_length")
+            .min_width(18))
+        .child(EditView::new()
+            .content(format!("0x{:X}", address_map.address_of(start)))
+            .with_id("export_address")
+            .min_width(18))
+        .child(EditView::new()
+            .content(format!("{}", export_path.display()))
+            .with_id("export_path")
+            .min_width(40))
+        .child(format_selector.with_id("export_format"));
+
+    let explanation = match columns {
+        Some(columns) => format!(
+            "\nThe selected columns are exported, {} bytes of each\n\
+             of {} rows, as a line for every row, with the\n\
+             address of the row. The length is the number of\n\
+             rows times the number of columns.", columns.columns, columns.rows),
+        None => String::from(
+            "\nThe hex dump has the line width and groups of the\n\
+             view. As JSON, it is an array with an object for\n\
+             every line, with its address, bytes and text. The\n\
+             address is where the first byte is shown to be.")
+    };
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Offset:  \nLength:  \nAddress:  \nExport to:  \nFormat:  "))
+            .child(editors))
+        .child(TextView::new(explanation));
+
+    let dialog = Dialog::around(layout)
+        .title(if columns.is_some() { "Export columns" } else { "Export" })
+        .dismiss_button("Cancel")
+        .button("Export", move |s| do_export(s, columns));
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, move |s| do_export(s, columns));
+
+    s.add_layer(event_view)
+}
+
+/// Export the bytes from the offset, or the selected columns of the rows from the offset, where
+/// the column selection is moved to.
+fn do_export(s: &mut Cursive, columns: Option<ColumnSelection>) {
+    let offset_str = s.call_on_id("export_offset", get_content).unwrap();
+    let length_str = s.call_on_id("export_length", get_content).unwrap();
+    let address_str = s.call_on_id("export_address", get_content).unwrap();
+    let path_str = s.call_on_id("export_path", get_content).unwrap();
+    let format = s.call_on_id("export_format", |v: &mut SelectView<ExportFormat>| v.selection())
+        .unwrap()
+        .map_or(ExportFormat::HexDump, |format| *format);
+
+    s.pop_layer();
+
+    let (file_length, width, group) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_length(), v.get_line_width(), v.get_group())
+    }).unwrap();
+    let offset = match parse_offset(&offset_str, file_length) {
+        Ok(offset) => offset,
+        Err(error) => return s.show_error(error)
+    };
+    let length = match parse_number(&length_str) {
+        Ok(length) => length.min(file_length.saturating_sub(offset)),
+        Err(error) => return s.show_error(error)
+    };
+    let address = match parse_number(&address_str) {
+        Ok(address) => address,
+        Err(error) => return s.show_error(error)
+    };
+    let path = expand_path(&path_str);
+    // The columns of as many rows as the length covers are exported, from the row at the offset.
+    let columns = columns.map(|columns| {
+        let rows = length.div_ceil(columns.columns).min(columns.rows);
+        ColumnSelection { start: offset, rows, ..columns }
+    });
+    if let (Some(_), ExportFormat::Records(_)) = (columns, format) {
+        return s.show_error(XvError::Input(String::from("Columns can only be exported as a hex dump or JSON.")));
+    }
+    let span = columns.map_or(length, |columns| columns.row(columns.rows.saturating_sub(1)).end - offset)
+        .min(file_length - offset);
+    let length = columns.map_or(length, |columns| columns.length().min(length));
+    let source = s.call_on_id("hex_view", |v: &mut HexView| {
+        v.open_background_source(offset, span)
+    }).unwrap();
+    let (source, source_offset) = match source {
+        Ok(source) => source,
+        Err(error) => return s.show_error(error)
+    };
+
+    let export = move |progress: &Progress| -> Result<()> {
+        let file = OpenOptions::new().write(true).create_new(true).open(&path)?;
+        let reader: Box<Read> = match columns {
+            Some(columns) => Box::new(ColumnReader { source, offset: source_offset, columns, row: 0, column: 0, progress }),
+            None => Box::new(SourceReader { source, offset: source_offset, end: source_offset + length, progress })
+        };
+        // A line for each row of a column selection, with the address of the row.
+        let dump = |reader| HexDump::new(reader)
+            .start_offset(address)
+            .width(columns.map_or(width, |columns| columns.columns) as usize)
+            .line_stride(columns.map_or(width, |columns| columns.line_width))
+            .group(usize::from(group));
+        let mut writer = BufWriter::new(file);
+        let result = match format {
+            ExportFormat::HexDump => dump(reader).render_to(&mut writer),
+            ExportFormat::Json => dump(reader).render_json_to(&mut writer),
+            ExportFormat::Records(format) => write_records(format, reader, address, length, &mut writer)
+        }.and_then(|()| writer.flush());
+        if result.is_err() {
+            // Leave no half written export behind.
+            let _ = fs::remove_file(&path);
+        }
+        if progress.is_cancelled() {
+            return Err(XvError::Cancelled);
+        }
+        Ok(result?)
+    };
+    run_with_progress(s, "Exporting", length, export, move |s, ()| {
+        s.add_layer(Dialog::info(format!("Exported {} bytes from offset 0x{:X}.", length, offset)));
+    });
+}
+
+/// Import Intel HEX or S-records into a flat binary next to them, with the gaps between the
+/// records filled with the given byte, and open it with the lowest address as the base address.
+/// The gaps are written in the background, as they can be gigabytes long.
+pub fn import_records(s: &mut Cursive, path: &Path, fill: u8) -> Result<()> {
+    let records = parse_records(&fs::read_to_string(path)?)?;
+    if records.is_empty() {
+        return Err(XvError::Input(String::from("The file has no data records.")));
+    }
+    let mut image = SparseImage::new(&records);
+    let (start, length) = (image.start(), image.length()?);
+    let binary_path = path.with_extension("bin");
+    let file = OpenOptions::new().write(true).create_new(true).open(&binary_path)?;
+
+    let output_path = binary_path.clone();
+    let import = move |progress: &Progress| -> Result<()> {
+        let mut writer = BufWriter::new(file);
+        let result = image.write_to(fill, &mut writer, progress)
+            .and_then(|()| Ok(writer.flush()?));
+        if result.is_err() {
+            // Leave no half written image behind.
+            let _ = fs::remove_file(&output_path);
+        }
+        result
+    };
+    run_with_progress(s, "Importing", length, import, move |s, ()| {
+        open_path(s, binary_path.as_os_str());
+        s.call_on_id("hex_view", |v: &mut HexView| {
+            if v.get_path() == binary_path {
+                v.set_base_address(start);
+            }
+        });
+    });
+    Ok(())
+}
+
+/// Reads a range of a byte source, and reports how much of it has been read.
+struct SourceReader<'a> {
+    source: Box<ByteSource + Send>,
+    offset: u64,
+    end: u64,
+    progress: &'a Progress
+}
+
+/// Reads the selected columns of every row of a byte source, one row after the other.
+struct ColumnReader<'a> {
+    source: Box<ByteSource + Send>,
+    /// Where the columns of the first row start in the source.
+    offset: u64,
+    columns: ColumnSelection,
+    row: u64,
+    column: u64,
+    progress: &'a Progress
+}
+
+impl<'a> Read for ColumnReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.row == self.columns.rows {
+            return Ok(0);
+        }
+        let len = (self.columns.columns - self.column).min(buf.len() as u64) as usize;
+        let offset = self.offset + self.row * self.columns.line_width + self.column;
+        let read = self.source.read_at(offset, &mut buf[0..len])?;
+        self.column += read as u64;
+        if self.column == self.columns.columns {
+            self.row += 1;
+            self.column = 0;
+        }
+        if self.progress.advance(read as u64).is_err() {
+            return Err(io::Error::other("Cancelled."));
+        }
+        Ok(read)
+    }
+}
+
+impl<'a> Read for SourceReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = (self.end - self.offset).min(buf.len() as u64) as usize;
+        let read = self.source.read_at(self.offset, &mut buf[0..len])?;
+        self.offset += read as u64;
+        if self.progress.advance(read as u64).is_err() {
+            return Err(io::Error::other("Cancelled."));
+        }
+        Ok(read)
+    }
+}
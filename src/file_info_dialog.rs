@@ -0,0 +1,156 @@
+//! The dialog that shows what the file system and the first bytes tell about the file.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::views::{Dialog, LinearLayout, OnEventView, TextView};
+use xv::magic::{mime_type, MAGIC_LENGTH};
+
+use crate::hex_view::HexView;
+use crate::utilities::{describe_size, describe_time};
+
+pub fn open_file_info_dialog(s: &mut Cursive) {
+    let (path, is_file, length, start, tile_config, tile_stats) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_path(), v.is_file(), v.get_length(), v.read_bytes(0, MAGIC_LENGTH).unwrap_or_default(),
+         v.get_tile_config(), v.get_tile_stats())
+    }).unwrap();
+
+    let mut info = vec![
+        ("Path", format!("{}", path.display())),
+        ("Size", format!("{} bytes ({})", length, describe_size(length as f64))),
+        ("Type", String::from(mime_type(&start)))
+    ];
+    if is_file {
+        if let Err(error) = describe_file(&path, length, &mut info) {
+            info.push(("Error", format!("{}", error)));
+        }
+    } else {
+        info.push(("Source", String::from("In memory")));
+    }
+    if tile_config.tile_size == 0 {
+        info.push(("Tiles", String::from("None, read directly")));
+    } else {
+        info.push(("Tiles", format!("{} bytes, {} read ahead", tile_config.tile_size, tile_config.read_ahead)));
+        info.push(("Tile reads", format!("{} fetched, {} read ahead, {} cache hits",
+                                         tile_stats.fetched, tile_stats.read_ahead, tile_stats.hits)));
+    }
+
+    let labels: Vec<String> = info.iter().map(|(label, _)| format!("{}:  ", label)).collect();
+    let values: Vec<&str> = info.iter().map(|(_, value)| value.as_str()).collect();
+    let layout = LinearLayout::horizontal()
+        .child(TextView::new(labels.join("\n")))
+        .child(TextView::new(values.join("\n")));
+
+    let dialog = Dialog::around(layout)
+        .title("File info")
+        .dismiss_button("Ok");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+fn describe_file(path: &Path, length: u64, info: &mut Vec<(&str, String)>) -> io::Result<()> {
+    let link_metadata = fs::symlink_metadata(path)?;
+    if link_metadata.file_type().is_symlink() {
+        info.push(("Link to", format!("{}", fs::read_link(path)?.display())));
+    }
+    let metadata = fs::metadata(path)?;
+    describe_owner(&metadata, length, info);
+    info.push(("Modified", describe_time_result(metadata.modified())));
+    info.push(("Accessed", describe_time_result(metadata.accessed())));
+    info.push(("Created", describe_time_result(metadata.created())));
+    Ok(())
+}
+
+fn describe_time_result(time: io::Result<SystemTime>) -> String {
+    match time {
+        Ok(time) => describe_time(time),
+        Err(_) => String::from("unknown")
+    }
+}
+
+#[cfg(unix)]
+fn describe_owner(metadata: &fs::Metadata, length: u64, info: &mut Vec<(&str, String)>) {
+    use std::os::unix::fs::MetadataExt;
+
+    let mode = metadata.mode();
+    info.push(("Permissions", format!("{} ({:04o})", describe_mode(mode), mode & 0o7777)));
+    info.push(("Owner", describe_id(metadata.uid(), "/etc/passwd")));
+    info.push(("Group", describe_id(metadata.gid(), "/etc/group")));
+    info.push(("Inode", format!("{} on device 0x{:X}", metadata.ino(), metadata.dev())));
+    info.push(("Hard links", format!("{}", metadata.nlink())));
+    // The blocks are always 512 bytes, whatever the block size of the file system is.
+    let allocated = metadata.blocks() * 512;
+    let sparse = if metadata.is_file() && allocated < length { "yes" } else { "no" };
+    info.push(("Sparse", format!("{}, {} bytes allocated", sparse, allocated)));
+}
+
+#[cfg(not(unix))]
+fn describe_owner(metadata: &fs::Metadata, _length: u64, info: &mut Vec<(&str, String)>) {
+    let read_only = if metadata.permissions().readonly() { "read only" } else { "read and write" };
+    info.push(("Permissions", String::from(read_only)));
+}
+
+/// Describe a user or group id, with the name that it has in the passwd or group file.
+#[cfg(unix)]
+fn describe_id(id: u32, database: &str) -> String {
+    let name = fs::read_to_string(database).ok().and_then(|entries| {
+        entries.lines()
+            .map(|line| line.split(':').collect::<Vec<&str>>())
+            .find(|fields| fields.len() > 2 && fields[2] == id.to_string())
+            .map(|fields| String::from(fields[0]))
+    });
+    match name {
+        Some(name) => format!("{} ({})", name, id),
+        None => format!("{}", id)
+    }
+}
+
+/// Describe the file type and permission bits of a mode the way `ls -l` does, like `-rw-r--r--`.
+#[cfg(unix)]
+fn describe_mode(mode: u32) -> String {
+    let file_type = match mode & 0o170_000 {
+        0o040_000 => 'd',
+        0o120_000 => 'l',
+        0o020_000 => 'c',
+        0o060_000 => 'b',
+        0o010_000 => 'p',
+        0o140_000 => 's',
+        _ => '-'
+    };
+    let mut text = file_type.to_string();
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')].iter() {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => *special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-'
+        });
+    }
+    text
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describing_modes() {
+        assert_eq!(describe_mode(0o100_644), "-rw-r--r--");
+        assert_eq!(describe_mode(0o040_755), "drwxr-xr-x");
+        assert_eq!(describe_mode(0o104_755), "-rwsr-xr-x");
+        assert_eq!(describe_mode(0o041_777), "drwxrwxrwt");
+        assert_eq!(describe_mode(0o102_640), "-rw-r-S---");
+    }
+}
@@ -0,0 +1,118 @@
+//! Running the bytes through a shell command, and reading back what it writes.
+
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use xv::error::{Result, XvError};
+use xv::progress::Progress;
+
+/// The number of bytes of input that are written to the command at once.
+const INPUT_CHUNK: usize = 64 * 1024;
+
+/// How often a running command is checked for having exited or having been cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Run a shell command with the given bytes as its standard input, and return what it writes
+/// to its standard output. The command fails if it exits with an error status. The progress is
+/// how much of the input has been written, and cancelling it kills the command.
+pub fn run_filter(command: &str, input: Vec<u8>, progress: &Progress) -> Result<Vec<u8>> {
+    let mut child = shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // The input is written and the output is read on other threads, so a command that writes a
+    // lot to one pipe before reading or writing another cannot block us both.
+    let mut stdin = child.stdin.take().unwrap();
+    let input_progress = progress.clone();
+    let writer = thread::spawn(move || {
+        for chunk in input.chunks(INPUT_CHUNK) {
+            // A command is allowed to stop reading its input early, like `head` does.
+            if stdin.write_all(chunk).is_err() || input_progress.advance(chunk.len() as u64).is_err() {
+                break;
+            }
+        }
+    });
+    let mut stdout = child.stdout.take().unwrap();
+    let output = thread::spawn(move || -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output)?;
+        Ok(output)
+    });
+    let mut stderr = child.stderr.take().unwrap();
+    let errors = thread::spawn(move || -> io::Result<String> {
+        let mut errors = String::new();
+        stderr.read_to_string(&mut errors)?;
+        Ok(errors)
+    });
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if progress.is_cancelled() {
+            // The threads are left to finish when the pipes are closed.
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(XvError::Cancelled);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    writer.join().unwrap();
+    let output = output.join().unwrap()?;
+    let errors = errors.join().unwrap()?;
+
+    if status.success() {
+        Ok(output)
+    } else {
+        let errors = errors.trim();
+        Err(XvError::Input(if errors.is_empty() {
+            format!("The command failed: {}.", status)
+        } else {
+            format!("The command failed: {}.\n\n{}", status, errors)
+        }))
+    }
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filtering_through_commands() {
+        let filter = |command, input: Vec<u8>| {
+            let progress = Progress::new(input.len() as u64);
+            run_filter(command, input, &progress)
+        };
+        assert_eq!(filter("tr a-z A-Z", b"hello".to_vec()).unwrap(), b"HELLO");
+        assert_eq!(filter("head -c 2", vec![7; 1 << 20]).unwrap(), vec![7, 7]);
+        let error = filter("echo oops >&2; exit 3", Vec::new()).unwrap_err();
+        assert!(format!("{}", error).ends_with("oops"));
+        let errors = "for i in $(seq 20000); do echo oops >&2; done; echo done";
+        assert_eq!(filter(errors, Vec::new()).unwrap(), b"done\n");
+
+        let progress = Progress::new(0);
+        progress.cancel();
+        match run_filter("sleep 10", Vec::new(), &progress) {
+            Err(XvError::Cancelled) => {},
+            result => panic!("{:?}", result)
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! The dialog that pipes a range of the file through a shell command, and views the output.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
+use xv::byte_reader::TilingByteReader;
+use xv::error::{Result, XvError};
+use xv::hex_reader::HexReader;
+use xv::progress::Progress;
+
+use crate::filter::run_filter;
+use crate::hex_view::HexView;
+use crate::progress_dialog::run_with_progress;
+use crate::utilities::{get_content, parse_number, parse_offset};
+use crate::xv_state::XvState;
+use crate::xv_tui::ShowError;
+
+const MAX_FILTER_INPUT: u64 = 64 * 1024 * 1024;
+
+pub fn open_filter_dialog(s: &mut Cursive) {
+    let (offset, length) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_offset(), v.get_length())
+    }).unwrap();
+
+    let editors = LinearLayout::vertical()
+        .child(EditView::new()
+            .content(format!("0x{:X}", offset))
+            .with_id("filter_offset")
+            .min_width(18))
+        .child(EditView::new()
+            .content(format!("{}", length.saturating_sub(offset).min(MAX_FILTER_INPUT)))
+            .with_id("filter_length")
+            .min_width(18))
+        .child(EditView::new()
+            .with_id("filter_command")
+            .min_width(40));
+
+    let layout = LinearLayout::horizontal()
+        .child(TextView::new("Offset:  \nLength:  \nCommand:  "))
+        .child(editors);
+
+    let dialog = Dialog::around(layout)
+        .title("Filter through command")
+        .dismiss_button("Cancel")
+        .button("Run", do_filter);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_filter);
+
+    s.add_layer(event_view)
+}
+
+fn do_filter(s: &mut Cursive) {
+    let offset_str = s.call_on_id("filter_offset", get_content).unwrap();
+    let length_str = s.call_on_id("filter_length", get_content).unwrap();
+    let command = s.call_on_id("filter_command", get_content).unwrap();
+
+    s.pop_layer();
+
+    let file_length = s.call_on_id("hex_view", |v: &mut HexView| v.get_length()).unwrap();
+    let offset = match parse_offset(&offset_str, file_length) {
+        Ok(offset) => offset,
+        Err(error) => return s.show_error(error)
+    };
+    let length = match parse_number(&length_str) {
+        Ok(length) if length <= MAX_FILTER_INPUT => length as usize,
+        Ok(_) => return s.show_error(XvError::Input(
+            format!("At most {} bytes can be filtered.", MAX_FILTER_INPUT))),
+        Err(error) => return s.show_error(error)
+    };
+    if command.trim().is_empty() {
+        return s.show_error(XvError::Input(String::from("No command was given.")));
+    }
+
+    let input = match s.call_on_id("hex_view", |v: &mut HexView| v.read_bytes(offset, length)).unwrap() {
+        Ok(input) => input,
+        Err(error) => return s.show_error(error)
+    };
+
+    let total = input.len() as u64;
+    let command = command.to_string();
+    let filter = move |progress: &Progress| run_filter(&command, input, progress)
+        .map(|output| (command, output));
+    run_with_progress(s, "Filtering", total, filter, |s, (command, output)| {
+        let reader = match open_filter_output(&command, output) {
+            Ok(reader) => reader,
+            Err(error) => return s.show_error(error)
+        };
+
+        let current_file = s.call_on_id("hex_view", |view: &mut HexView| {
+            view.get_reader_state()
+        }).unwrap();
+        s.with_user_data(|state: &mut XvState| state.close_reader(current_file));
+        s.call_on_id("hex_view", |view: &mut HexView| {
+            view.switch_reader(reader);
+        });
+    });
+}
+
+fn open_filter_output(command: &str, output: Vec<u8>) -> Result<HexReader> {
+    let name = format!("Output of {}", command.trim());
+    HexReader::new(TilingByteReader::from_source(output, name)?)
+}
@@ -2,17 +2,20 @@ use cursive::Cursive;
 use cursive::event::Key;
 use cursive::traits::{Boxable, Identifiable};
 use cursive::views::{Dialog, DummyView, EditView, LinearLayout, OnEventView, TextView};
+use xv::address_map::AddressMap;
 
 use crate::hex_view::HexView;
-use crate::utilities::{get_content, parse_number_or_zero};
+use crate::utilities::{get_content, parse_address, parse_number_or_zero};
+use crate::xv_tui::ShowError;
 
 pub fn open_goto_dialog(s: &mut Cursive) {
-    let (line_width, length) = s.call_on_id(
-        "hex_view", |v: &mut HexView| (v.get_line_width(), v.get_length())).unwrap();
-    let last_line_idx = length / line_width;
+    let (line_width, length, last_line_idx, address_map) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_line_width(), v.get_length(), v.get_last_line(), v.get_address_map().clone())
+    }).unwrap();
+    let start_address = address_map.address_of(0);
     
     let edit_boxes = LinearLayout::horizontal()
-        .child(EditView::new().content("0").with_id("offset").min_width(18))
+        .child(EditView::new().content(format!("0x{:X}", start_address)).with_id("offset").min_width(18))
         .child(TextView::new(" + "))
         .child(EditView::new().content("0").with_id("mul1").min_width(18))
         .child(TextView::new(" * "))
@@ -26,7 +29,14 @@ pub fn open_goto_dialog(s: &mut Cursive) {
     let layout = LinearLayout::vertical()
         .child(edit_boxes)
         .child(DummyView)
-        .child(info_boxes);
+        .child(info_boxes)
+        .child(DummyView)
+        .child(TextView::new("The offset can also be a percentage of the file, like 50%,\nor count back from the end of the file, like -0x200."));
+    let layout = if address_map != AddressMap::default() {
+        layout.child(TextView::new(format!("Other offsets are addresses, and the file starts at 0x{:X}.", start_address)))
+    } else {
+        layout
+    };
     
     let dialog = Dialog::around(layout)
         .dismiss_button("Cancel")
@@ -49,7 +59,13 @@ fn do_goto(s: &mut Cursive) {
     
     s.pop_layer();
 
-    let offset = parse_number_or_zero(&offset_str);
+    let result = s.call_on_id("hex_view", |v: &mut HexView| {
+        parse_address(&offset_str, v.get_length(), v.get_address_map())
+    }).unwrap();
+    let offset = match result {
+        Ok(offset) => offset,
+        Err(error) => return s.show_error(error)
+    };
     let mul1 = parse_number_or_zero(&mul1_str);
     let mul2 = parse_number_or_zero(&mul2_str);
     
@@ -59,3 +75,4 @@ fn do_goto(s: &mut Cursive) {
         view.go_to_offset(target);
     });
 }
+
@@ -0,0 +1,99 @@
+//! What the boundaries between groups of bytes are shown as, with larger groups that are set
+//! apart differently, like a space every 4 bytes and a bar every 16.
+
+use serde_derive::{Deserialize, Serialize};
+use xv::hex_tables::GROUP_SEP;
+
+/// What a group boundary is shown as.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Separator {
+    /// A broken bar, in place of the space between two bytes.
+    Broken,
+    /// An extra space.
+    Space,
+    Bar,
+    Dot,
+    /// A space in another color.
+    Color
+}
+
+pub const SEPARATORS: [Separator; 5] =
+    [Separator::Broken, Separator::Space, Separator::Bar, Separator::Dot, Separator::Color];
+
+impl Separator {
+    pub fn name(self) -> &'static str {
+        match self {
+            Separator::Broken => "broken",
+            Separator::Space => "space",
+            Separator::Bar => "bar",
+            Separator::Dot => "dot",
+            Separator::Color => "color"
+        }
+    }
+
+    /// The text that is printed at the boundary.
+    pub fn text(self) -> &'static str {
+        match self {
+            Separator::Broken => GROUP_SEP,
+            Separator::Space | Separator::Color => " ",
+            Separator::Bar => "|",
+            Separator::Dot => "\u{00B7}"
+        }
+    }
+}
+
+/// The separators of the groups, and of the larger groups that are made of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupSeparators {
+    groups: Separator,
+    /// The larger groups, by the number of bytes in them, from the smallest to the largest.
+    levels: Vec<(u64, Separator)>
+}
+
+impl Default for GroupSeparators {
+    fn default() -> Self {
+        GroupSeparators { groups: Separator::Broken, levels: Vec::new() }
+    }
+}
+
+impl GroupSeparators {
+    /// Choose the separator of the groups.
+    pub fn set_groups(&mut self, separator: Separator) {
+        self.groups = separator;
+    }
+
+    /// Set apart every `every` bytes with the separator, or stop setting them apart.
+    pub fn set_level(&mut self, every: u64, separator: Option<Separator>) {
+        self.levels.retain(|&(level, _)| level != every);
+        if let Some(separator) = separator {
+            self.levels.push((every, separator));
+            self.levels.sort_by_key(|&(level, _)| level);
+        }
+    }
+
+    /// The separator of the group boundary before a column, which is that of the largest group
+    /// that starts there.
+    pub fn at(&self, column: u64) -> Separator {
+        self.levels.iter().rev()
+            .find(|&&(every, _)| column.is_multiple_of(every))
+            .map_or(self.groups, |&(_, separator)| separator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nesting_separators() {
+        let mut separators = GroupSeparators::default();
+        separators.set_groups(Separator::Space);
+        separators.set_level(16, Some(Separator::Bar));
+        separators.set_level(64, Some(Separator::Color));
+        assert_eq!(separators.at(4), Separator::Space);
+        assert_eq!(separators.at(32), Separator::Bar);
+        assert_eq!(separators.at(128), Separator::Color);
+        separators.set_level(64, None);
+        assert_eq!(separators.at(128), Separator::Bar);
+    }
+}
@@ -0,0 +1,221 @@
+//! Rendering the bytes of any `Read` source as text, the way the hex view shows them, or as JSON.
+
+use std::io::{ErrorKind, Read, Result, Write};
+
+use serde_derive::Serialize;
+use unicode_width::UnicodeWidthStr;
+
+use crate::hex_reader::{HexMode, VisualMode};
+use crate::hex_tables::GROUP_SEP;
+
+/// Renders the bytes of a source as lines of text, with an offsets column, a hex column, and a
+/// visual column, like the XV hex viewer does.
+///
+/// The offsets are 8 hex digits wide, and grow to 16 digits when they no longer fit.
+pub struct HexDump<R> {
+    source: R,
+    start_offset: u64,
+    width: usize,
+    line_stride: Option<u64>,
+    group: usize,
+    vis_mode: VisualMode,
+    hex_mode: HexMode
+}
+
+impl<R: Read> HexDump<R> {
+    /// Create a hex dump of the given source, with 16 bytes per line in groups of 8.
+    pub fn new(source: R) -> HexDump<R> {
+        HexDump {
+            source,
+            start_offset: 0,
+            width: 16,
+            line_stride: None,
+            group: 8,
+            vis_mode: VisualMode::Unicode,
+            hex_mode: HexMode::Hex
+        }
+    }
+
+    /// The offset that is shown for the first byte of the source, when the source is a part of a
+    /// larger file.
+    pub fn start_offset(mut self, offset: u64) -> Self {
+        self.start_offset = offset;
+        self
+    }
+
+    /// The number of bytes on each line.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width.max(1);
+        self
+    }
+
+    /// How far apart the offsets of the lines are, when each line is a part of a longer line, like
+    /// the columns of every row of a table. By default it is the width.
+    pub fn line_stride(mut self, stride: u64) -> Self {
+        self.line_stride = Some(stride);
+        self
+    }
+
+    /// The number of bytes in each group, that are set apart by a separator.
+    pub fn group(mut self, group: usize) -> Self {
+        self.group = group.max(1);
+        self
+    }
+
+    /// How the visual column shows the bytes. `VisualMode::Off` leaves the column out.
+    pub fn visual_mode(mut self, mode: VisualMode) -> Self {
+        self.vis_mode = mode;
+        self
+    }
+
+    /// How the hex column shows the bytes.
+    pub fn hex_mode(mut self, mode: HexMode) -> Self {
+        self.hex_mode = mode;
+        self
+    }
+
+    /// Read the source to the end, and write the rendered lines to the writer.
+    pub fn render_to<W: Write>(mut self, mut writer: W) -> Result<()> {
+        let hex_table = self.hex_mode.table();
+        let vis_table = self.vis_mode.table();
+        let byte_width = hex_table[0].len();
+        let hex_column_width = self.width * (byte_width + 1) - 1;
+        let mut line = vec![0; self.width];
+        let mut offset = self.start_offset;
+
+        loop {
+            let len = self.fill_line(&mut line)?;
+            if len == 0 {
+                return Ok(());
+            }
+            let bytes = &line[0..len];
+
+            let mut text = if offset > u64::from(u32::MAX) {
+                format!("0x{:016X}  ", offset)
+            } else {
+                format!("0x{:08X}  ", offset)
+            };
+            let hex_start = text.width();
+            for (i, b) in bytes.iter().enumerate() {
+                if i > 0 {
+                    text.push_str(if i % self.group == 0 { GROUP_SEP } else { " " });
+                }
+                text.push_str(hex_table[usize::from(*b)]);
+            }
+            if let VisualMode::Off = self.vis_mode {
+                text.push('\n');
+            } else {
+                let padding = hex_column_width - (text.width() - hex_start);
+                text.push_str(&" ".repeat(padding + 2));
+                for (i, b) in bytes.iter().enumerate() {
+                    if i > 0 && i % self.group == 0 {
+                        text.push_str(GROUP_SEP);
+                    }
+                    text.push_str(vis_table[usize::from(*b)]);
+                }
+                text.push('\n');
+            }
+            writer.write_all(text.as_bytes())?;
+            offset += self.line_stride.unwrap_or(len as u64);
+        }
+    }
+
+    /// Read the source to the end, and write it to the writer as a JSON array, with an object for
+    /// every line, like `{"offset":16,"bytes":[72,105],"text":"Hi"}`. The text is the visual
+    /// column, and is empty when the visual mode is off.
+    pub fn render_json_to<W: Write>(mut self, mut writer: W) -> Result<()> {
+        let vis_table = self.vis_mode.table();
+        let mut line = vec![0; self.width];
+        let mut offset = self.start_offset;
+
+        writer.write_all(b"[")?;
+        loop {
+            let len = self.fill_line(&mut line)?;
+            if len == 0 {
+                break;
+            }
+            let bytes = &line[0..len];
+            let text = match self.vis_mode {
+                VisualMode::Off => String::new(),
+                _ => bytes.iter().map(|b| vis_table[usize::from(*b)]).collect()
+            };
+            if offset > self.start_offset {
+                writer.write_all(b",")?;
+            }
+            writer.write_all(b"\n  ")?;
+            serde_json::to_writer(&mut writer, &JsonLine { offset, bytes, text })?;
+            offset += self.line_stride.unwrap_or(len as u64);
+        }
+        writer.write_all(if offset > self.start_offset { b"\n]\n" } else { b"]\n" })
+    }
+
+    fn fill_line(&mut self, line: &mut [u8]) -> Result<usize> {
+        let mut len = 0;
+        while len < line.len() {
+            match self.source.read(&mut line[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(len)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    offset: u64,
+    bytes: &'a [u8],
+    text: String
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render<R: Read>(dump: HexDump<R>) -> String {
+        let mut out = Vec::new();
+        dump.render_to(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn rendering_lines_and_groups() {
+        let dump = HexDump::new(&b"0123456789"[..]).width(4).group(2);
+        assert_eq!(render(dump),
+                   "0x00000000  30 31\u{00A6}32 33  01\u{00A6}23\n\
+                    0x00000004  34 35\u{00A6}36 37  45\u{00A6}67\n\
+                    0x00000008  38 39        89\n");
+    }
+
+    #[test]
+    fn rendering_without_visual_column() {
+        let dump = HexDump::new(&b"\x00\xFF"[..])
+            .visual_mode(VisualMode::Off)
+            .hex_mode(HexMode::Bits);
+        assert_eq!(render(dump), "0x00000000  00000000 11111111\n");
+        assert_eq!(render(HexDump::new(&b""[..])), "");
+    }
+
+    #[test]
+    fn rendering_json() {
+        let dump = HexDump::new(&b"Hi\x00there"[..]).width(4).start_offset(0x10);
+        let mut out = Vec::new();
+        dump.render_json_to(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "[\n  {\"offset\":16,\"bytes\":[72,105,0,116],\"text\":\"Hi\u{2400}t\"},\n  \
+                    {\"offset\":20,\"bytes\":[104,101,114,101],\"text\":\"here\"}\n]\n");
+        let mut out = Vec::new();
+        HexDump::new(&b""[..]).render_json_to(&mut out).unwrap();
+        assert_eq!(out, b"[]\n");
+    }
+
+    #[test]
+    fn rendering_parts_of_longer_lines() {
+        let dump = HexDump::new(&b"ABCD"[..]).width(2).line_stride(0x10).start_offset(4);
+        assert_eq!(render(dump),
+                   "0x00000004  41 42  AB\n\
+                    0x00000014  43 44  CD\n");
+    }
+}
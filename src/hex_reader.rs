@@ -1,35 +1,273 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::convert::TryFrom;
-use std::io::Result;
+use std::fmt::Write;
+use std::ops::Range;
 use std::path::PathBuf;
 
-use crate::byte_reader::TilingByteReader;
+use serde_derive::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthChar;
+
+use crate::address_map::AddressMap;
+use crate::byte_reader::{ByteSource, TileConfig, TileStats, TilingByteReader, Window};
+use crate::byte_stats::ByteStats;
+use crate::error::{Result, XvError};
 use crate::hex_tables::*;
+use crate::progress::Progress;
+use crate::spool::Spool;
+
+const SCAN_CHUNK_SIZE: usize = 64 * 1024;
+const ENTROPY_WINDOW: usize = 512;
+const ENTROPY_STEP: u64 = 64;
+/// The entropy, in bits per byte, that high-entropy regions, like compressed data, have at least.
+pub const HIGH_ENTROPY: f64 = 7.0;
+/// The entropy that low-entropy regions, like padding, have at most.
+pub const LOW_ENTROPY: f64 = 2.0;
 
-#[derive(Copy, Clone, Debug)]
+/// The visual modes are stored by their names, like "Unicode", so that states saved by older
+/// versions can still be read.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
 pub enum VisualMode {
+    #[default]
     Unicode,
     Ascii,
     Off
 }
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum HexMode {
+    #[default]
+    Hex,
+    Bits
+}
+
+impl From<VisualMode> for String {
+    fn from(mode: VisualMode) -> Self {
+        match mode {
+            VisualMode::Unicode => String::from("Unicode"),
+            VisualMode::Ascii => String::from("Ascii"),
+            VisualMode::Off => String::from("Off"),
+        }
+    }
+}
+
+impl From<String> for VisualMode {
+    fn from(mode: String) -> Self {
+        match mode.as_str() {
+            "Unicode" => VisualMode::Unicode,
+            "Ascii" => VisualMode::Ascii,
+            "Off" => VisualMode::Off,
+            _ => VisualMode::Unicode
+        }
+    }
+}
+
+impl VisualMode {
+    /// The text that each byte value is shown as in the visual column.
+    pub fn table(self) -> &'static [&'static str; 256] {
+        match self {
+            VisualMode::Unicode => UNICODE_TEXT_TABLE,
+            VisualMode::Ascii => ASCII_TEXT_TABLE,
+            VisualMode::Off => ASCII_TEXT_TABLE
+        }
+    }
+}
+
+/// How the text of the visual column is rendered to make it easier to scan, which is chosen for
+/// every visual mode.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextRendering {
+    /// Control characters are shown in caret notation, like `^M` for a carriage return. Every byte
+    /// then takes up two cells, so that the columns stay aligned.
+    pub caret: bool,
+    /// In the Unicode mode, no-break spaces (0xA0) and soft hyphens (0xAD) are shown as symbols of
+    /// their own, instead of like every other byte above 0x7F.
+    pub distinct: bool,
+    /// Letters are shown in lowercase, and the symbols of the Unicode mode as the ASCII mode shows
+    /// them, so that text reads the same whatever its case.
+    pub fold: bool,
+    /// In the Unicode mode, the characters of more than one byte are decoded as UTF-8.
+    #[serde(default)]
+    pub utf8: bool
+}
+
+impl TextRendering {
+    /// The number of cells that every byte takes up.
+    pub fn width(self) -> usize {
+        if self.caret { 2 } else { 1 }
+    }
+    
+    /// The text that the byte is shown as in the visual mode.
+    pub fn render(self, mode: VisualMode, byte: u8) -> Cow<'static, str> {
+        let text = if self.caret && (byte < 0x20 || byte == 0x7F) {
+            return Cow::Owned(format!("^{}", char::from(byte ^ 0x40)));
+        } else if self.distinct && mode == VisualMode::Unicode && byte == 0xA0 {
+            "\u{237D}"
+        } else if self.distinct && mode == VisualMode::Unicode && byte == 0xAD {
+            "\u{2010}"
+        } else if self.fold {
+            ASCII_TEXT_TABLE[usize::from(byte.to_ascii_lowercase())]
+        } else {
+            mode.table()[usize::from(byte)]
+        };
+        if self.caret { Cow::Owned(format!("{} ", text)) } else { Cow::Borrowed(text) }
+    }
+}
+
+/// How a byte is shown in the visual column when the text is decoded as UTF-8.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Utf8Cell {
+    /// The byte is shown by itself, from the visual table.
+    Byte,
+    /// The byte starts a character of `bytes` bytes, which is shown as the text, padded to fill
+    /// the cells of all of them.
+    Char { text: String, bytes: usize },
+    /// The byte is in the cells of the character that an earlier byte started.
+    Covered
+}
+
+/// Decode a row of bytes as UTF-8, for a visual column with `cell_width` cells for every byte.
+/// Every character of more than one byte takes up the cells of its bytes, so that the columns stay
+/// aligned whatever the width of the character: wide characters fit in them, and characters with no
+/// width, like combining accents, are shown on a dotted circle. Characters that are cut off by the
+/// end of the row, or by a separator, which `is_break` tells is drawn before a byte, are shown
+/// byte by byte, like invalid bytes and control characters are.
+pub fn utf8_cells<F>(bytes: &[u8], cell_width: usize, is_break: F) -> Vec<Utf8Cell>
+    where F: Fn(usize) -> bool {
+    let mut cells = Vec::with_capacity(bytes.len());
+    while cells.len() < bytes.len() {
+        let start = cells.len();
+        let len = match bytes[start] {
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => 1
+        };
+        let decoded = bytes.get(start..start + len)
+            .filter(|_| len > 1 && !(start + 1..start + len).any(&is_break))
+            .and_then(|sequence| std::str::from_utf8(sequence).ok())
+            .and_then(|text| text.chars().next())
+            .filter(|c| !c.is_control());
+        match decoded {
+            Some(c) => {
+                let mut text = match c.width() {
+                    Some(0) | None => format!("\u{25CC}{}", c),
+                    _ => c.to_string()
+                };
+                let width = c.width().unwrap_or(0).max(1);
+                for _ in width..len * cell_width {
+                    text.push(' ');
+                }
+                cells.push(Utf8Cell::Char { text, bytes: len });
+                cells.extend((1..len).map(|_| Utf8Cell::Covered));
+            },
+            None => cells.push(Utf8Cell::Byte)
+        }
+    }
+    cells
+}
+
+/// The text rendering of each of the visual modes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextRenderings {
+    unicode: TextRendering,
+    ascii: TextRendering
+}
+
+impl TextRenderings {
+    pub fn of(&self, mode: VisualMode) -> TextRendering {
+        match mode {
+            VisualMode::Unicode => self.unicode,
+            VisualMode::Ascii => self.ascii,
+            VisualMode::Off => TextRendering::default()
+        }
+    }
+    
+    pub fn set(&mut self, mode: VisualMode, rendering: TextRendering) {
+        match mode {
+            VisualMode::Unicode => self.unicode = rendering,
+            VisualMode::Ascii => self.ascii = rendering,
+            VisualMode::Off => {}
+        }
+    }
+}
+
+impl HexMode {
+    /// The text that each byte value is shown as in the hex column.
+    pub fn table(self) -> &'static [&'static str; 256] {
+        match self {
+            HexMode::Hex => BYTE_RENDER,
+            HexMode::Bits => BIT_RENDER
+        }
+    }
+}
+
 pub trait OffsetsVisitor {
-    fn offset(&mut self, offset: &str);
+    /// Called for every row in the window, with the file offset of the first byte shown in the
+    /// row, which moves along when the window is scrolled sideways, and the line number of the
+    /// row. Use an `OffsetFormat` to turn these into the text of the offsets column.
+    fn offset(&mut self, offset: u64, line: u64);
     
     fn end(&mut self);
 }
 
+/// How the row offsets are written in the offsets column.
+#[derive(Clone, Debug)]
+pub struct OffsetFormat {
+    digits: usize,
+    address_map: AddressMap,
+    record_mode: bool
+}
+
+impl OffsetFormat {
+    /// Write the text for a row into the buffer, replacing what was there. The same buffer can be
+    /// reused for every row, so nothing is allocated once it has grown to fit an offset.
+    pub fn write(&self, offset: u64, line: u64, buf: &mut String) {
+        buf.clear();
+        if self.record_mode {
+            let digits = self.width() - 1;
+            write!(buf, "#{:0width$}", line, width = digits).unwrap();
+        } else {
+            let address = self.address_map.address_of(offset);
+            write!(buf, "0x{:0width$X}", address, width = self.digits).unwrap();
+        }
+    }
+    
+    /// The number of characters written for every row.
+    pub fn width(&self) -> usize {
+        self.digits + 2
+    }
+}
+
 pub trait HexVisitor {
-    fn byte(&mut self, index: usize);
+    /// Called for every captured byte, with the byte value as an index into the hex table, and
+    /// the file offset of the byte.
+    fn byte(&mut self, index: usize, offset: u64);
     
     fn group(&mut self);
     
+    /// Called between two bytes that are on either side of a field guide.
+    fn field(&mut self);
+    
     fn next_line(&mut self);
     
     fn end(&mut self);
 }
 
+/// Receives the captured bytes a whole line at a time, for renderers and exporters that work on
+/// rows in bulk.
+pub trait LineVisitor {
+    /// Called for every row in the window, with the file offset of the first byte in the row.
+    fn line(&mut self, offset: u64, bytes: &[u8]);
+    
+    fn end(&mut self);
+}
+
 pub trait VisualVisitor {
-    fn visual_element(&mut self, index: usize);
+    /// Called for every captured byte, with the byte value as an index into the visual table, and
+    /// the file offset of the byte.
+    fn visual_element(&mut self, index: usize, offset: u64);
     
     fn group(&mut self);
     
@@ -38,15 +276,184 @@ pub trait VisualVisitor {
     fn end(&mut self);
 }
 
+/// How the bytes are changed before they are shown, for captures whose bytes arrived in another
+/// order than they are read in, like from hardware that sends the least significant bit first.
+/// Only what is shown is changed, never the file.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DisplayTransform {
+    pub swap_nibbles: bool,
+    pub reverse_bits: bool,
+    /// Show the bytes of every group in reverse order.
+    pub reverse_groups: bool
+}
+
+impl DisplayTransform {
+    pub fn apply(self, byte: u8) -> u8 {
+        let byte = if self.swap_nibbles { byte.rotate_left(4) } else { byte };
+        if self.reverse_bits { byte.reverse_bits() } else { byte }
+    }
+    
+    pub fn is_identity(self) -> bool {
+        self == DisplayTransform::default()
+    }
+    
+    /// Describe the transform, like "swapped nibbles, reversed groups".
+    pub fn describe(self) -> String {
+        let names = [(self.swap_nibbles, "swapped nibbles"), (self.reverse_bits, "reversed bits"),
+                     (self.reverse_groups, "reversed groups")];
+        let names: Vec<&str> = names.iter().filter(|(on, _)| *on).map(|(_, name)| *name).collect();
+        names.join(", ")
+    }
+    
+    /// Wrap a hex or visual visitor, so that it is given the transformed bytes. When the groups are
+    /// reversed, the bytes of every group are given in reverse order, but the offsets are still
+    /// given in order, so that the visitor can tell where it is in the line.
+    pub fn visitor<'v, V>(self, inner: &'v mut V) -> TransformingVisitor<'v, V> {
+        TransformingVisitor { inner, transform: self, pending: Vec::new() }
+    }
+}
+
+pub struct TransformingVisitor<'v, V> {
+    inner: &'v mut V,
+    transform: DisplayTransform,
+    /// The bytes of the group so far, and their offsets, when the groups are reversed.
+    pending: Vec<(usize, u64)>
+}
+
+impl<'v, V> TransformingVisitor<'v, V> {
+    /// The byte at a position in the reversed group, which is the byte from the other end of the
+    /// group, at the offset of the byte at the position.
+    fn reversed(&self, i: usize) -> (usize, u64) {
+        (self.pending[self.pending.len() - 1 - i].0, self.pending[i].1)
+    }
+    
+    fn transform(&self, index: usize) -> usize {
+        usize::from(self.transform.apply(index as u8))
+    }
+}
+
+impl<'v, V: HexVisitor> TransformingVisitor<'v, V> {
+    fn flush_hex(&mut self) {
+        for i in 0..self.pending.len() {
+            let (index, offset) = self.reversed(i);
+            self.inner.byte(index, offset);
+        }
+        self.pending.clear();
+    }
+}
+
+impl<'v, V: HexVisitor> HexVisitor for TransformingVisitor<'v, V> {
+    fn byte(&mut self, index: usize, offset: u64) {
+        let index = self.transform(index);
+        if self.transform.reverse_groups {
+            self.pending.push((index, offset));
+        } else {
+            self.inner.byte(index, offset);
+        }
+    }
+    
+    fn group(&mut self) {
+        self.flush_hex();
+        self.inner.group();
+    }
+    
+    fn field(&mut self) {
+        self.flush_hex();
+        self.inner.field();
+    }
+    
+    fn next_line(&mut self) {
+        self.flush_hex();
+        self.inner.next_line();
+    }
+    
+    fn end(&mut self) {
+        self.flush_hex();
+        self.inner.end();
+    }
+}
+
+impl<'v, V: VisualVisitor> TransformingVisitor<'v, V> {
+    fn flush_visual(&mut self) {
+        for i in 0..self.pending.len() {
+            let (index, offset) = self.reversed(i);
+            self.inner.visual_element(index, offset);
+        }
+        self.pending.clear();
+    }
+}
+
+impl<'v, V: VisualVisitor> VisualVisitor for TransformingVisitor<'v, V> {
+    fn visual_element(&mut self, index: usize, offset: u64) {
+        let index = self.transform(index);
+        if self.transform.reverse_groups {
+            self.pending.push((index, offset));
+        } else {
+            self.inner.visual_element(index, offset);
+        }
+    }
+    
+    fn group(&mut self) {
+        self.flush_visual();
+        self.inner.group();
+    }
+    
+    fn next_line(&mut self) {
+        self.flush_visual();
+        self.inner.next_line();
+    }
+    
+    fn end(&mut self) {
+        self.flush_visual();
+        self.inner.end();
+    }
+}
+
+/// How to search for a pattern of bytes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    /// Search towards the start of the file, instead of towards the end.
+    pub backwards: bool,
+    /// Continue from the other end of the file, when the search reaches the end without a match.
+    pub wrap: bool,
+    /// Let ASCII letters match regardless of their case.
+    pub ignore_case: bool
+}
+
 #[derive(Debug)]
 pub struct HexReader {
     reader: TilingByteReader,
+    /// The distance in bytes from the start of one line to the start of the next.
     pub line_width: u64,
+    /// The number of bytes at the start of every line that are not shown.
+    pub line_skip: u64,
+    /// The number of bytes shown of every line, after the skipped bytes, or `None` to show the
+    /// rest of the line. Showing fewer bytes than the line width lets records of a fixed size be
+    /// lined up, with only their first few bytes in view.
+    pub line_shown: Option<u64>,
+    /// In record mode every line is a record, and the rows are numbered instead of showing offsets.
+    pub record_mode: bool,
+    /// The offsets within each line, where a field begins and a guide is drawn.
+    pub field_guides: Vec<u64>,
     pub group: u16,
     pub window_pos: (u64,u64),
     pub window_size: (u16,u16),
     capture: Vec<u8>,
-    pub vis_mode: VisualMode
+    /// The window and line width that the capture was read with, when it holds every byte of it.
+    captured_window: Option<(Window, u64)>,
+    /// The buffer that the next capture is assembled in, before it is swapped with the capture.
+    spare_capture: Vec<u8>,
+    /// The length the file had, before a capture found that it had shrunk.
+    shrunk_from: Option<u64>,
+    pub vis_mode: VisualMode,
+    pub text_rendering: TextRenderings,
+    pub hex_mode: HexMode,
+    /// The number of hex digits in the offsets column, 8 or 16, or `None` to use 16 digits only
+    /// when the largest offset in the window does not fit in 8.
+    pub offset_digits: Option<u8>,
+    /// The addresses that the bytes of the file are loaded at, like the flash address of a
+    /// firmware image. The offsets column shows addresses, but reading still uses offsets.
+    pub address_map: AddressMap
 }
 
 impl HexReader {
@@ -54,16 +461,28 @@ impl HexReader {
         Ok(HexReader {
             reader,
             line_width: 16,
+            line_skip: 0,
+            line_shown: None,
+            record_mode: false,
+            field_guides: Vec::new(),
             group: 8,
             window_pos: (0,0),
             window_size: (16,32),
             capture: Vec::new(),
-            vis_mode: VisualMode::Unicode
+            captured_window: None,
+            spare_capture: Vec::new(),
+            shrunk_from: None,
+            vis_mode: VisualMode::Unicode,
+            text_rendering: TextRenderings::default(),
+            hex_mode: HexMode::Hex,
+            offset_digits: None,
+            address_map: AddressMap::default()
         })
     }
     
     pub fn reopen(&mut self) -> Result<()> {
-        self.reader.reopen()
+        self.captured_window = None;
+        Ok(self.reader.reopen()?)
     }
     
     pub fn file_name(&self) -> &str {
@@ -74,65 +493,241 @@ impl HexReader {
         self.reader.get_path_clone()
     }
     
+    pub fn is_file(&self) -> bool {
+        self.reader.is_file()
+    }
+    
+    /// The spool that the bytes are read from, when they are still arriving.
+    pub fn get_spool(&self) -> Option<&Spool> {
+        self.reader.get_spool()
+    }
+    
+    pub fn get_tile_config(&self) -> TileConfig {
+        self.reader.get_tile_config()
+    }
+    
+    /// The tile configuration that was chosen instead of the one of the source, if one was.
+    pub fn get_chosen_tile_config(&self) -> Option<TileConfig> {
+        self.reader.get_chosen_tile_config()
+    }
+    
+    /// Read in tiles of another size, or go back to the tiles of the source with `None`.
+    pub fn set_tile_config(&mut self, config: Option<TileConfig>) {
+        self.captured_window = None;
+        self.reader.set_tile_config(config);
+    }
+    
+    pub fn get_tile_stats(&self) -> TileStats {
+        self.reader.get_tile_stats()
+    }
+    
     pub fn get_length(&self) -> u64 {
         self.reader.get_length()
     }
+
+    /// The holes of the file, which read as zeros without being read.
+    pub fn get_holes(&self) -> &[Range<u64>] {
+        self.reader.get_holes()
+    }
+
+    /// A clone of the source, if it can be cloned cheaply.
+    pub fn try_clone_source(&self) -> Option<Box<ByteSource + Send>> {
+        self.reader.try_clone_source()
+    }
     
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        Ok(self.reader.read_at(offset, buf)?)
+    }
+    
+    /// Read the bytes in the window. When the window has only moved up or down by less than its
+    /// height since the last capture, the rows that are still in view are kept, and only the newly
+    /// exposed rows are read. Capturing the same window again reads all of it.
+    ///
+    /// If the file has shrunk, the window is moved up so it is not beyond the end of the file, and
+    /// the old length can be had from `take_shrinkage`.
     pub fn capture(&mut self) -> Result<()> {
+        let old_length = self.reader.get_length();
+        let old_modified = self.reader.get_modified();
+        if self.reader.update_length()? < old_length {
+            self.captured_window = None;
+            self.shrunk_from = Some(self.shrunk_from.unwrap_or(old_length));
+            self.clamp_window();
+        }
+        if self.reader.get_modified() != old_modified {
+            // The rows that are still in view may have changed as well.
+            self.captured_window = None;
+        }
+        
         let (x, y) = self.window_pos;
         let (w, h) = self.window_size;
-        self.capture.clear();
-        // xxx Possible optimisation, since 'capture' is a Vec of u8 where drop is a no-op.
-//        unsafe { self.capture.set_len(0) };
-        self.reader.get_window((x, y, w, h), self.line_width, &mut self.capture)
+        let window = (x + self.line_skip, y, w, h);
+        let previous = self.captured_window.take();
+        let result = match previous {
+            Some(((px, py, pw, ph), line_width))
+                if (px, pw, ph, line_width) == (window.0, w, h, self.line_width) && py != y =>
+                self.capture_moved(window, py),
+            _ => {
+                self.capture.clear();
+                self.reader.get_window(window, self.line_width, &mut self.capture).map_err(XvError::from)
+            }
+        };
+        if result.is_ok() && self.capture.len() == usize::from(w) * usize::from(h) {
+            self.captured_window = Some((window, self.line_width));
+        }
+        result
+    }
+    
+    fn capture_moved(&mut self, window: Window, previous_y: u64) -> Result<()> {
+        let (x, y, w, h) = window;
+        let row = usize::from(w);
+        let spare = &mut self.spare_capture;
+        spare.clear();
+        if y > previous_y && y - previous_y < u64::from(h) {
+            let shift = (y - previous_y) as u16;
+            spare.extend_from_slice(&self.capture[usize::from(shift) * row..]);
+            self.reader.get_window((x, previous_y + u64::from(h), w, shift), self.line_width, spare)?;
+        } else if y < previous_y && previous_y - y < u64::from(h) {
+            let shift = (previous_y - y) as u16;
+            self.reader.get_window((x, y, w, shift), self.line_width, spare)?;
+            spare.extend_from_slice(&self.capture[0..usize::from(h - shift) * row]);
+        } else {
+            self.reader.get_window(window, self.line_width, spare)?;
+        }
+        std::mem::swap(&mut self.capture, &mut self.spare_capture);
+        Ok(())
+    }
+    
+    /// If a capture found that the file has shrunk, return the length it had before, once.
+    pub fn take_shrinkage(&mut self) -> Option<u64> {
+        self.shrunk_from.take()
+    }
+    
+    /// The file offset of the top-left byte in the window.
+    pub fn get_window_offset(&self) -> u64 {
+        self.window_pos.1 * self.line_width + self.line_skip + self.window_pos.0
+    }
+    
+    /// The number of bytes that can be shown of every line. The window is never wider than this.
+    pub fn get_visible_line_width(&self) -> u64 {
+        let rest = self.line_width.saturating_sub(self.line_skip);
+        self.line_shown.map_or(rest, |shown| shown.min(rest))
+    }
+    
+    pub fn get_captured_bytes(&self) -> &[u8] {
+        &self.capture
     }
     
     pub fn get_row_offsets_width(&self) -> usize {
-        if self.reader.use_large_addresses() { 16 + 2 } else { 8 + 2 }
+        self.get_offset_digits() + 2
+    }
+    
+    pub fn get_offset_format(&self) -> OffsetFormat {
+        OffsetFormat {
+            digits: self.get_offset_digits(),
+            address_map: self.address_map.clone(),
+            record_mode: self.record_mode
+        }
+    }
+    
+    fn get_offset_digits(&self) -> usize {
+        match self.offset_digits {
+            Some(digits) => usize::from(digits),
+            None if self.largest_shown_address() > u64::from(u32::MAX) => 16,
+            None => 8
+        }
+    }
+    
+    /// The largest of the addresses shown in the offsets column.
+    fn largest_shown_address(&self) -> u64 {
+        let last_row = self.window_pos.1.saturating_add(u64::from(self.window_size.1).saturating_sub(1));
+        let last_row = last_row.min(self.get_last_line());
+        (self.window_pos.1..=last_row)
+            .map(|row| row.saturating_mul(self.line_width).saturating_add(self.line_skip + self.window_pos.0))
+            .map(|offset| self.address_map.address_of(offset))
+            .max()
+            .unwrap_or(0)
     }
     
+    /// The number of lines in the file, counting a partial last line.
     pub fn get_lines_in_file(&self) -> u64 {
-        self.reader.get_length() / self.line_width
+        self.reader.get_length().div_ceil(self.line_width)
+    }
+    
+    /// The index of the last line in the file, or 0 if the file is empty.
+    pub fn get_last_line(&self) -> u64 {
+        self.get_lines_in_file().saturating_sub(1)
     }
     
-    pub fn visit_row_offsets(&self, visitor: &mut OffsetsVisitor) {
+    /// The offset of the last byte in the file, or 0 if the file is empty.
+    pub fn last_valid_offset(&self) -> u64 {
+        self.reader.get_length().saturating_sub(1)
+    }
+    
+    /// Move the window back inside the file, so its top line is not past the last line, and its
+    /// right edge is not past the end of the visible part of the lines.
+    pub fn clamp_window(&mut self) {
+        let last_line = self.get_last_line();
+        if self.window_pos.1 > last_line {
+            self.window_pos.1 = last_line;
+        }
+        let max_x = self.get_visible_line_width().saturating_sub(u64::from(self.window_size.0));
+        if self.window_pos.0 > max_x {
+            self.window_pos.0 = max_x;
+        }
+    }
+    
+    pub fn visit_row_offsets<V: OffsetsVisitor>(&self, visitor: &mut V) {
+        self.visit_row_offsets_in(0..usize::from(self.window_size.1), visitor);
+    }
+    
+    /// Like `visit_row_offsets`, for the rows of the window in the range only, like the rows of
+    /// one of the pages that the window is shown in.
+    pub fn visit_row_offsets_in<V: OffsetsVisitor>(&self, rows: Range<usize>, visitor: &mut V) {
         let w = usize::from(self.window_size.0);
         let h = usize::from(self.window_size.1);
-        let base_offset = self.window_pos.1 * self.line_width;
+        let base_offset = self.get_window_offset();
         let mut capture_height = self.capture.len() / w;
         if capture_height * w < self.capture.len() {
             capture_height += 1;
         }
-        let height = u64::try_from(h.min(capture_height)).unwrap();
+        let height = u64::try_from(rows.end.min(h).min(capture_height)).unwrap();
         
-        if self.reader.use_large_addresses() {
-            for i in 0..height {
-                let offset = base_offset + i * self.line_width;
-                visitor.offset(&format!("0x{:016X}", offset));
-            }
-        } else {
-            for i in 0..height {
-                let offset = base_offset + i * self.line_width;
-                visitor.offset(&format!("0x{:08X}", offset));
-            }
+        for i in u64::try_from(rows.start).unwrap()..height {
+            visitor.offset(base_offset + i * self.line_width, self.window_pos.1 + i);
         }
         visitor.end();
     }
     
-    pub fn visit_hex(&self, visitor: &mut HexVisitor) {
-        let capture = self.capture.as_slice();
+    /// The captured bytes of the rows of the window in the range, and the offset of the first.
+    fn captured_rows(&self, rows: Range<usize>) -> (&[u8], u64) {
+        let line_cap = usize::from(self.window_size.0);
+        let end = rows.end.saturating_mul(line_cap).min(self.capture.len());
+        let start = (rows.start * line_cap).min(end);
+        (&self.capture[start..end], self.get_window_offset() + rows.start as u64 * self.line_width)
+    }
+    
+    pub fn visit_hex<V: HexVisitor>(&self, visitor: &mut V) {
+        self.visit_hex_in(0..usize::from(self.window_size.1), visitor);
+    }
+    
+    /// Like `visit_hex`, for the rows of the window in the range only.
+    pub fn visit_hex_in<V: HexVisitor>(&self, rows: Range<usize>, visitor: &mut V) {
+        let (capture, mut line_offset) = self.captured_rows(rows);
         let line_cap = u64::from(self.window_size.0);
         let group = u64::from(self.group);
 
         let mut i = 0;
         for b in capture {
-            i += 1;
             let r = usize::from(*b);
-            visitor.byte(r);
+            visitor.byte(r, line_offset + i);
+            i += 1;
 
             if i == line_cap {
                 visitor.next_line();
+                line_offset += self.line_width;
                 i = 0;
+            } else if self.field_guides.contains(&(self.line_skip + self.window_pos.0 + i)) {
+                visitor.field();
             } else if (self.window_pos.0 + i) % group == 0 {
                 visitor.group();
             }
@@ -141,19 +736,35 @@ impl HexReader {
         visitor.end();
     }
     
-    pub fn visit_visual(&self, visitor: &mut VisualVisitor) {
-        let capture = self.capture.as_slice();
+    pub fn visit_lines<V: LineVisitor>(&self, visitor: &mut V) {
+        let w = usize::from(self.window_size.0).max(1);
+        let mut offset = self.get_window_offset();
+        for line in self.capture.chunks(w) {
+            visitor.line(offset, line);
+            offset += self.line_width;
+        }
+        visitor.end();
+    }
+    
+    pub fn visit_visual<V: VisualVisitor>(&self, visitor: &mut V) {
+        self.visit_visual_in(0..usize::from(self.window_size.1), visitor);
+    }
+    
+    /// Like `visit_visual`, for the rows of the window in the range only.
+    pub fn visit_visual_in<V: VisualVisitor>(&self, rows: Range<usize>, visitor: &mut V) {
+        let (capture, mut line_offset) = self.captured_rows(rows);
         let line_cap = u64::from(self.window_size.0);
         let group = u64::from(self.group);
 
         let mut i = 0;
         for b in capture {
-            i += 1;
             let r = usize::from(*b);
-            visitor.visual_element(r);
+            visitor.visual_element(r, line_offset + i);
+            i += 1;
 
             if i == line_cap {
                 visitor.next_line();
+                line_offset += self.line_width;
                 i = 0;
             } else if (self.window_pos.0 + i) % group == 0 {
                 visitor.group();
@@ -163,31 +774,50 @@ impl HexReader {
         visitor.end();
     }
     
-    fn vis_table(&self) -> &'static [&'static str; 256] {
-        match self.vis_mode {
-            VisualMode::Unicode => UNICODE_TEXT_TABLE,
-            VisualMode::Ascii => ASCII_TEXT_TABLE,
-            VisualMode::Off => ASCII_TEXT_TABLE
-        }
+    fn hex_table(&self) -> &'static [&'static str; 256] {
+        self.hex_mode.table()
     }
 
     pub fn map_hex_table<F, T>(&self, callback: F) -> Vec<T>
         where F: Fn(&ByteCategory, &'static str) -> T {
-        let mut vec = Vec::with_capacity(BYTE_RENDER.len());
-        for i in 0..BYTE_RENDER.len() {
-            vec.push(callback(&BYTE_CATEGORY[i], BYTE_RENDER[i]));
+        let tbl = self.hex_table();
+        let mut vec = Vec::with_capacity(tbl.len());
+        for i in 0..tbl.len() {
+            vec.push(callback(&BYTE_CATEGORY[i], tbl[i]));
         }
         vec
     }
     
+    /// The number of characters each byte takes up in the hex column.
+    pub fn get_hex_byte_width(&self) -> usize {
+        self.hex_table()[0].len()
+    }
+    
     pub fn map_visual_table<F, T>(&self, callback: F) -> Vec<T>
-        where F: Fn(&ByteCategory, &'static str) -> T {
-        let tbl = self.vis_table();
-        let mut vec = Vec::with_capacity(tbl.len());
-        for i in 0..tbl.len() {
-            vec.push(callback(&BYTE_CATEGORY[i], tbl[i]));
+        where F: Fn(&ByteCategory, &str) -> T {
+        let rendering = self.text_rendering.of(self.vis_mode);
+        (0..=255u8).map(|byte| callback(&BYTE_CATEGORY[usize::from(byte)], &rendering.render(self.vis_mode, byte)))
+            .collect()
+    }
+    
+    /// The number of cells each byte takes up in the visual column.
+    pub fn get_visual_byte_width(&self) -> usize {
+        self.text_rendering.of(self.vis_mode).width()
+    }
+    
+    /// The cells of the captured bytes, row by row, when the visual column decodes them as UTF-8,
+    /// or nothing when it does not.
+    pub fn decode_visual(&self) -> Vec<Utf8Cell> {
+        let rendering = self.text_rendering.of(self.vis_mode);
+        if !rendering.utf8 || self.vis_mode != VisualMode::Unicode {
+            return Vec::new();
         }
-        vec
+        let line_cap = usize::from(self.window_size.0).max(1);
+        let group = u64::from(self.group);
+        let first_column = self.window_pos.0;
+        self.capture.chunks(line_cap)
+            .flat_map(|row| utf8_cells(row, rendering.width(), |i| (first_column + i as u64).is_multiple_of(group)))
+            .collect()
     }
     
     pub fn set_visual_mode(&mut self, mode: VisualMode) {
@@ -197,6 +827,167 @@ impl HexReader {
     pub fn get_visual_mode(&self) -> &VisualMode {
         &self.vis_mode
     }
+    
+    pub fn set_hex_mode(&mut self, mode: HexMode) {
+        self.hex_mode = mode;
+    }
+    
+    pub fn get_hex_mode(&self) -> &HexMode {
+        &self.hex_mode
+    }
+}
+
+/// Find the next match of the pattern after the given offset, or the previous match before it
+/// when searching backwards. When wrapping, the search goes on from the other end of the file,
+/// back to the given offset, so a match at the offset itself is found if there is no other. The
+/// progress goes up by the number of bytes searched, which is at most the length of the source.
+pub fn find_pattern(source: &mut ByteSource, from: u64, pattern: &[u8], options: SearchOptions,
+                    progress: &Progress) -> Result<Option<u64>> {
+    let length = source.length()?;
+    for (first, last) in search_ranges(length, from, pattern.len() as u64, options) {
+        if let Some(found) = find_pattern_between(source, first, last, pattern, options, progress)? {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+/// The ranges of offsets, both inclusive, that `find_pattern` looks for matches of a pattern of
+/// the given length to start in, in the order that it goes through them. A range may be empty.
+pub fn search_ranges(length: u64, from: u64, pattern_len: u64, options: SearchOptions) -> Vec<(u64, u64)> {
+    if pattern_len == 0 || pattern_len > length {
+        return Vec::new();
+    }
+    let last_start = length - pattern_len;
+    let mut ranges = Vec::new();
+    if options.backwards {
+        if let Some(before) = from.checked_sub(1) {
+            ranges.push((0, before.min(last_start)));
+        }
+        if options.wrap {
+            ranges.push((from, last_start));
+        }
+    } else {
+        ranges.push((from + 1, last_start));
+        if options.wrap {
+            ranges.push((0, from.min(last_start)));
+        }
+    }
+    ranges
+}
+
+/// Find the start of the next high-entropy (if `high` is true) or low-entropy region after the
+/// given offset. The entropy is computed over a window that rolls through the source, and the
+/// region that the offset is already in, is skipped.
+pub fn find_entropy_region(source: &mut ByteSource, from: u64, high: bool, progress: &Progress)
+    -> Result<Option<u64>> {
+    let mut window = VecDeque::with_capacity(ENTROPY_WINDOW);
+    let mut stats = ByteStats::new();
+    let mut left_start_region = false;
+    let mut chunk = vec![0; SCAN_CHUNK_SIZE];
+    let mut offset = from;
+
+    loop {
+        let len = source.read_at(offset, &mut chunk)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        for (i, b) in chunk[0..len].iter().enumerate() {
+            window.push_back(*b);
+            stats.add_byte(*b);
+            if window.len() > ENTROPY_WINDOW {
+                stats.remove_byte(window.pop_front().unwrap());
+            }
+            let end = offset + i as u64 + 1;
+            if window.len() == ENTROPY_WINDOW && (end - from).is_multiple_of(ENTROPY_STEP) {
+                let entropy = stats.entropy();
+                let in_region = if high { entropy >= HIGH_ENTROPY } else { entropy <= LOW_ENTROPY };
+                if !in_region {
+                    left_start_region = true;
+                } else if left_start_region {
+                    return Ok(Some(end - ENTROPY_WINDOW as u64));
+                }
+            }
+        }
+        offset += len as u64;
+        progress.advance(len as u64)?;
+    }
+}
+
+/// Find the offset of the first byte after the given offset, that matches the predicate.
+/// The source is scanned in large chunks, so long runs of uninteresting bytes are skipped quickly.
+pub fn find_byte<P>(source: &mut ByteSource, from: u64, predicate: P, progress: &Progress) -> Result<Option<u64>>
+    where P: Fn(u8) -> bool {
+    let mut chunk = vec![0; SCAN_CHUNK_SIZE];
+    let mut offset = from + 1;
+
+    loop {
+        let len = source.read_at(offset, &mut chunk)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        if let Some(i) = chunk[0..len].iter().position(|b| predicate(*b)) {
+            return Ok(Some(offset + i as u64));
+        }
+        offset += len as u64;
+        progress.advance(len as u64)?;
+    }
+}
+
+/// Find the first byte after the given offset, that is different from the byte at the offset.
+pub fn find_end_of_run(source: &mut ByteSource, from: u64, progress: &Progress) -> Result<Option<u64>> {
+    let mut buf = [0];
+    if source.read_at(from, &mut buf)? == 0 {
+        return Ok(None);
+    }
+    let run_byte = buf[0];
+    find_byte(source, from, |b| b != run_byte, progress)
+}
+
+/// Find the first, or last when searching backwards, match that starts between the first and
+/// the last offset, both inclusive. The file is read in chunks that overlap by the length of the
+/// pattern, so matches that cross the chunk boundaries are found too.
+pub fn find_pattern_between(source: &mut ByteSource, first: u64, last: u64, pattern: &[u8],
+                        options: SearchOptions, progress: &Progress) -> Result<Option<u64>> {
+    if first > last {
+        return Ok(None);
+    }
+    let chunk_starts = SCAN_CHUNK_SIZE as u64;
+    let mut chunk = vec![0; SCAN_CHUNK_SIZE + pattern.len() - 1];
+    let mut start = if options.backwards {
+        last - (last - first) % chunk_starts
+    } else {
+        first
+    };
+    loop {
+        let starts_in_chunk = (last - start + 1).min(chunk_starts) as usize;
+        let len = source.read_at(start, &mut chunk[0..starts_in_chunk + pattern.len() - 1])?;
+        progress.advance(len.min(starts_in_chunk) as u64)?;
+        let candidates = (len + 1).saturating_sub(pattern.len()).min(starts_in_chunk);
+        let is_match = |i: &usize| {
+            let bytes = &chunk[*i..*i + pattern.len()];
+            if options.ignore_case { bytes.eq_ignore_ascii_case(pattern) } else { bytes == pattern }
+        };
+        let found = if options.backwards {
+            (0..candidates).rev().find(is_match)
+        } else {
+            (0..candidates).find(is_match)
+        };
+        if let Some(i) = found {
+            return Ok(Some(start + i as u64));
+        }
+        if options.backwards {
+            if start == first {
+                return Ok(None);
+            }
+            start -= chunk_starts;
+        } else {
+            start += chunk_starts;
+            if start > last || len < starts_in_chunk {
+                return Ok(None);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -207,19 +998,36 @@ mod tests {
 
     use super::*;
 
-    impl OffsetsVisitor for String {
-        fn offset(&mut self, offset: &str) {
-            self.push_str(offset);
-            self.push('\n');
+    struct RowOffsets {
+        format: OffsetFormat,
+        buf: String,
+        text: String
+    }
+
+    impl OffsetsVisitor for RowOffsets {
+        fn offset(&mut self, offset: u64, line: u64) {
+            self.format.write(offset, line, &mut self.buf);
+            self.text.push_str(&self.buf);
+            self.text.push('\n');
         }
 
         fn end(&mut self) {
-            self.pop();
+            self.text.pop();
         }
     }
     
+    fn row_offsets(reader: &HexReader) -> String {
+        let mut offsets = RowOffsets {
+            format: reader.get_offset_format(),
+            buf: String::new(),
+            text: String::new()
+        };
+        reader.visit_row_offsets(&mut offsets);
+        offsets.text
+    }
+    
     impl HexVisitor for String {
-        fn byte(&mut self, index: usize) {
+        fn byte(&mut self, index: usize, _offset: u64) {
             self.push_str(BYTE_RENDER[index]);
             self.push(' ');
         }
@@ -228,6 +1036,11 @@ mod tests {
             // Nothing to do.
         }
 
+        fn field(&mut self) {
+            self.pop();
+            self.push('|');
+        }
+
         fn next_line(&mut self) {
             self.pop();
             self.push('\n');
@@ -238,6 +1051,94 @@ mod tests {
         }
     }
     
+    #[test]
+    fn finding_patterns() {
+        let mut bytes = vec![0; 3 * SCAN_CHUNK_SIZE];
+        bytes[10..13].copy_from_slice(b"abc");
+        bytes[SCAN_CHUNK_SIZE - 1..SCAN_CHUNK_SIZE + 2].copy_from_slice(b"ABC");
+        bytes[3 * SCAN_CHUNK_SIZE - 3..].copy_from_slice(b"abc");
+        let mut find = |from, pattern: &[u8], options| {
+            find_pattern(&mut bytes, from, pattern, options, &Progress::new(0)).unwrap()
+        };
+        let end = 3 * SCAN_CHUNK_SIZE as u64 - 3;
+        let boundary = SCAN_CHUNK_SIZE as u64 - 1;
+        let forward = SearchOptions::default();
+        let backwards = SearchOptions { backwards: true, ..forward };
+        let ignore_case = SearchOptions { ignore_case: true, ..forward };
+
+        assert_eq!(find(0, b"abc", forward), Some(10));
+        assert_eq!(find(10, b"abc", forward), Some(end));
+        assert_eq!(find(10, b"abc", ignore_case), Some(boundary));
+        assert_eq!(find(end, b"abc", forward), None);
+        assert_eq!(find(end, b"abc", SearchOptions { wrap: true, ..forward }), Some(10));
+        assert_eq!(find(end, b"ABC", backwards), Some(boundary));
+        assert_eq!(find(end + 2, b"abc", backwards), Some(end));
+        assert_eq!(find(10, b"abc", backwards), None);
+        assert_eq!(find(10, b"abc", SearchOptions { wrap: true, ..backwards }), Some(end));
+        assert_eq!(find(0, b"xyz", SearchOptions { wrap: true, ..forward }), None);
+        assert_eq!(find(0, b"", forward), None);
+    }
+    
+    #[test]
+    fn serializing_modes() {
+        let ascii = rmp_serde::to_vec(&VisualMode::Ascii).unwrap();
+        assert_eq!(ascii, rmp_serde::to_vec("Ascii").unwrap());
+        let mode: VisualMode = rmp_serde::from_slice(&ascii).unwrap();
+        assert_eq!(mode, VisualMode::Ascii);
+        let bits = rmp_serde::to_vec(&HexMode::Bits).unwrap();
+        let mode: HexMode = rmp_serde::from_slice(&bits).unwrap();
+        assert_eq!(mode, HexMode::Bits);
+    }
+    
+    #[test]
+    fn rendering_text() {
+        let plain = TextRendering::default();
+        assert_eq!(plain.render(VisualMode::Unicode, b'\r'), "\u{240D}");
+        assert_eq!(plain.render(VisualMode::Unicode, 0xA0), "\u{FFFD}");
+        
+        let caret = TextRendering { caret: true, ..plain };
+        assert_eq!(caret.width(), 2);
+        assert_eq!(caret.render(VisualMode::Ascii, b'\r'), "^M");
+        assert_eq!(caret.render(VisualMode::Unicode, 0), "^@");
+        assert_eq!(caret.render(VisualMode::Unicode, 0x7F), "^?");
+        assert_eq!(caret.render(VisualMode::Ascii, b'A'), "A ");
+        
+        let distinct = TextRendering { distinct: true, ..plain };
+        assert_eq!(distinct.render(VisualMode::Unicode, 0xA0), "\u{237D}");
+        assert_eq!(distinct.render(VisualMode::Unicode, 0xAD), "\u{2010}");
+        assert_eq!(distinct.render(VisualMode::Ascii, 0xA0), ".");
+        
+        let fold = TextRendering { fold: true, ..plain };
+        assert_eq!(fold.render(VisualMode::Unicode, b'Q'), "q");
+        assert_eq!(fold.render(VisualMode::Unicode, 0), ".");
+        
+        let utf8 = TextRendering { utf8: true, ..plain };
+        assert_eq!(utf8.render(VisualMode::Unicode, 0xC3), "\u{FFFD}");
+        
+        let mut renderings = TextRenderings::default();
+        renderings.set(VisualMode::Ascii, caret);
+        assert_eq!(renderings.of(VisualMode::Ascii), caret);
+        assert_eq!(renderings.of(VisualMode::Unicode), plain);
+    }
+    
+    #[test]
+    fn decoding_utf8_cells() {
+        let char_cell = |text: &str, bytes| Utf8Cell::Char { text: String::from(text), bytes };
+        let no_breaks = |_| false;
+        assert_eq!(utf8_cells("aé".as_bytes(), 1, no_breaks),
+                   vec![Utf8Cell::Byte, char_cell("é ", 2), Utf8Cell::Covered]);
+        // Wide characters fill two cells of their three bytes.
+        assert_eq!(utf8_cells("漢".as_bytes(), 1, no_breaks),
+                   vec![char_cell("漢 ", 3), Utf8Cell::Covered, Utf8Cell::Covered]);
+        assert_eq!(utf8_cells("漢".as_bytes(), 2, no_breaks)[0], char_cell("漢    ", 3));
+        // Combining accents are shown on a dotted circle.
+        assert_eq!(utf8_cells("\u{301}".as_bytes(), 1, no_breaks)[0], char_cell("\u{25CC}\u{301} ", 2));
+        // Cut off, invalid and control characters are shown byte by byte.
+        assert_eq!(utf8_cells(&"é".as_bytes()[0..1], 1, no_breaks), vec![Utf8Cell::Byte]);
+        assert_eq!(utf8_cells("é".as_bytes(), 1, |i| i == 1), vec![Utf8Cell::Byte, Utf8Cell::Byte]);
+        assert_eq!(utf8_cells(b"\xC0\x80\xC2\x85", 1, no_breaks), vec![Utf8Cell::Byte; 4]);
+    }
+    
     #[test]
     fn getting_hex_of_file_top_left_window() {
         let mut tmpf = tempfile::NamedTempFile::new().unwrap();
@@ -256,6 +1157,311 @@ mod tests {
         assert_eq!(hex, "30 31\n34 35")
     }
     
+    #[test]
+    fn getting_hex_of_skipped_and_shown_bytes() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(b"0123456789abcdef").unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.line_width = 8;
+        reader.line_skip = 1;
+        reader.line_shown = Some(2);
+        assert_eq!(reader.get_visible_line_width(), 2);
+        reader.window_pos = (0,0);
+        reader.window_size = (2,4);
+        reader.capture().unwrap();
+        let mut hex = String::new();
+        reader.visit_hex(&mut hex);
+        // Bytes:  Hex:
+        //  12      31 32
+        //  9a      39 61
+        assert_eq!(hex, "31 32\n39 61");
+        let offsets = row_offsets(&reader);
+        assert_eq!(offsets, "0x00000001\n0x00000009");
+        reader.window_pos = (1,1);
+        assert_eq!(reader.get_window_offset(), 10);
+        reader.capture().unwrap();
+        let offsets = row_offsets(&reader);
+        assert_eq!(offsets, "0x0000000A");
+        reader.line_shown = None;
+        assert_eq!(reader.get_visible_line_width(), 7);
+    }
+    
+    impl HexVisitor for Vec<u64> {
+        fn byte(&mut self, _index: usize, offset: u64) {
+            self.push(offset);
+        }
+
+        fn group(&mut self) {
+            // Nothing to do.
+        }
+
+        fn field(&mut self) {
+            // Nothing to do.
+        }
+
+        fn next_line(&mut self) {
+            // Nothing to do.
+        }
+
+        fn end(&mut self) {
+            // Nothing to do.
+        }
+    }
+    
+    #[test]
+    fn visiting_byte_offsets() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(b"0123456789abcde").unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.line_width = 4;
+        reader.window_pos = (1,1);
+        reader.window_size = (2,4);
+        reader.capture().unwrap();
+        let mut offsets = Vec::new();
+        reader.visit_hex(&mut offsets);
+        assert_eq!(offsets, vec![5, 6, 9, 10, 13, 14]);
+    }
+    
+    /// The bytes that a hex visitor is given, and their offsets.
+    struct VisitedBytes(Vec<(usize, u64)>);
+    
+    impl HexVisitor for VisitedBytes {
+        fn byte(&mut self, index: usize, offset: u64) {
+            self.0.push((index, offset));
+        }
+
+        fn group(&mut self) {
+            // Nothing to do.
+        }
+
+        fn field(&mut self) {
+            // Nothing to do.
+        }
+
+        fn next_line(&mut self) {
+            // Nothing to do.
+        }
+
+        fn end(&mut self) {
+            // Nothing to do.
+        }
+    }
+    
+    #[test]
+    fn transforming_visited_bytes() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(b"0123456789").unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.line_width = 8;
+        reader.group = 4;
+        reader.window_size = (8,2);
+        reader.capture().unwrap();
+        let transform = DisplayTransform { swap_nibbles: true, reverse_bits: false, reverse_groups: true };
+        let mut bytes = VisitedBytes(Vec::new());
+        reader.visit_hex(&mut transform.visitor(&mut bytes));
+        assert_eq!(bytes.0, vec![(0x33, 0), (0x23, 1), (0x13, 2), (0x03, 3), (0x73, 4), (0x63, 5), (0x53, 6),
+                                 (0x43, 7), (0x93, 8), (0x83, 9)]);
+        assert_eq!(DisplayTransform { reverse_bits: true, ..DisplayTransform::default() }.apply(0x01), 0x80);
+        assert_eq!(transform.describe(), "swapped nibbles, reversed groups");
+    }
+    
+    impl LineVisitor for Vec<(u64, Vec<u8>)> {
+        fn line(&mut self, offset: u64, bytes: &[u8]) {
+            self.push((offset, bytes.to_vec()));
+        }
+
+        fn end(&mut self) {
+            // Nothing to do.
+        }
+    }
+    
+    #[test]
+    fn visiting_whole_lines() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(b"0123456789abcde").unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.line_width = 4;
+        reader.window_pos = (1,1);
+        reader.window_size = (3,4);
+        reader.capture().unwrap();
+        let mut lines = Vec::new();
+        reader.visit_lines(&mut lines);
+        assert_eq!(lines, vec![
+            (5, b"567".to_vec()),
+            (9, b"9ab".to_vec()),
+            (13, b"de".to_vec())]);
+    }
+    
+    #[test]
+    fn reusing_capture_after_small_moves() {
+        let data: Vec<u8> = (0..=255u8).collect();
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(&data).unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        let mut fresh = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.line_width = 16;
+        reader.window_size = (8,4);
+        fresh.line_width = 16;
+        fresh.window_size = (8,4);
+        for &(x, y) in &[(2,0), (2,1), (2,4), (2,2), (2,9), (2,13), (2,14), (2,12), (2,11), (3,11)] {
+            reader.window_pos = (x, y);
+            reader.capture().unwrap();
+            fresh.window_pos = (x, y);
+            fresh.captured_window = None;
+            fresh.capture().unwrap();
+            assert_eq!(reader.get_captured_bytes(), fresh.get_captured_bytes(), "at {:?}", (x, y));
+        }
+    }
+    
+    #[test]
+    fn capturing_after_file_shrinks() {
+        let data: Vec<u8> = (0..64).collect();
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(&data).unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.line_width = 16;
+        reader.window_pos = (0,3);
+        reader.window_size = (16,1);
+        reader.capture().unwrap();
+        assert_eq!(reader.get_captured_bytes(), &data[48..64]);
+        assert_eq!(reader.take_shrinkage(), None);
+        
+        tmpf.set_len(20).unwrap();
+        reader.capture().unwrap();
+        assert_eq!(reader.window_pos, (0,1));
+        assert_eq!(reader.get_captured_bytes(), &data[16..20]);
+        assert_eq!(reader.take_shrinkage(), Some(64));
+        assert_eq!(reader.take_shrinkage(), None);
+    }
+    
+    #[test]
+    fn choosing_offset_digits() {
+        let tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.set_len(0x1_0000_0000 + 64).unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.line_width = 16;
+        reader.window_size = (16,4);
+        let mut buf = String::new();
+        reader.get_offset_format().write(0x30, 3, &mut buf);
+        assert_eq!(buf, "0x00000030");
+        reader.window_pos = (0, 0x1000_0000 - 4);
+        assert_eq!(reader.get_row_offsets_width(), 10);
+        reader.window_pos.1 += 1;
+        assert_eq!(reader.get_row_offsets_width(), 18);
+        reader.get_offset_format().write(0x1_0000_0000, 0, &mut buf);
+        assert_eq!(buf, "0x0000000100000000");
+        reader.offset_digits = Some(8);
+        assert_eq!(reader.get_row_offsets_width(), 10);
+        reader.window_pos = (0,0);
+        reader.offset_digits = Some(16);
+        assert_eq!(reader.get_row_offsets_width(), 18);
+    }
+    
+    #[test]
+    fn showing_addresses_from_a_base() {
+        let data: Vec<u8> = (0..64).collect();
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(&data).unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.window_size = (16,4);
+        reader.address_map.base_address = 0xFFFF_FFC0;
+        let mut buf = String::new();
+        reader.get_offset_format().write(0x30, 3, &mut buf);
+        assert_eq!(buf, "0xFFFFFFF0");
+        reader.address_map.base_address = 0xFFFF_FFD0;
+        reader.get_offset_format().write(0x30, 3, &mut buf);
+        assert_eq!(buf, "0x0000000100000000");
+    }
+    
+    #[test]
+    fn clamping_the_window() {
+        let data: Vec<u8> = (0..40).collect();
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(&data).unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.line_width = 16;
+        reader.window_size = (8,4);
+        assert_eq!(reader.get_lines_in_file(), 3);
+        assert_eq!(reader.get_last_line(), 2);
+        assert_eq!(reader.last_valid_offset(), 39);
+        reader.window_pos = (12,5);
+        reader.clamp_window();
+        assert_eq!(reader.window_pos, (8,2));
+        reader.line_width = 20;
+        assert_eq!(reader.get_lines_in_file(), 2);
+        reader.clamp_window();
+        assert_eq!(reader.window_pos, (8,1));
+        reader.line_width = 4;
+        reader.clamp_window();
+        assert_eq!(reader.window_pos, (0,1));
+    }
+    
+    #[test]
+    fn getting_records_with_field_guides() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write(b"0123456789abcdef").unwrap();
+        
+        let mut reader = HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap();
+        reader.line_width = 4;
+        reader.record_mode = true;
+        reader.field_guides = vec![1, 3];
+        reader.window_pos = (0,2);
+        reader.window_size = (4,2);
+        reader.capture().unwrap();
+        let mut hex = String::new();
+        reader.visit_hex(&mut hex);
+        assert_eq!(hex, "38|39 61|62\n63|64 65|66");
+        let offsets = row_offsets(&reader);
+        assert_eq!(offsets, "#000000002\n#000000003");
+    }
+    
+    #[test]
+    fn finding_entropy_regions() {
+        let mut data = vec![0; 4096];
+        let mut x: u32 = 1;
+        for _ in 0..4096 {
+            // A simple xorshift generator, for bytes with high entropy.
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            data.push(x as u8);
+        }
+        data.extend_from_slice(&[0; 4096]);
+        let progress = Progress::new(data.len() as u64);
+        let mut source = &data[..];
+
+        let high = find_entropy_region(&mut source, 0, true, &progress).unwrap().unwrap();
+        assert!(high > 3584 && high <= 4096, "high entropy region at {}", high);
+        let low = find_entropy_region(&mut source, high, false, &progress).unwrap().unwrap();
+        assert!(low > 7680 && low <= 8192, "low entropy region at {}", low);
+        assert_eq!(find_entropy_region(&mut source, low, true, &progress).unwrap(), None);
+    }
+    
+    #[test]
+    fn skipping_runs_of_bytes() {
+        let mut data = vec![0xFF; 100_000];
+        data.extend_from_slice(b"\0\0\x01xy");
+        let progress = Progress::new(data.len() as u64);
+        let mut source = &data[..];
+
+        assert_eq!(find_end_of_run(&mut source, 0, &progress).unwrap(), Some(100_000));
+        assert_eq!(find_end_of_run(&mut source, 100_000, &progress).unwrap(), Some(100_002));
+        assert_eq!(find_byte(&mut source, 100_000, |b| b != 0 && b != 0xFF, &progress).unwrap(), Some(100_002));
+        assert_eq!(find_byte(&mut source, 0, |b| b == b'x', &progress).unwrap(), Some(100_003));
+        assert_eq!(find_byte(&mut source, 100_003, |b| b == b'x', &progress).unwrap(), None);
+        assert_eq!(find_end_of_run(&mut source, 100_004, &progress).unwrap(), None);
+        assert_eq!(find_end_of_run(&mut source, 200_000, &progress).unwrap(), None);
+    }
+    
     #[test]
     fn hex_view_bigger_than_file() {
         let mut tmpf = tempfile::NamedTempFile::new().unwrap();
@@ -274,8 +1480,7 @@ mod tests {
         //  89ab    38 39 61 62
         //  cdef    63 64 65 66
         assert_eq!(hex, "30 31 32 33\n34 35 36 37\n38 39 61 62\n63 64 65 66");
-        let mut offsets = String::new();
-        reader.visit_row_offsets(&mut offsets);
+        let offsets = row_offsets(&reader);
         assert_eq!(offsets, "0x00000000\n0x00000004\n0x00000008\n0x0000000C");
     }
     
@@ -297,8 +1502,7 @@ mod tests {
         //  89ab    38 39 61 62
         //  cdef    63 64 65 66
         assert_eq!(hex, "30 31 32 33\n34 35 36 37\n38 39 61 62\n63 64 65");
-        let mut offsets = String::new();
-        reader.visit_row_offsets(&mut offsets);
+        let offsets = row_offsets(&reader);
         assert_eq!(offsets, "0x00000000\n0x00000004\n0x00000008\n0x0000000C");
     }
 }
@@ -0,0 +1,365 @@
+//! Writing and reading Intel HEX and Motorola S-records, and the sparse image that records
+//! describe.
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::ops::Range;
+
+use crate::byte_reader::ByteSource;
+use crate::error::{Result, XvError};
+use crate::progress::Progress;
+
+/// The number of data bytes in every record that is written.
+const RECORD_SIZE: usize = 16;
+
+/// The value that the bytes in the gaps of a sparse image read as, which is what erased flash
+/// memory reads as.
+const GAP_FILL: u8 = 0xFF;
+
+/// The most fill bytes that are written at once, for the gaps of a sparse image.
+const GAP_CHUNK: usize = 64 * 1024;
+
+/// The text formats that flashing tools use for firmware images, with a checksum on every line.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RecordFormat {
+    /// Intel HEX, with extended linear address records for addresses above 64 KiB.
+    IntelHex,
+    /// Motorola S-records, with 16, 24 or 32 bit addresses, as the highest address needs.
+    SRecord
+}
+
+/// A run of bytes, and the address of the first of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataRecord {
+    pub address: u64,
+    pub bytes: Vec<u8>
+}
+
+/// Read the source to the end, and write it as records, with the first byte at the given address.
+/// Both formats have 32 bit addresses, so the length of the source must be given, to check that
+/// all of it fits.
+pub fn write_records<R: Read, W: Write>(format: RecordFormat, mut source: R, address: u64, length: u64,
+                                        mut writer: W) -> io::Result<()> {
+    let end = address.checked_add(length).filter(|end| *end <= 1 << 32).ok_or_else(|| io::Error::new(
+        ErrorKind::InvalidInput, "The records can only have addresses below 4 GiB."))?;
+    let address_size = match format {
+        RecordFormat::IntelHex => 2,
+        RecordFormat::SRecord if end <= 1 << 16 => 2,
+        RecordFormat::SRecord if end <= 1 << 24 => 3,
+        RecordFormat::SRecord => 4
+    };
+    let mut upper_address = 0;
+    let mut address = address;
+    let mut buf = [0; RECORD_SIZE];
+    loop {
+        // An Intel HEX record must not go past the end of a 64 KiB segment.
+        let want = match format {
+            RecordFormat::IntelHex => RECORD_SIZE.min(0x1_0000 - (address & 0xFFFF) as usize),
+            RecordFormat::SRecord => RECORD_SIZE
+        };
+        let len = fill(&mut source, &mut buf[0..want])?;
+        if len == 0 {
+            break;
+        }
+        if format == RecordFormat::IntelHex && address >> 16 != upper_address {
+            upper_address = address >> 16;
+            write_record(&mut writer, format, 4, 0, 2, &[(upper_address >> 8) as u8, upper_address as u8])?;
+        }
+        let record_type = match address_size {
+            2 if format == RecordFormat::IntelHex => 0,
+            2 => 1,
+            3 => 2,
+            _ => 3
+        };
+        write_record(&mut writer, format, record_type, address, address_size, &buf[0..len])?;
+        address += len as u64;
+    }
+    match format {
+        RecordFormat::IntelHex => write_record(&mut writer, format, 1, 0, 2, &[]),
+        RecordFormat::SRecord => write_record(&mut writer, format, 11 - address_size as u8, 0, address_size, &[])
+    }
+}
+
+/// Parse Intel HEX or S-records, and check their checksums. The data records are returned in the
+/// order that they are in.
+pub fn parse_records(text: &str) -> Result<Vec<DataRecord>> {
+    let mut records = Vec::new();
+    let mut upper_address = 0;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: &str| XvError::Parse(format!("Line {}: {}", i + 1, message));
+        if !line.is_ascii() {
+            return Err(error("Not an Intel HEX record or an S-record."));
+        }
+        let (format, hex) = if let Some(hex) = line.strip_prefix(':') {
+            (RecordFormat::IntelHex, hex)
+        } else if line.starts_with('S') && line.len() > 1 {
+            (RecordFormat::SRecord, &line[2..])
+        } else {
+            return Err(error("Not an Intel HEX record or an S-record."));
+        };
+        let bytes = parse_hex(hex).ok_or_else(|| error("The record is not in hex."))?;
+        if bytes.is_empty() || !checksum_matches(format, &bytes) {
+            return Err(error("The checksum of the record is wrong."));
+        }
+        let data = &bytes[0..bytes.len() - 1];
+        match format {
+            RecordFormat::IntelHex => {
+                if data.len() < 4 || usize::from(data[0]) != data.len() - 4 {
+                    return Err(error("The length of the record is wrong."));
+                }
+                let address = u64::from(data[1]) << 8 | u64::from(data[2]);
+                match data[3] {
+                    0 => records.push(DataRecord { address: upper_address + address, bytes: data[4..].to_vec() }),
+                    2 if data.len() == 6 => upper_address = (u64::from(data[4]) << 8 | u64::from(data[5])) << 4,
+                    4 if data.len() == 6 => upper_address = (u64::from(data[4]) << 8 | u64::from(data[5])) << 16,
+                    1 | 3 | 5 => {},
+                    _ => return Err(error("Unknown record type."))
+                }
+            },
+            RecordFormat::SRecord => {
+                if data.is_empty() || usize::from(data[0]) != data.len() {
+                    return Err(error("The length of the record is wrong."));
+                }
+                let address_size = match line.as_bytes()[1] {
+                    b'1' => 2,
+                    b'2' => 3,
+                    b'3' => 4,
+                    b'0' | b'5' | b'6' | b'7' | b'8' | b'9' => continue,
+                    _ => return Err(error("Unknown record type."))
+                };
+                if data.len() < 1 + address_size {
+                    return Err(error("The record is too short for its address."));
+                }
+                let address = data[1..=address_size].iter().fold(0, |address, b| address << 8 | u64::from(*b));
+                records.push(DataRecord { address, bytes: data[1 + address_size..].to_vec() });
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// The bytes that records describe, from the lowest address to the end of the highest record,
+/// without storing the gaps between them. The offsets are relative to the lowest address, and the
+/// gaps read as 0xFF.
+#[derive(Clone, Debug)]
+pub struct SparseImage {
+    start: u64,
+    length: u64,
+    /// The runs of bytes, sorted by address, with adjacent and overlapping records merged.
+    runs: Vec<DataRecord>
+}
+
+impl SparseImage {
+    /// Put the records together. Where records overlap, the later ones win.
+    pub fn new(records: &[DataRecord]) -> SparseImage {
+        let mut sorted: Vec<&DataRecord> = records.iter().filter(|record| !record.bytes.is_empty()).collect();
+        sorted.sort_by_key(|record| record.address);
+        let mut runs: Vec<DataRecord> = Vec::new();
+        for record in sorted {
+            match runs.last_mut() {
+                Some(run) if record.address <= run.address + run.bytes.len() as u64 => {
+                    let offset = (record.address - run.address) as usize;
+                    let end = offset + record.bytes.len();
+                    if end > run.bytes.len() {
+                        run.bytes.resize(end, 0);
+                    }
+                    run.bytes[offset..end].copy_from_slice(&record.bytes);
+                },
+                _ => runs.push(record.clone())
+            }
+        }
+        let start = runs.first().map_or(0, |run| run.address);
+        let length = runs.last().map_or(0, |run| run.address + run.bytes.len() as u64 - start);
+        SparseImage { start, length, runs }
+    }
+
+    /// The address of the first byte.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// The offset ranges that no record covers.
+    pub fn gaps(&self) -> Vec<Range<u64>> {
+        self.runs.windows(2)
+            .map(|pair| pair[0].address + pair[0].bytes.len() as u64 - self.start..pair[1].address - self.start)
+            .collect()
+    }
+
+    /// Write the bytes, from the lowest address to the end of the highest record, with the gaps
+    /// filled with the given byte, without holding more than a chunk of a gap in memory.
+    pub fn write_to<W: Write>(&self, fill: u8, writer: &mut W, progress: &Progress) -> Result<()> {
+        let gap = vec![fill; GAP_CHUNK];
+        let mut address = self.start;
+        for run in &self.runs {
+            let mut left = run.address - address;
+            while left > 0 {
+                let len = left.min(GAP_CHUNK as u64) as usize;
+                writer.write_all(&gap[0..len])?;
+                progress.advance(len as u64)?;
+                left -= len as u64;
+            }
+            writer.write_all(&run.bytes)?;
+            progress.advance(run.bytes.len() as u64)?;
+            address = run.address + run.bytes.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+impl ByteSource for SparseImage {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.length.saturating_sub(offset).min(buf.len() as u64) as usize;
+        let buf = &mut buf[0..len];
+        for b in buf.iter_mut() {
+            *b = GAP_FILL;
+        }
+        let (from, to) = (self.start + offset, self.start + offset + len as u64);
+        for run in &self.runs {
+            let run_end = run.address + run.bytes.len() as u64;
+            if run_end <= from || run.address >= to {
+                continue;
+            }
+            let copy_from = run.address.max(from);
+            let copy_to = run_end.min(to);
+            let source = &run.bytes[(copy_from - run.address) as usize..(copy_to - run.address) as usize];
+            buf[(copy_from - from) as usize..(copy_to - from) as usize].copy_from_slice(source);
+        }
+        Ok(len)
+    }
+
+    fn length(&mut self) -> io::Result<u64> {
+        Ok(self.length)
+    }
+
+    fn try_clone(&self) -> Option<Box<ByteSource + Send>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+fn write_record<W: Write>(writer: &mut W, format: RecordFormat, record_type: u8, address: u64,
+                          address_size: usize, data: &[u8]) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(data.len() + 8);
+    match format {
+        RecordFormat::IntelHex => {
+            bytes.push(data.len() as u8);
+            bytes.extend_from_slice(&[(address >> 8) as u8, address as u8, record_type]);
+        },
+        RecordFormat::SRecord => {
+            bytes.push((address_size + data.len() + 1) as u8);
+            bytes.extend((0..address_size).rev().map(|i| (address >> (8 * i)) as u8));
+        }
+    }
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    bytes.push(match format {
+        RecordFormat::IntelHex => sum.wrapping_neg(),
+        RecordFormat::SRecord => !sum
+    });
+    let mut line = match format {
+        RecordFormat::IntelHex => String::from(":"),
+        RecordFormat::SRecord => format!("S{}", record_type)
+    };
+    for b in bytes {
+        line.push_str(&format!("{:02X}", b));
+    }
+    line.push('\n');
+    writer.write_all(line.as_bytes())
+}
+
+fn checksum_matches(format: RecordFormat, bytes: &[u8]) -> bool {
+    let sum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    match format {
+        RecordFormat::IntelHex => sum == 0,
+        RecordFormat::SRecord => sum == 0xFF
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect()
+}
+
+fn fill<R: Read>(source: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match source.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {},
+            Err(e) => return Err(e)
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(format: RecordFormat, bytes: &[u8], address: u64) -> String {
+        let mut out = Vec::new();
+        write_records(format, bytes, address, bytes.len() as u64, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn writing_intel_hex() {
+        assert_eq!(write(RecordFormat::IntelHex, b"Hi!", 0x0800_FFFE),
+                   ":020000040800F2\n:02FFFE00486950\n:020000040801F1\n:0100000021DE\n:00000001FF\n");
+        let mut out = Vec::new();
+        assert!(write_records(RecordFormat::IntelHex, &b"Hi"[..], 0xFFFF_FFFF, 2, &mut out).is_err());
+    }
+
+    #[test]
+    fn writing_s_records() {
+        assert_eq!(write(RecordFormat::SRecord, b"Hi", 0x1234), "S1051234486903\nS9030000FC\n");
+        assert_eq!(write(RecordFormat::SRecord, b"Hi", 0x0800_0000), "S3070800000048693F\nS70500000000FA\n");
+    }
+
+    #[test]
+    fn parsing_and_flattening_records() {
+        let text = write(RecordFormat::IntelHex, b"Hi!", 0x0800_FFFE) + ":0100100021CE\n";
+        let records = parse_records(&text).unwrap();
+        assert_eq!(records, vec![
+            DataRecord { address: 0x0800_FFFE, bytes: b"Hi".to_vec() },
+            DataRecord { address: 0x0801_0000, bytes: b"!".to_vec() },
+            DataRecord { address: 0x0801_0010, bytes: b"!".to_vec() }
+        ]);
+        let mut image = Vec::new();
+        let sparse = SparseImage::new(&records);
+        sparse.write_to(0x00, &mut image, &Progress::new(0x13)).unwrap();
+        assert_eq!(sparse.start(), 0x0800_FFFE);
+        assert_eq!(image.len(), 0x13);
+        assert_eq!(&image[0..4], b"Hi!\x00");
+        assert_eq!(image[0x12], b'!');
+
+        let records = parse_records(&write(RecordFormat::SRecord, b"Hi", 0x0800_0000)).unwrap();
+        assert_eq!(records, vec![DataRecord { address: 0x0800_0000, bytes: b"Hi".to_vec() }]);
+        assert_eq!(format!("{}", parse_records(":0100000021DF").unwrap_err()),
+                   "Line 1: The checksum of the record is wrong.");
+        assert_eq!(format!("{}", parse_records("S\u{e9}00").unwrap_err()),
+                   "Line 1: Not an Intel HEX record or an S-record.");
+    }
+
+    #[test]
+    fn reading_sparse_images() {
+        let mut image = SparseImage::new(&[
+            DataRecord { address: 0x1010, bytes: b"World".to_vec() },
+            DataRecord { address: 0x1000, bytes: b"Hello".to_vec() },
+            DataRecord { address: 0x1004, bytes: b"o,".to_vec() }
+        ]);
+        assert_eq!(image.start(), 0x1000);
+        assert_eq!(image.length().unwrap(), 0x15);
+        assert_eq!(image.gaps(), vec![6..0x10]);
+        let mut buf = [0; 13];
+        assert_eq!(image.read_at(4, &mut buf).unwrap(), 13);
+        assert_eq!(&buf, b"o,\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFFW");
+        assert_eq!(image.read_at(0x13, &mut buf).unwrap(), 2);
+        assert_eq!(&buf[0..2], b"ld");
+    }
+}
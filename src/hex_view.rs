@@ -1,57 +1,409 @@
+use std::cell::{Cell, RefCell};
 use std::convert::TryFrom;
+use std::fmt::Write;
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
 
 use cursive::align::HAlign;
 use cursive::event::{Event, Key, MouseEvent};
 use cursive::event::EventResult;
+use cursive::Cursive;
 use cursive::Printer;
-use cursive::theme::{ColorStyle, Style};
+use cursive::theme::{Color, ColorStyle, ColorType, Effect, PaletteColor, Style};
 use cursive::traits::View;
 use cursive::utils::markup::StyledString;
 use cursive::utils::span::*;
 use cursive::Vec2;
-use unicode_width::UnicodeWidthStr;
+use cursive::views::{Dialog, TextContent};
+use serde_derive::{Deserialize, Serialize};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use xv::address_map::AddressMap;
+use xv::block_index::BlockIndex;
+use xv::byte_reader::{ByteSource, TileConfig, TileStats};
+use xv::byte_stats::ByteStats;
+use xv::error::{Result, XvError};
+use xv::hex_reader::{DisplayTransform, HexMode, HexReader, SearchOptions, TextRendering, Utf8Cell, VisualMode,
+                     VisualVisitor};
+use xv::hex_reader::{find_byte, find_end_of_run, find_entropy_region};
+use xv::hex_reader::HexVisitor;
+use xv::hex_reader::{OffsetFormat, OffsetsVisitor};
+use xv::hex_tables::{ByteCategory, BYTE_CATEGORY};
+use xv::memory::{Reservation, Subsystem};
+use xv::progress::Progress;
+use xv::search_index::SearchIndex;
+use xv::sparse::SparseFile;
+use xv::spool::Spool;
+use xv::strings::{WindowStrings, DEFAULT_MIN_LENGTH};
+
+use crate::annotations::{overlapping, Annotation};
+use crate::block_indexing::{load_index, load_search_index};
+use crate::group_separators::{GroupSeparators, Separator};
+use crate::map_files::{describe_address, Symbol};
+use crate::points_of_interest::{next_point, PointOfInterest, Source, SOURCES};
+use crate::progress_dialog::run_with_progress;
+use crate::search_dialog::search_again;
+use crate::status_bar::{describe_byte, describe_line, describe_position, update_measure};
+use crate::xv_state::{DisplayProfile, ReaderState};
+use crate::xv_tui::ShowError;
+
+/// The kinds of bytes that are shown in their own colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorKind {
+    Category(ByteCategory),
+    Annotation,
+    Gap,
+    Hole
+}
+
+pub const COLOR_KINDS: [ColorKind; 7] = [
+    ColorKind::Category(ByteCategory::AsciiPrintable),
+    ColorKind::Category(ByteCategory::AsciiWhitespace),
+    ColorKind::Category(ByteCategory::AsciiControl),
+    ColorKind::Category(ByteCategory::Other),
+    ColorKind::Annotation,
+    ColorKind::Gap,
+    ColorKind::Hole
+];
+
+impl ColorKind {
+    /// What the bytes of this kind are.
+    pub fn description(self) -> &'static str {
+        match self {
+            ColorKind::Category(ByteCategory::AsciiPrintable) => "Printable ASCII characters",
+            ColorKind::Category(ByteCategory::AsciiWhitespace) => "ASCII spaces, tabs and line breaks",
+            ColorKind::Category(ByteCategory::AsciiControl) => "Other ASCII control characters",
+            ColorKind::Category(ByteCategory::Other) => "Bytes above 0x7F",
+            ColorKind::Annotation => "Imported annotations, in their own colors",
+            ColorKind::Gap => "Gaps between the records of an image",
+            ColorKind::Hole => "Holes of a sparse file, which take no space on disk"
+        }
+    }
 
-use crate::hex_reader::{HexReader, VisualMode, VisualVisitor};
-use crate::hex_reader::HexVisitor;
-use crate::hex_reader::OffsetsVisitor;
-use crate::hex_tables::ByteCategory;
-use crate::xv_state::ReaderState;
+    /// The style of the bytes of this kind. Without colors, the kinds are told apart by bold,
+    /// underlined and reversed text instead.
+    pub fn style(self, profile: DisplayProfile) -> Style {
+        match (self, profile) {
+            (ColorKind::Category(category), DisplayProfile::Monochrome) => category_to_effects(&category),
+            (ColorKind::Category(category), _) => Style::from(category_to_color(&category)),
+            (ColorKind::Annotation, DisplayProfile::Monochrome) => Style::from(Effect::Reverse),
+            (ColorKind::Annotation, _) => Style::from(ColorStyle::highlight()),
+            (ColorKind::Gap, _) | (ColorKind::Hole, _) => Style::from(ColorStyle::secondary())
+        }
+    }
+}
 
 pub struct HexView {
     reader: HexReader,
     invalidated_resize: bool,
     invalidated_data_changed: bool,
-    show_visual_view: bool,
+    panes: Panes,
+    screen_mode: ScreenMode,
+    /// The most pages of consecutive rows that are shown side by side, and how many of them fit
+    /// and how wide each of them is, with the line on its left.
+    pages: u16,
+    shown_pages: u16,
+    page_width: usize,
     offsets_column_pos: Vec2,
     offsets_column_size: Vec2,
     hex_column_pos: Vec2,
     hex_column_size: Vec2,
     visual_column_pos: Vec2,
     visual_column_size: Vec2,
-    prestyled_hex_table: Vec<StyledString>,
-    prestyled_visual_table: Vec<StyledString>
+    /// The column to the right of the bytes, where the names of the symbols in every row are shown.
+    labels_column_pos: Vec2,
+    labels_column_size: Vec2,
+    styled_tables: StyledTables,
+    /// The text of a row of the offsets column, which is kept from one frame to the next, so
+    /// that drawing does not allocate it again.
+    offsets_text: RefCell<String>,
+    /// A message shown on the bottom border until the next key press.
+    notice: Option<String>,
+    /// The offsets of the A and B marks, that the status bar measures the distance between.
+    marks: (Option<u64>, Option<u64>),
+    /// The repeat count that is being typed, for the next command.
+    count: Option<u64>,
+    /// Whether mark B follows the position, so that the bytes from mark A to the position are
+    /// selected.
+    selecting: bool,
+    /// Whether the marks select the same columns of every row from one to the other, rather than
+    /// every byte between them.
+    column_selection: bool,
+    /// Where the window is in the file, for the status bar.
+    position: TextContent,
+    /// The pattern and options of the last search, that `n` and `N` search for again.
+    last_search: Option<(Vec<u8>, SearchOptions)>,
+    /// The offset of the last match, and the window offset that going to it resulted in.
+    last_match: Option<(u64, u64)>,
+    /// The imported annotations of the file, sorted by offset.
+    annotations: Vec<Annotation>,
+    /// The length of the longest annotation.
+    longest_annotation: u64,
+    /// The loaded symbols, sorted by address.
+    symbols: Vec<Symbol>,
+    /// The offsets of the symbols that are in the file, with their index, sorted by offset.
+    symbol_offsets: Vec<(u64, usize)>,
+    /// The ranges of bytes that are not really there, like the gaps between the records of a
+    /// firmware image, sorted by offset.
+    gaps: Vec<Range<u64>>,
+    /// The sources of the points of interest that `{` and `}` go to.
+    point_sources: Vec<Source>,
+    /// The kinds of bytes that are not shown in their colors.
+    hidden_colors: Vec<ColorKind>,
+    display_profile: DisplayProfile,
+    /// Whether the byte at the position is described in words, and the bytes are told apart
+    /// without colors.
+    screen_reader: bool,
+    /// The background of every other row, if the rows are shaded.
+    stripe_color: Option<Color>,
+    /// The number of bytes between the guide lines in the hex column, or 0 for no guide lines.
+    column_guides: u64,
+    group_separators: GroupSeparators,
+    /// The separators that are shown instead in presentations, with the groups set further
+    /// apart.
+    presented_separators: Option<GroupSeparators>,
+    /// How the bytes are changed before they are shown.
+    transform: DisplayTransform,
+    /// Whether the window goes to the end when bytes are added to a growing source, until it is
+    /// moved away from the end.
+    following: bool,
+    /// The live source that the reader reads from, when the time that every row arrived is shown
+    /// next to its offset.
+    spool: Option<Spool>,
+    /// The block index of the file, once it has been built.
+    block_index: Option<BlockIndex>,
+    /// The search index of the file, which is shared with the searches that run in the
+    /// background.
+    search_index: Option<Arc<SearchIndex>>,
+    /// The memory that the indexes take up.
+    index_memory: Reservation,
+    /// The least time between two captures of the window, when the frames are limited to a
+    /// number per second.
+    frame_interval: Option<Duration>,
+    /// The window that was captured last, which is shown while a move is put off.
+    last_frame: Option<Frame>,
+    /// Where the window was moved to, when the move was put off until the next frame.
+    deferred_pos: Option<(u64, u64)>,
+    /// Asks for the view to be laid out again after a while, to capture a move that was put off.
+    frame_wakeup: Option<Sender<Duration>>,
+    wakeup_pending: bool,
+    /// The strings around the window, when the strings panel is shown, and what it shows.
+    window_strings: Option<WindowStrings>,
+    strings_content: TextContent,
+    /// The cells of the captured bytes in the visual column, when they are decoded as UTF-8.
+    utf8_cells: Vec<Utf8Cell>,
+    /// The offset of the window, as of the last layout, for the views that follow this one.
+    published_offset: Rc<Cell<u64>>,
+    scroll_lock: Option<ScrollLock>
+}
+
+/// Keeps a view at the same distance from the window of another view, as that window moves.
+struct ScrollLock {
+    leader: Rc<Cell<u64>>,
+    /// How far after the window of the other view the window of this view is.
+    delta: i64,
+    /// The offset of the other view that this view last followed.
+    followed: Option<u64>
+}
+
+/// The same columns of the rows from one mark to the other, which are the bytes that a column
+/// selection selects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColumnSelection {
+    /// The offset of the first selected byte of the first row.
+    pub start: u64,
+    pub line_width: u64,
+    pub columns: u64,
+    pub rows: u64
+}
+
+impl ColumnSelection {
+    /// The selected bytes of a row, counting from the first selected row.
+    pub fn row(&self, row: u64) -> Range<u64> {
+        let start = self.start + row * self.line_width;
+        start..start + self.columns
+    }
+
+    /// The number of selected bytes.
+    pub fn length(&self) -> u64 {
+        self.columns * self.rows
+    }
+}
+
+/// When the window was captured, and where it was.
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    pos: (u64, u64),
+    size: (u16, u16),
+    line_width: u64,
+    at: Instant
+}
+
+/// Which of the columns are shown, which is remembered across restarts. The hex column is shown
+/// anyway when the visual column is not.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Panes {
+    pub offsets: bool,
+    pub hex: bool,
+    pub visual: bool
+}
+
+impl Default for Panes {
+    fn default() -> Self {
+        Panes { offsets: true, hex: true, visual: true }
+    }
 }
 
+/// What is shown around the bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScreenMode {
+    /// The border and the title of the view, and the status bar with the key hints.
+    Normal,
+    /// Only the bytes, in all of the screen.
+    FullScreen,
+    /// Only the bytes, with the groups set further apart, for demos and sharing the screen.
+    Presentation
+}
+
+/// The names of the columns, that the `pane` command shows and hides.
+pub const PANES: [&str; 3] = ["offsets", "hex", "visual"];
+
+/// The least width of a page, so that more pages are only shown side by side on wide terminals.
+const MIN_PAGE_WIDTH: usize = 60;
+
+/// The number of bytes before and after the window that strings are found in for the strings
+/// panel.
+const STRINGS_MARGIN: u64 = 64 * 1024;
+
+/// The highest repeat count, as every repeat is done before the screen is drawn again.
+const MAX_COUNT: u64 = 1_000_000;
+
+/// How long to wait before capturing the window again, when some of it is still being fetched.
+const FETCH_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// The frames per second that the window is captured at, at most, unless it is set.
+pub const DEFAULT_MAX_FPS: u64 = 30;
+
+/// The block index that was kept for the file that the reader reads, if there is one.
+fn stored_index(reader: &HexReader) -> Option<BlockIndex> {
+    if reader.is_file() { load_index(&reader.get_path(), reader.get_length()) } else { None }
+}
+
+fn stored_search_index(reader: &HexReader) -> Option<Arc<SearchIndex>> {
+    if !reader.is_file() {
+        return None;
+    }
+    load_search_index(&reader.get_path(), reader.get_length()).map(Arc::new)
+}
+
+/// The memory that the indexes of a file take up.
+fn indexes_size(block_index: &Option<BlockIndex>, search_index: &Option<Arc<SearchIndex>>) -> u64 {
+    block_index.as_ref().map_or(0, BlockIndex::memory_size)
+        + search_index.as_ref().map_or(0, |index| index.memory_size())
+}
+
+/// The width of the arrival time of a row, in seconds.
+const ARRIVAL_WIDTH: usize = 10;
+
 impl HexView {
     pub fn new(reader: HexReader) -> HexView {
+        // The end of a spool is followed from the start.
+        let spool = reader.get_spool().cloned();
+        let block_index = stored_index(&reader);
+        let search_index = stored_search_index(&reader);
+        let index_memory = Reservation::new(Subsystem::Indexes, indexes_size(&block_index, &search_index));
         HexView {
             reader,
             invalidated_resize: true,
             invalidated_data_changed: true,
-            show_visual_view: true,
+            panes: Panes::default(),
+            screen_mode: ScreenMode::Normal,
+            pages: 1,
+            shown_pages: 1,
+            page_width: 0,
             offsets_column_pos: Vec2::new(0, 0),
             offsets_column_size: Vec2::new(0, 0),
             hex_column_pos: Vec2::new(0, 0),
             hex_column_size: Vec2::new(0, 0),
             visual_column_pos: Vec2::new(0, 0),
             visual_column_size: Vec2::new(0, 0),
-            prestyled_hex_table: Vec::new(),
-            prestyled_visual_table: Vec::new()
+            labels_column_pos: Vec2::new(0, 0),
+            labels_column_size: Vec2::new(0, 0),
+            styled_tables: StyledTables::default(),
+            offsets_text: RefCell::new(String::new()),
+            notice: None,
+            marks: (None, None),
+            count: None,
+            selecting: false,
+            column_selection: false,
+            position: TextContent::new(""),
+            last_search: None,
+            last_match: None,
+            annotations: Vec::new(),
+            longest_annotation: 0,
+            symbols: Vec::new(),
+            symbol_offsets: Vec::new(),
+            gaps: Vec::new(),
+            point_sources: SOURCES.to_vec(),
+            hidden_colors: Vec::new(),
+            display_profile: DisplayProfile::Color,
+            screen_reader: false,
+            stripe_color: None,
+            column_guides: 0,
+            group_separators: GroupSeparators::default(),
+            presented_separators: None,
+            transform: DisplayTransform::default(),
+            following: spool.is_some(),
+            spool,
+            block_index,
+            search_index,
+            index_memory,
+            frame_interval: None,
+            last_frame: None,
+            deferred_pos: None,
+            frame_wakeup: None,
+            wakeup_pending: false,
+            window_strings: None,
+            strings_content: TextContent::new(""),
+            utf8_cells: Vec::new(),
+            published_offset: Rc::new(Cell::new(0)),
+            scroll_lock: None
         }
     }
     
     pub fn switch_reader(&mut self, reader: HexReader) {
         self.reader = reader;
+        self.last_match = None;
+        // The marks and a repeat count that is being typed are for the other file.
+        self.marks = (None, None);
+        self.selecting = false;
+        self.count = None;
+        self.annotations.clear();
+        self.longest_annotation = 0;
+        self.symbols.clear();
+        self.symbol_offsets.clear();
+        self.gaps.clear();
+        self.transform = DisplayTransform::default();
+        self.spool = self.reader.get_spool().cloned();
+        self.following = self.spool.is_some();
+        self.last_frame = None;
+        self.deferred_pos = None;
+        // The file can have its own text rendering.
+        self.styled_tables.clear();
+        if let Some(window_strings) = &mut self.window_strings {
+            window_strings.clear();
+        }
+        // The indexes of the other file are let go of first, to make room for these.
+        self.set_block_index(None);
+        self.set_search_index(None);
+        self.block_index = stored_index(&self.reader);
+        self.search_index = stored_search_index(&self.reader);
+        self.index_memory.resize(indexes_size(&self.block_index, &self.search_index));
         self.invalidated_data_changed = true;
         self.invalidated_resize = true;
     }
@@ -60,25 +412,532 @@ impl HexView {
         ReaderState::new(&self.reader)
     }
     
-    pub fn go_to_offset(&mut self, offset: u64) {
-        let line = offset / self.reader.line_width;
-        let line_offset = offset % self.reader.line_width;
-        let lines_in_file = self.reader.get_lines_in_file();
-        if line <= lines_in_file {
-            self.reader.window_pos = (line_offset, line);
+    /// The text that is kept up to date with where the window is in the file.
+    pub fn get_position_content(&self) -> TextContent {
+        self.position.clone()
+    }
+    
+    pub fn get_annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+    
+    /// Show the annotations as colored ranges of bytes. They must be sorted by offset.
+    pub fn set_annotations(&mut self, annotations: Vec<Annotation>) {
+        self.longest_annotation = annotations.iter().map(|annotation| annotation.length).max().unwrap_or(0);
+        self.annotations = annotations;
+    }
+    
+    /// Show the bytes in the ranges as gaps instead of their values. They must be sorted.
+    pub fn set_gaps(&mut self, gaps: Vec<Range<u64>>) {
+        self.gaps = gaps;
+    }
+    
+    pub fn get_symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+    
+    /// Show the names of the symbols next to the rows they are in. They must be sorted by address.
+    pub fn set_symbols(&mut self, symbols: Vec<Symbol>) {
+        self.symbols = symbols;
+        self.map_symbol_offsets();
+        self.invalidated_resize = true;
+        self.invalidated_data_changed = true;
+    }
+    
+    fn map_symbol_offsets(&mut self) {
+        let length = self.reader.get_length();
+        let address_map = &self.reader.address_map;
+        let mut symbol_offsets: Vec<(u64, usize)> = self.symbols.iter().enumerate()
+            .filter_map(|(i, symbol)| address_map.offset_of(symbol.address).map(|offset| (offset, i)))
+            .filter(|&(offset, _)| offset < length)
+            .collect();
+        symbol_offsets.sort();
+        self.symbol_offsets = symbol_offsets;
+    }
+    
+    /// The marks, the last search match, the annotations, the symbols and the starts of the
+    /// segments, sorted by offset.
+    pub fn get_points_of_interest(&self) -> Vec<PointOfInterest> {
+        let mut points = Vec::new();
+        let point = |offset, source, label: String| PointOfInterest { offset, source, label };
+        if let Some(a) = self.marks.0 {
+            points.push(point(a, Source::Mark, String::from("Mark A")));
+        }
+        if let Some(b) = self.marks.1 {
+            points.push(point(b, Source::Mark, String::from("Mark B")));
+        }
+        if let Some((offset, _)) = self.last_match {
+            points.push(point(offset, Source::SearchMatch, String::from("Last match")));
+        }
+        for annotation in &self.annotations {
+            points.push(point(annotation.offset, Source::Annotation, annotation.label.clone()));
+        }
+        for &(offset, i) in &self.symbol_offsets {
+            points.push(point(offset, Source::Symbol, self.symbols[i].name.clone()));
+        }
+        for segment in self.reader.address_map.segments() {
+            points.push(point(segment.offset, Source::Segment, format!("Segment at 0x{:X}", segment.address)));
+        }
+        for gap in &self.gaps {
+            let address = self.reader.address_map.address_of(gap.end);
+            points.push(point(gap.end, Source::Segment, format!("Records at 0x{:X}", address)));
+        }
+        for hole in self.reader.get_holes() {
+            points.push(point(hole.end, Source::Segment, String::from("Data after a hole")));
+        }
+        if let Some(index) = self.get_block_index() {
+            for (offset, mime_type) in index.magic_hits() {
+                points.push(point(*offset, Source::Magic, mime_type.clone()));
+            }
+        }
+        points.sort_by_key(|point| point.offset);
+        points
+    }
+    
+    pub fn is_color_shown(&self, kind: ColorKind) -> bool {
+        !self.hidden_colors.contains(&kind)
+    }
+    
+    /// Show the bytes of a kind in their color, or in the normal color instead.
+    pub fn show_color(&mut self, kind: ColorKind, shown: bool) {
+        self.hidden_colors.retain(|hidden| *hidden != kind);
+        if !shown {
+            self.hidden_colors.push(kind);
+        }
+        self.styled_tables.clear();
+    }
+    
+    /// The profile that the bytes are shown in, which is always monochrome for screen readers.
+    pub fn get_display_profile(&self) -> DisplayProfile {
+        if self.screen_reader {
+            DisplayProfile::Monochrome
         } else {
-            self.reader.window_pos = (0, lines_in_file);
+            self.display_profile
         }
-        // todo adjust window size if it would overflow at new position
+    }
+    
+    pub fn set_display_profile(&mut self, profile: DisplayProfile) {
+        self.display_profile = profile;
+        self.styled_tables.clear();
+    }
+    
+    pub fn get_screen_reader(&self) -> bool {
+        self.screen_reader
+    }
+    
+    pub fn set_screen_reader(&mut self, screen_reader: bool) {
+        self.screen_reader = screen_reader;
+        self.styled_tables.clear();
+        self.invalidated_data_changed = true;
+    }
+    
+    /// Shade every other row in the color, or stop shading them.
+    pub fn set_stripe_color(&mut self, color: Option<Color>) {
+        self.stripe_color = color;
+        self.styled_tables.clear();
+    }
+    
+    pub fn get_panes(&self) -> Panes {
+        self.panes
+    }
+    
+    /// Show or hide the offsets, hex and visual columns.
+    pub fn set_panes(&mut self, panes: Panes) {
+        self.panes = panes;
+        self.invalidated_resize = true;
+    }
+    
+    pub fn get_screen_mode(&self) -> ScreenMode {
+        self.screen_mode
+    }
+    
+    /// Show the bytes with or without the border and the title around them. The status bar is
+    /// shown and hidden with them by `xv_tui::set_screen_mode`.
+    pub fn set_screen_mode(&mut self, mode: ScreenMode) {
+        self.screen_mode = mode;
+        self.present_separators();
+        self.invalidated_resize = true;
+    }
+    
+    fn has_border(&self) -> bool {
+        self.screen_mode == ScreenMode::Normal
+    }
+    
+    /// Set every group apart with an extra space in presentations, and larger groups like they
+    /// are chosen to be.
+    fn present_separators(&mut self) {
+        self.presented_separators = if self.screen_mode == ScreenMode::Presentation {
+            let mut separators = self.group_separators.clone();
+            separators.set_groups(Separator::Space);
+            Some(separators)
+        } else {
+            None
+        };
+    }
+    
+    /// The separators of the groups that are shown.
+    fn separators(&self) -> &GroupSeparators {
+        self.presented_separators.as_ref().unwrap_or(&self.group_separators)
+    }
+    
+    /// Show up to this many pages of consecutive rows side by side, as many as fit in the width.
+    pub fn set_pages(&mut self, pages: u16) {
+        self.pages = pages.max(1);
+        self.invalidated_resize = true;
+    }
+    
+    /// The number of pages that fit side by side in the width of the view.
+    fn fitting_pages(&self, width: usize) -> u16 {
+        let fitting = (width.saturating_sub(1) / MIN_PAGE_WIDTH).max(1);
+        u16::try_from(fitting).unwrap_or(u16::MAX).min(self.pages)
+    }
+    
+    fn shows_visual(&self) -> bool {
+        self.panes.visual && *self.reader.get_visual_mode() != VisualMode::Off
+    }
+    
+    /// Whether the hex column is shown, which it is when the visual column is not, even if it is
+    /// hidden, so that the bytes are always shown.
+    fn shows_hex(&self) -> bool {
+        self.panes.hex || !self.shows_visual()
+    }
+    
+    /// Render the text of the visual column this way in the current visual mode.
+    pub fn set_text_rendering(&mut self, rendering: TextRendering) {
+        let mode = *self.reader.get_visual_mode();
+        self.reader.text_rendering.set(mode, rendering);
+        self.styled_tables.visual.retain(|(styled_mode, _)| *styled_mode != mode);
+        // Caret notation makes the bytes wider, and decoding needs the captured bytes.
+        self.invalidated_resize = true;
+        self.invalidated_data_changed = true;
+    }
+    
+    /// Draw a guide line in the hex column every `every` bytes, independent of the groups.
+    pub fn set_column_guides(&mut self, every: u64) {
+        self.column_guides = every;
+    }
+    
+    pub fn set_group_separators(&mut self, separators: GroupSeparators) {
+        self.group_separators = separators;
+        self.present_separators();
+        // Spaces make the groups wider.
+        self.invalidated_resize = true;
+    }
+    
+    /// Show the bytes transformed, like with their nibbles swapped. The status bar tells that
+    /// they are.
+    pub fn set_transform(&mut self, transform: DisplayTransform) {
+        self.transform = transform;
+        self.invalidated_data_changed = true;
+    }
+    
+    pub fn is_following(&self) -> bool {
+        self.following
+    }
+    
+    /// Follow the end of a growing source, or stop following it.
+    pub fn set_following(&mut self, following: bool) {
+        self.following = following;
+        if following {
+            self.go_to_end();
+        }
+    }
+    
+    /// Capture the window at most `fps` times a second, or as often as it moves with 0. Moves in
+    /// between, like from a key that is held down, are put off until the next frame, so that they
+    /// are read together.
+    pub fn set_max_fps(&mut self, fps: u64) {
+        self.frame_interval = match fps {
+            0 => None,
+            fps => Some(Duration::from_secs(1) / fps.min(1000) as u32)
+        };
+    }
+    
+    /// The offset of the window, that other views can follow with `set_scroll_lock`.
+    pub fn get_published_offset(&self) -> Rc<Cell<u64>> {
+        self.published_offset.clone()
+    }
+    
+    /// Keep the window `delta` bytes after the published offset of another view, as that view
+    /// moves, or stop following it.
+    pub fn set_scroll_lock(&mut self, lock: Option<(Rc<Cell<u64>>, i64)>) {
+        self.scroll_lock = lock.map(|(leader, delta)| ScrollLock { leader, delta, followed: None });
+    }
+    
+    /// Move along with the followed view, if it has moved since it was last followed.
+    fn follow_scroll_lock(&mut self) {
+        let target = match &mut self.scroll_lock {
+            Some(lock) if lock.followed != Some(lock.leader.get()) => {
+                let offset = lock.leader.get();
+                lock.followed = Some(offset);
+                offset.saturating_add_signed(lock.delta)
+            },
+            _ => return
+        };
+        self.go_to_offset(target);
+    }
+    
+    /// Where to ask for the view to be laid out again, after the given time.
+    pub fn set_frame_wakeup(&mut self, wakeup: Sender<Duration>) {
+        self.frame_wakeup = Some(wakeup);
+    }
+    
+    /// Go on from where the window was moved to, if the move was put off, unless the window has
+    /// been moved again since, like by a command.
+    fn resume_deferred_move(&mut self) {
+        if let (Some(target), Some(frame)) = (self.deferred_pos.take(), self.last_frame) {
+            if self.reader.window_pos == frame.pos {
+                self.reader.window_pos = target;
+            }
+        }
+    }
+    
+    /// How much longer the capture of a move should be put off, when the window was captured
+    /// less than a frame ago, and has only moved since.
+    fn capture_delay(&self) -> Option<Duration> {
+        let interval = self.frame_interval?;
+        let frame = self.last_frame?;
+        self.frame_wakeup.as_ref()?;
+        let moved_only = frame.pos != self.reader.window_pos && frame.size == self.reader.window_size
+            && frame.line_width == self.reader.line_width;
+        let elapsed = frame.at.elapsed();
+        if moved_only && elapsed < interval { Some(interval - elapsed) } else { None }
+    }
+    
+    /// Ask for the view to be laid out again after the delay, unless that has been asked already.
+    fn request_frame(&mut self, delay: Duration) {
+        if self.wakeup_pending {
+            return;
+        }
+        if let Some(wakeup) = &self.frame_wakeup {
+            self.wakeup_pending = wakeup.send(delay).is_ok();
+        }
+    }
+    
+    /// Look for bytes that were added to a growing source, and go to the end if following it.
+    pub fn refresh(&mut self) {
+        if let Err(error) = self.reader.reopen() {
+            self.notice = Some(format!("{}", error));
+        }
+        if self.following {
+            self.go_to_end();
+        }
+        if let Some(window_strings) = &mut self.window_strings {
+            window_strings.clear();
+        }
+        self.invalidated_data_changed = true;
+    }
+    
+    /// Find the strings around the window as it moves, for the strings panel, or stop finding
+    /// them.
+    pub fn set_strings_panel(&mut self, shown: bool) {
+        self.window_strings = if shown { Some(WindowStrings::new(DEFAULT_MIN_LENGTH, STRINGS_MARGIN)) } else { None };
+        // The panel takes up some of the width.
+        self.invalidated_resize = true;
+        self.invalidated_data_changed = true;
+    }
+    
+    pub fn has_strings_panel(&self) -> bool {
+        self.window_strings.is_some()
+    }
+    
+    /// What the strings panel shows.
+    pub fn get_strings_content(&self) -> TextContent {
+        self.strings_content.clone()
+    }
+    
+    /// Find the strings around the window again, if it moved away from those found before, and
+    /// list the ones from the top of the window on, with those in the window in bold.
+    fn update_strings(&mut self) {
+        let window_strings = match &mut self.window_strings {
+            Some(window_strings) => window_strings,
+            None => return
+        };
+        let start = self.reader.get_window_offset();
+        let end = start + self.reader.line_width * u64::from(self.reader.window_size.1);
+        let length = self.reader.get_length();
+        let reader = &mut self.reader;
+        let found = window_strings.update(start, end, length, |offset, len| {
+            let mut buf = vec![0; len];
+            let read = reader.read_at(offset, &mut buf)?;
+            buf.truncate(read);
+            Ok(buf)
+        });
+        if let Err(error) = found {
+            self.notice = Some(format!("{}", error));
+        }
+        let mut content = StyledString::new();
+        for string in window_strings.from(start).iter().take(usize::from(self.reader.window_size.1)) {
+            let line = format!("{:08X} {}\n", string.offset, string.text);
+            if string.offset < end {
+                content.append_styled(line, Effect::Bold);
+            } else {
+                content.append_plain(line);
+            }
+        }
+        if content.is_empty() {
+            content.append_plain("No strings here");
+        }
+        self.strings_content.set_content(content);
+    }
+    
+    /// The spool that the bytes are read from, when they are still arriving.
+    pub fn get_spool(&self) -> Option<Spool> {
+        self.spool.clone()
+    }
+    
+    /// The width of the offsets column, with the arrival times when they are shown.
+    fn get_offsets_column_width(&self) -> usize {
+        self.reader.get_row_offsets_width() + if self.spool.is_some() { ARRIVAL_WIDTH } else { 0 }
+    }
+    
+    pub fn set_notice(&mut self, notice: String) {
+        self.notice = Some(notice);
+    }
+    
+    /// Describe the bytes of the top line of the window in words.
+    pub fn describe_top_line(&mut self) -> Result<String> {
+        let offset = self.reader.get_window_offset();
+        let bytes = self.read_bytes(offset, usize::from(self.reader.window_size.0))?;
+        Ok(describe_line(self.reader.address_map.address_of(offset), &bytes))
+    }
+    
+    pub fn get_point_sources(&self) -> &[Source] {
+        &self.point_sources
+    }
+    
+    /// Choose the sources of the points of interest that `{` and `}` go to.
+    pub fn set_point_sources(&mut self, sources: Vec<Source>) {
+        self.point_sources = sources;
+    }
+    
+    pub fn get_marks(&self) -> (Option<u64>, Option<u64>) {
+        self.marks
+    }
+    
+    /// Set the marks, and stop mark B from following the position.
+    pub fn set_marks(&mut self, marks: (Option<u64>, Option<u64>)) {
+        self.marks = marks;
+        self.selecting = false;
+    }
+    
+    /// The start and the end of the selected bytes, from the lower of the two marks up to the
+    /// higher, which is not included, like the region of Emacs. There is only a selection when
+    /// both marks are set, at different offsets.
+    pub fn get_selection(&self) -> Option<(u64, u64)> {
+        match self.marks {
+            (Some(a), Some(b)) if a != b => Some((a.min(b), a.max(b))),
+            _ => None
+        }
+    }
+    
+    /// The columns between the two marks, of the rows from one mark to the other, when columns are
+    /// selected. There is only a selection when the marks are in different columns.
+    pub fn get_column_selection(&self) -> Option<ColumnSelection> {
+        let (a, b) = match self.marks {
+            (Some(a), Some(b)) if self.column_selection => (a, b),
+            _ => return None
+        };
+        let width = self.reader.line_width;
+        let (first_column, last_column) = ((a % width).min(b % width), (a % width).max(b % width));
+        let (first_row, last_row) = ((a / width).min(b / width), (a / width).max(b / width));
+        if first_column == last_column {
+            return None;
+        }
+        Some(ColumnSelection {
+            start: first_row * width + first_column,
+            line_width: width,
+            columns: last_column - first_column,
+            rows: last_row - first_row + 1
+        })
+    }
+    
+    pub fn get_path(&self) -> PathBuf {
+        self.reader.get_path()
+    }
+    
+    pub fn is_file(&self) -> bool {
+        self.reader.is_file()
+    }
+    
+    /// Open a source that `length` bytes from the offset can be read from on another thread, and
+    /// the offset of the bytes in it. Files are opened again, and in-memory sources have the bytes
+    /// copied out of them.
+    pub fn open_background_source(&mut self, offset: u64, length: u64)
+        -> Result<(Box<ByteSource + Send>, u64)> {
+        if self.reader.is_file() {
+            Ok((Box::new(SparseFile::open(File::open(self.reader.get_path())?)?), offset))
+        } else if let Some(source) = self.reader.try_clone_source() {
+            Ok((source, offset))
+        } else {
+            Ok((Box::new(self.read_bytes(offset, length as usize)?), 0))
+        }
+    }
+    
+    pub fn get_offset(&self) -> u64 {
+        self.reader.get_window_offset()
+    }
+    
+    pub fn go_to_offset(&mut self, offset: u64) {
+        let offset = offset.min(self.reader.last_valid_offset());
+        let line = offset / self.reader.line_width;
+        let visible_width = self.reader.get_visible_line_width();
+        let line_offset = (offset % self.reader.line_width).saturating_sub(self.reader.line_skip)
+            .min(visible_width.saturating_sub(1));
+        self.reader.window_pos = (line_offset, line);
+        self.reader.clamp_window();
+        self.invalidated_data_changed = true;
+        self.follow_selection();
+    }
+    
+    /// Remember the pattern to search for, so that `n` and `N` can search for it again.
+    pub fn set_search(&mut self, pattern: Vec<u8>, options: SearchOptions) {
+        self.last_search = Some((pattern, options));
+        self.last_match = None;
+    }
+    
+    /// The last pattern and search options, in the opposite direction if `reverse` is true, and the
+    /// offset to search from. Returns `None` if nothing has been searched for yet.
+    pub fn next_search(&self, reverse: bool) -> Option<(Vec<u8>, SearchOptions, u64)> {
+        let (pattern, mut options) = match self.last_search {
+            Some((ref pattern, options)) => (pattern.clone(), options),
+            None => return None
+        };
+        options.backwards ^= reverse;
+        // Continue from the last match, if the window is still where going to it put it.
+        let window_offset = self.reader.get_window_offset();
+        let from = match self.last_match {
+            Some((found, window)) if window == window_offset => found,
+            _ => window_offset
+        };
+        Some((pattern, options, from))
+    }
+    
+    /// Go to a match of the last search.
+    pub fn show_match(&mut self, found: u64) {
+        self.go_to_offset(found);
+        self.last_match = Some((found, self.reader.get_window_offset()));
+    }
+    
+    /// Show the first lines of the file.
+    pub fn go_to_start(&mut self) {
+        self.reader.window_pos = (0, 0);
+        self.invalidated_data_changed = true;
+    }
+    
+    /// Show the last lines of the file, with the last line at the bottom of the window.
+    pub fn go_to_end(&mut self) {
+        let height = u64::from(self.reader.window_size.1).max(1);
+        self.reader.window_pos = (0, self.reader.get_last_line().saturating_sub(height - 1));
         self.invalidated_data_changed = true;
     }
     
     pub fn set_line_width(&mut self, length: u64) {
         self.reader.line_width = length;
-        let lines_in_file = self.reader.get_lines_in_file();
-        if self.reader.window_pos.1 > lines_in_file {
-            self.reader.window_pos.1 = lines_in_file;
+        if self.reader.line_skip >= length {
+            self.reader.line_skip = 0;
         }
+        self.reader.clamp_window();
         self.invalidated_resize = true;
         self.invalidated_data_changed = true;
     }
@@ -87,6 +946,30 @@ impl HexView {
         self.reader.line_width
     }
     
+    /// Skip the given number of bytes at the start of every line, and show only `shown` bytes of
+    /// what is left, or the rest of the line if `shown` is `None`.
+    pub fn set_line_columns(&mut self, skip: u64, shown: Option<u64>) {
+        self.reader.line_skip = skip;
+        self.reader.line_shown = shown;
+        self.reader.clamp_window();
+        self.invalidated_resize = true;
+        self.invalidated_data_changed = true;
+    }
+    
+    pub fn get_line_columns(&self) -> (u64, Option<u64>) {
+        (self.reader.line_skip, self.reader.line_shown)
+    }
+    
+    pub fn set_record_mode(&mut self, record_mode: bool, field_guides: Vec<u64>) {
+        self.reader.record_mode = record_mode;
+        self.reader.field_guides = field_guides;
+        self.invalidated_data_changed = true;
+    }
+    
+    pub fn get_record_mode(&self) -> (bool, &[u64]) {
+        (self.reader.record_mode, &self.reader.field_guides)
+    }
+    
     pub fn set_group(&mut self, group: u16) {
         self.reader.group = group;
         self.invalidated_resize = true;
@@ -97,30 +980,120 @@ impl HexView {
         self.reader.group
     }
     
+    pub fn get_tile_config(&self) -> TileConfig {
+        self.reader.get_tile_config()
+    }
+    
+    /// The block index of the file, if it has been built, and the file has not changed since.
+    pub fn get_block_index(&self) -> Option<&BlockIndex> {
+        self.block_index.as_ref().filter(|index| index.length() == self.reader.get_length())
+    }
+    
+    pub fn set_block_index(&mut self, block_index: Option<BlockIndex>) {
+        self.block_index = block_index;
+        self.index_memory.resize(indexes_size(&self.block_index, &self.search_index));
+    }
+    
+    /// The search index of the file, if it has been built, and the file has not changed since.
+    pub fn get_search_index(&self) -> Option<Arc<SearchIndex>> {
+        self.search_index.as_ref().filter(|index| index.length() == self.reader.get_length()).cloned()
+    }
+    
+    pub fn set_search_index(&mut self, search_index: Option<Arc<SearchIndex>>) {
+        self.search_index = search_index;
+        self.index_memory.resize(indexes_size(&self.block_index, &self.search_index));
+    }
+    
+    /// Read the file in tiles of another size, or go back to the tiles of its source with `None`.
+    pub fn set_tile_config(&mut self, config: Option<TileConfig>) {
+        self.reader.set_tile_config(config);
+        self.invalidated_data_changed = true;
+    }
+    
+    pub fn get_tile_stats(&self) -> TileStats {
+        self.reader.get_tile_stats()
+    }
+    
+    /// Show the offsets as addresses, with the start of the file at the given address.
+    pub fn set_base_address(&mut self, base_address: u64) {
+        let segments = self.reader.address_map.segments().to_vec();
+        self.set_address_map(AddressMap::new(base_address, segments));
+    }
+    
+    pub fn set_address_map(&mut self, address_map: AddressMap) {
+        self.reader.address_map = address_map;
+        self.map_symbol_offsets();
+        self.invalidated_resize = true;
+        self.invalidated_data_changed = true;
+    }
+    
+    pub fn get_address_map(&self) -> &AddressMap {
+        &self.reader.address_map
+    }
+    
+    /// Show offsets with 8 or 16 hex digits, or with `None`, as many as the offsets in view need.
+    pub fn set_offset_digits(&mut self, digits: Option<u8>) {
+        self.reader.offset_digits = digits;
+        self.invalidated_resize = true;
+        self.invalidated_data_changed = true;
+    }
+    
     pub fn get_length(&self) -> u64 {
         self.reader.get_length()
     }
     
+    pub fn get_last_line(&self) -> u64 {
+        self.reader.get_last_line()
+    }
+    
+    /// Read up to `len` bytes from the given offset. Fewer bytes are returned near the end of the file.
+    /// Read the bytes at the offset. Bytes that are still being fetched are waited for, from a
+    /// clone of the source that waits, since the dialogs that read them need them now.
+    pub fn read_bytes(&mut self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+        let read = match self.reader.read_at(offset, &mut buf) {
+            Err(XvError::Io(ref error)) if error.kind() == io::ErrorKind::WouldBlock => {
+                match self.reader.try_clone_source() {
+                    Some(mut source) => source.read_at(offset, &mut buf)?,
+                    None => return Err(XvError::Io(io::Error::new(io::ErrorKind::WouldBlock, error.to_string())))
+                }
+            },
+            result => result?
+        };
+        buf.truncate(read);
+        Ok(buf)
+    }
+    
+    pub fn get_window_stats(&self) -> ByteStats {
+        ByteStats::from_bytes(self.reader.get_captured_bytes())
+    }
+    
     fn toggle_visual(&mut self) -> EventResult {
-        self.prestyled_visual_table.clear();
         match self.reader.get_visual_mode() {
             VisualMode::Unicode => {
                 self.reader.set_visual_mode(VisualMode::Ascii);
             },
             VisualMode::Ascii => {
                 self.reader.set_visual_mode(VisualMode::Off);
-                self.show_visual_view = false;
                 self.invalidated_resize = true;
             },
             VisualMode::Off => {
                 self.reader.set_visual_mode(VisualMode::Unicode);
-                self.show_visual_view = true;
                 self.invalidated_resize = true;
             }
         }
         EventResult::Consumed(None)
     }
     
+    fn toggle_bits(&mut self) -> EventResult {
+        match self.reader.get_hex_mode() {
+            HexMode::Hex => self.reader.set_hex_mode(HexMode::Bits),
+            HexMode::Bits => self.reader.set_hex_mode(HexMode::Hex)
+        }
+        self.invalidated_resize = true;
+        EventResult::Consumed(None)
+    }
+    
     fn reload_data(&mut self) -> EventResult {
         self.invalidated_data_changed = true;
         EventResult::Consumed(None)
@@ -131,6 +1104,157 @@ impl HexView {
         self.reload_data()
     }
     
+    /// Go to the next region with high or low entropy. The block index knows where those are, if
+    /// there is one, and otherwise the file is scanned on another thread.
+    fn jump_to_entropy_region(&mut self, high: bool) -> EventResult {
+        let kind = if high { "high" } else { "low" };
+        let not_found = format!("No {}-entropy region found after this position.", kind);
+        if let Some(index) = self.get_block_index() {
+            let from = self.reader.get_window_offset();
+            return match index.find_entropy_block(from, high) {
+                Some(offset) => {
+                    self.go_to_offset(offset);
+                    EventResult::Consumed(None)
+                },
+                None => notify(not_found)
+            };
+        }
+        EventResult::with_cb(move |s| scan_from_window(s, not_found.clone(), move |source, from, progress| {
+            find_entropy_region(source, from, high, progress)
+        }))
+    }
+    
+    fn skip_run(&mut self) -> EventResult {
+        EventResult::with_cb(|s| {
+            let not_found = String::from("No different byte found after this position.");
+            scan_from_window(s, not_found, find_end_of_run)
+        })
+    }
+    
+    fn skip_to_byte<P>(&mut self, kind: &str, predicate: P) -> EventResult
+        where P: Fn(u8) -> bool + Copy + Send + 'static {
+        let not_found = format!("No {} byte found after this position.", kind);
+        EventResult::with_cb(move |s| scan_from_window(s, not_found.clone(), move |source, from, progress| {
+            find_byte(source, from, predicate, progress)
+        }))
+    }
+    
+    /// Set mark A or B at the current position, or remove it if it is already there.
+    fn toggle_mark(&mut self, b: bool) -> EventResult {
+        self.selecting = false;
+        let offset = Some(self.reader.get_window_offset());
+        let mark = if b { &mut self.marks.1 } else { &mut self.marks.0 };
+        *mark = if *mark == offset { None } else { offset };
+        EventResult::with_cb(update_measure)
+    }
+    
+    /// Go to the next point of interest, or to the previous one.
+    fn go_to_point_of_interest(&mut self, backwards: bool) -> EventResult {
+        let points = self.get_points_of_interest();
+        let offset = self.reader.get_window_offset();
+        match next_point(&points, offset, backwards, &self.point_sources) {
+            Some(point) => {
+                self.go_to_offset(point.offset);
+                self.notice = Some(point.label.clone());
+                EventResult::Consumed(None)
+            },
+            None => notify(String::from(if backwards {
+                "There are no points of interest before this one."
+            } else {
+                "There are no points of interest after this one."
+            }))
+        }
+    }
+    
+    /// Start selecting from the current position, like the set-mark command of Emacs: mark A is
+    /// put at the position, and mark B follows the position until the selection is stopped.
+    /// Setting the mark while selecting stops mark B where it is.
+    fn set_mark(&mut self) -> EventResult {
+        if self.selecting {
+            self.selecting = false;
+            return EventResult::Consumed(None);
+        }
+        let offset = self.reader.get_window_offset();
+        self.marks = (Some(offset), Some(offset));
+        self.selecting = true;
+        EventResult::with_cb(update_measure)
+    }
+    
+    /// Go to mark A, and put mark A where the position was, so the selection stays the same but
+    /// can be changed from its other end.
+    fn exchange_mark(&mut self) -> EventResult {
+        let mark = match self.marks.0 {
+            Some(mark) => mark,
+            None => return notify(String::from("Mark A is not set."))
+        };
+        self.marks.0 = Some(self.reader.get_window_offset());
+        self.go_to_offset(mark);
+        EventResult::with_cb(update_measure)
+    }
+    
+    /// Stop selecting, and remove both marks.
+    fn clear_selection(&mut self) -> EventResult {
+        self.selecting = false;
+        self.marks = (None, None);
+        EventResult::with_cb(update_measure)
+    }
+    
+    /// Switch between selecting the bytes between the marks, and the columns between them, like
+    /// the rectangles of Emacs.
+    fn toggle_column_selection(&mut self) -> EventResult {
+        self.column_selection = !self.column_selection;
+        self.notice = Some(String::from(if self.column_selection { "Selecting columns" } else { "Selecting bytes" }));
+        EventResult::with_cb(update_measure)
+    }
+    
+    /// Move while holding Shift, which selects from where the movement started, the way text
+    /// editors do it.
+    fn extend_selection(&mut self, key: Key) -> EventResult {
+        if !self.selecting {
+            let offset = self.reader.get_window_offset();
+            self.marks = (Some(offset), Some(offset));
+            self.selecting = true;
+        }
+        self.on_key_event(key)
+    }
+    
+    fn follow_selection(&mut self) {
+        if self.selecting {
+            self.marks.1 = Some(self.reader.get_window_offset());
+        }
+    }
+    
+    fn on_command(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Char(c) => {
+                self.notice = None;
+                self.on_char_event(c)
+            },
+            Event::Key(k) => {
+                self.notice = None;
+                self.on_key_event(k)
+            },
+            Event::CtrlChar(' ') | Event::CtrlChar('@') => self.set_mark(),
+            Event::CtrlChar('x') => self.exchange_mark(),
+            Event::CtrlChar('g') => self.clear_selection(),
+            Event::CtrlChar('r') => self.toggle_column_selection(),
+            Event::Shift(key @ Key::Up) | Event::Shift(key @ Key::Down)
+                | Event::Shift(key @ Key::PageUp) | Event::Shift(key @ Key::PageDown) => self.extend_selection(key),
+            Event::Shift(Key::Left) => self.scroll_sideways(-1),
+            Event::Shift(Key::Right) => self.scroll_sideways(1),
+            Event::Ctrl(Key::Home) => {
+                self.go_to_start();
+                EventResult::Consumed(None)
+            },
+            Event::Ctrl(Key::End) => {
+                self.go_to_end();
+                EventResult::Consumed(None)
+            },
+            Event::Mouse { offset, position, event } => self.on_mouse_event(offset, position, event),
+            _ => EventResult::Ignored
+        }
+    }
+    
     fn on_char_event(&mut self, c: char) -> EventResult {
         match c {
             'j' => self.on_key_event(Key::Down),
@@ -141,9 +1265,24 @@ impl HexView {
             'H' => self.on_key_event(Key::Home),
             'l' => self.on_key_event(Key::Right),
             'L' => self.on_key_event(Key::End),
+            '<' => self.scroll_sideways(-1),
+            '>' => self.scroll_sideways(1),
+            'n' => EventResult::with_cb(|s| search_again(s, false)),
+            'N' => EventResult::with_cb(|s| search_again(s, true)),
+            '[' => self.toggle_mark(false),
+            ']' => self.toggle_mark(true),
+            '{' => self.go_to_point_of_interest(true),
+            '}' => self.go_to_point_of_interest(false),
             'v' => self.toggle_visual(),
+            'B' => self.toggle_bits(),
             'r' => self.reload_data(),
             'R' => self.reopen_and_reload_data(),
+            'e' => self.jump_to_entropy_region(true),
+            'E' => self.jump_to_entropy_region(false),
+            'x' => self.skip_run(),
+            'z' => self.skip_to_byte("non-zero", |b| b != 0),
+            'p' => self.skip_to_byte("printable", |b| matches!(BYTE_CATEGORY[usize::from(b)],
+                ByteCategory::AsciiPrintable | ByteCategory::AsciiWhitespace)),
             _ => EventResult::Ignored
         }
     }
@@ -157,8 +1296,9 @@ impl HexView {
     }
     
     fn on_key_event(&mut self, k: Key) -> EventResult {
-        let inner_height = i64::try_from(self.offsets_column_size.y).unwrap();
-        let line_width = i64::try_from(self.reader.line_width).unwrap();
+        // A page down moves past the rows of all the pages.
+        let inner_height = i64::from(self.reader.window_size.1);
+        let line_width = i64::try_from(self.reader.get_visible_line_width()).unwrap();
         let pos_x = i64::try_from(self.reader.window_pos.0).unwrap();
         let size_x = i64::from(self.reader.window_size.0);
         let offset = match k {
@@ -175,6 +1315,12 @@ impl HexView {
         self.navigate(offset)
     }
     
+    /// Scroll left (negative) or right (positive) by a whole window width.
+    fn scroll_sideways(&mut self, direction: i64) -> EventResult {
+        let size_x = i64::from(self.reader.window_size.0);
+        self.navigate((direction * size_x, 0))
+    }
+    
     fn navigate(&mut self, offset: (i64, i64)) -> EventResult {
         if offset != (0, 0) {
             let (x, y) = offset;
@@ -183,23 +1329,16 @@ impl HexView {
                 let curr = self.reader.window_pos.0;
                 self.reader.window_pos.0 = if curr < diff { 0 } else { curr - diff };
             } else {
-                let diff = u64::try_from(x).unwrap();
-                let next = self.reader.window_pos.0 + diff;
-                let line = self.reader.line_width;
-                let width = u64::from(self.reader.window_size.0);
-                let max = if width > line { 0 } else { line - width };
-                self.reader.window_pos.0 = if next > max { max } else { next };
+                self.reader.window_pos.0 += u64::try_from(x).unwrap();
             }
             if y < 0 {
                 let diff = u64::try_from(-y).unwrap();
                 let curr = self.reader.window_pos.1;
                 self.reader.window_pos.1 = if curr < diff { 0 } else { curr - diff };
             } else {
-                let diff = u64::try_from(y).unwrap();
-                let next = self.reader.window_pos.1 + diff;
-                let lines = self.reader.get_lines_in_file();
-                self.reader.window_pos.1 = if next > lines { lines } else { next };
+                self.reader.window_pos.1 += u64::try_from(y).unwrap();
             }
+            self.reader.clamp_window();
             self.invalidated_resize = true;
             self.invalidated_data_changed = true;
             EventResult::Consumed(None)
@@ -208,6 +1347,25 @@ impl HexView {
         }
     }
     
+    /// Draw the names of the symbols that are in each row, from the line.
+    fn draw_labels(&self, printer: &Printer, first_line: u64) {
+        let line_width = self.reader.line_width;
+        let first_line_start = first_line.saturating_mul(line_width);
+        let mut symbol_offsets = self.symbol_offsets.iter()
+            .skip_while(|&&(offset, _)| offset < first_line_start)
+            .peekable();
+        let mut names = Vec::new();
+        for row in 0..printer.size.y {
+            let line_end = (first_line + row as u64 + 1).saturating_mul(line_width);
+            names.clear();
+            while let Some(&&(_, i)) = symbol_offsets.peek().filter(|&&&(offset, _)| offset < line_end) {
+                names.push(self.symbols[i].name.as_str());
+                symbol_offsets.next();
+            }
+            printer.with_color(ColorStyle::secondary(), |p| p.print((0, row), &names.join(" ")));
+        }
+    }
+    
     fn draw_bg(&self, printer: &Printer) {
         for y in 0..printer.size.y {
             printer.print_hline((0, y), printer.size.x, " ");
@@ -215,135 +1373,341 @@ impl HexView {
     }
     
     fn draw_title(&self, printer: &Printer) {
-        let title = self.reader.file_name();
-        let mut len = title.width();
         let container_width = printer.size.x;
         let spacing = 3;
-        let spacing_both_ends = 2 * spacing;
-        if len + spacing_both_ends > container_width {
-            len = printer.size.x - spacing_both_ends;
-        }
-        let offset = spacing + HAlign::Center.get_offset(len, container_width - spacing_both_ends);
+        let available = container_width.saturating_sub(2 * spacing);
+        let title = fit_title(self.reader.file_name(), available);
+        let len = title.width();
+        let offset = spacing + HAlign::Center.get_offset(len, available);
         printer.with_high_border(false, |p| {
             p.print((offset - 2, 0), "┤ ");
             p.print((offset + len, 0), " ├");
         });
 
         printer.with_color(ColorStyle::title_primary(), |p| {
-            if len < title.len() {
-                p.print((offset, 0), &title[0..len]);
-                p.print((offset + len - 1, 0), "…");
-            } else {
-                p.print((offset, 0), title);
-            }
+            p.print((offset, 0), &title);
         });
     }
     
-    fn build_prestyled_hex_table(&mut self) {
-        self.prestyled_hex_table = self.reader.map_hex_table(|category, s| {
-            StyledString::styled(s, category_to_color(category))
+    /// Draw arrows on the top border, at either end of the hex column, when there are more bytes
+    /// in the lines to that side.
+    fn draw_scroll_markers(&self, printer: &Printer) {
+        let pos_x = self.reader.window_pos.0;
+        let shown = u64::from(self.reader.window_size.0);
+        let (start, width) = if self.shows_hex() {
+            (self.hex_column_pos.x, self.hex_column_size.x)
+        } else {
+            (self.visual_column_pos.x, self.visual_column_size.x)
+        };
+        printer.with_color(ColorStyle::title_primary(), |p| {
+            if pos_x > 0 {
+                p.print((start, 0), "◀");
+            }
+            if pos_x + shown < self.reader.get_visible_line_width() {
+                p.print((start + width.saturating_sub(1), 0), "▶");
+            }
         });
     }
     
-    fn build_prestyled_visual_table(&mut self) {
-        self.prestyled_visual_table = self.reader.map_visual_table(|category, s| {
-            StyledString::styled(s, category_to_color(category))
-        });
+    fn draw_notice(&self, printer: &Printer) {
+        if let Some(ref notice) = self.notice {
+            let spacing = 3;
+            let max_len = printer.size.x.saturating_sub(2 * spacing);
+            let len = notice.width().min(max_len);
+            let y = printer.size.y - 1;
+            if self.has_border() {
+                printer.with_high_border(false, |p| {
+                    p.print((spacing - 2, y), "┤ ");
+                    p.print((spacing + len, y), " ├");
+                });
+            }
+            printer.with_color(ColorStyle::highlight(), |p| {
+                if len < notice.width() {
+                    let shown = truncate_to_width(notice, len.saturating_sub(1));
+                    p.print((spacing, y), shown);
+                    p.print((spacing + shown.width(), y), "…");
+                } else {
+                    p.print((spacing, y), notice);
+                }
+            });
+        }
+    }
+    
+    /// The style of a byte in the category, or the normal style when the color of the category is
+    /// hidden.
+    fn style_of(&self, category: &ByteCategory) -> Style {
+        if self.is_color_shown(ColorKind::Category(*category)) {
+            ColorKind::Category(*category).style(self.get_display_profile())
+        } else {
+            Style::from(ColorStyle::primary())
+        }
+    }
+    
+    fn build_hex_table(&self) -> StyledTable {
+        StyledTable {
+            plain: self.reader.map_hex_table(|category, s| {
+                StyledString::styled(s, self.style_of(category))
+            }),
+            striped: match self.stripe_color {
+                Some(color) => self.reader.map_hex_table(|category, s| {
+                    StyledString::styled(s, on_stripe(self.style_of(category), color))
+                }),
+                None => Vec::new()
+            }
+        }
+    }
+    
+    fn build_visual_table(&self) -> StyledTable {
+        StyledTable {
+            plain: self.reader.map_visual_table(|category, s| {
+                StyledString::styled(s, self.style_of(category))
+            }),
+            striped: match self.stripe_color {
+                Some(color) => self.reader.map_visual_table(|category, s| {
+                    StyledString::styled(s, on_stripe(self.style_of(category), color))
+                }),
+                None => Vec::new()
+            }
+        }
+    }
+    
+    /// Style the tables of the modes that the columns are shown in, unless they already are.
+    fn style_tables(&mut self) {
+        let hex_mode = *self.reader.get_hex_mode();
+        if self.styled_tables.hex(hex_mode).is_none() {
+            let table = self.build_hex_table();
+            self.styled_tables.hex.push((hex_mode, table));
+        }
+        let visual_mode = *self.reader.get_visual_mode();
+        if self.styled_tables.visual(visual_mode).is_none() {
+            let table = self.build_visual_table();
+            self.styled_tables.visual.push((visual_mode, table));
+        }
+    }
+    
+    /// Shade every other row of a column, counting from the first line of the file, so that the
+    /// shading stays with the lines when scrolling.
+    fn draw_stripes(&self, printer: &Printer, first_line: u64) {
+        if let Some(color) = self.stripe_color {
+            printer.with_color(ColorStyle::new(PaletteColor::Primary, color), |p| {
+                for y in (0..p.size.y).filter(|y| is_striped(first_line, *y)) {
+                    p.print_hline((0, y), p.size.x, " ");
+                }
+            });
+        }
     }
-}
-
-impl View for HexView {
-    fn draw(&self, printer: &Printer) {
-        self.draw_bg(printer);
-        printer.print_box((0, 0), printer.size, true);
-        self.draw_title(printer);
+    /// Draw the offsets, bytes and labels of the rows of a page, which start at the row of the
+    /// window.
+    fn draw_page(&self, printer: &Printer, first_row: usize, annotations: &[&Annotation], gaps: &[Range<u64>]) {
+        let (top, inner_height) = (self.offsets_column_pos.y, self.offsets_column_size.y);
+        let rows = first_row..first_row + inner_height;
+        let first_line = self.reader.window_pos.1 + first_row as u64;
         
         let mut offset_printer = OffsetPrinter {
             pos: Vec2::new(0, 0),
             printer: &printer.offset(self.offsets_column_pos).cropped(self.offsets_column_size),
-            spans: Vec::with_capacity(1)
+            format: self.reader.get_offset_format(),
+            spool: self.spool.as_ref(),
+            buf: &mut self.offsets_text.borrow_mut()
         };
-        self.reader.visit_row_offsets(&mut offset_printer);
-        
-        let inner_height = self.offsets_column_size.y;
-        let border_offset = self.offsets_column_size.x + self.offsets_column_pos.x;
-        printer.print_vline(Vec2::new(border_offset, 1), inner_height, "│");
+        if self.panes.offsets {
+            self.reader.visit_row_offsets_in(rows.clone(), &mut offset_printer);
+            let border_offset = self.offsets_column_size.x + self.offsets_column_pos.x;
+            printer.print_vline(Vec2::new(border_offset, top), inner_height, "│");
+        }
         
+        let stripes = Stripes {
+            first_line,
+            color: self.stripe_color
+        };
+        let hex_table = self.styled_tables.hex(*self.reader.get_hex_mode())
+            .expect("The tables are styled when the view is laid out.");
+        let hex_column = printer.offset(self.hex_column_pos).cropped(self.hex_column_size);
+        if self.shows_hex() {
+            self.draw_stripes(&hex_column, first_line);
+        }
         let mut hex_printer = HexPrinter {
             max_width: 0,
             pos: Vec2::new(0, 0),
-            table: &self.prestyled_hex_table,
-            printer: &printer.offset(self.hex_column_pos).cropped(self.hex_column_size)
+            table: &hex_table.plain,
+            striped_table: &hex_table.striped,
+            stripes,
+            annotations,
+            gaps,
+            monochrome: self.get_display_profile() == DisplayProfile::Monochrome,
+            guides: self.column_guides,
+            separators: self.separators(),
+            line_width: self.reader.line_width,
+            line_skip: self.reader.line_skip,
+            next_column: 0,
+            separated: false,
+            printer: &hex_column
         };
-        self.reader.visit_hex(&mut hex_printer);
+        if self.shows_hex() {
+            self.reader.visit_hex_in(rows.clone(), &mut self.transform.visitor(&mut hex_printer));
+        }
 
-        if self.show_visual_view {
-            let border_offset = self.hex_column_pos.x + self.hex_column_size.x;
-            printer.print_vline(Vec2::new(border_offset, 1), inner_height, "│");
+        if self.shows_visual() {
+            if self.shows_hex() {
+                let border_offset = self.hex_column_pos.x + self.hex_column_size.x;
+                printer.print_vline(Vec2::new(border_offset, top), inner_height, "│");
+            }
             
+            let visual_table = self.styled_tables.visual(*self.reader.get_visual_mode())
+                .expect("The tables are styled when the view is laid out.");
+            let visual_column = printer.offset(self.visual_column_pos).cropped(self.visual_column_size);
+            self.draw_stripes(&visual_column, first_line);
             let mut visual_printer = VisualPrinter {
                 pos: Vec2::new(0,0),
-                table: &self.prestyled_visual_table,
-                printer: &printer.offset(self.visual_column_pos).cropped(self.visual_column_size)
+                table: &visual_table.plain,
+                striped_table: &visual_table.striped,
+                utf8_cells: &self.utf8_cells,
+                cell: first_row * usize::from(self.reader.window_size.0),
+                text_style: self.style_of(&ByteCategory::AsciiPrintable),
+                stripes,
+                annotations,
+                gaps,
+                monochrome: self.get_display_profile() == DisplayProfile::Monochrome,
+                separators: self.separators(),
+                line_width: self.reader.line_width,
+                line_skip: self.reader.line_skip,
+                next_column: 0,
+                printer: &visual_column
             };
-            self.reader.visit_visual(&mut visual_printer);
+            self.reader.visit_visual_in(rows, &mut self.transform.visitor(&mut visual_printer));
+        }
+        
+        if self.labels_column_size.x > 0 {
+            printer.print_vline(Vec2::new(self.labels_column_pos.x - 2, top), inner_height, "│");
+            self.draw_labels(&printer.offset(self.labels_column_pos).cropped(self.labels_column_size), first_line);
         }
     }
+}
 
-    fn layout(&mut self, constraint: Vec2) {
-        if self.prestyled_hex_table.is_empty() {
-            self.build_prestyled_hex_table();
+impl View for HexView {
+    fn draw(&self, printer: &Printer) {
+        self.draw_bg(printer);
+        let inner_height = self.offsets_column_size.y;
+        if self.has_border() {
+            printer.print_box((0, 0), printer.size, true);
+            for page in 1..usize::from(self.shown_pages) {
+                let x = page * self.page_width;
+                printer.print((x, 0), "┬");
+                printer.print_vline(Vec2::new(x, 1), inner_height, "│");
+                printer.print((x, inner_height + 1), "┴");
+            }
+            self.draw_title(printer);
+            self.draw_scroll_markers(printer);
+        } else {
+            // The pages are still set apart, right before the start of each of them.
+            for page in 1..usize::from(self.shown_pages) {
+                printer.print_vline(Vec2::new(page * self.page_width - 1, 0), inner_height, "│");
+            }
+        }
+        
+        let window_start = self.reader.get_window_offset();
+        let window_end = window_start + u64::from(self.reader.window_size.1) * self.reader.line_width;
+        let annotations = if self.is_color_shown(ColorKind::Annotation) {
+            overlapping(&self.annotations, self.longest_annotation, window_start, window_end)
+        } else {
+            Vec::new()
+        };
+        let mut shown_gaps = Vec::new();
+        if self.is_color_shown(ColorKind::Gap) {
+            shown_gaps.extend_from_slice(&self.gaps);
         }
-        if self.prestyled_visual_table.is_empty() {
-            self.build_prestyled_visual_table();
+        if self.is_color_shown(ColorKind::Hole) {
+            shown_gaps.extend_from_slice(self.reader.get_holes());
         }
-        if self.invalidated_resize {
-            // The viewing area changed size, or the visual column was toggled.
+        shown_gaps.sort_by_key(|gap| gap.start);
+        let gaps: Vec<Range<u64>> = shown_gaps.into_iter()
+            .skip_while(|gap| gap.end <= window_start)
+            .take_while(|gap| gap.start < window_end)
+            .collect();
+        
+        for page in 0..usize::from(self.shown_pages) {
+            self.draw_page(&printer.offset((page * self.page_width, 0)), page * inner_height, &annotations, &gaps);
+        }
+        self.draw_notice(printer);
+    }
 
-            // The available height inside the box border:
-            let inner_height = constraint.y - 2;
-            let inner_height_u16 = u16::try_from(inner_height).unwrap();
-            if self.reader.window_size.1 != (inner_height_u16) {
-                self.reader.window_size.1 = inner_height_u16;
+    fn layout(&mut self, constraint: Vec2) {
+        self.resume_deferred_move();
+        self.follow_scroll_lock();
+        if self.panes.offsets && self.get_offsets_column_width() != self.offsets_column_size.x {
+            // Scrolling brought offsets into view that need more or fewer digits.
+            self.invalidated_resize = true;
+        }
+        self.style_tables();
+        if self.invalidated_resize {
+            // The viewing area changed size, or a column was shown or hidden.
+
+            // The available height inside the box border, if there is one, and the pages side by
+            // side, which are laid out alike and share the lines between them:
+            let border = if self.has_border() { 1 } else { 0 };
+            let inner_height = constraint.y - 2 * border;
+            self.shown_pages = self.fitting_pages(constraint.x);
+            self.page_width = (constraint.x + 1 - 2 * border) / usize::from(self.shown_pages);
+            // The end of the bytes of a page, before the line on its right:
+            let page_end = border + self.page_width - 1;
+            let rows = u16::try_from(inner_height).unwrap().saturating_mul(self.shown_pages);
+            if self.reader.window_size.1 != rows {
+                self.reader.window_size.1 = rows;
                 self.invalidated_data_changed = true;
             }
 
-            let colw_offsets = self.reader.get_row_offsets_width();
-            self.offsets_column_pos = Vec2::new(1, 1);
+            let (show_hex, show_visual) = (self.shows_hex(), self.shows_visual());
+            let colw_offsets = if self.panes.offsets { self.get_offsets_column_width() } else { 0 };
+            self.offsets_column_pos = Vec2::new(border, border);
             self.offsets_column_size = Vec2::new(colw_offsets, inner_height);
             
-            // Box-border, offsets column, separator line + space line:
-            let hex_col_start = 1 + colw_offsets + 2;
-            self.hex_column_pos = Vec2::new(hex_col_start, 1);
-            self.hex_column_size = Vec2::new(constraint.x - hex_col_start - 1, inner_height);
+            // Box-border, offsets column, separator line + space line, or only a space line
+            // without the offsets:
+            let hex_col_start = if self.panes.offsets { border + colw_offsets + 2 } else { border + 1 };
+            self.hex_column_pos = Vec2::new(hex_col_start, border);
+            self.hex_column_size = Vec2::new(page_end - hex_col_start, inner_height);
 
             let group = u64::from(self.reader.group);
             let reader_pos_x = group - 1;
-            let vis_group_spacer: isize = if self.show_visual_view { 1 } else { 0 };
-            let vis_byte_width: isize = if self.show_visual_view { 1 } else { 0 };
+            let vis_group_spacer: isize = if show_visual { 1 } else { 0 };
+            let vis_byte_width: isize = if show_visual {
+                isize::try_from(self.reader.get_visual_byte_width()).unwrap()
+            } else {
+                0
+            };
+            let hex_byte_width = if show_hex { isize::try_from(self.reader.get_hex_byte_width()).unwrap() } else { 0 };
             
-            let avail_width = self.hex_column_size.x;
+            // Leave room for the symbol names, when there are any.
+            let labels_width = if self.symbols.is_empty() { 0 } else { MIN_LABELS_WIDTH + 2 };
+            let avail_width = self.hex_column_size.x.saturating_sub(labels_width).max(1);
             let avail_width_isize = isize::try_from(avail_width).unwrap();
             let mut space_left = avail_width_isize;
             let mut hex_width: isize = 0;
             let mut vis_width: isize = 0;
             let mut bytes_consumed = 0;
-            let bytes_left_in_line = self.reader.line_width;
+            let bytes_left_in_line = self.reader.get_visible_line_width();
 
             for i in 0..bytes_left_in_line {
-                let byte_pair_spacer = if i == 0 { 0 } else { 1 };
-                let consumed_by_byte = byte_pair_spacer + 2 + vis_byte_width;
+                let byte_pair_spacer = if i == 0 || !show_hex { 0 } else { 1 };
+                let consumed_by_byte = byte_pair_spacer + hex_byte_width + vis_byte_width;
                 if space_left - consumed_by_byte >= 0 {
                     space_left -= consumed_by_byte;
-                    hex_width += 2 + byte_pair_spacer;
+                    hex_width += hex_byte_width + byte_pair_spacer;
                     vis_width += vis_byte_width;
                     bytes_consumed += 1;
                     
                     if ((reader_pos_x + i) % group) == 0 && i != 0 {
-                        // The hex column group spacer replaces the byte pair spacer automatically.
-                        if space_left - vis_group_spacer > 0 {
-                            space_left -= vis_group_spacer;
+                        // The hex column group spacer replaces the byte pair spacer automatically,
+                        // unless it is an extra space.
+                        let column = self.reader.window_pos.0 + reader_pos_x + i;
+                        let hex_group_spacer: isize = match self.separators().at(column) {
+                            Separator::Space if show_hex => 1,
+                            _ => 0
+                        };
+                        if space_left - vis_group_spacer - hex_group_spacer > 0 {
+                            space_left -= vis_group_spacer + hex_group_spacer;
                             vis_width += vis_group_spacer;
+                            hex_width += hex_group_spacer;
                         } else {
                             break;
                         }
@@ -353,7 +1717,9 @@ impl View for HexView {
                 }
             }
             
-            if hex_width + vis_width + 1 < avail_width_isize {
+            if !show_hex {
+                // The visual column takes the place of the hex column, without a separator line.
+            } else if hex_width + vis_width + 1 < avail_width_isize {
                 // Add right padding to hex column.
                 hex_width += 1;
             } else if hex_width + vis_width == avail_width_isize {
@@ -364,26 +1730,90 @@ impl View for HexView {
             let hex_uw = usize::try_from(hex_width).unwrap();
             let vis_uw = usize::try_from(vis_width).unwrap();
             self.hex_column_size = Vec2::new(hex_uw, inner_height);
-            self.visual_column_pos = Vec2::new(self.hex_column_pos.x + hex_uw + 1, 1);
+            let vis_col_start = if show_hex { self.hex_column_pos.x + hex_uw + 1 } else { self.hex_column_pos.x };
+            self.visual_column_pos = Vec2::new(vis_col_start, border);
             self.visual_column_size = Vec2::new(vis_uw, inner_height);
             
+            if self.symbols.is_empty() {
+                self.labels_column_size = Vec2::new(0, 0);
+            } else {
+                // Separator line + space line:
+                let bytes_end = if show_visual {
+                    self.visual_column_pos.x + vis_uw
+                } else {
+                    self.hex_column_pos.x + hex_uw
+                };
+                self.labels_column_pos = Vec2::new(bytes_end + 2, border);
+                self.labels_column_size = Vec2::new(page_end.saturating_sub(bytes_end + 2), inner_height);
+            }
+            
             if bytes_consumed != self.reader.window_size.0 {
                 self.reader.window_size.0 = bytes_consumed;
+                self.reader.clamp_window();
                 self.invalidated_data_changed = true;
             }
             
             self.invalidated_resize = false;
         }
 
-        if self.invalidated_data_changed {
+        if let Some(delay) = self.capture_delay().filter(|_| self.invalidated_data_changed) {
+            // The window is shown where it was captured until the next frame, and is still to be
+            // captured where it was moved to.
+            self.deferred_pos = Some(self.reader.window_pos);
+            self.reader.window_pos = self.last_frame.unwrap().pos;
+            self.request_frame(delay);
+        } else if self.invalidated_data_changed {
             // The viewing area was moved or changed size.
-            self.reader.capture().unwrap();
-            self.invalidated_data_changed = false;
+            self.last_frame = Some(Frame {
+                pos: self.reader.window_pos,
+                size: self.reader.window_size,
+                line_width: self.reader.line_width,
+                at: Instant::now()
+            });
+            self.wakeup_pending = false;
+            let mut fetching = false;
+            match self.reader.capture() {
+                Ok(()) => if let Some(old_length) = self.reader.take_shrinkage() {
+                    self.notice = Some(format!("The file shrank from {} to {} bytes",
+                                               old_length, self.reader.get_length()));
+                },
+                Err(XvError::Io(ref error)) if error.kind() == io::ErrorKind::WouldBlock => {
+                    // The bytes are being fetched in the background, so look again in a while.
+                    self.notice = Some(format!("{}", error));
+                    self.request_frame(FETCH_RETRY_DELAY);
+                    fetching = true;
+                },
+                Err(error) => self.notice = Some(format!("{}", error))
+            }
+            // Transformed bytes are not decoded, since they are not the bytes of the text.
+            self.utf8_cells = if self.transform.is_identity() { self.reader.decode_visual() } else { Vec::new() };
+            self.update_strings();
+            let address = self.reader.address_map.address_of(self.reader.get_window_offset());
+            let symbol = describe_address(&self.symbols, address).map_or(String::new(), |symbol| format!("   {}", symbol));
+            let mut position = describe_position(
+                self.reader.window_pos.1, self.reader.window_size.1, self.reader.get_lines_in_file());
+            if !self.transform.is_identity() {
+                position = format!("   [{}]{}", self.transform.describe(), position);
+            }
+            if self.following {
+                position = format!("   Following{}", position);
+            }
+            if self.screen_reader {
+                let byte = self.reader.get_captured_bytes().first().cloned();
+                self.position.set_content(format!("{}{}   {}", describe_byte(address, byte), symbol, position));
+            } else {
+                self.position.set_content(symbol + position.as_str());
+            }
+            // While bytes are being fetched, the window is captured again at the next layout.
+            self.invalidated_data_changed = fetching;
         }
+        self.published_offset.set(self.reader.get_window_offset());
     }
 
     fn needs_relayout(&self) -> bool {
-        self.invalidated_resize || self.invalidated_data_changed
+        let followed_moved = self.scroll_lock.as_ref()
+            .is_some_and(|lock| lock.followed != Some(lock.leader.get()));
+        self.invalidated_resize || self.invalidated_data_changed || followed_moved
     }
 
     fn required_size(&mut self, constraint: Vec2) -> Vec2 {
@@ -391,15 +1821,50 @@ impl View for HexView {
     }
 
     fn on_event(&mut self, event: Event) -> EventResult {
+        self.resume_deferred_move();
         match event {
             Event::WindowResize => {
                 self.invalidated_resize = true;
-                EventResult::Consumed(None)
+                return EventResult::Consumed(None);
             },
-            Event::Char(c) => self.on_char_event(c),
-            Event::Key(k) => self.on_key_event(k),
-            Event::Mouse { offset, position, event } => self.on_mouse_event(offset, position, event),
-            _ => EventResult::Ignored
+            Event::Char(c @ '0'..='9') if c != '0' || self.count.is_some() => {
+                let digit = u64::from(c.to_digit(10).unwrap());
+                // No movement needs more repeats than there are bytes in the file.
+                let max_count = self.reader.get_length().clamp(1, MAX_COUNT);
+                let count = self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit)
+                    .min(max_count);
+                self.count = Some(count);
+                self.notice = Some(format!("Repeat {} times", count));
+                return EventResult::Consumed(None);
+            },
+            _ => {}
+        }
+
+        // Commands that open a dialog or run in the background are only done once.
+        let count = match self.count.take() {
+            Some(count) => {
+                self.notice = None;
+                count
+            },
+            None => 1
+        };
+        let marks = self.marks;
+        let mut result = self.on_command(event.clone());
+        for _ in 1..count {
+            match result {
+                EventResult::Consumed(None) => result = self.on_command(event.clone()),
+                _ => break
+            }
+        }
+        self.follow_selection();
+        if self.following {
+            // Moving away from the end stops following it.
+            let height = u64::from(self.reader.window_size.1).max(1);
+            self.following = self.reader.window_pos.1 + height > self.reader.get_last_line();
+        }
+        match result {
+            EventResult::Consumed(None) if self.marks != marks => EventResult::with_cb(update_measure),
+            _ => result
         }
     }
 }
@@ -407,19 +1872,26 @@ impl View for HexView {
 struct OffsetPrinter<'a, 'b, 'x> {
     pos: Vec2,
     printer: &'x Printer<'a, 'b>,
-    spans: Vec<IndexedSpan<Style>>
+    format: OffsetFormat,
+    spool: Option<&'x Spool>,
+    buf: &'x mut String
 }
 
 impl<'a, 'b, 'x> OffsetsVisitor for OffsetPrinter<'a, 'b, 'x> {
-    fn offset(&mut self, offset: &str) {
-        if self.spans.is_empty() {
-            self.spans.push(IndexedSpan {
-                content: IndexedCow::Borrowed {start: 0, end: offset.len()},
-                attr: Style::from(ColorStyle::secondary()),
-                width: offset.width()
-            });
+    fn offset(&mut self, offset: u64, line: u64) {
+        self.format.write(offset, line, self.buf);
+        if let Some(spool) = self.spool {
+            match spool.arrival_of(offset) {
+                Some(arrival) => write!(self.buf, " {:>4}.{:03}s", arrival.as_secs(), arrival.subsec_millis()).unwrap(),
+                None => write!(self.buf, "{:1$}", "", ARRIVAL_WIDTH).unwrap()
+            }
         }
-        let styled_offset = SpannedStr::new(offset, &self.spans);
+        let spans = [IndexedSpan {
+            content: IndexedCow::Borrowed {start: 0, end: self.buf.len()},
+            attr: Style::from(ColorStyle::secondary()),
+            width: self.buf.width()
+        }];
+        let styled_offset = SpannedStr::new(self.buf, &spans);
         self.printer.print_styled(self.pos, styled_offset);
         self.pos.y += 1;
     }
@@ -429,26 +1901,135 @@ impl<'a, 'b, 'x> OffsetsVisitor for OffsetPrinter<'a, 'b, 'x> {
     }
 }
 
+/// The styled text of every byte value, on the normal background, and on the background of the
+/// shaded rows when every other row is shaded.
+struct StyledTable {
+    plain: Vec<StyledString>,
+    striped: Vec<StyledString>
+}
+
+/// The styled tables of the modes that the columns have been shown in, with the current styles,
+/// so that switching back and forth between the modes does not style the tables again. They are
+/// let go of when the styles change.
+#[derive(Default)]
+struct StyledTables {
+    hex: Vec<(HexMode, StyledTable)>,
+    visual: Vec<(VisualMode, StyledTable)>
+}
+
+impl StyledTables {
+    fn hex(&self, mode: HexMode) -> Option<&StyledTable> {
+        self.hex.iter().find(|(table_mode, _)| *table_mode == mode).map(|(_, table)| table)
+    }
+
+    fn visual(&self, mode: VisualMode) -> Option<&StyledTable> {
+        self.visual.iter().find(|(table_mode, _)| *table_mode == mode).map(|(_, table)| table)
+    }
+
+    fn clear(&mut self) {
+        self.hex.clear();
+        self.visual.clear();
+    }
+}
+
+/// Which rows are shaded.
+#[derive(Clone, Copy)]
+struct Stripes {
+    /// The line of the file at the top of the window.
+    first_line: u64,
+    color: Option<Color>
+}
+
+impl Stripes {
+    /// The table to print the bytes of a row from.
+    fn table<'x>(self, y: usize, table: &'x [StyledString], striped_table: &'x [StyledString]) -> &'x [StyledString] {
+        if self.color.is_some() && is_striped(self.first_line, y) { striped_table } else { table }
+    }
+
+    /// The style to print something in on a row.
+    fn style(self, y: usize, style: Style) -> Style {
+        match self.color {
+            Some(color) if is_striped(self.first_line, y) => on_stripe(style, color),
+            _ => style
+        }
+    }
+
+    /// The background of a row.
+    fn background(self, y: usize) -> ColorType {
+        match self.color {
+            Some(color) if is_striped(self.first_line, y) => ColorType::Color(color),
+            _ => ColorType::Palette(PaletteColor::View)
+        }
+    }
+}
+
 struct HexPrinter<'a, 'b, 'x> {
     max_width: usize,
     pos: Vec2,
     table: &'x [StyledString],
+    striped_table: &'x [StyledString],
+    stripes: Stripes,
+    annotations: &'x [&'x Annotation],
+    gaps: &'x [Range<u64>],
+    /// Whether annotations are shown reversed, instead of in their colors.
+    monochrome: bool,
+    /// The number of bytes between the guide lines, or 0 for no guide lines.
+    guides: u64,
+    separators: &'x GroupSeparators,
+    line_width: u64,
+    line_skip: u64,
+    /// The column in the line of the byte after the last one.
+    next_column: u64,
+    /// Whether a group or field separator was printed before the next byte.
+    separated: bool,
     printer: &'x Printer<'a, 'b>
 }
 
-const GROUP_SEP: &str = "\u{00A6}";
+const FIELD_SEP: &str = "\u{2502}";
+
+/// What the column guides are drawn with.
+const GUIDE_SEP: &str = "\u{250A}";
+
+/// What every column of a byte in a gap is shown as.
+const GAP_DOT: &str = "\u{00B7}";
+
+/// The least width that is kept free for the names of symbols, when there are symbols.
+const MIN_LABELS_WIDTH: usize = 16;
 
 impl<'a, 'b, 'x> HexVisitor for HexPrinter<'a, 'b, 'x> {
-    fn byte(&mut self, index: usize) {
+    #[inline]
+    fn byte(&mut self, index: usize, offset: u64) {
+        let column = (offset % self.line_width).saturating_sub(self.line_skip);
+        self.next_column = column + 1;
         if self.pos.x != 0 {
+            if self.guides > 0 && column.is_multiple_of(self.guides) && !self.separated {
+                let style = ColorStyle::new(PaletteColor::Secondary, self.stripes.background(self.pos.y));
+                let pos = self.pos;
+                self.printer.with_color(style, |p| p.print(pos, GUIDE_SEP));
+            }
             self.pos.x += 1;
         }
-        self.printer.print_styled(self.pos, (&self.table[index]).into());
-        self.pos.x += 2;
+        self.separated = false;
+        let hex_element = &self.stripes.table(self.pos.y, self.table, self.striped_table)[index];
+        print_element(self.printer, self.pos, hex_element, self.annotations, self.gaps, self.monochrome, offset);
+        self.pos.x += hex_element.width();
     }
 
+    #[inline]
     fn group(&mut self) {
-        self.printer.print(self.pos, GROUP_SEP);
+        let separator = self.separators.at(self.next_column);
+        print_separator(self.printer, self.pos, separator, self.stripes);
+        if separator == Separator::Space {
+            self.pos.x += 1;
+        }
+        self.separated = true;
+    }
+
+    fn field(&mut self) {
+        let style = ColorStyle::new(PaletteColor::TitlePrimary, self.stripes.background(self.pos.y));
+        let pos = self.pos;
+        self.printer.with_color(style, |p| p.print(pos, FIELD_SEP));
+        self.separated = true;
     }
 
     fn next_line(&mut self) {
@@ -465,18 +2046,50 @@ impl<'a, 'b, 'x> HexVisitor for HexPrinter<'a, 'b, 'x> {
 struct VisualPrinter<'a, 'b, 'x> {
     pos: Vec2,
     table: &'x [StyledString],
+    striped_table: &'x [StyledString],
+    /// The cells of the bytes when they are decoded as UTF-8, and the cell of the next byte.
+    utf8_cells: &'x [Utf8Cell],
+    cell: usize,
+    /// The style of decoded characters.
+    text_style: Style,
+    stripes: Stripes,
+    annotations: &'x [&'x Annotation],
+    gaps: &'x [Range<u64>],
+    /// Whether annotations are shown reversed, instead of in their colors.
+    monochrome: bool,
+    separators: &'x GroupSeparators,
+    line_width: u64,
+    line_skip: u64,
+    /// The column in the line of the byte after the last one.
+    next_column: u64,
     printer: &'x Printer<'a, 'b>
 }
 
 impl<'a, 'b, 'x> VisualVisitor for VisualPrinter<'a, 'b, 'x> {
-    fn visual_element(&mut self, index: usize) {
-        let vis_element = &self.table[index];
-        self.printer.print_styled(self.pos, vis_element.into());
-        self.pos.x += vis_element.width();
+    #[inline]
+    fn visual_element(&mut self, index: usize, offset: u64) {
+        self.next_column = (offset % self.line_width).saturating_sub(self.line_skip) + 1;
+        let cell = self.utf8_cells.get(self.cell);
+        self.cell += 1;
+        match cell {
+            Some(Utf8Cell::Char { text, .. }) => {
+                let element = StyledString::styled(text.as_str(), self.stripes.style(self.pos.y, self.text_style));
+                print_element(self.printer, self.pos, &element, self.annotations, self.gaps, self.monochrome, offset);
+                self.pos.x += element.width();
+            },
+            Some(Utf8Cell::Covered) => {},
+            Some(Utf8Cell::Byte) | None => {
+                let vis_element = &self.stripes.table(self.pos.y, self.table, self.striped_table)[index];
+                print_element(self.printer, self.pos, vis_element, self.annotations, self.gaps, self.monochrome,
+                              offset);
+                self.pos.x += vis_element.width();
+            }
+        }
     }
 
+    #[inline]
     fn group(&mut self) {
-        self.printer.print(self.pos, GROUP_SEP);
+        print_separator(self.printer, self.pos, self.separators.at(self.next_column), self.stripes);
         self.pos.x += 1;
     }
 
@@ -490,6 +2103,109 @@ impl<'a, 'b, 'x> VisualVisitor for VisualPrinter<'a, 'b, 'x> {
     }
 }
 
+/// Print the hex or visual element of a byte, in the color of the last annotation that covers the
+/// byte, if any do, or reversed without colors. Bytes in gaps are shown as dots instead.
+#[inline]
+fn print_element(printer: &Printer, pos: Vec2, element: &StyledString, annotations: &[&Annotation],
+                 gaps: &[Range<u64>], monochrome: bool, offset: u64) {
+    if gaps.iter().any(|gap| gap.contains(&offset)) {
+        printer.with_color(ColorStyle::secondary(), |p| p.print_hline(pos, element.width(), GAP_DOT));
+        return;
+    }
+    match annotations.iter().rev().find(|annotation| annotation.contains(offset)) {
+        Some(_) if monochrome => printer.with_effect(Effect::Reverse, |p| p.print(pos, element.source())),
+        Some(annotation) => {
+            let style = match annotation.color {
+                Some(color) => ColorStyle::new(PaletteColor::View, color),
+                None => ColorStyle::highlight()
+            };
+            printer.with_color(style, |p| p.print(pos, element.source()));
+        },
+        None => printer.print_styled(pos, element.into())
+    }
+}
+
+/// Print a group boundary. A color boundary is a space in the color of inactive highlights.
+fn print_separator(printer: &Printer, pos: Vec2, separator: Separator, stripes: Stripes) {
+    let style = match separator {
+        Separator::Color => ColorStyle::highlight_inactive(),
+        _ => ColorStyle::new(PaletteColor::Primary, stripes.background(pos.y))
+    };
+    printer.with_color(style, |p| p.print(pos, separator.text()));
+}
+
+/// Whether a row of the window is one of the shaded rows.
+fn is_striped(first_line: u64, y: usize) -> bool {
+    (first_line + y as u64) % 2 == 1
+}
+
+/// The style on the background of a shaded row.
+fn on_stripe(style: Style, color: Color) -> Style {
+    let front = style.color.map_or(ColorType::Palette(PaletteColor::Primary), |color_style| color_style.front);
+    Style { color: Some(ColorStyle::new(front, color)), ..style }
+}
+
+/// Scan the file from the offset of the window on another thread, with the progress shown, and go
+/// to the offset that the scan finds. The scan is given the source, the offset in it to start
+/// from, and the progress to report to.
+fn scan_from_window<F>(s: &mut Cursive, not_found: String, scan: F)
+    where F: FnOnce(&mut ByteSource, u64, &Progress) -> Result<Option<u64>> + Send + 'static {
+    let (from, length, source) = s.call_on_id("hex_view", |v: &mut HexView| {
+        let from = v.get_offset();
+        let length = v.get_length().saturating_sub(from);
+        (from, length, v.open_background_source(from, length))
+    }).unwrap();
+    let (mut source, start) = match source {
+        Ok(source) => source,
+        Err(error) => return s.show_error(error)
+    };
+    let work = move |progress: &Progress| scan(source.as_mut(), start, progress);
+    run_with_progress(s, "Searching", length, work, move |s, found| match found {
+        Some(found) => {
+            s.call_on_id("hex_view", |v: &mut HexView| v.go_to_offset(found - start + from));
+        },
+        None => s.add_layer(Dialog::info(not_found))
+    });
+}
+
+fn notify(message: String) -> EventResult {
+    EventResult::with_cb(move |s| s.add_layer(Dialog::info(message.clone())))
+}
+
+/// The longest start of the text that is at most the given width on the screen.
+fn truncate_to_width(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (i, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            return &text[0..i];
+        }
+    }
+    text
+}
+
+/// The title shortened to the given width, with an ellipsis at the end when it is cut.
+fn fit_title(title: &str, width: usize) -> String {
+    if title.width() <= width {
+        title.to_string()
+    } else if width == 0 {
+        String::new()
+    } else {
+        format!("{}…", truncate_to_width(title, width.saturating_sub(1)))
+    }
+}
+
+/// Tell the categories apart without colors. Most bytes of binary files are above 0x7F, so those
+/// are shown plainly.
+fn category_to_effects(category: &ByteCategory) -> Style {
+    match category {
+        ByteCategory::AsciiControl => Style::from(Effect::Underline),
+        ByteCategory::AsciiPrintable => Style::from(Effect::Bold),
+        ByteCategory::AsciiWhitespace => Style::from(Effect::Bold).combine(Effect::Underline),
+        ByteCategory::Other => Style::none()
+    }
+}
+
 fn category_to_color(category: &ByteCategory) -> ColorStyle {
     match category {
         ByteCategory::AsciiControl => ColorStyle::title_primary(),
@@ -503,7 +2219,7 @@ fn category_to_color(category: &ByteCategory) -> ColorStyle {
 mod tests {
     use std::io::Write;
 
-    use crate::byte_reader::TilingByteReader;
+    use xv::byte_reader::TilingByteReader;
 
     use super::*;
 
@@ -666,6 +2382,30 @@ mod tests {
         assert_eq!(view.visual_column_size, Vec2::new(17, 21));
     }
 
+    #[test]
+    fn layout_w80_h24_ll16_bits() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(b"0123456789abcdef0123456789abcdef").unwrap();
+
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let hex_reader = HexReader::new(byte_reader).unwrap();
+        let mut view = HexView::new(hex_reader);
+
+        view.toggle_bits();
+        let constraint = Vec2::new(80, 23);
+        view.layout(constraint);
+
+        // Each byte takes 8 bits, a space and a visual character, and there's a group spacer.
+        assert_eq!(view.reader.line_width, 16);
+        assert_eq!(view.reader.window_size, (6, 21));
+
+        assert_eq!(view.hex_column_pos, Vec2::new(13, 1));
+        assert_eq!(view.hex_column_size, Vec2::new(54, 21));
+
+        assert_eq!(view.visual_column_pos, Vec2::new(68, 1));
+        assert_eq!(view.visual_column_size, Vec2::new(7, 21));
+    }
+
     #[test]
     fn layout_w82_h24_ll32() {
         let mut tmpf = tempfile::NamedTempFile::new().unwrap();
@@ -710,4 +2450,269 @@ mod tests {
         assert_eq!(view.visual_column_pos, Vec2::new(62, 1));
         assert_eq!(view.visual_column_size, Vec2::new(18, 21));
     }
+
+    #[test]
+    fn keeping_the_styled_tables_of_every_mode() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(b"0123456789abcdef0123456789abcdef").unwrap();
+
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let hex_reader = HexReader::new(byte_reader).unwrap();
+        let mut view = HexView::new(hex_reader);
+        let constraint = Vec2::new(80, 23);
+        view.layout(constraint);
+
+        // Switching to bits and back styles the bits table once, and keeps both.
+        view.toggle_bits();
+        view.layout(constraint);
+        view.toggle_bits();
+        view.layout(constraint);
+        assert_eq!(view.styled_tables.hex.len(), 2);
+        assert!(view.styled_tables.hex(HexMode::Bits).is_some());
+        assert_eq!(view.styled_tables.visual.len(), 1);
+
+        // Changing the styles lets go of the tables of the other modes.
+        view.set_display_profile(DisplayProfile::Monochrome);
+        view.layout(constraint);
+        assert_eq!(view.styled_tables.hex.len(), 1);
+        assert!(view.styled_tables.hex(HexMode::Hex).is_some());
+    }
+
+    #[test]
+    fn layout_w80_h24_ll16_visual_only() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(b"0123456789abcdef0123456789abcdef").unwrap();
+
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let hex_reader = HexReader::new(byte_reader).unwrap();
+        let mut view = HexView::new(hex_reader);
+        view.set_panes(Panes { offsets: false, hex: false, visual: true });
+        let constraint = Vec2::new(80, 23);
+        view.layout(constraint);
+
+        assert_eq!(view.reader.window_size, (16, 21));
+        assert_eq!(view.offsets_column_size, Vec2::new(0, 21));
+        assert_eq!(view.hex_column_size, Vec2::new(0, 21));
+        assert_eq!(view.visual_column_pos, Vec2::new(2, 1));
+        assert_eq!(view.visual_column_size, Vec2::new(18, 21));
+
+        // Without the visual column, the hex column is shown after all.
+        view.toggle_visual();
+        view.toggle_visual();
+        view.layout(constraint);
+        assert_eq!(view.hex_column_pos, Vec2::new(2, 1));
+        assert_eq!(view.hex_column_size, Vec2::new(48, 21));
+        assert_eq!(view.visual_column_size, Vec2::new(0, 21));
+    }
+
+    #[test]
+    fn selecting_up_to_the_later_mark() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(b"0123456789abcdef0123456789abcdef").unwrap();
+
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let hex_reader = HexReader::new(byte_reader).unwrap();
+        let mut view = HexView::new(hex_reader);
+        view.set_marks((Some(0x10), Some(4)));
+        assert_eq!(view.get_selection(), Some((4, 0x10)));
+        view.set_marks((Some(4), Some(4)));
+        assert_eq!(view.get_selection(), None);
+
+        view.set_marks((Some(4), Some(0x10)));
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        view.switch_reader(HexReader::new(byte_reader).unwrap());
+        assert_eq!(view.get_marks(), (None, None));
+    }
+
+    #[test]
+    fn selecting_columns_between_the_marks() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(&[0; 64]).unwrap();
+
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let mut view = HexView::new(HexReader::new(byte_reader).unwrap());
+        view.reader.line_width = 16;
+        view.set_marks((Some(0x26), Some(0x03)));
+        assert_eq!(view.get_column_selection(), None);
+        view.on_event(Event::CtrlChar('r'));
+        let columns = view.get_column_selection().unwrap();
+        assert_eq!(columns, ColumnSelection { start: 0x03, line_width: 16, columns: 3, rows: 3 });
+        assert_eq!(columns.row(2), 0x23..0x26);
+        assert_eq!(columns.length(), 9);
+        view.set_marks((Some(0x03), Some(0x13)));
+        assert_eq!(view.get_column_selection(), None);
+    }
+
+    #[test]
+    fn truncating_to_a_width() {
+        assert_eq!(truncate_to_width("Not found", 3), "Not");
+        assert_eq!(truncate_to_width("Ünïcödé", 4), "Ünïc");
+        assert_eq!(truncate_to_width("日本語", 5), "日本");
+        assert_eq!(truncate_to_width("short", 10), "short");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn fitting_a_non_utf8_title() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut name = format!("xv-title-{}-x", std::process::id()).into_bytes();
+        name.extend_from_slice(b"\xffyz");
+        let path = std::env::temp_dir().join(OsStr::from_bytes(&name));
+        std::fs::write(&path, b"0123456789abcdef").unwrap();
+
+        let byte_reader = TilingByteReader::new(&path).unwrap();
+        let hex_reader = HexReader::new(byte_reader).unwrap();
+        let view = HexView::new(hex_reader);
+        let title = view.reader.file_name().to_string();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(title.ends_with("-x\u{FFFD}yz"));
+        assert_eq!(fit_title(&title, title.width()), title);
+        assert!(fit_title(&title, title.width() - 1).ends_with("-x\u{FFFD}…"));
+        assert_eq!(fit_title(&title, 1), "…");
+        assert_eq!(fit_title(&title, 0), "");
+    }
+
+    #[test]
+    fn repeat_count_is_capped() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(b"0123456789abcdef0123456789abcdef").unwrap();
+
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let hex_reader = HexReader::new(byte_reader).unwrap();
+        let mut view = HexView::new(hex_reader);
+        for _ in 0..30 {
+            view.on_event(Event::Char('9'));
+        }
+        assert_eq!(view.count, Some(32));
+    }
+
+    #[test]
+    fn layout_w80_h24_ll16_full_screen() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(b"0123456789abcdef0123456789abcdef").unwrap();
+
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let hex_reader = HexReader::new(byte_reader).unwrap();
+        let mut view = HexView::new(hex_reader);
+        view.set_screen_mode(ScreenMode::FullScreen);
+        let constraint = Vec2::new(80, 24);
+        view.layout(constraint);
+
+        // Without the border, the columns take up all the rows and start at the left edge.
+        assert_eq!(view.reader.window_size, (16, 24));
+        assert_eq!(view.offsets_column_pos, Vec2::new(0, 0));
+        assert_eq!(view.offsets_column_size, Vec2::new(10, 24));
+        assert_eq!(view.hex_column_pos, Vec2::new(12, 0));
+        assert_eq!(view.hex_column_size, Vec2::new(48, 24));
+        assert_eq!(view.visual_column_pos, Vec2::new(61, 0));
+        assert_eq!(view.visual_column_size, Vec2::new(18, 24));
+
+        // Presentations set the groups further apart.
+        view.set_screen_mode(ScreenMode::Presentation);
+        view.layout(constraint);
+        assert_eq!(view.separators().at(4), Separator::Space);
+        assert_eq!(view.hex_column_size, Vec2::new(49, 24));
+        assert_eq!(view.visual_column_pos, Vec2::new(62, 0));
+
+        view.set_screen_mode(ScreenMode::Normal);
+        view.layout(constraint);
+        assert_eq!(view.reader.window_size, (16, 22));
+        assert_eq!(view.offsets_column_pos, Vec2::new(1, 1));
+    }
+
+    #[test]
+    fn layout_w159_h24_ll16_two_pages() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(b"0123456789abcdef0123456789abcdef").unwrap();
+
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let hex_reader = HexReader::new(byte_reader).unwrap();
+        let mut view = HexView::new(hex_reader);
+        view.set_pages(2);
+        let constraint = Vec2::new(159, 23);
+        view.layout(constraint);
+
+        // Every page is laid out like a view of 80 columns, and the window has the rows of both.
+        assert_eq!(view.shown_pages, 2);
+        assert_eq!(view.page_width, 79);
+        assert_eq!(view.reader.window_size, (16, 42));
+        assert_eq!(view.offsets_column_pos, Vec2::new(1, 1));
+        assert_eq!(view.offsets_column_size, Vec2::new(10, 21));
+        assert_eq!(view.hex_column_pos, Vec2::new(13, 1));
+        assert_eq!(view.hex_column_size, Vec2::new(47, 21));
+        assert_eq!(view.visual_column_pos, Vec2::new(61, 1));
+        assert_eq!(view.visual_column_size, Vec2::new(18, 21));
+
+        // Only one page fits in a narrower view.
+        view.layout(Vec2::new(100, 23));
+        assert_eq!(view.shown_pages, 2);
+        view.invalidated_resize = true;
+        view.layout(Vec2::new(100, 23));
+        assert_eq!(view.shown_pages, 1);
+        assert_eq!(view.reader.window_size, (16, 21));
+    }
+
+    #[test]
+    fn putting_off_moves_until_the_next_frame() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(&[0x55; 4096]).unwrap();
+
+        let byte_reader = TilingByteReader::new(tmpf.path()).unwrap();
+        let hex_reader = HexReader::new(byte_reader).unwrap();
+        let mut view = HexView::new(hex_reader);
+        let (wakeup, delays) = std::sync::mpsc::channel();
+        view.set_frame_wakeup(wakeup);
+        view.set_max_fps(1);
+        let constraint = Vec2::new(80, 23);
+        view.layout(constraint);
+
+        // Two pages down within a frame are captured together, and the wakeup is asked for once.
+        view.on_event(Event::Key(Key::PageDown));
+        view.layout(constraint);
+        view.on_event(Event::Key(Key::PageDown));
+        view.layout(constraint);
+        assert_eq!(view.reader.window_pos, (0, 0));
+        assert_eq!(view.deferred_pos, Some((0, 42)));
+        assert!(delays.try_recv().unwrap() <= Duration::from_secs(1));
+        assert!(delays.try_recv().is_err());
+
+        // The next frame captures where the window was moved to.
+        view.last_frame.as_mut().unwrap().at -= Duration::from_secs(1);
+        view.layout(constraint);
+        assert_eq!(view.reader.window_pos, (0, 42));
+        assert_eq!(view.deferred_pos, None);
+        assert!(!view.needs_relayout());
+    }
+
+    #[test]
+    fn scrolling_a_view_along_with_another() {
+        let mut tmpf = tempfile::NamedTempFile::new().unwrap();
+        tmpf.write_all(&[0x55; 4096]).unwrap();
+        let new_view = || HexView::new(HexReader::new(TilingByteReader::new(tmpf.path()).unwrap()).unwrap());
+        let (mut leader, mut follower) = (new_view(), new_view());
+        follower.set_scroll_lock(Some((leader.get_published_offset(), 0x100)));
+        let constraint = Vec2::new(80, 23);
+        leader.layout(constraint);
+        follower.layout(constraint);
+        assert_eq!(follower.get_offset(), 0x100);
+
+        leader.go_to_offset(0x400);
+        leader.layout(constraint);
+        assert!(follower.needs_relayout());
+        follower.layout(constraint);
+        assert_eq!(follower.get_offset(), 0x500);
+
+        // The follower can be moved on its own, and stays where it is once it is unlocked.
+        follower.go_to_offset(0x10);
+        follower.layout(constraint);
+        assert_eq!(follower.get_offset(), 0x10);
+        follower.set_scroll_lock(None);
+        leader.go_to_offset(0);
+        leader.layout(constraint);
+        follower.layout(constraint);
+        assert_eq!(follower.get_offset(), 0x10);
+    }
 }
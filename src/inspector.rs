@@ -0,0 +1,110 @@
+//! The extension point of the data inspector, for describing the bytes at an offset.
+
+use std::fmt;
+
+/// Something that can interpret the bytes at an offset, and describe them as rows of labels
+/// and values, for the data inspector.
+///
+/// Inspectors are registered with `Inspectors` when the program starts, so support for a niche
+/// format can live in its own module, or in another crate, instead of in the viewer itself.
+pub trait Inspector {
+    /// The name of the inspector, that its rows are listed under.
+    fn name(&self) -> &str;
+
+    /// The number of bytes the inspector would like to look at.
+    fn size(&self) -> usize;
+
+    /// Add rows describing the bytes to `rows`. There may be fewer bytes than `size()` near the
+    /// end of a file, and the inspector should only describe what it can.
+    fn inspect(&self, bytes: &[u8], rows: &mut Vec<InspectorRow>);
+}
+
+/// One line of a description made by an inspector.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InspectorRow {
+    pub label: String,
+    pub value: String
+}
+
+impl InspectorRow {
+    pub fn new<L: Into<String>, V: Into<String>>(label: L, value: V) -> InspectorRow {
+        InspectorRow { label: label.into(), value: value.into() }
+    }
+}
+
+/// The registered inspectors, in the order they were registered.
+#[derive(Default)]
+pub struct Inspectors {
+    inspectors: Vec<Box<Inspector>>
+}
+
+impl Inspectors {
+    pub fn new() -> Inspectors {
+        Inspectors::default()
+    }
+
+    pub fn register<I: Inspector + 'static>(&mut self, inspector: I) {
+        self.inspectors.push(Box::new(inspector));
+    }
+
+    /// The number of bytes needed for every inspector to see all the bytes it would like.
+    pub fn max_size(&self) -> usize {
+        self.inspectors.iter().map(|i| i.size()).max().unwrap_or(0)
+    }
+
+    /// Ask every inspector to describe the bytes. Inspectors that have nothing to say are left
+    /// out of the result.
+    pub fn inspect(&self, bytes: &[u8]) -> Vec<(&str, Vec<InspectorRow>)> {
+        let mut result = Vec::new();
+        for inspector in &self.inspectors {
+            let mut rows = Vec::new();
+            let size = inspector.size().min(bytes.len());
+            inspector.inspect(&bytes[0..size], &mut rows);
+            if !rows.is_empty() {
+                result.push((inspector.name(), rows));
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Debug for Inspectors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.inspectors.iter().map(|i| i.name())).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FirstByte;
+
+    impl Inspector for FirstByte {
+        fn name(&self) -> &str {
+            "First byte"
+        }
+
+        fn size(&self) -> usize {
+            1
+        }
+
+        fn inspect(&self, bytes: &[u8], rows: &mut Vec<InspectorRow>) {
+            if let Some(b) = bytes.first() {
+                rows.push(InspectorRow::new("Value", format!("{}", b)));
+            }
+        }
+    }
+
+    #[test]
+    fn inspecting_with_registered_inspectors() {
+        let mut inspectors = Inspectors::new();
+        assert_eq!(inspectors.max_size(), 0);
+        inspectors.register(FirstByte);
+        assert_eq!(inspectors.max_size(), 1);
+        assert_eq!(inspectors.inspect(b"\x2A\x01"),
+                   vec![("First byte", vec![InspectorRow::new("Value", "42")])]);
+        assert!(inspectors.inspect(b"").is_empty());
+        assert_eq!(format!("{:?}", inspectors), "[\"First byte\"]");
+    }
+}
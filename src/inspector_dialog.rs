@@ -0,0 +1,57 @@
+//! The data inspector, that shows what every registered inspector makes of the bytes at the
+//! current position.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::Boxable;
+use cursive::views::{Dialog, DummyView, LinearLayout, OnEventView, ScrollView, TextView};
+
+use crate::hex_view::HexView;
+use crate::utilities::height_left;
+use crate::xv_state::XvState;
+use crate::xv_tui::ShowError;
+
+pub fn open_inspector_dialog(s: &mut Cursive) {
+    let size = s.with_user_data(|state: &mut XvState| state.inspectors().max_size()).unwrap();
+    let result = s.call_on_id("hex_view", |v: &mut HexView| {
+        let offset = v.get_offset();
+        let address = v.get_address_map().address_of(offset);
+        v.read_bytes(offset, size).map(|bytes| (address, bytes))
+    }).unwrap();
+    let (address, bytes) = match result {
+        Ok(found) => found,
+        Err(error) => return s.show_error(error)
+    };
+
+    let mut sections = LinearLayout::vertical();
+    let mut section_count = 0;
+    s.with_user_data(|state: &mut XvState| {
+        for (name, rows) in state.inspectors().inspect(&bytes) {
+            let labels: Vec<String> = rows.iter().map(|r| format!("{}:  ", r.label)).collect();
+            let values: Vec<&str> = rows.iter().map(|r| r.value.as_str()).collect();
+            if section_count > 0 {
+                sections.add_child(DummyView);
+            }
+            sections.add_child(TextView::new(name));
+            sections.add_child(LinearLayout::horizontal()
+                .child(TextView::new(labels.join("\n")))
+                .child(TextView::new(values.join("\n"))));
+            section_count += 1;
+        }
+    });
+    if section_count == 0 {
+        sections.add_child(TextView::new("There is nothing to inspect here."));
+    }
+
+    let dialog = Dialog::around(ScrollView::new(sections)
+        .max_height(height_left(s, 10).min(50)))
+        .title(format!("Inspect 0x{:X}", address))
+        .dismiss_button("Ok");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
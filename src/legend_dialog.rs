@@ -0,0 +1,46 @@
+//! The legend of what the colors of the bytes mean, with a checkbox to turn each kind off.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::utils::markup::StyledString;
+use cursive::views::{Checkbox, Dialog, LinearLayout, OnEventView, TextView};
+
+use crate::hex_view::{ColorKind, HexView, COLOR_KINDS};
+
+pub fn open_legend_dialog(s: &mut Cursive) {
+    let (shown, profile) = s.call_on_id("hex_view", |v: &mut HexView| {
+        let shown: Vec<bool> = COLOR_KINDS.iter().map(|kind| v.is_color_shown(*kind)).collect();
+        (shown, v.get_display_profile())
+    }).unwrap();
+
+    let mut layout = LinearLayout::vertical();
+    for (kind, shown) in COLOR_KINDS.iter().zip(shown) {
+        let kind = *kind;
+        let sample = match kind {
+            ColorKind::Gap | ColorKind::Hole => "\u{00B7}\u{00B7}",
+            _ => "41"
+        };
+        layout.add_child(LinearLayout::horizontal()
+            .child(Checkbox::new()
+                .with_checked(shown)
+                .on_change(move |s, checked| {
+                    s.call_on_id("hex_view", |v: &mut HexView| v.show_color(kind, checked));
+                }))
+            .child(TextView::new("  "))
+            .child(TextView::new(StyledString::styled(sample, kind.style(profile))))
+            .child(TextView::new(format!("  {}", kind.description()))));
+    }
+    layout.add_child(TextView::new(
+        "\nUncheck a kind of bytes to show it in the normal color."));
+
+    let dialog = Dialog::around(layout)
+        .title("Colors")
+        .dismiss_button("Close");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
@@ -0,0 +1,82 @@
+//! The hex rendering parts of the XV hex viewer, for use in other programs.
+//!
+//! The `hex_dump` module renders bytes from any `Read` source as text, the same way the XV
+//! hex viewer shows them, or as JSON:
+//!
+//! ```
+//! use xv::hex_dump::HexDump;
+//!
+//! let mut out = Vec::new();
+//! HexDump::new(&b"Hello,World!"[..]).width(8).render_to(&mut out).unwrap();
+//! assert_eq!(String::from_utf8(out).unwrap(),
+//!            "0x00000000  48 65 6c 6c 6f 2c 57 6f  Hello,Wo\n\
+//!             0x00000008  72 6c 64 21              rld!\n");
+//! ```
+//!
+//! The `hex_reader` module is the windowed reader that the XV text user interface is built on.
+//! It reads through a `byte_reader::TilingByteReader`, which can read from a file, or from any
+//! other `ByteSource`, such as a `Vec<u8>` or a `Cursor`.
+//!
+//! The `address_map` module maps file offsets to the addresses that the bytes are loaded at, for
+//! firmware images made of segments.
+//!
+//! The `block_index` module builds a coarse index of the entropy and the kinds of bytes of every
+//! block of a file, which can be saved and loaded again.
+//!
+//! The `checksum` module computes CRC-32, MD5 and SHA-256 checksums of any `ByteSource`.
+//!
+//! The `diff` module finds the inserted, deleted and changed ranges between two files, and the
+//! changes that two files made to a common base file, including the conflicting ones.
+//!
+//! The `inspector` module has the `Inspector` trait, for adding descriptions of the bytes at an
+//! offset to the data inspector.
+//!
+//! The `hex_records` module writes and reads Intel HEX and Motorola S-records, the text formats
+//! that firmware flashing tools use, and has a `ByteSource` for the sparse image that records
+//! describe.
+//!
+//! The `magic` module guesses the MIME type of a file from its first bytes.
+//!
+//! The `memory` module keeps count of the memory that the tiles, captures, searches, indexes and
+//! duplicate blocks use, and of the budget that they share.
+//!
+//! The `object_store` module has a `ByteSource` for objects in S3 and Google Cloud Storage, that
+//! fetches the parts of an object that are read with ranged requests.
+//!
+//! The `sparse` module has a `ByteSource` for sparse files, that finds their holes and does not
+//! read them.
+//!
+//! The `spool` module reads streams that grow while they are viewed, like serial ports, into a
+//! `ByteSource` on another thread.
+//!
+//! The `search_index` module indexes the trigrams in every block of a file, so that searching it
+//! again only reads the blocks that can have a match.
+//!
+//! The `progress` module tracks how far long running operations, like checksumming, comparing and
+//! searching, have come, and lets them be cancelled from another thread.
+//!
+//! The `strings` module finds the runs of printable text in any `ByteSource`, and keeps those
+//! around a window that moves through it.
+
+#![forbid(unsafe_code)]
+
+pub mod address_map;
+pub mod block_index;
+pub mod byte_reader;
+pub mod byte_stats;
+pub mod checksum;
+pub mod diff;
+pub mod error;
+pub mod hex_dump;
+pub mod hex_reader;
+pub mod hex_records;
+pub mod hex_tables;
+pub mod inspector;
+pub mod magic;
+pub mod memory;
+pub mod object_store;
+pub mod progress;
+pub mod search_index;
+pub mod sparse;
+pub mod spool;
+pub mod strings;
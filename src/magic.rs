@@ -0,0 +1,100 @@
+//! Guessing the MIME type of a file from the magic number that it starts with.
+
+/// The number of bytes at the start of a file that `mime_type` looks at.
+pub const MAGIC_LENGTH: usize = 512;
+
+/// Magic numbers at the start of files, and the MIME types of files that start with them.
+const SIGNATURES: [(&[u8], &str); 21] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xFF\xD8\xFF", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"PK\x05\x06", "application/zip"),
+    (b"\x1F\x8B", "application/gzip"),
+    (b"BZh", "application/x-bzip2"),
+    (b"\xFD7zXZ\x00", "application/x-xz"),
+    (b"7z\xBC\xAF\x27\x1C", "application/x-7z-compressed"),
+    (b"\x28\xB5\x2F\xFD", "application/zstd"),
+    (b"\x7FELF", "application/x-executable"),
+    (b"MZ", "application/x-msdownload"),
+    (b"\xCF\xFA\xED\xFE", "application/x-mach-binary"),
+    (b"\xCE\xFA\xED\xFE", "application/x-mach-binary"),
+    (b"\x00asm", "application/wasm"),
+    (b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"\x1A\x45\xDF\xA3", "video/webm")
+];
+
+/// Guess the MIME type of a file from its first bytes, by looking for well known magic numbers.
+/// Files without a known magic number are `text/plain` if they are valid UTF-8 without control
+/// characters, other than whitespace, and `application/octet-stream` otherwise. Empty files are
+/// `application/x-empty`.
+///
+/// Only the first `MAGIC_LENGTH` bytes are needed.
+pub fn mime_type(bytes: &[u8]) -> &'static str {
+    if bytes.is_empty() {
+        return "application/x-empty";
+    }
+    if let Some(mime_type) = magic_type(bytes) {
+        return mime_type;
+    }
+    if is_text(bytes) {
+        return "text/plain";
+    }
+    "application/octet-stream"
+}
+
+/// The MIME type of a file that starts with the bytes, if they start with a known magic number.
+pub fn magic_type(bytes: &[u8]) -> Option<&'static str> {
+    if let Some((_, mime_type)) = SIGNATURES.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return Some(mime_type);
+    }
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" {
+        match &bytes[8..12] {
+            b"WAVE" => return Some("audio/wav"),
+            b"AVI " => return Some("video/x-msvideo"),
+            b"WEBP" => return Some("image/webp"),
+            _ => {}
+        }
+    }
+    if bytes.len() >= 262 && &bytes[257..262] == b"ustar" {
+        return Some("application/x-tar");
+    }
+    None
+}
+
+fn is_text(bytes: &[u8]) -> bool {
+    let bytes = &bytes[0..bytes.len().min(MAGIC_LENGTH)];
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        // The bytes may end in the middle of a character.
+        Err(error) if error.error_len().is_none() => {
+            std::str::from_utf8(&bytes[0..error.valid_up_to()]).unwrap()
+        },
+        Err(_) => return false
+    };
+    text.chars().all(|c| !c.is_control() || c.is_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detecting_mime_types() {
+        assert_eq!(mime_type(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0DIHDR"), "image/png");
+        assert_eq!(mime_type(b"\x7FELF\x02\x01\x01"), "application/x-executable");
+        assert_eq!(mime_type(b"RIFF\x24\x08\x00\x00WAVEfmt "), "audio/wav");
+        let mut tar = vec![0; 512];
+        tar[257..263].copy_from_slice(b"ustar\x00");
+        assert_eq!(mime_type(&tar), "application/x-tar");
+        assert_eq!(mime_type("Grüße,\r\n\tworld!".as_bytes()), "text/plain");
+        assert_eq!(mime_type(&"é".as_bytes()[0..1]), "text/plain");
+        assert_eq!(mime_type(b"\x00\x01\x02\x03"), "application/octet-stream");
+        assert_eq!(mime_type(b"abc\xFFdef"), "application/octet-stream");
+        assert_eq!(mime_type(b""), "application/x-empty");
+    }
+}
@@ -16,57 +16,218 @@
 extern crate serde;
 extern crate serde_derive;
 
-use std::io::Result;
+use std::ffi::OsString;
+use std::path::Path;
+
+use xv::byte_reader::TilingByteReader;
+use xv::error::Result;
+use xv::hex_reader::HexReader;
+use xv::spool::{connect_tcp, listen_tcp, listen_udp, open_serial_port, socket_address};
 
 use crate::utilities::{PKG_NAME, PKG_VERSION, PKG_DESCRIPTION};
 use crate::xv_state::XvState;
 
 mod utilities;
+mod path_serde;
 mod panic_hook;
 mod xv_state;
-mod byte_reader;
-mod hex_tables;
-mod hex_reader;
 mod hex_view;
+mod block_analysis;
+mod bitfield;
+mod typed_array;
+mod filter;
+mod calculator;
+mod disassembler;
+mod annotations;
+mod map_files;
+mod points_of_interest;
+mod scripts;
+mod block_indexing;
+mod group_separators;
+mod core_dumps;
+mod captures;
+mod progress_dialog;
 mod set_width_dialog;
 mod goto_dialog;
+mod peek_dialog;
+mod record_dialog;
 mod open_file_dialog;
 mod switch_file_dialog;
+mod duplicates_dialog;
+mod stats_dialog;
+mod file_info_dialog;
+mod memory_dialog;
+mod bitfield_dialog;
+mod typed_array_dialog;
+mod inspector_dialog;
+mod filter_dialog;
+mod calculator_dialog;
+mod search_dialog;
+mod compare_dialog;
+mod verify_dialog;
+mod export_dialog;
+mod annotations_dialog;
+mod address_map_dialog;
+mod symbols_dialog;
+mod disassembler_dialog;
+mod core_dump_dialog;
+mod packets_dialog;
+mod points_dialog;
+mod legend_dialog;
+mod workspace_dialog;
+mod command_line;
+mod analyze;
+mod overview_view;
 mod status_bar;
+mod strings_panel;
+mod split_view;
 mod help_text;
 mod xv_tui;
 
-fn main() -> Result<()> {
+fn main() {
     panic_hook::install();
 
-    let mut args = std::env::args_os();
+    let mut args = std::env::args_os().peekable();
     args.next(); // The first argument is (most likely) the path to our executable.
-    let file_arg = args.next();
+    // A file that is called analyze is viewed, rather than taken for the analyze command.
+    if args.peek().is_some_and(|arg| arg == "--analyze" || (arg == "analyze" && !Path::new(arg).exists())) {
+        args.next();
+        std::process::exit(analyze::run_analyze(args));
+    }
+
+    let mut file_arg = None;
+    let mut live_arg = None;
+    let mut baud = 115_200;
+    let mut commands = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg.eq("-h") || arg.eq("--help") {
+            eprintln!("{} {}", PKG_NAME, PKG_VERSION);
+            eprintln!("{}", PKG_DESCRIPTION);
+            eprintln!();
+            eprintln!("{}", include_str!("usage.txt"));
+            return;
+        }
+
+        if arg.eq("-v") || arg.eq("--version") {
+            eprintln!("{} {}", PKG_NAME, PKG_VERSION);
+            return;
+        }
+
+        if arg.eq("-c") || arg.eq("--cmd") {
+            match args.next().map(|command| command.into_string()) {
+                Some(Ok(command)) => commands.push(command),
+                _ => {
+                    eprintln!("Error: The '{}' option needs a command.", arg.to_string_lossy());
+                    std::process::exit(1);
+                }
+            }
+        } else if arg.eq("--serial") {
+            live_arg = Some(LiveSource::Serial(option_value(&arg, args.next())));
+        } else if arg.eq("--listen") {
+            let address = option_value(&arg, args.next()).to_string_lossy().into_owned();
+            live_arg = Some(if let Some(address) = address.strip_prefix("udp:") {
+                LiveSource::ListenUdp(address.to_string())
+            } else if let Some(address) = address.strip_prefix("tcp:") {
+                LiveSource::ListenTcp(address.to_string())
+            } else {
+                LiveSource::ListenTcp(address)
+            });
+        } else if arg.eq("--connect") {
+            live_arg = Some(LiveSource::Connect(option_value(&arg, args.next()).to_string_lossy().into_owned()));
+        } else if arg.eq("--baud") {
+            let value = option_value(&arg, args.next());
+            match value.to_str().and_then(|value| value.parse().ok()) {
+                Some(value) => baud = value,
+                None => {
+                    eprintln!("Error: The baud rate must be a number, but '{}' was given.", value.to_string_lossy());
+                    std::process::exit(1);
+                }
+            }
+        } else if file_arg.is_none() {
+            file_arg = Some(arg);
+        } else {
+            eprintln!("Error: Only one file can be opened, but '{}' was given as well.", arg.to_string_lossy());
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(source) = live_arg {
+        if file_arg.is_some() {
+            eprintln!("Error: Either a file or a live source can be opened, not both.");
+            std::process::exit(1);
+        }
+        let state = XvState::load();
+        match open_live(&source, baud) {
+            Ok(h_reader) => xv_tui::run_tui(h_reader, state, commands),
+            Err(error) => {
+                eprintln!("Error: Could not open {}: {}", source.name(), error);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
     if file_arg.is_none() {
         eprintln!("Error: The 'file' argument is required.");
         eprintln!();
         eprintln!("For more information, try --help.");
-        return Ok(());
+        return;
     }
     
     let file_name = file_arg.unwrap();
     
-    if file_name.eq("-h") || file_name.eq("--help") {
-        eprintln!("{} {}", PKG_NAME, PKG_VERSION);
-        eprintln!("{}", PKG_DESCRIPTION);
-        eprintln!();
-        eprintln!("{}", include_str!("usage.txt"));
-        return Ok(());
+    let mut state = XvState::load();
+    match state.open_reader(&file_name) {
+        Ok(h_reader) => xv_tui::run_tui(h_reader, state, commands),
+        Err(error) => {
+            eprintln!("Error: Could not open {}: {}", file_name.to_string_lossy(), error);
+            std::process::exit(1);
+        }
     }
+}
 
-    if file_name.eq("-v") || file_name.eq("--version") {
-        eprintln!("{} {}", PKG_NAME, PKG_VERSION);
-        return Ok(());
+/// The value of an option, or exit with an error when there is none.
+fn option_value(option: &OsString, value: Option<OsString>) -> OsString {
+    match value {
+        Some(value) => value,
+        None => {
+            eprintln!("Error: The '{}' option needs a value.", option.to_string_lossy());
+            std::process::exit(1);
+        }
     }
-    
-    let mut state = XvState::load();
-    let h_reader = state.open_reader(file_name)?;
-    xv_tui::run_tui(h_reader, state);
-    Ok(())
+}
+
+/// Where the bytes that are viewed live, as they arrive, come from.
+enum LiveSource {
+    Serial(OsString),
+    ListenTcp(String),
+    ListenUdp(String),
+    Connect(String)
+}
+
+impl LiveSource {
+    fn name(&self) -> String {
+        match self {
+            LiveSource::Serial(port) => {
+                let name = Path::new(port).file_name().unwrap_or(port.as_os_str()).to_string_lossy();
+                format!("{} (serial)", name)
+            },
+            LiveSource::ListenTcp(address) => format!("tcp {}", socket_address(address)),
+            LiveSource::ListenUdp(address) => format!("udp {}", socket_address(address)),
+            LiveSource::Connect(address) => format!("tcp {} (connected)", address)
+        }
+    }
+}
+
+/// Spool what arrives from a live source, and read from the spool.
+fn open_live(source: &LiveSource, baud: u32) -> Result<HexReader> {
+    let spool = match source {
+        LiveSource::Serial(port) => open_serial_port(port, baud)?,
+        LiveSource::ListenTcp(address) => listen_tcp(address)?,
+        LiveSource::ListenUdp(address) => listen_udp(address)?,
+        LiveSource::Connect(address) => connect_tcp(address)?
+    };
+    let byte_reader = TilingByteReader::from_spool(spool, source.name())?;
+    HexReader::new(byte_reader)
 }
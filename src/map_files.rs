@@ -0,0 +1,314 @@
+//! Reading the layout and the symbols of firmware images from the map files that linkers write.
+
+use xv::address_map::Segment;
+use xv::error::{Result, XvError};
+
+use crate::utilities::parse_number;
+
+/// The sections that a GNU ld map lists, but that take no space in the image.
+const NOT_LOADED: [&str; 9] = [".bss", ".tbss", ".noinit", ".heap", ".stack", ".debug", ".comment", ".stab",
+                               ".ARM.attributes"];
+
+/// A name for an address, like a function or a variable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u64
+}
+
+/// Parse the segments of an address map, from a GNU ld map file, or from lines with a file offset, a
+/// length and an address, separated by spaces or commas.
+///
+/// In a GNU ld map, every section that is loaded becomes a segment. The image is taken to start
+/// with the section that has the lowest load address, as `objcopy -O binary` writes it.
+pub fn parse_segments(text: &str) -> Result<Vec<Segment>> {
+    if text.contains("Linker script and memory map") {
+        parse_ld_segments(text)
+    } else {
+        parse_segment_lines(text)
+    }
+}
+
+fn parse_segment_lines(text: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .collect();
+        let segment = match fields.as_slice() {
+            [offset, length, address] => Segment {
+                offset: parse_number(offset)?,
+                length: parse_number(length)?,
+                address: parse_number(address)?
+            },
+            _ => return Err(XvError::Parse(format!("Line {}: Give a file offset, a length and an address.", i + 1)))
+        };
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+fn parse_ld_segments(text: &str) -> Result<Vec<Segment>> {
+    // Every loaded section, as its address, its size and its load address.
+    let mut sections = Vec::new();
+    let mut lines = text.lines().skip_while(|line| !line.starts_with("Linker script and memory map"));
+    while let Some(line) = lines.next() {
+        if !line.starts_with('.') {
+            continue;
+        }
+        let mut fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() == 1 {
+            // Long section names are on a line of their own.
+            fields.extend(lines.next().unwrap_or("").split_whitespace());
+        }
+        let name = fields[0];
+        if NOT_LOADED.iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+        let (address, size) = match (fields.get(1).map(|f| parse_number(f)), fields.get(2).map(|f| parse_number(f))) {
+            (Some(Ok(address)), Some(Ok(size))) if size > 0 => (address, size),
+            _ => continue
+        };
+        let load_address = match fields.get(3..6) {
+            Some(["load", "address", load_address]) => parse_number(load_address)?,
+            _ => address
+        };
+        sections.push((address, size, load_address));
+    }
+    let image_start = match sections.iter().map(|section| section.2).min() {
+        Some(start) => start,
+        None => return Err(XvError::Parse(String::from("The map file has no loaded sections.")))
+    };
+    Ok(sections.iter()
+        .map(|&(address, length, load_address)| Segment { offset: load_address - image_start, length, address })
+        .collect())
+}
+
+/// Parse the symbols of a GNU ld or MSVC map file, or from lines with an address and a name,
+/// separated by spaces or commas. The symbols are sorted by address.
+pub fn parse_symbols(text: &str) -> Result<Vec<Symbol>> {
+    let mut symbols = if text.contains("Linker script and memory map") {
+        parse_ld_symbols(text)
+    } else if text.contains("Publics by Value") {
+        parse_msvc_symbols(text)
+    } else {
+        parse_symbol_lines(text)?
+    };
+    symbols.sort_by_key(|symbol| symbol.address);
+    Ok(symbols)
+}
+
+/// The symbol at or before the address, that is closest to it. Of several symbols at the same
+/// address, the first is used.
+pub fn nearest_symbol(symbols: &[Symbol], address: u64) -> Option<&Symbol> {
+    let end = match symbols.binary_search_by_key(&address, |symbol| symbol.address) {
+        Ok(i) => i + 1,
+        Err(i) => i
+    };
+    if end == 0 {
+        return None;
+    }
+    let mut i = end - 1;
+    while i > 0 && symbols[i - 1].address == symbols[i].address {
+        i -= 1;
+    }
+    Some(&symbols[i])
+}
+
+/// Describe an address by the nearest symbol, like `main+0x1C`.
+pub fn describe_address(symbols: &[Symbol], address: u64) -> Option<String> {
+    nearest_symbol(symbols, address).map(|symbol| match address - symbol.address {
+        0 => symbol.name.clone(),
+        distance => format!("{}+0x{:X}", symbol.name, distance)
+    })
+}
+
+/// The symbols whose names have the characters of the pattern in order, ignoring case, with the
+/// best matches first. A match is better when the characters are closer together, and nearer the
+/// start of the name.
+pub fn find_symbols<'a>(symbols: &'a [Symbol], pattern: &str, limit: usize) -> Vec<&'a Symbol> {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let mut found: Vec<(usize, &Symbol)> = symbols.iter()
+        .filter_map(|symbol| fuzzy_score(&pattern, &symbol.name).map(|score| (score, symbol)))
+        .collect();
+    found.sort_by(|(a_score, a), (b_score, b)| {
+        a_score.cmp(b_score).then(a.name.len().cmp(&b.name.len())).then(a.name.cmp(&b.name))
+    });
+    found.into_iter().take(limit).map(|(_, symbol)| symbol).collect()
+}
+
+/// Score how well the lower case pattern matches the name, as the position of its first character
+/// plus the number of characters skipped between the others, or `None` if it does not match.
+fn fuzzy_score(pattern: &[char], name: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut last_match = None;
+    let mut pattern_chars = pattern.iter().peekable();
+    for (i, c) in name.to_lowercase().chars().enumerate() {
+        match pattern_chars.peek() {
+            Some(&&p) if p == c => {
+                score += match last_match {
+                    Some(last) => i - last - 1,
+                    None => i
+                };
+                last_match = Some(i);
+                pattern_chars.next();
+            },
+            Some(_) => {},
+            None => break
+        }
+    }
+    if pattern_chars.peek().is_none() { Some(score) } else { None }
+}
+
+fn parse_ld_symbols(text: &str) -> Vec<Symbol> {
+    // Symbols are on indented lines of their own, with just an address and a name. Assignments,
+    // like `_sdata = .`, have more fields.
+    text.lines()
+        .skip_while(|line| !line.starts_with("Linker script and memory map"))
+        .filter(|line| line.starts_with(' '))
+        .filter_map(|line| match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            [address, name] if address.starts_with("0x") && !name.starts_with('*') => {
+                parse_number(address).ok().map(|address| Symbol { name: String::from(*name), address })
+            },
+            _ => None
+        })
+        .collect()
+}
+
+fn parse_msvc_symbols(text: &str) -> Vec<Symbol> {
+    // The lines have the section and offset, the name, and the address, like
+    // ` 0001:00000000       _main                      00401000 f   main.obj`. Section 0 has the
+    // absolute symbols, that are not addresses.
+    text.lines()
+        .skip_while(|line| !line.contains("Publics by Value"))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [section, name, address, ..] if section.contains(':') && !section.starts_with("0000:") => {
+                    u64::from_str_radix(address, 16).ok().map(|address| Symbol { name: String::from(*name), address })
+                },
+                _ => None
+            }
+        })
+        .collect()
+}
+
+fn parse_symbol_lines(text: &str) -> Result<Vec<Symbol>> {
+    let mut symbols = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .collect();
+        let symbol = match fields.as_slice() {
+            [address, name] | [name, address] if parse_number(address).is_ok() => {
+                Symbol { name: String::from(*name), address: parse_number(address)? }
+            },
+            // A first line without an address is a header.
+            [_, _] if symbols.is_empty() => continue,
+            _ => return Err(XvError::Parse(format!("Line {}: Give an address and a name.", i + 1)))
+        };
+        symbols.push(symbol);
+    }
+    Ok(symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_segment_lines() {
+        let text = "# offset, length, address\n0, 0x4000, 0x08000000\n0x4000 0x100 0x20000000\n";
+        assert_eq!(parse_segments(text).unwrap(), vec![
+            Segment { offset: 0, length: 0x4000, address: 0x0800_0000 },
+            Segment { offset: 0x4000, length: 0x100, address: 0x2000_0000 }
+        ]);
+        assert!(parse_segments("0 1").is_err());
+    }
+
+    #[test]
+    fn parsing_ld_map_segments() {
+        let map = "Memory Configuration\n\
+                   \n\
+                   FLASH            0x08000000         0x00010000         xr\n\
+                   \n\
+                   Linker script and memory map\n\
+                   \n\
+                   .isr_vector     0x08000000      0x188\n \
+                    *(.isr_vector)\n\
+                   .text           0x08000188     0x1a00\n\
+                   .ARM.exidx\n                \
+                                   0x08001b88        0x8\n\
+                   .data           0x20000000       0x10 load address 0x08001b90\n\
+                   .bss            0x20000010      0x200\n\
+                   .debug_info     0x00000000     0x4000\n";
+        assert_eq!(parse_segments(map).unwrap(), vec![
+            Segment { offset: 0, length: 0x188, address: 0x0800_0000 },
+            Segment { offset: 0x188, length: 0x1A00, address: 0x0800_0188 },
+            Segment { offset: 0x1B88, length: 8, address: 0x0800_1B88 },
+            Segment { offset: 0x1B90, length: 0x10, address: 0x2000_0000 }
+        ]);
+    }
+
+    fn symbol(name: &str, address: u64) -> Symbol {
+        Symbol { name: String::from(name), address }
+    }
+
+    #[test]
+    fn parsing_symbols() {
+        let ld_map = "Linker script and memory map
+                      
+                      .text           0x08000188       0x40
+                        *(.text*)
+                        .text          0x08000188       0x40 main.o
+                                                      0x080001a0                helper
+                                                      0x08000188                main
+                                                      0x08000200                _etext = .
+";
+        assert_eq!(parse_symbols(ld_map).unwrap(), vec![symbol("main", 0x0800_0188), symbol("helper", 0x0800_01A0)]);
+
+        let msvc_map = "  Address         Publics by Value              Rva+Base       Lib:Object
+                        
+                         0000:00000000       ___safe_se_handler_count   00000000     <absolute>
+                         0001:00000000       _main                      00401000 f   main.obj
+";
+        assert_eq!(parse_symbols(msvc_map).unwrap(), vec![symbol("_main", 0x0040_1000)]);
+
+        let csv = "name,address
+reset,0x100
+0x80, vectors
+";
+        assert_eq!(parse_symbols(csv).unwrap(), vec![symbol("vectors", 0x80), symbol("reset", 0x100)]);
+        assert!(parse_symbols("0x10").is_err());
+    }
+
+    #[test]
+    fn finding_symbols() {
+        let symbols = vec![symbol("uart_init", 1), symbol("USART1_IRQHandler", 2), symbol("main", 3),
+                           symbol("init", 4)];
+        let names = |pattern, limit| -> Vec<String> {
+            find_symbols(&symbols, pattern, limit).iter().map(|symbol| symbol.name.clone()).collect()
+        };
+        assert_eq!(names("init", 10), vec!["init", "uart_init"]);
+        assert_eq!(names("ui", 10), vec!["uart_init", "USART1_IRQHandler"]);
+        assert_eq!(names("", 2), vec!["init", "main"]);
+        assert!(names("xyz", 10).is_empty());
+    }
+
+    #[test]
+    fn describing_addresses() {
+        let symbols = vec![symbol("start", 0x100), symbol("alias", 0x200), symbol("main", 0x200)];
+        assert_eq!(describe_address(&symbols, 0xFF), None);
+        assert_eq!(describe_address(&symbols, 0x100), Some(String::from("start")));
+        assert_eq!(describe_address(&symbols, 0x1FF), Some(String::from("start+0xFF")));
+        assert_eq!(describe_address(&symbols, 0x21C), Some(String::from("alias+0x1C")));
+    }
+}
@@ -0,0 +1,146 @@
+//! A memory budget that the caches and the other data that xv keeps in memory share, so that it
+//! uses about as much memory as it is given, however large the files are.
+//!
+//! Every subsystem keeps count of what it uses with `Reservation`s. Caches, like the tiles, give
+//! up what they keep when the budget is used up, spools of live captures go on in a temporary
+//! file, and work that would go over the budget stops.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The part of xv that memory is used by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subsystem {
+    /// The tiles of the files that are read.
+    Tiles,
+    /// The bytes of live captures and streams, that are kept in memory.
+    Captures,
+    /// What searches keep track of while they run.
+    SearchResults,
+    /// The block indexes and search indexes of files.
+    Indexes,
+    /// The blocks and runs that finding duplicate blocks keeps track of.
+    Duplicates
+}
+
+pub const SUBSYSTEMS: [Subsystem; 5] = [Subsystem::Tiles, Subsystem::Captures, Subsystem::SearchResults,
+                                        Subsystem::Indexes, Subsystem::Duplicates];
+
+impl Subsystem {
+    pub fn name(self) -> &'static str {
+        match self {
+            Subsystem::Tiles => "Tile cache",
+            Subsystem::Captures => "Captures",
+            Subsystem::SearchResults => "Search results",
+            Subsystem::Indexes => "Indexes",
+            Subsystem::Duplicates => "Duplicate blocks"
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The budget when none is set.
+pub const DEFAULT_BUDGET: u64 = 512 * 1024 * 1024;
+
+static BUDGET: AtomicU64 = AtomicU64::new(DEFAULT_BUDGET);
+
+static USAGE: [AtomicU64; 5] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
+                                AtomicU64::new(0)];
+
+/// The number of bytes that all the subsystems together should keep below.
+pub fn budget() -> u64 {
+    BUDGET.load(Ordering::Relaxed)
+}
+
+/// Change the budget. What is already used above it is given up as the subsystems go on.
+pub fn set_budget(bytes: u64) {
+    BUDGET.store(bytes, Ordering::Relaxed);
+}
+
+/// The number of bytes that the subsystem has reserved.
+pub fn usage(subsystem: Subsystem) -> u64 {
+    USAGE[subsystem.index()].load(Ordering::Relaxed)
+}
+
+pub fn total_usage() -> u64 {
+    SUBSYSTEMS.iter().map(|&subsystem| usage(subsystem)).sum()
+}
+
+/// The number of bytes that are left of the budget.
+pub fn available() -> u64 {
+    budget().saturating_sub(total_usage())
+}
+
+/// Memory that a subsystem uses, which counts towards the budget until it is dropped.
+#[derive(Debug)]
+pub struct Reservation {
+    subsystem: Subsystem,
+    bytes: u64
+}
+
+impl Reservation {
+    /// Count the bytes towards the budget, whether they fit in it or not.
+    pub fn new(subsystem: Subsystem, bytes: u64) -> Reservation {
+        USAGE[subsystem.index()].fetch_add(bytes, Ordering::Relaxed);
+        Reservation { subsystem, bytes }
+    }
+
+    /// Count the bytes towards the budget, if they fit in what is left of it.
+    pub fn try_new(subsystem: Subsystem, bytes: u64) -> Option<Reservation> {
+        if bytes <= available() { Some(Reservation::new(subsystem, bytes)) } else { None }
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Count another number of bytes towards the budget instead, whether they fit in it or not.
+    pub fn resize(&mut self, bytes: u64) {
+        let usage = &USAGE[self.subsystem.index()];
+        if bytes > self.bytes {
+            usage.fetch_add(bytes - self.bytes, Ordering::Relaxed);
+        } else {
+            usage.fetch_sub(self.bytes - bytes, Ordering::Relaxed);
+        }
+        self.bytes = bytes;
+    }
+
+    /// Count more bytes towards the budget, if they fit in what is left of it. Returns whether
+    /// they did.
+    pub fn try_grow(&mut self, bytes: u64) -> bool {
+        if bytes > available() {
+            return false;
+        }
+        let total = self.bytes + bytes;
+        self.resize(total);
+        true
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.resize(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserving_memory() {
+        let before = usage(Subsystem::SearchResults);
+        let mut reservation = Reservation::new(Subsystem::SearchResults, 1000);
+        assert_eq!(usage(Subsystem::SearchResults), before + 1000);
+        assert!(reservation.try_grow(24));
+        assert_eq!(reservation.bytes(), 1024);
+        assert!(!reservation.try_grow(budget() + 1));
+        assert!(Reservation::try_new(Subsystem::SearchResults, budget() + 1).is_none());
+        reservation.resize(10);
+        assert_eq!(usage(Subsystem::SearchResults), before + 10);
+        drop(reservation);
+        assert_eq!(usage(Subsystem::SearchResults), before);
+    }
+}
@@ -0,0 +1,46 @@
+//! The dialog that shows how the memory budget is used.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::views::{Dialog, LinearLayout, OnEventView, TextView};
+use xv::memory::{self, SUBSYSTEMS};
+
+use crate::hex_view::HexView;
+use crate::utilities::describe_size;
+
+/// Show how much of the memory budget every part of xv uses, and how well the tiles are cached.
+pub fn open_memory_dialog(s: &mut Cursive) {
+    let tile_stats = s.call_on_id("hex_view", |v: &mut HexView| v.get_tile_stats()).unwrap();
+
+    let mut info: Vec<(&str, String)> = SUBSYSTEMS.iter()
+        .map(|&subsystem| (subsystem.name(), describe_size(memory::usage(subsystem) as f64)))
+        .collect();
+    let (total, budget) = (memory::total_usage(), memory::budget());
+    info.push(("Total", format!("{} of {} ({}%)", describe_size(total as f64), describe_size(budget as f64),
+                                total * 100 / budget.max(1))));
+    info.push(("Available", describe_size(memory::available() as f64)));
+    let reads = tile_stats.hits + tile_stats.fetched;
+    info.push(("Tile reads", format!("{} fetched, {} read ahead, {} cache hits ({}%)", tile_stats.fetched,
+                                     tile_stats.read_ahead, tile_stats.hits, tile_stats.hits * 100 / reads.max(1))));
+
+    let labels: Vec<String> = info.iter().map(|(label, _)| format!("{}:  ", label)).collect();
+    let values: Vec<&str> = info.iter().map(|(_, value)| value.as_str()).collect();
+    let layout = LinearLayout::horizontal()
+        .child(TextView::new(labels.join("\n")))
+        .child(TextView::new(values.join("\n")));
+
+    let dialog = Dialog::around(layout)
+        .title("Memory")
+        .button("Refresh", |s| {
+            s.pop_layer();
+            open_memory_dialog(s);
+        })
+        .dismiss_button("Ok");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
@@ -0,0 +1,277 @@
+//! Objects in cloud storage, like disk images in S3 or Google Cloud Storage, that are read in
+//! tiles with ranged requests as they are viewed, instead of being downloaded first.
+//!
+//! The requests are made with the `aws` and `gsutil` command line tools, so that their
+//! credentials and configuration are used. The source that is viewed makes them on another
+//! thread, so that the screen does not wait for them.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Error, ErrorKind, Result};
+use std::path::PathBuf;
+use std::process::{self, Command, Output};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use crate::byte_reader::{ByteSource, TileConfig};
+use crate::error::{self, XvError};
+
+/// Every request starts a program, so large tiles are read, with fewer requests.
+const OBJECT_TILES: TileConfig = TileConfig { tile_size: 1024 * 1024, read_ahead: 1 };
+
+/// How many fetched ranges are kept until they are read. Ranges that are not read by then, like
+/// those read ahead of a window that moved elsewhere, are let go, the oldest first.
+const MAX_DONE_FETCHES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Service {
+    S3,
+    Gcs
+}
+
+/// Where an object is stored, from a URL like `s3://bucket/key` or `gs://bucket/key`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectUrl {
+    pub service: Service,
+    pub bucket: String,
+    pub key: String
+}
+
+impl ObjectUrl {
+    /// The object that the text is the URL of, if it is one.
+    pub fn parse(text: &str) -> Option<ObjectUrl> {
+        let (service, rest) = if let Some(rest) = text.strip_prefix("s3://") {
+            (Service::S3, rest)
+        } else if let Some(rest) = text.strip_prefix("gs://") {
+            (Service::Gcs, rest)
+        } else {
+            return None;
+        };
+        let slash = rest.find('/')?;
+        let (bucket, key) = (&rest[..slash], &rest[slash + 1..]);
+        if bucket.is_empty() || key.is_empty() {
+            return None;
+        }
+        Some(ObjectUrl { service, bucket: bucket.to_string(), key: key.to_string() })
+    }
+
+    /// The name of the object, which is the last part of its key.
+    pub fn file_name(&self) -> &str {
+        self.key.rsplit('/').next().unwrap_or(&self.key)
+    }
+}
+
+impl fmt::Display for ObjectUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scheme = match self.service {
+            Service::S3 => "s3",
+            Service::Gcs => "gs"
+        };
+        write!(f, "{}://{}/{}", scheme, self.bucket, self.key)
+    }
+}
+
+/// An object in cloud storage. Every read is a request, which is either made right away, or
+/// handed to a thread that fetches in the background.
+pub struct ObjectSource {
+    url: ObjectUrl,
+    length: u64,
+    fetcher: Option<Fetcher>
+}
+
+/// The ranges that have been asked for and are being fetched on another thread, and those that
+/// have been fetched, until they are read or too many others have been fetched since.
+#[derive(Default)]
+struct Fetches {
+    pending: HashSet<(u64, u64)>,
+    done: HashMap<(u64, u64), Result<Vec<u8>>>,
+    /// The ranges that have been fetched, from the oldest.
+    done_order: VecDeque<(u64, u64)>
+}
+
+impl Fetches {
+    fn finish(&mut self, range: (u64, u64), result: Result<Vec<u8>>) {
+        self.pending.remove(&range);
+        if self.done.insert(range, result).is_none() {
+            self.done_order.push_back(range);
+        }
+        while self.done_order.len() > MAX_DONE_FETCHES {
+            let oldest = self.done_order.pop_front().unwrap();
+            self.done.remove(&oldest);
+        }
+    }
+
+    fn take(&mut self, range: (u64, u64)) -> Option<Result<Vec<u8>>> {
+        let result = self.done.remove(&range)?;
+        self.done_order.retain(|&done| done != range);
+        Some(result)
+    }
+}
+
+/// Fetches ranges of an object on another thread, which ends when this is dropped.
+struct Fetcher {
+    requests: Sender<(u64, u64)>,
+    fetches: Arc<Mutex<Fetches>>
+}
+
+impl Fetcher {
+    fn start(url: ObjectUrl) -> Fetcher {
+        let (requests, received) = mpsc::channel::<(u64, u64)>();
+        let fetches = Arc::new(Mutex::new(Fetches::default()));
+        let thread_fetches = fetches.clone();
+        thread::spawn(move || {
+            for (offset, length) in received {
+                let result = ObjectSource::fetch(&url, offset, length);
+                thread_fetches.lock().unwrap().finish((offset, length), result);
+            }
+        });
+        Fetcher { requests, fetches }
+    }
+
+    /// The bytes of the range, once they have been fetched, or a `WouldBlock` error until they
+    /// have been. Failed fetches are made again when the range is asked for again.
+    fn take(&self, url: &ObjectUrl, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let mut fetches = self.fetches.lock().unwrap();
+        if let Some(result) = fetches.take((offset, length)) {
+            return result;
+        }
+        if fetches.pending.insert((offset, length)) {
+            self.requests.send((offset, length))
+                .map_err(|_| Error::other("The fetching of the object stopped."))?;
+        }
+        Err(Error::new(ErrorKind::WouldBlock, format!("Fetching from {}...", url)))
+    }
+}
+
+impl fmt::Debug for ObjectSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ObjectSource({}, {} bytes)", self.url, self.length)
+    }
+}
+
+impl ObjectSource {
+    /// Look up the length of the object, without fetching any of it.
+    pub fn open(url: ObjectUrl) -> error::Result<ObjectSource> {
+        let length = match url.service {
+            Service::S3 => run("aws", &["s3api", "head-object", "--bucket", &url.bucket, "--key", &url.key,
+                                        "--query", "ContentLength", "--output", "text"])?,
+            Service::Gcs => run("gsutil", &["du", &url.to_string()])?
+        };
+        let length = String::from_utf8_lossy(&length).split_whitespace().next()
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| XvError::Decode(format!("Could not find the size of {}.", url)))?;
+        let fetcher = Some(Fetcher::start(url.clone()));
+        Ok(ObjectSource { url, length, fetcher })
+    }
+
+    /// Fetch the given range of the object.
+    fn fetch(url: &ObjectUrl, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let range = format!("{}-{}", offset, offset + length - 1);
+        match url.service {
+            Service::S3 => {
+                // The bytes can only be written to a file, since the response is printed.
+                let path = temp_file()?;
+                let result = run("aws", &["s3api", "get-object", "--bucket", &url.bucket, "--key", &url.key,
+                                          "--range", &format!("bytes={}", range), &path.to_string_lossy()])
+                    .map_err(io::Error::from)
+                    .and_then(|_| fs::read(&path));
+                let _ = fs::remove_file(&path);
+                result
+            },
+            Service::Gcs => Ok(run("gsutil", &["cat", "-r", &range, &url.to_string()])?)
+        }
+    }
+}
+
+/// Tells the temporary files of the requests of a process apart.
+static TEMP_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Create an empty temporary file for a request to write to. It is created here, rather than by
+/// the request, so that it cannot be a link that something else put there.
+fn temp_file() -> Result<PathBuf> {
+    let count = TEMP_FILE_COUNT.fetch_add(1, Ordering::SeqCst);
+    let path = std::env::temp_dir().join(format!("xv-object-{}-{}", process::id(), count));
+    OpenOptions::new().write(true).create_new(true).open(&path)?;
+    Ok(path)
+}
+
+/// Run a program, and return what it printed, or what it printed as the error when it failed.
+fn run(program: &str, args: &[&str]) -> error::Result<Vec<u8>> {
+    let Output { status, stdout, stderr } = Command::new(program).args(args).output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => XvError::Unsupported(format!(
+                "Objects in cloud storage are read with {}, which is not installed.", program)),
+            _ => XvError::Io(e)
+        })?;
+    if !status.success() {
+        let message = String::from_utf8_lossy(&stderr);
+        let message = format!("{} failed: {}", program, message.trim());
+        return Err(XvError::Io(Error::other(message)));
+    }
+    Ok(stdout)
+}
+
+impl ByteSource for ObjectSource {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        if offset >= self.length || buf.is_empty() {
+            return Ok(0);
+        }
+        let length = (buf.len() as u64).min(self.length - offset);
+        let bytes = match &self.fetcher {
+            Some(fetcher) => fetcher.take(&self.url, offset, length)?,
+            None => ObjectSource::fetch(&self.url, offset, length)?
+        };
+        let len = bytes.len().min(buf.len());
+        buf[0..len].copy_from_slice(&bytes[0..len]);
+        Ok(len)
+    }
+
+    fn length(&mut self) -> Result<u64> {
+        Ok(self.length)
+    }
+
+    /// The clone waits for its requests, since it is read on a thread that can wait.
+    fn try_clone(&self) -> Option<Box<ByteSource + Send>> {
+        Some(Box::new(ObjectSource { url: self.url.clone(), length: self.length, fetcher: None }))
+    }
+
+    fn tile_config(&self) -> TileConfig {
+        OBJECT_TILES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_object_urls() {
+        let url = ObjectUrl::parse("s3://images/cases/disk.img").unwrap();
+        assert_eq!(url.service, Service::S3);
+        assert_eq!(url.bucket, "images");
+        assert_eq!(url.key, "cases/disk.img");
+        assert_eq!(url.file_name(), "disk.img");
+        assert_eq!(url.to_string(), "s3://images/cases/disk.img");
+        assert_eq!(ObjectUrl::parse("gs://images/disk.img").unwrap().service, Service::Gcs);
+        assert_eq!(ObjectUrl::parse("s3://images"), None);
+        assert_eq!(ObjectUrl::parse("/tmp/disk.img"), None);
+    }
+
+    #[test]
+    fn letting_go_of_fetches_that_are_not_read() {
+        let mut fetches = Fetches::default();
+        for offset in 0..MAX_DONE_FETCHES as u64 + 4 {
+            fetches.pending.insert((offset, 1));
+            fetches.finish((offset, 1), Ok(vec![offset as u8]));
+        }
+        assert!(fetches.pending.is_empty());
+        assert_eq!(fetches.done.len(), MAX_DONE_FETCHES);
+        assert!(fetches.take((0, 1)).is_none());
+        assert_eq!(fetches.take((4, 1)).unwrap().unwrap(), vec![4]);
+        assert!(fetches.take((4, 1)).is_none());
+        assert_eq!(fetches.done_order.len(), MAX_DONE_FETCHES - 1);
+    }
+}
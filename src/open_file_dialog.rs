@@ -1,32 +1,59 @@
 use cursive::Cursive;
-use cursive::views::{Dialog, OnEventView, SelectView, LinearLayout, ScrollView, TextView, DummyView};
+use cursive::views::{Dialog, EditView, OnEventView, SelectView, LinearLayout, ScrollView, TextView, DummyView};
 use cursive::event::Key;
 use cursive::traits::{Boxable, Identifiable};
 use crate::xv_state::XvState;
 use std::ffi::{OsStr, OsString};
-use std::io::Result;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use xv::byte_reader::{describe_file_type, TilingByteReader};
+use xv::error::{Result, XvError};
+use xv::hex_reader::HexReader;
+use xv::hex_records::{parse_records, SparseImage};
+use xv::object_store::ObjectUrl;
+use xv::progress::Progress;
 use cursive::theme::Effect;
+use directories::BaseDirs;
 use crate::hex_view::HexView;
-use crate::xv_tui::ShowError;
+use crate::progress_dialog::run_with_progress;
+use crate::xv_tui::{watch_spool, ShowError};
+use crate::utilities::{get_content, height_left, parse_hex_bytes, parse_number, width_left};
+
+const FILL_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The extensions of Intel HEX and S-record files, that can be opened as the image they describe.
+const RECORDS_EXTENSIONS: [&str; 8] = ["hex", "ihex", "ihx", "srec", "s19", "s28", "s37", "mot"];
 
 pub fn open_file_dialog(s: &mut Cursive) {
     let dir_selector: SelectView<OsString> = SelectView::new()
         .on_submit(select_directory)
         .autojump();
     let file_selector: SelectView<OsString> = SelectView::new().autojump();
+    let favorite_selector: SelectView<PathBuf> = SelectView::new()
+        .on_submit(|s, dir: &PathBuf| go_to_directory(s, dir.clone()));
+    let favorites = LinearLayout::vertical()
+        .child(TextView::new("Favorites:").effect(Effect::Bold))
+        .child(ScrollView::new(OnEventView::new(favorite_selector.with_id("favorite_selector"))
+            .on_event(Key::Del, remove_selected_favorite)));
 
     let layout = LinearLayout::vertical()
         .child(TextView::new("").center().effect(Effect::Bold).with_id("current_dir"))
         .child(DummyView)
         .child(LinearLayout::horizontal()
+            .child(favorites.max_width(30))
+            .child(DummyView)
             .child(ScrollView::new(dir_selector.with_id("dir_selector").full_width()))
             .child(ScrollView::new(file_selector.with_id("file_selector").full_width())))
-        .fixed_height(s.screen_size().y - 11)
-        .fixed_width(s.screen_size().x - 20);
+        .fixed_height(height_left(s, 11))
+        .fixed_width(width_left(s, 20));
     
     let file_picker = Dialog::new()
         .title("Open file")
         .content(layout)
+        .button("New file", open_new_file_dialog)
+        .button("New directory", open_new_directory_dialog)
+        .button("Pin", toggle_favorite_directory)
         .dismiss_button("Cancel")
         .button("Open", do_open_file);
 
@@ -34,11 +61,91 @@ pub fn open_file_dialog(s: &mut Cursive) {
         .on_event(Key::Esc, |s| {
             s.pop_layer();
         })
-        .on_event(Key::Enter, do_open_file);
+        .on_event(Key::Enter, do_open_file)
+        .on_event(Key::Backspace, |s| select_directory(s, OsStr::new("..")))
+        .on_pre_event('~', go_to_home_directory)
+        .on_pre_event('/', go_to_root_directory);
+    #[cfg(windows)]
+    let event_view = event_view.on_pre_event(':', open_drive_dialog);
     s.add_layer(event_view);
+    fill_favorites(s);
     select_directory(s, &OsString::new());
 }
 
+fn fill_favorites(s: &mut Cursive) {
+    let favorites = s.with_user_data(|state: &mut XvState| state.favorite_directories().to_vec()).unwrap();
+    s.call_on_id("favorite_selector", |v: &mut SelectView<PathBuf>| {
+        v.clear();
+        for dir in favorites {
+            v.add_item(format!("{}", dir.display()), dir);
+        }
+    });
+}
+
+fn go_to_directory(s: &mut Cursive, dir: PathBuf) {
+    s.with_user_data(|state: &mut XvState| state.set_directory(dir));
+    select_directory(s, &OsString::new());
+}
+
+fn go_to_home_directory(s: &mut Cursive) {
+    if let Some(base_dirs) = BaseDirs::new() {
+        go_to_directory(s, base_dirs.home_dir().to_path_buf());
+    }
+}
+
+/// Go to the root of the file system, or of the current drive on Windows.
+fn go_to_root_directory(s: &mut Cursive) {
+    let root = s.with_user_data(|state: &mut XvState| {
+        state.current_directory().ancestors().last().map(Path::to_path_buf)
+    }).unwrap();
+    if let Some(root) = root {
+        go_to_directory(s, root);
+    }
+}
+
+#[cfg(windows)]
+fn open_drive_dialog(s: &mut Cursive) {
+    let mut drive_selector: SelectView<PathBuf> = SelectView::new()
+        .on_submit(|s, drive: &PathBuf| {
+            s.pop_layer();
+            go_to_directory(s, drive.clone());
+        })
+        .autojump();
+    for letter in b'A'..=b'Z' {
+        let drive = PathBuf::from(format!("{}:\\", letter as char));
+        if drive.exists() {
+            drive_selector.add_item(format!("{}:", letter as char), drive);
+        }
+    }
+
+    let dialog = Dialog::around(ScrollView::new(drive_selector))
+        .title("Drives")
+        .dismiss_button("Cancel");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view);
+}
+
+/// Pin the current directory to the favorites, or unpin it.
+fn toggle_favorite_directory(s: &mut Cursive) {
+    s.with_user_data(|state: &mut XvState| state.toggle_favorite_directory());
+    fill_favorites(s);
+}
+
+fn remove_selected_favorite(s: &mut Cursive) {
+    let mut favorite_selector = s.find_id::<SelectView<PathBuf>>("favorite_selector").unwrap();
+    if let Some(id) = favorite_selector.selected_id() {
+        favorite_selector.remove_item(id)(s);
+        s.with_user_data(|state: &mut XvState| {
+            state.remove_favorite_directory(id);
+        });
+    }
+}
+
 fn select_directory(s: &mut Cursive, dir: &OsStr) {
     let mut current_dir = s.find_id::<TextView>("current_dir").unwrap();
     let mut dir_selector = s.find_id::<SelectView<OsString>>("dir_selector").unwrap();
@@ -80,17 +187,45 @@ fn fill_selectors(
  
     match state.list_directory() {
         Ok(list) => {
+            // Entries can vanish while the directory is listed, or be impossible to look at, so
+            // those are skipped and counted, rather than failing the whole listing.
+            let mut skipped = 0;
             for entry in list {
-                let dir_entry = entry.unwrap();
-                let file_type = dir_entry.file_type().unwrap();
-                let label: String = dir_entry.file_name().as_os_str().to_string_lossy().into();
-                if file_type.is_dir() {
+                let entry = entry.and_then(|dir_entry| {
+                    let file_type = dir_entry.file_type()?;
+                    Ok((dir_entry, file_type))
+                });
+                let (dir_entry, file_type) = match entry {
+                    Ok(entry) => entry,
+                    Err(_) => {
+                        skipped += 1;
+                        continue;
+                    }
+                };
+                let mut label: String = dir_entry.file_name().as_os_str().to_string_lossy().into();
+                // Symbolic links are listed with what they point to, and links that point to
+                // nothing are listed with the files.
+                let is_dir = if file_type.is_symlink() {
+                    label.push_str(" [link]");
+                    fs::metadata(dir_entry.path()).map(|m| m.is_dir()).unwrap_or(false)
+                } else {
+                    if !file_type.is_dir() && !file_type.is_file() {
+                        label.push_str(&format!(" [{}]", describe_file_type(&file_type)));
+                    }
+                    file_type.is_dir()
+                };
+                if is_dir {
                     dir_selector.add_item(label, dir_entry.file_name());
-                } else if file_type.is_file() {
+                } else {
                     file_selector.add_item(label, dir_entry.file_name());
                 }
             }
-            current_dir.set_content(state.current_directory().as_os_str().to_string_lossy());
+            let dir_name = state.current_directory().as_os_str().to_string_lossy();
+            match skipped {
+                0 => current_dir.set_content(dir_name),
+                1 => current_dir.set_content(format!("{}  (1 entry could not be read)", dir_name)),
+                _ => current_dir.set_content(format!("{}  ({} entries could not be read)", dir_name, skipped))
+            }
             dir_selector.sort_by_label();
             file_selector.sort_by_label();
             Ok(())
@@ -103,24 +238,220 @@ fn do_open_file(s: &mut Cursive) {
     let file_selector = s.find_id::<SelectView<OsString>>("file_selector").unwrap();
     s.pop_layer();
     if let Some(rc_file) = file_selector.selection() {
-        let file_name = rc_file.as_ref();
-        let current_file = s.call_on_id("hex_view", |view: &mut HexView| {
-            view.get_reader_state()
-        }).unwrap();
-        if let Some(reader_result) = s.with_user_data(|state: &mut XvState| {
-            let path = state.resolve_path(file_name);
-            state.close_reader(current_file);
-            state.open_reader(path)
-        }) {
-            match reader_result {
-                Ok(reader) => s.call_on_id("hex_view", |view: &mut HexView| {
-                    view.switch_reader(reader);
-                }),
-                Err(error) => {
-                    s.show_error(error);
-                    None
-                }
-            };
+        open_path(s, rc_file.as_ref());
+    }
+}
+
+/// Close the current file, and open the given one instead. A relative path is resolved against
+/// the current directory of the open file dialog, and object URLs, like `s3://bucket/key`, are
+/// opened as they are. For Intel HEX and S-record files, the user is
+/// asked whether to show the text, or the image that the records describe.
+pub fn open_path(s: &mut Cursive, file_name: &OsStr) {
+    if file_name.to_str().and_then(ObjectUrl::parse).is_some() {
+        open_resolved_path(s, PathBuf::from(file_name));
+        return;
+    }
+    let path = s.with_user_data(|state: &mut XvState| state.resolve_path(file_name)).unwrap();
+    if is_records_file(&path) {
+        offer_sparse_image(s, path);
+    } else {
+        open_resolved_path(s, path);
+    }
+}
+
+fn is_records_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|extension| RECORDS_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+fn offer_sparse_image(s: &mut Cursive, path: PathBuf) {
+    let text_path = path.clone();
+    let dialog = Dialog::text(
+        "This looks like Intel HEX or S-records. Show the bytes that\n\
+         the records describe, at their addresses, or the text?")
+        .title("Open records")
+        .dismiss_button("Cancel")
+        .button("Text", move |s| {
+            s.pop_layer();
+            open_resolved_path(s, text_path.clone());
+        })
+        .button("Image", move |s| {
+            s.pop_layer();
+            if let Err(error) = open_sparse_image(s, &path) {
+                s.show_error(error);
+            }
+        });
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view);
+}
+
+/// Show the bytes that the records describe, with the addresses of the records in the offsets
+/// column, and the gaps between the records shown as gaps.
+fn open_sparse_image(s: &mut Cursive, path: &Path) -> Result<()> {
+    let records = parse_records(&fs::read_to_string(path)?)?;
+    if records.is_empty() {
+        return Err(XvError::Input(String::from("The file has no data records.")));
+    }
+    let image = SparseImage::new(&records);
+    let (start, gaps) = (image.start(), image.gaps());
+    let name = format!("{} (image)", path.file_name().unwrap_or_default().to_string_lossy());
+    let reader = HexReader::new(TilingByteReader::from_source(image, name)?)?;
+
+    let current_file = s.call_on_id("hex_view", |view: &mut HexView| {
+        view.get_reader_state()
+    }).unwrap();
+    s.with_user_data(|state: &mut XvState| state.close_reader(current_file));
+    s.call_on_id("hex_view", |view: &mut HexView| {
+        view.switch_reader(reader);
+        view.set_base_address(start);
+        view.set_gaps(gaps);
+    });
+    Ok(())
+}
+
+fn open_resolved_path(s: &mut Cursive, path: PathBuf) {
+    let current_file = s.call_on_id("hex_view", |view: &mut HexView| {
+        view.get_reader_state()
+    }).unwrap();
+    if let Some(reader_result) = s.with_user_data(|state: &mut XvState| {
+        state.close_reader(current_file);
+        state.open_reader(path)
+    }) {
+        match reader_result {
+            Ok(reader) => {
+                s.call_on_id("hex_view", |view: &mut HexView| view.switch_reader(reader));
+                watch_spool(s);
+            },
+            Err(error) => s.show_error(error)
+        }
+    }
+}
+
+fn open_new_file_dialog(s: &mut Cursive) {
+    let editors = LinearLayout::vertical()
+        .child(EditView::new().with_id("new_file_name").min_width(30))
+        .child(EditView::new().content("0").with_id("new_file_size").min_width(18))
+        .child(EditView::new().content("00").with_id("new_file_fill").min_width(4));
+
+    let layout = LinearLayout::horizontal()
+        .child(TextView::new("Name:  \nSize:  \nFill byte:  "))
+        .child(editors);
+
+    let dialog = Dialog::around(layout)
+        .title("New file")
+        .dismiss_button("Cancel")
+        .button("Create", do_new_file);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_new_file);
+
+    s.add_layer(event_view);
+}
+
+fn do_new_file(s: &mut Cursive) {
+    let name = s.call_on_id("new_file_name", get_content).unwrap();
+    let size_str = s.call_on_id("new_file_size", get_content).unwrap();
+    let fill_str = s.call_on_id("new_file_fill", get_content).unwrap();
+
+    s.pop_layer();
+
+    let size = match parse_number(&size_str) {
+        Ok(size) => size,
+        Err(error) => return s.show_error(error)
+    };
+    let fill = match parse_hex_bytes(&fill_str) {
+        Ok(ref bytes) if bytes.len() == 1 => bytes[0],
+        Ok(_) => return s.show_error(XvError::Input(
+            String::from("The fill byte must be a single byte, like 00 or FF."))),
+        Err(error) => return s.show_error(error)
+    };
+    let path = match resolve_new_path(s, &name) {
+        Ok(path) => path,
+        Err(error) => return s.show_error(error)
+    };
+    // Existing files are never overwritten.
+    let file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(file) => file,
+        Err(error) => return s.show_error(error)
+    };
+    let create = move |progress: &Progress| {
+        let result = fill_file(file, size, fill, progress);
+        if result.is_err() {
+            // Leave no half filled file behind.
+            let _ = fs::remove_file(&path);
         }
+        result
+    };
+    run_with_progress(s, "Creating the file", size, create, |s, ()| {
+        select_directory(s, &OsString::new());
+    });
+}
+
+/// The path of a new file or directory with the given name, in the current directory.
+fn resolve_new_path(s: &mut Cursive, name: &str) -> Result<PathBuf> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(XvError::Input(String::from("No name was given.")));
+    }
+    Ok(s.with_user_data(|state: &mut XvState| state.resolve_path(OsStr::new(name))).unwrap())
+}
+
+/// Make a new, empty file the given size, with every byte set to the fill byte. Zeros are not
+/// written, as a file that is made longer reads as zeros, and can take no space on disk.
+fn fill_file(mut file: File, size: u64, fill: u8, progress: &Progress) -> Result<()> {
+    if fill == 0 {
+        return Ok(file.set_len(size)?);
+    }
+    let chunk = vec![fill; FILL_CHUNK_SIZE.min(size as usize)];
+    let mut written = 0;
+    while written < size {
+        let len = (size - written).min(chunk.len() as u64) as usize;
+        file.write_all(&chunk[0..len])?;
+        written += len as u64;
+        progress.advance(len as u64)?;
+    }
+    Ok(())
+}
+
+fn open_new_directory_dialog(s: &mut Cursive) {
+    let layout = LinearLayout::horizontal()
+        .child(TextView::new("Name:  "))
+        .child(EditView::new().with_id("new_directory_name").min_width(30));
+
+    let dialog = Dialog::around(layout)
+        .title("New directory")
+        .dismiss_button("Cancel")
+        .button("Create", do_new_directory);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_new_directory);
+
+    s.add_layer(event_view);
+}
+
+fn do_new_directory(s: &mut Cursive) {
+    let name = s.call_on_id("new_directory_name", get_content).unwrap();
+
+    s.pop_layer();
+
+    let path = match resolve_new_path(s, &name) {
+        Ok(path) => path,
+        Err(error) => return s.show_error(error)
+    };
+    if let Err(error) = fs::create_dir(&path) {
+        return s.show_error(error);
     }
+    select_directory(s, &OsString::new());
 }
@@ -0,0 +1,398 @@
+//! A zoomed-out view of the whole file, colored by the kinds of bytes in each part of it.
+
+use cursive::align::HAlign;
+use cursive::Cursive;
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
+use cursive::Printer;
+use cursive::theme::{BaseColor, Color, ColorStyle};
+use cursive::traits::{Boxable, View};
+use cursive::Vec2;
+use xv::block_index::{BlockIndex, BlockSummary, INDEX_BLOCK_SIZE};
+use xv::byte_reader::ByteSource;
+use xv::hex_reader::HIGH_ENTROPY;
+
+use crate::hex_view::HexView;
+use crate::xv_tui::ShowError;
+
+/// The number of bytes that are sampled from each cell, to decide its class.
+const CELL_SAMPLE_SIZE: u64 = 256;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ByteClass {
+    Zero,
+    Low,
+    Printable,
+    High,
+    Full
+}
+
+impl ByteClass {
+    fn of(b: u8) -> ByteClass {
+        match b {
+            0x00 => ByteClass::Zero,
+            0xFF => ByteClass::Full,
+            0x20..=0x7E => ByteClass::Printable,
+            0x01..=0x1F | 0x7F => ByteClass::Low,
+            _ => ByteClass::High
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The class that most of the bytes of the blocks are in, going by what the index says about
+    /// them.
+    fn of_blocks(blocks: &[BlockSummary]) -> ByteClass {
+        let count = blocks.len().max(1) as f32;
+        let share = |part: fn(&BlockSummary) -> f32| blocks.iter().map(part).sum::<f32>() / count;
+        let entropy = f64::from(share(|block| block.entropy));
+        if share(|block| block.zeros) >= 0.5 {
+            ByteClass::Zero
+        } else if share(|block| block.full) >= 0.5 {
+            ByteClass::Full
+        } else if share(|block| block.printable) >= 0.5 {
+            ByteClass::Printable
+        } else if entropy >= HIGH_ENTROPY {
+            ByteClass::High
+        } else {
+            ByteClass::Low
+        }
+    }
+}
+
+const CLASSES: [ByteClass; 5] = [
+    ByteClass::Zero, ByteClass::Low, ByteClass::Printable, ByteClass::High, ByteClass::Full];
+
+/// How the cells, in file order, are placed on the screen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum CellLayout {
+    /// Row by row, like the lines of text in the hex view.
+    Linear,
+    /// Along a Hilbert curve, so bytes that are close in the file are also close on the screen.
+    Hilbert
+}
+
+/// A zoomed-out view of the whole file, where each cell represents a range of bytes, colored by
+/// the most common class of byte values in that range.
+pub struct OverviewView {
+    /// The bytes of the file that is viewed, which are read separately from the hex view, and
+    /// their number.
+    source: Box<ByteSource + Send>,
+    length: u64,
+    /// The block index of the file, which describes all of its bytes, rather than samples.
+    block_index: Option<BlockIndex>,
+    grid_size: Vec2,
+    bytes_per_cell: u64,
+    cells: Vec<ByteClass>,
+    cell_layout: CellLayout,
+    positions: Vec<Vec2>,
+    cell_at: Vec<Option<usize>>,
+    cursor: usize,
+    target_offset: u64
+}
+
+pub fn open_overview(s: &mut Cursive) {
+    let opened = s.call_on_id("hex_view", |v: &mut HexView| {
+        let length = v.get_length();
+        v.open_background_source(0, length)
+            .map(|(source, _)| OverviewView::new(source, length, v.get_offset(), v.get_block_index().cloned()))
+    }).unwrap();
+
+    match opened {
+        Ok(view) => s.add_layer(view.full_screen()),
+        Err(error) => s.show_error(error)
+    }
+}
+
+impl OverviewView {
+    pub fn new(source: Box<ByteSource + Send>, length: u64, offset: u64, block_index: Option<BlockIndex>)
+        -> OverviewView {
+        OverviewView {
+            source,
+            length,
+            block_index,
+            grid_size: Vec2::new(0, 0),
+            bytes_per_cell: 1,
+            cells: Vec::new(),
+            cell_layout: CellLayout::Linear,
+            positions: Vec::new(),
+            cell_at: Vec::new(),
+            cursor: 0,
+            target_offset: offset
+        }
+    }
+
+    fn sample_cells(&mut self) {
+        let length = self.length;
+        let cell_count = (self.grid_size.x * self.grid_size.y).max(1) as u64;
+        self.bytes_per_cell = length.div_ceil(cell_count).max(1);
+        let used_cells = length.div_ceil(self.bytes_per_cell);
+        let mut sample = vec![0; CELL_SAMPLE_SIZE.min(self.bytes_per_cell) as usize];
+
+        self.cells.clear();
+        for i in 0..used_cells {
+            if let Some(index) = self.block_index.as_ref().filter(|_| self.bytes_per_cell >= INDEX_BLOCK_SIZE) {
+                let first = (i * self.bytes_per_cell / INDEX_BLOCK_SIZE) as usize;
+                let end = ((i + 1) * self.bytes_per_cell).div_ceil(INDEX_BLOCK_SIZE) as usize;
+                let blocks = index.blocks();
+                self.cells.push(ByteClass::of_blocks(&blocks[first.min(blocks.len())..end.min(blocks.len())]));
+                continue;
+            }
+            let len = self.source.read_at(i * self.bytes_per_cell, &mut sample).unwrap_or(0);
+            let mut counts = [0; 5];
+            for b in &sample[0..len] {
+                counts[ByteClass::of(*b).index()] += 1;
+            }
+            let mut class = ByteClass::Zero;
+            for c in CLASSES.iter() {
+                if counts[c.index()] > counts[class.index()] {
+                    class = *c;
+                }
+            }
+            self.cells.push(class);
+        }
+        self.place_cells();
+        self.move_cursor_to(self.target_offset);
+    }
+
+    fn place_cells(&mut self) {
+        let (w, h) = (self.grid_size.x, self.grid_size.y);
+        self.positions.clear();
+        self.cell_at = vec![None; w * h];
+        match self.cell_layout {
+            CellLayout::Linear => {
+                for i in 0..self.cells.len() {
+                    self.positions.push(Vec2::new(i % w, i / w));
+                }
+            },
+            CellLayout::Hilbert => {
+                // Walk a square curve big enough to cover the grid, and skip the points outside it.
+                let n = w.max(h).next_power_of_two();
+                let mut d = 0;
+                while self.positions.len() < self.cells.len() && d < n * n {
+                    let (x, y) = hilbert_point(n, d);
+                    if x < w && y < h {
+                        self.positions.push(Vec2::new(x, y));
+                    }
+                    d += 1;
+                }
+            }
+        }
+        for (i, pos) in self.positions.iter().enumerate() {
+            self.cell_at[pos.y * w + pos.x] = Some(i);
+        }
+    }
+
+    fn toggle_layout(&mut self) -> EventResult {
+        self.cell_layout = match self.cell_layout {
+            CellLayout::Linear => CellLayout::Hilbert,
+            CellLayout::Hilbert => CellLayout::Linear
+        };
+        self.place_cells();
+        EventResult::Consumed(None)
+    }
+
+    fn move_cursor_to(&mut self, offset: u64) {
+        let cell = offset / self.bytes_per_cell;
+        self.cursor = (cell as usize).min(self.cells.len().saturating_sub(1));
+    }
+
+    fn move_cursor_to_cell(&mut self, cell: usize) -> EventResult {
+        self.cursor = cell.min(self.cells.len().saturating_sub(1));
+        self.target_offset = self.cursor_offset();
+        EventResult::Consumed(None)
+    }
+
+    /// Move the cursor on the screen, rather than through the file, so the arrow keys behave the
+    /// same regardless of the cell layout.
+    fn move_cursor(&mut self, dx: isize, dy: isize) -> EventResult {
+        if let Some(pos) = self.positions.get(self.cursor) {
+            let max_x = self.grid_size.x as isize - 1;
+            let max_y = self.grid_size.y as isize - 1;
+            let x = (pos.x as isize + dx).max(0).min(max_x) as usize;
+            let y = (pos.y as isize + dy).max(0).min(max_y) as usize;
+            if let Some(cell) = self.cell_at[y * self.grid_size.x + x] {
+                return self.move_cursor_to_cell(cell);
+            }
+        }
+        EventResult::Consumed(None)
+    }
+
+    fn cursor_offset(&self) -> u64 {
+        self.cursor as u64 * self.bytes_per_cell
+    }
+
+    fn open_at_cursor(&self) -> EventResult {
+        let offset = self.cursor_offset();
+        EventResult::with_cb(move |s| {
+            s.pop_layer();
+            s.call_on_id("hex_view", |v: &mut HexView| v.go_to_offset(offset));
+        })
+    }
+
+    fn on_mouse_event(&mut self, offset: Vec2, position: Vec2, event: MouseEvent) -> EventResult {
+        if event != MouseEvent::Press(MouseButton::Left) || position.x <= offset.x ||
+            position.y <= offset.y {
+            return EventResult::Ignored;
+        }
+        let x = position.x - offset.x - 1;
+        let y = position.y - offset.y - 1;
+        if x >= self.grid_size.x || y >= self.grid_size.y {
+            return EventResult::Ignored;
+        }
+        if let Some(cell) = self.cell_at[y * self.grid_size.x + x] {
+            self.cursor = cell;
+            self.open_at_cursor()
+        } else {
+            EventResult::Ignored
+        }
+    }
+
+    fn draw_title(&self, printer: &Printer) {
+        let title = match self.cell_layout {
+            CellLayout::Linear => "Overview",
+            CellLayout::Hilbert => "Overview (Hilbert curve)"
+        };
+        let offset = HAlign::Center.get_offset(title.len(), printer.size.x);
+        printer.with_high_border(false, |p| {
+            p.print((offset - 2, 0), "┤ ");
+            p.print((offset + title.len(), 0), " ├");
+        });
+        printer.with_color(ColorStyle::title_primary(), |p| {
+            p.print((offset, 0), title);
+        });
+    }
+
+    fn draw_cursor_info(&self, printer: &Printer) {
+        let start = self.cursor_offset();
+        let info = format!(" 0x{:X}  ({} bytes per cell) ", start, self.bytes_per_cell);
+        if info.len() + 4 < printer.size.x {
+            printer.with_color(ColorStyle::secondary(), |p| {
+                p.print((2, printer.size.y - 1), &info);
+            });
+        }
+    }
+}
+
+impl View for OverviewView {
+    fn draw(&self, printer: &Printer) {
+        for y in 0..printer.size.y {
+            printer.print_hline((0, y), printer.size.x, " ");
+        }
+        printer.print_box((0, 0), printer.size, true);
+        self.draw_title(printer);
+        self.draw_cursor_info(printer);
+
+        let grid = printer.offset((1, 1));
+        for (i, (class, pos)) in self.cells.iter().zip(self.positions.iter()).enumerate() {
+            let pos = *pos;
+            if i == self.cursor {
+                let style = ColorStyle::new(Color::Light(BaseColor::Yellow), class_to_color(*class));
+                grid.with_color(style, |p| p.print(pos, "◆"));
+            } else {
+                let style = ColorStyle::new(Color::Dark(BaseColor::Black), class_to_color(*class));
+                grid.with_color(style, |p| p.print(pos, " "));
+            }
+        }
+    }
+
+    fn layout(&mut self, constraint: Vec2) {
+        let grid_size = Vec2::new(constraint.x.saturating_sub(2), constraint.y.saturating_sub(2));
+        if grid_size != self.grid_size {
+            self.grid_size = grid_size;
+            self.sample_cells();
+        }
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        let half_height = (self.grid_size.y / 2).max(1) as isize;
+        match event {
+            Event::Key(Key::Esc) => EventResult::with_cb(|s| {
+                s.pop_layer();
+            }),
+            Event::Key(Key::Enter) => self.open_at_cursor(),
+            Event::Char('c') => self.toggle_layout(),
+            Event::Key(Key::Left) | Event::Char('h') => self.move_cursor(-1, 0),
+            Event::Key(Key::Right) | Event::Char('l') => self.move_cursor(1, 0),
+            Event::Key(Key::Up) | Event::Char('k') => self.move_cursor(0, -1),
+            Event::Key(Key::Down) | Event::Char('j') => self.move_cursor(0, 1),
+            Event::Key(Key::PageUp) | Event::Char('K') => self.move_cursor(0, -half_height),
+            Event::Key(Key::PageDown) | Event::Char('J') => self.move_cursor(0, half_height),
+            Event::Key(Key::Home) => self.move_cursor_to_cell(0),
+            Event::Key(Key::End) => self.move_cursor_to_cell(self.cells.len()),
+            Event::Mouse { offset, position, event } => self.on_mouse_event(offset, position, event),
+            _ => EventResult::Ignored
+        }
+    }
+}
+
+fn class_to_color(class: ByteClass) -> Color {
+    match class {
+        ByteClass::Zero => Color::Dark(BaseColor::Black),
+        ByteClass::Low => Color::Dark(BaseColor::Green),
+        ByteClass::Printable => Color::Light(BaseColor::Blue),
+        ByteClass::High => Color::Dark(BaseColor::Red),
+        ByteClass::Full => Color::Light(BaseColor::White)
+    }
+}
+
+/// Map a distance along a Hilbert curve, to a point in the `n` by `n` square that the curve fills.
+/// The `n` must be a power of two.
+fn hilbert_point(n: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hilbert_curve_of_order_two() {
+        let points: Vec<(usize, usize)> = (0..16).map(|d| hilbert_point(4, d)).collect();
+        assert_eq!(points, vec![
+            (0, 0), (1, 0), (1, 1), (0, 1), (0, 2), (0, 3), (1, 3), (1, 2),
+            (2, 2), (2, 3), (3, 3), (3, 2), (3, 1), (2, 1), (2, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn classes_of_indexed_blocks() {
+        let block = |entropy, zeros, printable| BlockSummary { entropy, zeros, full: 0.0, printable };
+        assert_eq!(ByteClass::of_blocks(&[block(0.0, 1.0, 0.0), block(7.9, 0.0, 0.3)]), ByteClass::Zero);
+        assert_eq!(ByteClass::of_blocks(&[block(7.9, 0.0, 0.3), block(7.8, 0.0, 0.4)]), ByteClass::High);
+        assert_eq!(ByteClass::of_blocks(&[block(4.5, 0.0, 0.9)]), ByteClass::Printable);
+        assert_eq!(ByteClass::of_blocks(&[block(3.0, 0.2, 0.2)]), ByteClass::Low);
+    }
+
+    #[test]
+    fn hilbert_curve_steps_to_neighbours() {
+        for d in 1..(64 * 64) {
+            let (x0, y0) = hilbert_point(64, d - 1);
+            let (x1, y1) = hilbert_point(64, d);
+            let distance = (x0 as isize - x1 as isize).abs() + (y0 as isize - y1 as isize).abs();
+            assert_eq!(distance, 1);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! The dialog that lists the packets of a network capture, and selects the one that is chosen.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::Boxable;
+use cursive::views::{Dialog, LinearLayout, OnEventView, ScrollView, SelectView, TextView};
+use xv::error::XvError;
+
+use crate::captures::{describe_time, find_packets, Packet};
+use crate::hex_view::HexView;
+use crate::progress_dialog::run_with_progress;
+use crate::status_bar::update_measure;
+use crate::utilities::height_left;
+use crate::xv_tui::ShowError;
+
+/// The most packets that the packet list shows.
+const MAX_LISTED_PACKETS: usize = 10000;
+
+pub fn open_packets_dialog(s: &mut Cursive) {
+    let (length, source) = s.call_on_id("hex_view", |v: &mut HexView| {
+        let length = v.get_length();
+        (length, v.open_background_source(0, length))
+    }).unwrap();
+    let (mut source, _) = match source {
+        Ok(source) => source,
+        Err(error) => return s.show_error(error)
+    };
+
+    run_with_progress(s, "Finding packets", length, move |progress| {
+        find_packets(&mut *source, progress)
+    }, |s, packets| match packets {
+        Some(packets) => show_packets(s, &packets),
+        None => s.show_error(XvError::Input(String::from("This is not a pcap or pcapng capture.")))
+    });
+}
+
+fn show_packets(s: &mut Cursive, packets: &[Packet]) {
+    let mut packet_selector: SelectView<(u64, u64)> = SelectView::new().on_submit(select_packet);
+    if let Some(first) = packets.first() {
+        for (i, packet) in packets.iter().enumerate().take(MAX_LISTED_PACKETS) {
+            let truncated = if packet.length < packet.original_length { " (truncated)" } else { "" };
+            packet_selector.add_item(
+                format!("{:>6}  {:>14}  {:>6} bytes  at 0x{:X}{}",
+                        i + 1, describe_time(packet, first), packet.length, packet.offset, truncated),
+                (packet.offset, packet.length));
+        }
+    }
+
+    let summary = if packets.len() > MAX_LISTED_PACKETS {
+        format!("{} packets, of which the first {} are listed.", packets.len(), MAX_LISTED_PACKETS)
+    } else {
+        format!("{} packets. The times are in seconds from the first packet.", packets.len())
+    };
+
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(summary))
+        .child(ScrollView::new(packet_selector))
+        .max_height(height_left(s, 11).min(50));
+
+    let dialog = Dialog::around(layout)
+        .title("Packets")
+        .dismiss_button("Close");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+/// Go to the bytes of the packet, and select them.
+fn select_packet(s: &mut Cursive, packet: &(u64, u64)) {
+    let (offset, length) = *packet;
+    s.pop_layer();
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        v.go_to_offset(offset);
+        if length > 0 {
+            v.set_marks((Some(offset), Some(offset + length - 1)));
+        }
+    });
+    update_measure(s);
+}
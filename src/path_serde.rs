@@ -0,0 +1,126 @@
+//! Serialization of paths that are not valid UTF-8.
+//!
+//! Serde can only serialize paths that are valid UTF-8, but file names on Unix can be any bytes.
+//! These functions store paths as strings when they are valid UTF-8, so states and workspaces look
+//! the same as before, and as bytes when they are not. Use them with `#[serde(with = "...")]`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde_derive::{Deserialize, Serialize};
+
+pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    match path.to_str() {
+        Some(text) => serializer.serialize_str(text),
+        None => serializer.serialize_bytes(&path_to_bytes(path))
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    deserializer.deserialize_any(PathVisitor)
+}
+
+/// The same, for lists of paths.
+pub mod vec {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct SerdePath(#[serde(with = "super")] PathBuf);
+
+    pub fn serialize<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(paths.iter().map(|path| SerdePath(path.clone())))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PathBuf>, D::Error> {
+        let paths: Vec<SerdePath> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(paths.into_iter().map(|path| path.0).collect())
+    }
+}
+
+struct PathVisitor;
+
+impl<'de> Visitor<'de> for PathVisitor {
+    type Value = PathBuf;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a path, as a string or as bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<PathBuf, E> {
+        Ok(PathBuf::from(text))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<PathBuf, E> {
+        Ok(path_from_bytes(bytes.to_vec()))
+    }
+
+    /// Formats without a byte type, like JSON, store the bytes as a list of numbers.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PathBuf, A::Error> {
+        let mut bytes = Vec::new();
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(path_from_bytes(bytes))
+    }
+}
+
+/// The bytes of the path, as the system has them where it can tell.
+#[cfg(unix)]
+pub fn path_to_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+// Paths on other systems can only fail to be UTF-8 in ways that bytes cannot tell apart, so the
+// best that can be done is to replace the bad parts.
+#[cfg(not(unix))]
+pub fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    use rmp_serde::Serializer;
+    use serde::ser::Serialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Paths {
+        #[serde(with = "super")]
+        one: PathBuf,
+        #[serde(with = "super::vec")]
+        many: Vec<PathBuf>
+    }
+
+    #[test]
+    fn serializing_paths_that_are_not_utf8() {
+        let odd = PathBuf::from(OsString::from_vec(b"/tmp/caf\xE9.bin".to_vec()));
+        let paths = Paths { one: odd.clone(), many: vec![PathBuf::from("/tmp/plain"), odd] };
+
+        let json = serde_json::to_string(&paths).unwrap();
+        assert!(json.contains("\"/tmp/plain\""));
+        assert_eq!(serde_json::from_str::<Paths>(&json).unwrap(), paths);
+
+        let mut msgpack = Vec::new();
+        paths.serialize(&mut Serializer::new(&mut msgpack)).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Paths>(&msgpack).unwrap(), paths);
+    }
+}
@@ -0,0 +1,93 @@
+//! Peeking at the bytes at another offset or address in a popup, without moving the view.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
+use xv::error::{Result, XvError};
+use xv::hex_dump::HexDump;
+
+use crate::hex_view::HexView;
+use crate::utilities::{get_content, parse_address, parse_number};
+use crate::xv_tui::ShowError;
+
+/// The number of bytes that are peeked at, unless another number is given.
+pub const DEFAULT_PEEK_LENGTH: u64 = 64;
+
+/// The most bytes that are peeked at, which still fit in a popup.
+const MAX_PEEK_LENGTH: u64 = 1024;
+
+/// Ask for an offset and a number of bytes to peek at.
+pub fn open_peek_dialog(s: &mut Cursive) {
+    let layout = LinearLayout::vertical()
+        .child(TextView::new("Offset:"))
+        .child(EditView::new().with_id("peek_offset").min_width(20))
+        .child(TextView::new("Bytes:"))
+        .child(EditView::new().content(format!("{}", DEFAULT_PEEK_LENGTH)).with_id("peek_length").min_width(20));
+
+    let dialog = Dialog::around(layout)
+        .dismiss_button("Cancel")
+        .button("Peek", do_peek)
+        .title("Peek");
+
+    let esc_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_peek);
+
+    s.add_layer(esc_view);
+}
+
+fn do_peek(s: &mut Cursive) {
+    let offset_str = s.call_on_id("peek_offset", get_content).unwrap();
+    let length_str = s.call_on_id("peek_length", get_content).unwrap();
+
+    s.pop_layer();
+
+    let length = match parse_number(&length_str) {
+        Ok(length) => length,
+        Err(error) => return s.show_error(error)
+    };
+    if let Err(error) = peek(s, &offset_str, length) {
+        s.show_error(error);
+    }
+}
+
+/// Show the bytes at an offset or an address in a popup, without moving the window, like what
+/// a pointer points to. The popup can go to them, too.
+pub fn peek(s: &mut Cursive, offset_str: &str, length: u64) -> Result<()> {
+    if length == 0 || length > MAX_PEEK_LENGTH {
+        return Err(XvError::Input(format!("Peek at 1 to {} bytes.", MAX_PEEK_LENGTH)));
+    }
+    let (offset, address, bytes, group) = s.call_on_id("hex_view", |v: &mut HexView| -> Result<_> {
+        let offset = parse_address(offset_str, v.get_length(), v.get_address_map())?;
+        let bytes = v.read_bytes(offset, length as usize)?;
+        Ok((offset, v.get_address_map().address_of(offset), bytes, v.get_group()))
+    }).unwrap()?;
+    if bytes.is_empty() {
+        return Err(XvError::Input(format!("0x{:X} is past the end of the file.", address)));
+    }
+
+    let mut dump = Vec::new();
+    HexDump::new(bytes.as_slice())
+        .start_offset(address)
+        .group(usize::from(group))
+        .render_to(&mut dump)?;
+
+    let dialog = Dialog::around(TextView::new(String::from_utf8_lossy(&dump).trim_end()))
+        .title(format!("{} bytes at 0x{:X}", bytes.len(), address))
+        .button("Go to", move |s| {
+            s.pop_layer();
+            s.call_on_id("hex_view", |v: &mut HexView| v.go_to_offset(offset));
+        })
+        .dismiss_button("Ok");
+
+    let esc_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(esc_view);
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! The dialog that lists the points of interest, and chooses which sources `{` and `}` go to.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Checkbox, Dialog, LinearLayout, OnEventView, ScrollView, SelectView, TextView};
+
+use crate::hex_view::HexView;
+use crate::points_of_interest::{Source, SOURCES};
+use crate::utilities::height_left;
+
+pub fn open_points_dialog(s: &mut Cursive) {
+    let sources = s.call_on_id("hex_view", |v: &mut HexView| v.get_point_sources().to_vec()).unwrap();
+
+    let mut source_toggles = LinearLayout::horizontal();
+    for source in SOURCES.iter() {
+        let source = *source;
+        source_toggles.add_child(Checkbox::new()
+            .with_checked(sources.contains(&source))
+            .on_change(move |s, checked| toggle_source(s, source, checked)));
+        source_toggles.add_child(TextView::new(format!(" {}   ", source.name())));
+    }
+
+    let point_selector = SelectView::<u64>::new()
+        .on_submit(|s, offset: &u64| {
+            let offset = *offset;
+            s.pop_layer();
+            s.call_on_id("hex_view", |v: &mut HexView| v.go_to_offset(offset));
+        });
+
+    let layout = LinearLayout::vertical()
+        .child(source_toggles)
+        .child(TextView::new(""))
+        .child(ScrollView::new(point_selector.with_id("point_list"))
+            .max_height(height_left(s, 13).min(30)));
+
+    let dialog = Dialog::around(layout)
+        .title("Points of interest")
+        .dismiss_button("Close");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view);
+    update_point_list(s);
+}
+
+fn toggle_source(s: &mut Cursive, source: Source, checked: bool) {
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        let mut sources: Vec<Source> = v.get_point_sources().iter().cloned().filter(|s| *s != source).collect();
+        if checked {
+            sources.push(source);
+        }
+        v.set_point_sources(sources);
+    });
+    update_point_list(s);
+}
+
+/// List the points of interest from the chosen sources.
+fn update_point_list(s: &mut Cursive) {
+    let items: Vec<(String, u64)> = s.call_on_id("hex_view", |v: &mut HexView| {
+        let sources = v.get_point_sources();
+        let address_map = v.get_address_map();
+        v.get_points_of_interest().iter()
+            .filter(|point| sources.contains(&point.source))
+            .map(|point| (format!("0x{:08X}  {:<12}  {}", address_map.address_of(point.offset), point.source.name(),
+                                  point.label), point.offset))
+            .collect()
+    }).unwrap();
+    s.call_on_id("point_list", |v: &mut SelectView<u64>| {
+        v.clear();
+        v.add_all(items);
+    });
+}
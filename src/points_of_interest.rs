@@ -0,0 +1,66 @@
+//! The points of interest of a file, gathered from everything that xv knows about it, so that
+//! they can be gone through in order, whatever found them.
+
+/// What found a point of interest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    Mark,
+    SearchMatch,
+    Annotation,
+    Symbol,
+    Segment,
+    /// A magic number at the start of a sector, that the block index found.
+    Magic
+}
+
+pub const SOURCES: [Source; 6] = [Source::Mark, Source::SearchMatch, Source::Annotation, Source::Symbol,
+                                  Source::Segment, Source::Magic];
+
+impl Source {
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Mark => "Marks",
+            Source::SearchMatch => "Search match",
+            Source::Annotation => "Annotations",
+            Source::Symbol => "Symbols",
+            Source::Segment => "Segments",
+            Source::Magic => "Magic numbers"
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointOfInterest {
+    pub offset: u64,
+    pub source: Source,
+    pub label: String
+}
+
+/// The first point after the offset, or the last point before it when going backwards, of the
+/// points from the given sources. The points must be sorted by offset.
+pub fn next_point<'a>(points: &'a [PointOfInterest], from: u64, backwards: bool, sources: &[Source])
+    -> Option<&'a PointOfInterest> {
+    let mut shown = points.iter().filter(|point| sources.contains(&point.source));
+    if backwards {
+        shown.rev().find(|point| point.offset < from)
+    } else {
+        shown.find(|point| point.offset > from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finding_the_next_point() {
+        let point = |offset, source| PointOfInterest { offset, source, label: String::new() };
+        let points = vec![point(0x10, Source::Symbol), point(0x20, Source::Mark), point(0x30, Source::Symbol)];
+        let offset_of = |point: Option<&PointOfInterest>| point.map(|point| point.offset);
+        assert_eq!(offset_of(next_point(&points, 0x10, false, &SOURCES)), Some(0x20));
+        assert_eq!(offset_of(next_point(&points, 0x10, false, &[Source::Symbol])), Some(0x30));
+        assert_eq!(offset_of(next_point(&points, 0x30, true, &SOURCES)), Some(0x20));
+        assert_eq!(offset_of(next_point(&points, 0x10, true, &SOURCES)), None);
+        assert_eq!(offset_of(next_point(&points, 0x30, false, &SOURCES)), None);
+    }
+}
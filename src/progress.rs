@@ -0,0 +1,132 @@
+//! Tracking how far long running operations have come, and cancelling them from another thread.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, XvError};
+
+/// The progress of an operation that goes through many bytes, such as checksumming or searching a
+/// file.
+///
+/// The operation runs on one thread and reports how many bytes it has processed, while another
+/// thread shows the progress, and can ask the operation to stop. Clones share the same progress.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    state: Arc<ProgressState>
+}
+
+#[derive(Debug)]
+struct ProgressState {
+    total: u64,
+    done: AtomicU64,
+    cancelled: AtomicBool,
+    finished: AtomicBool,
+    started: Instant
+}
+
+impl Progress {
+    /// Start tracking an operation that processes `total` bytes.
+    pub fn new(total: u64) -> Progress {
+        Progress {
+            state: Arc::new(ProgressState {
+                total,
+                done: AtomicU64::new(0),
+                cancelled: AtomicBool::new(false),
+                finished: AtomicBool::new(false),
+                started: Instant::now()
+            })
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.state.total
+    }
+
+    /// The number of bytes processed so far. This never goes above the total.
+    pub fn done(&self) -> u64 {
+        self.state.done.load(Ordering::Relaxed).min(self.state.total)
+    }
+
+    /// Report that more bytes have been processed, and check whether the operation should stop.
+    /// Returns `XvError::Cancelled` if it should, so this can be used with `?` between chunks of
+    /// work.
+    pub fn advance(&self, bytes: u64) -> Result<()> {
+        self.state.done.fetch_add(bytes, Ordering::Relaxed);
+        if self.is_cancelled() {
+            return Err(XvError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Ask the operation to stop.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Report that the operation is over, whether it succeeded or not.
+    pub fn finish(&self) {
+        self.state.finished.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::SeqCst)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.state.started.elapsed()
+    }
+
+    /// The number of bytes processed per second, on average since the operation started.
+    pub fn throughput(&self) -> f64 {
+        let seconds = duration_seconds(self.elapsed());
+        if seconds > 0.0 { self.done() as f64 / seconds } else { 0.0 }
+    }
+
+    /// How long the rest of the operation will take, if it keeps the same pace. This is `None`
+    /// until some bytes have been processed.
+    pub fn remaining_time(&self) -> Option<Duration> {
+        let throughput = self.throughput();
+        if throughput <= 0.0 {
+            return None;
+        }
+        let seconds = (self.total() - self.done()) as f64 / throughput;
+        Some(Duration::from_millis((seconds * 1000.0) as u64))
+    }
+}
+
+fn duration_seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn tracking_progress() {
+        let progress = Progress::new(100);
+        let shown = progress.clone();
+        assert_eq!(shown.remaining_time(), None);
+        progress.advance(40).unwrap();
+        progress.advance(80).unwrap();
+        thread::sleep(Duration::from_millis(2));
+        assert_eq!(shown.done(), 100);
+        assert_eq!(shown.remaining_time(), Some(Duration::from_millis(0)));
+
+        shown.cancel();
+        match progress.advance(1) {
+            Err(XvError::Cancelled) => {},
+            other => panic!("Expected the operation to be cancelled, but got {:?}.", other)
+        }
+        assert!(!shown.is_finished());
+        progress.finish();
+        assert!(shown.is_finished());
+    }
+}
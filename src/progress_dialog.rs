@@ -0,0 +1,134 @@
+//! Running work in the background, with a dialog that shows its progress and can cancel it.
+
+use std::thread;
+use std::time::Duration;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, OnEventView, TextView};
+use xv::error::{Result, XvError};
+use xv::progress::Progress;
+
+use crate::utilities::describe_size;
+use crate::xv_tui::ShowError;
+
+/// How often the progress is shown. Operations that are done before this have no progress dialog.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Run the work on another thread, and show how far it has come in a dialog, with a button to
+/// cancel it. The work reports its progress through the `Progress` it is given, of `total` bytes.
+/// When the work is done, the dialog is closed, and `on_done` is called with the result. Errors
+/// are shown instead, unless the work was cancelled.
+pub fn run_with_progress<T, W, D>(s: &mut Cursive, title: &str, total: u64, work: W, on_done: D)
+    where T: Send + 'static,
+          W: FnOnce(&Progress) -> Result<T> + Send + 'static,
+          D: FnOnce(&mut Cursive, T) + Send + 'static {
+    let progress = Progress::new(total);
+    let cb_sink = s.cb_sink().clone();
+    let title = String::from(title);
+
+    let reporter_progress = progress.clone();
+    let reporter_sink = cb_sink.clone();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(REFRESH_INTERVAL);
+            if reporter_progress.is_finished() {
+                break;
+            }
+            let progress = reporter_progress.clone();
+            let title = title.clone();
+            let sent = reporter_sink.send(Box::new(move |s: &mut Cursive| {
+                show_progress(s, &title, progress)
+            }));
+            if sent.is_err() {
+                break;
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        let result = work(&progress);
+        // The dialog is only opened while the work is not finished, and the callbacks are run in
+        // the order they are sent, so the dialog is always opened before this closes it.
+        progress.finish();
+        let _ = cb_sink.send(Box::new(move |s: &mut Cursive| {
+            if s.find_id::<TextView>("progress_text").is_some() {
+                s.pop_layer();
+            }
+            match result {
+                Ok(value) => on_done(s, value),
+                Err(XvError::Cancelled) => {},
+                Err(error) => s.show_error(error)
+            }
+        }));
+    });
+}
+
+fn show_progress(s: &mut Cursive, title: &str, progress: Progress) {
+    if progress.is_finished() {
+        return;
+    }
+    let text = if progress.is_cancelled() {
+        String::from("Cancelling...")
+    } else {
+        describe_progress(progress.done(), progress.total(), progress.throughput(),
+                          progress.remaining_time())
+    };
+    if s.call_on_id("progress_text", |v: &mut TextView| v.set_content(text.clone())).is_some() {
+        return;
+    }
+
+    let cancel_progress = progress.clone();
+    let dialog = Dialog::around(TextView::new(text).with_id("progress_text").min_width(36))
+        .title(title)
+        .button("Cancel", move |s| cancel(s, &cancel_progress));
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, move |s| cancel(s, &progress));
+
+    s.add_layer(event_view);
+}
+
+fn cancel(s: &mut Cursive, progress: &Progress) {
+    progress.cancel();
+    s.call_on_id("progress_text", |v: &mut TextView| v.set_content("Cancelling..."));
+}
+
+fn describe_progress(done: u64, total: u64, throughput: f64, remaining: Option<Duration>) -> String {
+    let percentage = if total > 0 { done as f64 * 100.0 / total as f64 } else { 100.0 };
+    let remaining = match remaining {
+        Some(remaining) => describe_duration(remaining),
+        None => String::from("unknown")
+    };
+    format!("{} of {} ({:.0}%)\n{}/s, {} left",
+            describe_size(done as f64), describe_size(total as f64), percentage,
+            describe_size(throughput), remaining)
+}
+
+fn describe_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds < 60 {
+        format!("{} s", seconds)
+    } else if seconds < 3600 {
+        format!("{} min {} s", seconds / 60, seconds % 60)
+    } else {
+        format!("{} h {} min", seconds / 3600, seconds % 3600 / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describing_progress() {
+        assert_eq!(describe_progress(512, 2048, 100.0, None),
+                   "512 bytes of 2.0 KiB (25%)\n100 bytes/s, unknown left");
+        let mib = 1024 * 1024;
+        assert_eq!(describe_progress(3 * mib, 12 * mib, 1.5 * mib as f64, Some(Duration::from_secs(6))),
+                   "3.0 MiB of 12.0 MiB (25%)\n1.5 MiB/s, 6 s left");
+        assert_eq!(describe_duration(Duration::from_secs(125)), "2 min 5 s");
+        assert_eq!(describe_duration(Duration::from_secs(7300)), "2 h 1 min");
+    }
+}
@@ -0,0 +1,75 @@
+//! The dialog that sets the record length and the field guides, for files of fixed size records.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
+
+use crate::hex_view::HexView;
+use crate::utilities::{get_content, parse_number};
+
+pub fn open_record_dialog(s: &mut Cursive) {
+    let (line_width, guides) = s.call_on_id("hex_view", |v: &mut HexView| {
+        let (_, guides) = v.get_record_mode();
+        let guides: Vec<String> = guides.iter().map(|g| format!("{}", g)).collect();
+        (v.get_line_width(), guides.join(", "))
+    }).unwrap();
+
+    let editors = LinearLayout::vertical()
+        .child(EditView::new()
+            .content(format!("{}", line_width))
+            .with_id("record_length")
+            .min_width(8))
+        .child(EditView::new()
+            .content(guides)
+            .with_id("field_guides")
+            .min_width(30));
+
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Record length:  \nFields start at:  "))
+            .child(editors))
+        .child(TextView::new("\nList the offsets within the record where\nfields start, such as \"4, 8, 20\"."));
+
+    let dialog = Dialog::around(layout)
+        .title("Records")
+        .dismiss_button("Cancel")
+        .button("Off", do_records_off)
+        .button("Ok", do_records_on);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_records_on);
+
+    s.add_layer(event_view)
+}
+
+fn do_records_on(s: &mut Cursive) {
+    let record_length = s.call_on_id("record_length", get_content).unwrap();
+    let field_guides = s.call_on_id("field_guides", get_content).unwrap();
+
+    s.pop_layer();
+
+    let record_length = match parse_number(&record_length) {
+        Ok(length) if length > 0 => length,
+        _ => return
+    };
+    let mut guides: Vec<u64> = field_guides.split(',')
+        .filter_map(|g| parse_number(g.trim()).ok())
+        .filter(|g| *g > 0 && *g < record_length)
+        .collect();
+    guides.sort();
+    guides.dedup();
+
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        v.set_line_width(record_length);
+        v.set_record_mode(true, guides);
+    });
+}
+
+fn do_records_off(s: &mut Cursive) {
+    s.pop_layer();
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_record_mode(false, Vec::new()));
+}
@@ -0,0 +1,273 @@
+//! Scripts written in Rhai, that walk the bytes of the file and annotate, select or go to what
+//! they find, like the records of a format that no inspector knows. Scripts run in the background,
+//! and can be bound to the function keys.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::views::Dialog;
+use rhai::{Blob, Dynamic, Engine, EvalAltResult, INT};
+use xv::byte_reader::ByteSource;
+use xv::error::{Result, XvError};
+use xv::progress::Progress;
+
+use crate::annotations::Annotation;
+use crate::hex_view::HexView;
+use crate::progress_dialog::run_with_progress;
+use crate::status_bar::update_measure;
+use crate::xv_state::XvState;
+use crate::xv_tui::ShowError;
+
+/// The keys that scripts can be bound to, which are the function keys that xv does not use.
+pub const SCRIPT_KEYS: [(&str, Key); 10] = [
+    ("F2", Key::F2), ("F3", Key::F3), ("F4", Key::F4), ("F5", Key::F5), ("F6", Key::F6),
+    ("F7", Key::F7), ("F8", Key::F8), ("F9", Key::F9), ("F10", Key::F10), ("F12", Key::F12)];
+
+/// The most bytes that one call to `read_bytes` returns, so a mistake in a script does not use up
+/// the memory.
+const MAX_SCRIPT_READ: u64 = 16 * 1024 * 1024;
+
+/// The errors of the functions that scripts call, which end the script.
+type ScriptResult<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+/// What a script can see of the hex view.
+#[derive(Clone, Copy, Debug)]
+pub struct ScriptContext {
+    pub length: u64,
+    pub position: u64,
+    pub selection: Option<(u64, u64)>
+}
+
+/// What a script asked the hex view to do, which is done once the script is over.
+#[derive(Debug, Default, PartialEq)]
+pub struct ScriptOutcome {
+    pub position: Option<u64>,
+    pub selection: Option<(u64, u64)>,
+    pub annotations: Vec<Annotation>,
+    /// What the script printed.
+    pub output: String
+}
+
+/// Run a script over the bytes of the source. These functions are given to the script:
+///
+/// * `length()`, `position()`, `selection_start()` and `selection_end()`, which tell what the hex
+///   view shows. The selection functions return `()` when nothing is selected.
+/// * `read_u8(offset)`, `read_le(offset, size)` and `read_be(offset, size)`, which read unsigned
+///   numbers of 1 to 8 bytes, and `read_bytes(offset, length)`, which reads a blob.
+/// * `go_to(offset)`, `select(start, end)` and `annotate(offset, length, label)`.
+pub fn run_script(script: &str, source: Box<ByteSource + Send>, context: ScriptContext, progress: &Progress)
+    -> Result<ScriptOutcome> {
+    let source = Rc::new(RefCell::new(source));
+    let outcome = Rc::new(RefCell::new(ScriptOutcome::default()));
+    let mut engine = Engine::new();
+
+    let cancelled = progress.clone();
+    engine.on_progress(move |_| if cancelled.is_cancelled() { Some(().into()) } else { None });
+    let printed = outcome.clone();
+    engine.on_print(move |text| {
+        let output = &mut printed.borrow_mut().output;
+        output.push_str(text);
+        output.push('\n');
+    });
+
+    engine.register_fn("length", move || context.length as INT);
+    engine.register_fn("position", move || context.position as INT);
+    engine.register_fn("selection_start", move || {
+        context.selection.map_or(Dynamic::UNIT, |(start, _)| Dynamic::from(start as INT))
+    });
+    engine.register_fn("selection_end", move || {
+        context.selection.map_or(Dynamic::UNIT, |(_, end)| Dynamic::from(end as INT))
+    });
+
+    let reader = Reader { source: source.clone(), progress: progress.clone() };
+    engine.register_fn("read_u8", move |offset: INT| reader.read_number(offset, 1, false));
+    let reader = Reader { source: source.clone(), progress: progress.clone() };
+    engine.register_fn("read_le", move |offset: INT, size: INT| reader.read_number(offset, size, false));
+    let reader = Reader { source: source.clone(), progress: progress.clone() };
+    engine.register_fn("read_be", move |offset: INT, size: INT| reader.read_number(offset, size, true));
+    let reader = Reader { source, progress: progress.clone() };
+    engine.register_fn("read_bytes", move |offset: INT, length: INT| -> ScriptResult<Blob> {
+        let length = to_offset(length)?.min(MAX_SCRIPT_READ) as usize;
+        let mut bytes = vec![0; length];
+        let count = reader.read(to_offset(offset)?, &mut bytes)?;
+        bytes.truncate(count);
+        Ok(bytes)
+    });
+
+    let moved = outcome.clone();
+    engine.register_fn("go_to", move |offset: INT| -> ScriptResult<()> {
+        moved.borrow_mut().position = Some(to_offset(offset)?);
+        Ok(())
+    });
+    let selected = outcome.clone();
+    engine.register_fn("select", move |start: INT, end: INT| -> ScriptResult<()> {
+        selected.borrow_mut().selection = Some((to_offset(start)?, to_offset(end)?));
+        Ok(())
+    });
+    let annotated = outcome.clone();
+    engine.register_fn("annotate", move |offset: INT, length: INT, label: &str| -> ScriptResult<()> {
+        let (offset, length) = (to_offset(offset)?, to_offset(length)?);
+        let annotation = Annotation { offset, length, label: label.to_string(), color: None };
+        annotated.borrow_mut().annotations.push(annotation);
+        Ok(())
+    });
+
+    match engine.run(script) {
+        Ok(()) => {},
+        Err(error) => return Err(match *error {
+            EvalAltResult::ErrorTerminated(..) => XvError::Cancelled,
+            error => XvError::Input(format!("The script failed: {}", error))
+        })
+    }
+    drop(engine);
+    let outcome = Rc::try_unwrap(outcome).map_err(|_| XvError::Input(String::from("The script is still running.")))?;
+    let mut outcome = outcome.into_inner();
+    outcome.annotations.sort_by_key(|annotation| annotation.offset);
+    Ok(outcome)
+}
+
+/// Reads the source for the functions of a script.
+struct Reader {
+    source: Rc<RefCell<Box<ByteSource + Send>>>,
+    progress: Progress
+}
+
+impl Reader {
+    fn read(&self, offset: u64, buf: &mut [u8]) -> ScriptResult<usize> {
+        let count = self.source.borrow_mut().read_at(offset, buf).map_err(|error| error.to_string())?;
+        self.progress.advance(count as u64).map_err(|error| error.to_string())?;
+        Ok(count)
+    }
+
+    fn read_number(&self, offset: INT, size: INT, big_endian: bool) -> ScriptResult<INT> {
+        if !(1..=8).contains(&size) {
+            return Err(format!("Numbers are 1 to 8 bytes long, not {}.", size).into());
+        }
+        let offset = to_offset(offset)?;
+        let mut bytes = [0; 8];
+        let bytes = &mut bytes[..size as usize];
+        if self.read(offset, bytes)? < bytes.len() {
+            return Err(format!("There are not {} bytes at offset {}.", size, offset).into());
+        }
+        if !big_endian {
+            bytes.reverse();
+        }
+        Ok(bytes.iter().fold(0u64, |number, &byte| number << 8 | u64::from(byte)) as INT)
+    }
+}
+
+fn to_offset(value: INT) -> ScriptResult<u64> {
+    if value < 0 {
+        return Err(format!("{} is negative, and cannot be an offset or a length.", value).into());
+    }
+    Ok(value as u64)
+}
+
+/// Run the script in the file, over the whole of the file that is viewed.
+pub fn run_script_file(s: &mut Cursive, path: &Path) -> Result<()> {
+    let script = fs::read_to_string(path)?;
+    let (context, source) = s.call_on_id("hex_view", |v: &mut HexView| {
+        let length = v.get_length();
+        let context = ScriptContext { length, position: v.get_offset(), selection: v.get_selection() };
+        (context, v.open_background_source(0, length))
+    }).unwrap();
+    let (source, _) = source?;
+    let work = move |progress: &Progress| run_script(&script, source, context, progress);
+    run_with_progress(s, "Running script", context.length, work, show_outcome);
+    Ok(())
+}
+
+/// Run the script that is bound to the key.
+pub fn run_bound_script(s: &mut Cursive, key: &str) {
+    let path = s.with_user_data(|state: &mut XvState| state.script_binding(key).map(Path::to_path_buf)).unwrap();
+    let result = match path {
+        Some(path) => run_script_file(s, &path),
+        None => Err(XvError::Input(format!("No script is bound to {}. Bind one with the bind-script command.", key)))
+    };
+    if let Err(error) = result {
+        s.show_error(error);
+    }
+}
+
+fn show_outcome(s: &mut Cursive, outcome: ScriptOutcome) {
+    let ScriptOutcome { position, selection, annotations: added, output } = outcome;
+    let added_count = added.len();
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        if !added.is_empty() {
+            let mut annotations = v.get_annotations().to_vec();
+            annotations.extend(added);
+            annotations.sort_by_key(|annotation| annotation.offset);
+            v.set_annotations(annotations);
+        }
+        if let Some((start, end)) = selection {
+            v.set_marks((Some(start), Some(end)));
+        }
+        if let Some(position) = position {
+            v.go_to_offset(position);
+        }
+    });
+    update_measure(s);
+
+    let mut message = output;
+    if added_count > 0 {
+        message.push_str(&format!("The script added {} annotations.", added_count));
+    }
+    if !message.is_empty() {
+        s.add_layer(Dialog::info(message.trim_end()).title("Script"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(script: &str, bytes: &[u8]) -> Result<ScriptOutcome> {
+        let context = ScriptContext { length: bytes.len() as u64, position: 0, selection: Some((1, 3)) };
+        run_script(script, Box::new(bytes.to_vec()), context, &Progress::new(bytes.len() as u64))
+    }
+
+    #[test]
+    fn walking_records_with_a_script() {
+        // Records of a tag byte, a big endian length of two bytes, and the value.
+        let bytes = [1, 0, 2, 0xAA, 0xBB, 2, 0, 1, 0xCC, 3, 0, 0];
+        let script = r#"
+            let offset = 0;
+            while offset < length() {
+                let size = read_be(offset + 1, 2);
+                annotate(offset, 3 + size, "tag " + read_u8(offset));
+                offset += 3 + size;
+            }
+            print(read_bytes(3, 2).len());
+            select(selection_start(), selection_end() + 1);
+            go_to(offset);
+        "#;
+        let outcome = run(script, &bytes).unwrap();
+        let labels: Vec<(u64, u64, &str)> = outcome.annotations.iter()
+            .map(|annotation| (annotation.offset, annotation.length, annotation.label.as_str()))
+            .collect();
+        assert_eq!(labels, vec![(0, 5, "tag 1"), (5, 4, "tag 2"), (9, 3, "tag 3")]);
+        assert_eq!(outcome.output, "2\n");
+        assert_eq!(outcome.selection, Some((1, 4)));
+        assert_eq!(outcome.position, Some(12));
+        assert_eq!(run("read_le(0, 4)", &bytes).unwrap(), ScriptOutcome::default());
+    }
+
+    #[test]
+    fn reporting_the_errors_of_scripts() {
+        assert!(run("read_le(10, 4)", &[0; 12]).is_err());
+        assert!(run("read_be(0, 9)", &[0; 12]).is_err());
+        assert!(run("go_to(-1)", &[0; 12]).is_err());
+        assert!(run("let x = ;", &[0; 12]).is_err());
+        let progress = Progress::new(1);
+        progress.cancel();
+        let context = ScriptContext { length: 1, position: 0, selection: None };
+        match run_script("loop {}", Box::new(vec![0]), context, &progress) {
+            Err(XvError::Cancelled) => {},
+            result => panic!("Expected the script to be cancelled, not {:?}", result)
+        }
+    }
+}
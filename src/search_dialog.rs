@@ -0,0 +1,145 @@
+//! The search dialog, with a history of searches, and searching again for the next match.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Checkbox, Dialog, EditView, LinearLayout, OnEventView, TextView};
+use xv::hex_reader::{find_pattern, SearchOptions};
+use xv::progress::Progress;
+
+use crate::hex_view::HexView;
+use crate::progress_dialog::run_with_progress;
+use crate::utilities::{get_content, parse_hex_bytes};
+use crate::xv_state::{SearchQuery, SearchSettings, XvState};
+use crate::xv_tui::ShowError;
+
+pub fn open_search_dialog(s: &mut Cursive) {
+    let settings = s.with_user_data(|state: &mut XvState| state.search_settings().clone()).unwrap();
+
+    // The position in the search history, that the up and down keys move through.
+    let history_pos: Rc<Cell<Option<usize>>> = Rc::new(Cell::new(None));
+    let older = history_pos.clone();
+    let newer = history_pos;
+    let pattern_edit = OnEventView::new(EditView::new().with_id("search_pattern"))
+        .on_pre_event(Key::Up, move |s| recall_search(s, &older, true))
+        .on_pre_event(Key::Down, move |s| recall_search(s, &newer, false));
+
+    let editors = LinearLayout::vertical()
+        .child(pattern_edit.min_width(40))
+        .child(checkbox(settings.hex).with_id("search_hex"))
+        .child(checkbox(settings.options.ignore_case).with_id("search_ignore_case"))
+        .child(checkbox(settings.options.backwards).with_id("search_backwards"))
+        .child(checkbox(settings.options.wrap).with_id("search_wrap"));
+
+    let layout = LinearLayout::horizontal()
+        .child(TextView::new("Search for:  \nHex bytes:  \nIgnore case:  \nBackwards:  \nWrap around:  "))
+        .child(editors);
+
+    let dialog = Dialog::around(layout)
+        .title("Search")
+        .dismiss_button("Cancel")
+        .button("Search", do_search);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_search);
+
+    s.add_layer(event_view)
+}
+
+/// Fill in an older search from the history, or a newer one.
+fn recall_search(s: &mut Cursive, history_pos: &Cell<Option<usize>>, older: bool) {
+    let history = s.with_user_data(|state: &mut XvState| state.search_history().to_vec()).unwrap();
+    let pos = match (history_pos.get(), older) {
+        (None, true) if !history.is_empty() => Some(0),
+        (Some(pos), true) => Some((pos + 1).min(history.len() - 1)),
+        (Some(pos), false) => pos.checked_sub(1),
+        (pos, _) => pos
+    };
+    history_pos.set(pos);
+    let query = match pos {
+        Some(pos) => history[pos].clone(),
+        None => SearchQuery { pattern: String::new(), hex: is_checked(s, "search_hex") }
+    };
+    let hex = query.hex;
+    s.call_on_id("search_pattern", |v: &mut EditView| v.set_content(query.pattern));
+    s.call_on_id("search_hex", |c: &mut Checkbox| c.set_checked(hex));
+}
+
+fn checkbox(checked: bool) -> Checkbox {
+    let mut checkbox = Checkbox::new();
+    checkbox.set_checked(checked);
+    checkbox
+}
+
+fn is_checked(s: &mut Cursive, id: &str) -> bool {
+    s.call_on_id(id, |c: &mut Checkbox| c.is_checked()).unwrap()
+}
+
+fn do_search(s: &mut Cursive) {
+    let pattern_str = s.call_on_id("search_pattern", get_content).unwrap();
+    let settings = SearchSettings {
+        hex: is_checked(s, "search_hex"),
+        options: SearchOptions {
+            backwards: is_checked(s, "search_backwards"),
+            wrap: is_checked(s, "search_wrap"),
+            ignore_case: is_checked(s, "search_ignore_case")
+        }
+    };
+
+    s.pop_layer();
+
+    let pattern = if settings.hex {
+        match parse_hex_bytes(&pattern_str) {
+            Ok(bytes) => bytes,
+            Err(error) => return s.show_error(error)
+        }
+    } else {
+        pattern_str.as_bytes().to_vec()
+    };
+    if pattern.is_empty() {
+        return;
+    }
+    let options = settings.options;
+    let query = SearchQuery { pattern: pattern_str.to_string(), hex: settings.hex };
+    s.with_user_data(|state: &mut XvState| {
+        state.set_search_settings(settings);
+        state.add_search_to_history(query);
+    });
+
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_search(pattern, options));
+    search_again(s, false);
+}
+
+/// Search for the last pattern in the background, in the opposite direction if `reverse` is true,
+/// and go to the match.
+pub fn search_again(s: &mut Cursive, reverse: bool) {
+    let (search, source, length, index) = s.call_on_id("hex_view", |v: &mut HexView| {
+        let length = v.get_length();
+        (v.next_search(reverse), v.open_background_source(0, length), length, v.get_search_index())
+    }).unwrap();
+    let (pattern, options, from) = match search {
+        Some(search) => search,
+        None => return s.add_layer(Dialog::info("Press / to search first."))
+    };
+    let (mut source, _) = match source {
+        Ok(source) => source,
+        Err(error) => return s.show_error(error)
+    };
+
+    let search = move |progress: &Progress| match index {
+        Some(index) => index.find(source.as_mut(), from, &pattern, options, progress),
+        None => find_pattern(source.as_mut(), from, &pattern, options, progress)
+    };
+    run_with_progress(s, "Searching", length, search, |s, found| match found {
+        Some(found) => {
+            s.call_on_id("hex_view", |v: &mut HexView| v.show_match(found));
+        },
+        None => s.add_layer(Dialog::info("No match found."))
+    });
+}
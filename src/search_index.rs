@@ -0,0 +1,230 @@
+//! An index of the three byte sequences, or trigrams, in every block of a file, so that searching
+//! it again only has to read the blocks that can have a match, instead of all of them.
+//!
+//! The trigrams of a block are kept as the bits of a small bitmap that they hash to, so a block
+//! that is marked as having all the trigrams of a pattern may still not have a match, but a block
+//! that is not marked never has one.
+
+use std::fs;
+use std::path::Path;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::byte_reader::ByteSource;
+use crate::error::{Result, XvError};
+use crate::hex_reader::{find_pattern_between, search_ranges, SearchOptions};
+use crate::progress::Progress;
+
+/// The number of bytes of every block of the index.
+pub const SEARCH_BLOCK_SIZE: u64 = 16 * 1024;
+
+/// The number of bits in the bitmap of a block, which is one for every byte of the block.
+const GRAM_BITS: usize = SEARCH_BLOCK_SIZE as usize;
+
+/// Only the trigrams in the first bytes of a pattern are looked up. The trigrams that start in
+/// that many bytes after a block are counted as being in the block too, so that a match that
+/// starts in the block has all the trigrams that are looked up in it.
+const PATTERN_PREFIX: usize = 64;
+
+/// The trigrams of a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Grams {
+    /// The bits that are set, when there are only a few, like in a block of zeros.
+    Sparse(Vec<u16>),
+    Bitmap(Vec<u8>),
+    /// Most of the bits are set, so the block has to be searched anyway.
+    All
+}
+
+impl Grams {
+    fn from_bits(bits: &[u8]) -> Grams {
+        let count = bits.iter().map(|byte| byte.count_ones() as usize).sum::<usize>();
+        if count > GRAM_BITS * 3 / 4 {
+            Grams::All
+        } else if count * 2 < bits.len() {
+            Grams::Sparse((0..GRAM_BITS).filter(|&bit| bits[bit / 8] & (1 << (bit % 8)) != 0)
+                .map(|bit| bit as u16).collect())
+        } else {
+            Grams::Bitmap(bits.to_vec())
+        }
+    }
+
+    fn contains(&self, bit: usize) -> bool {
+        match self {
+            Grams::Sparse(set) => set.binary_search(&(bit as u16)).is_ok(),
+            Grams::Bitmap(bits) => bits[bit / 8] & (1 << (bit % 8)) != 0,
+            Grams::All => true
+        }
+    }
+}
+
+/// The bit of a trigram. Letters are hashed as lowercase, so that the same index serves searches
+/// that ignore case.
+fn gram_bit(gram: &[u8]) -> usize {
+    let value = gram.iter().fold(0u32, |value, byte| value << 8 | u32::from(byte.to_ascii_lowercase()));
+    (value.wrapping_mul(0x9E37_79B1) >> 16) as usize % GRAM_BITS
+}
+
+/// The search index of a file, which is stored as a msgpack array.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchIndex {
+    /// The length and modification time, in seconds, of the file that was indexed, to tell
+    /// whether the index is still of the same bytes.
+    length: u64,
+    modified: Option<u64>,
+    blocks: Vec<Grams>
+}
+
+impl SearchIndex {
+    /// Index `length` bytes of the source, which were last modified at the given time.
+    pub fn build(source: &mut ByteSource, length: u64, modified: Option<u64>, progress: &Progress)
+        -> Result<SearchIndex> {
+        let mut blocks = Vec::with_capacity((length / SEARCH_BLOCK_SIZE + 1) as usize);
+        let mut block = vec![0; SEARCH_BLOCK_SIZE as usize + PATTERN_PREFIX];
+        let mut bits = vec![0u8; GRAM_BITS / 8];
+        let mut offset = 0;
+        while offset < length {
+            let len = source.read_at(offset, &mut block)?;
+            if len == 0 {
+                break;
+            }
+            for byte in bits.iter_mut() {
+                *byte = 0;
+            }
+            for gram in block[0..len].windows(3) {
+                let bit = gram_bit(gram);
+                bits[bit / 8] |= 1 << (bit % 8);
+            }
+            blocks.push(Grams::from_bits(&bits));
+            let advanced = (len as u64).min(SEARCH_BLOCK_SIZE);
+            offset += advanced;
+            progress.advance(advanced)?;
+        }
+        Ok(SearchIndex { length, modified, blocks })
+    }
+
+    /// Whether the index is of a file with the given length and modification time.
+    pub fn is_of(&self, length: u64, modified: Option<u64>) -> bool {
+        self.length == length && self.modified == modified
+    }
+
+    /// The length of the file that was indexed.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// About how many bytes of memory the index takes up.
+    pub fn memory_size(&self) -> u64 {
+        self.blocks.iter().map(|grams| match grams {
+            Grams::Sparse(set) => 32 + set.len() as u64 * 2,
+            Grams::Bitmap(bits) => 32 + bits.len() as u64,
+            Grams::All => 32
+        }).sum()
+    }
+
+    /// Whether a match of the pattern may start in the block.
+    fn may_match(&self, block: usize, pattern: &[u8]) -> bool {
+        let prefix = &pattern[0..pattern.len().min(PATTERN_PREFIX)];
+        self.blocks.get(block).is_none_or(|grams| {
+            prefix.windows(3).all(|gram| grams.contains(gram_bit(gram)))
+        })
+    }
+
+    /// Find a match of the pattern in the source that was indexed, like `find_pattern`, but only
+    /// read the blocks that may have a match. Patterns shorter than a trigram are searched for
+    /// in all of the blocks.
+    pub fn find(&self, source: &mut ByteSource, from: u64, pattern: &[u8], options: SearchOptions,
+                progress: &Progress) -> Result<Option<u64>> {
+        let length = source.length()?;
+        for (first, last) in search_ranges(length, from, pattern.len() as u64, options) {
+            if first > last {
+                continue;
+            }
+            if pattern.len() < 3 {
+                if let Some(found) = find_pattern_between(source, first, last, pattern, options, progress)? {
+                    return Ok(Some(found));
+                }
+                continue;
+            }
+            let first_block = first / SEARCH_BLOCK_SIZE;
+            let last_block = last / SEARCH_BLOCK_SIZE;
+            let mut candidates = (first_block..=last_block)
+                .filter(|&block| self.may_match(block as usize, pattern))
+                .collect::<Vec<_>>();
+            if options.backwards {
+                candidates.reverse();
+            }
+            for block in candidates {
+                let start = first.max(block * SEARCH_BLOCK_SIZE);
+                let end = last.min((block + 1) * SEARCH_BLOCK_SIZE - 1);
+                if let Some(found) = find_pattern_between(source, start, end, pattern, options, progress)? {
+                    return Ok(Some(found));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SearchIndex> {
+        let bytes = fs::read(path)?;
+        rmp_serde::from_slice(&bytes)
+            .map_err(|e| XvError::Decode(format!("The search index could not be read: {}", e)))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let bytes = rmp_serde::to_vec(self)
+            .map_err(|e| XvError::Parse(format!("The search index could not be written: {}", e)))?;
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hex_reader::find_pattern;
+
+    use super::*;
+
+    #[test]
+    fn searching_with_an_index() {
+        let mut file = vec![0; SEARCH_BLOCK_SIZE as usize * 4];
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        file.extend((0..SEARCH_BLOCK_SIZE).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 24) as u8
+        }));
+        let needle = b"Needle in a haystack";
+        let starts = [100, SEARCH_BLOCK_SIZE as usize - 5, SEARCH_BLOCK_SIZE as usize * 4 + 10];
+        for &start in starts.iter() {
+            file[start..start + needle.len()].copy_from_slice(needle);
+        }
+        let length = file.len() as u64;
+        let index = SearchIndex::build(&mut file, length, None, &Progress::new(length)).unwrap();
+        assert_eq!(index.blocks.len(), 5);
+        assert_eq!(index.blocks[2], Grams::Sparse(vec![gram_bit(&[0, 0, 0]) as u16]));
+
+        let forward = SearchOptions::default();
+        let backwards = SearchOptions { backwards: true, ..forward };
+        let options = [forward, backwards, SearchOptions { wrap: true, ..forward },
+                       SearchOptions { wrap: true, ..backwards }, SearchOptions { ignore_case: true, ..forward }];
+        let patterns: [&[u8]; 4] = [needle, b"NEEDLE", b"\0N", b"haystack!"];
+        for &from in [0, 100, 50_000, length - 1].iter() {
+            for &options in options.iter() {
+                for pattern in patterns.iter() {
+                    let indexed = index.find(&mut file, from, pattern, options, &Progress::new(length)).unwrap();
+                    let scanned = find_pattern(&mut file, from, pattern, options, &Progress::new(length)).unwrap();
+                    assert_eq!(indexed, scanned, "{:?} from {} {:?}", pattern, from, options);
+                }
+            }
+        }
+
+        let progress = Progress::new(length);
+        assert_eq!(index.find(&mut file, 200, needle, forward, &progress).unwrap(), Some(starts[1] as u64));
+        assert!(progress.done() < length / 2);
+
+        let saved = rmp_serde::to_vec(&index).unwrap();
+        assert_eq!(rmp_serde::from_slice::<SearchIndex>(&saved).unwrap(), index);
+    }
+}
@@ -6,12 +6,17 @@ use cursive::traits::{Boxable, Identifiable};
 use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
 
 use crate::hex_view::HexView;
-use crate::utilities::{get_content, parse_number};
+use crate::utilities::{get_content, parse_number, parse_number_or_zero};
 
 pub fn open_set_width_dialog(s: &mut Cursive) {
     let (current_width, current_group) = get_current_width_and_group(s);
+    let (current_skip, current_shown) =
+        s.call_on_id("hex_view", |v: &mut HexView| v.get_line_columns()).unwrap();
     let current_width_str = format!("{}", current_width);
     let current_group_str = format!("{}", current_group);
+    let current_skip_str = format!("{}", current_skip);
+    let current_shown_str = current_shown.map_or(String::new(), |shown| format!("{}", shown));
+    let base_address = s.call_on_id("hex_view", |v: &mut HexView| v.get_address_map().base_address).unwrap();
     
     let line_width_edit = EditView::new()
         .content(current_width_str)
@@ -23,13 +28,33 @@ pub fn open_set_width_dialog(s: &mut Cursive) {
         .with_id("group")
         .min_width(8);
     
+    let skip_edit = EditView::new()
+        .content(current_skip_str)
+        .with_id("line_skip")
+        .min_width(8);
+    
+    let shown_edit = EditView::new()
+        .content(current_shown_str)
+        .with_id("line_shown")
+        .min_width(8);
+    
+    let base_address_edit = EditView::new()
+        .content(format!("0x{:X}", base_address))
+        .with_id("base_address")
+        .min_width(20);
+    
     let editors = LinearLayout::vertical()
         .child(line_width_edit)
-        .child(group_edit);
+        .child(group_edit)
+        .child(skip_edit)
+        .child(shown_edit)
+        .child(base_address_edit);
     
-    let layout = LinearLayout::horizontal()
-        .child(TextView::new("Line width:  \nGroup:  "))
-        .child(editors);
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Line width:  \nGroup:  \nSkip bytes:  \nShow bytes:  \nBase address:  "))
+            .child(editors))
+        .child(TextView::new("\nLeave \"Show bytes\" empty to show\nthe whole line. The offsets are shown\nas addresses from the base address."));
     
     let dialog = Dialog::around(layout)
         .title("Line Width")
@@ -52,6 +77,9 @@ fn get_current_width_and_group(s: &mut Cursive) -> (u64, u16) {
 fn do_set_widths(s: &mut Cursive) {
     let line_width = s.call_on_id("line_width", get_content).unwrap();
     let group = s.call_on_id("group", get_content).unwrap();
+    let skip = s.call_on_id("line_skip", get_content).unwrap();
+    let shown = s.call_on_id("line_shown", get_content).unwrap();
+    let base_address = s.call_on_id("base_address", get_content).unwrap();
     
     s.pop_layer();
     
@@ -76,4 +104,16 @@ fn do_set_widths(s: &mut Cursive) {
             _ => None,
         };
     }
+    
+    let skip = parse_number_or_zero(&skip);
+    let shown = match parse_number(&shown) {
+        Ok(shown) if shown > 0 => Some(shown),
+        _ => None
+    };
+    s.call_on_id("hex_view", |v: &mut HexView| {
+        if skip < v.get_line_width() {
+            v.set_line_columns(skip, shown);
+        }
+        v.set_base_address(parse_number_or_zero(&base_address));
+    });
 }
@@ -0,0 +1,166 @@
+//! Sparse files, whose holes take no space on disk and read as zeros. The holes are found with
+//! `SEEK_HOLE` and `SEEK_DATA`, where the file system supports them, and are not read at all.
+
+use std::fs::File;
+use std::io::Result;
+use std::ops::Range;
+use std::time::SystemTime;
+
+use crate::byte_reader::{ByteSource, TileConfig};
+
+/// The most holes that are looked up. The rest of a file with more holes than that is read as if
+/// it had none, which shows the same bytes.
+const MAX_HOLES: usize = 64 * 1024;
+
+/// A file, read with the holes filled in with zeros. The holes are looked up again when the file
+/// changes.
+#[derive(Debug)]
+pub struct SparseFile {
+    file: File,
+    holes: Vec<Range<u64>>,
+    length: u64,
+    modified: Option<SystemTime>
+}
+
+impl SparseFile {
+    pub fn open(file: File) -> Result<SparseFile> {
+        let mut sparse = SparseFile { file, holes: Vec::new(), length: 0, modified: None };
+        sparse.find_holes()?;
+        Ok(sparse)
+    }
+
+    /// Look up the holes again if the file has changed since they were last looked up.
+    fn refresh(&mut self) -> Result<()> {
+        let metadata = self.file.metadata()?;
+        if metadata.len() != self.length || metadata.modified().ok() != self.modified {
+            self.find_holes()?;
+        }
+        Ok(())
+    }
+
+    fn find_holes(&mut self) -> Result<()> {
+        let metadata = self.file.metadata()?;
+        self.length = metadata.len();
+        self.modified = metadata.modified().ok();
+        self.holes = find_holes(&self.file, self.length)?;
+        Ok(())
+    }
+}
+
+impl ByteSource for SparseFile {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            let position = offset + len as u64;
+            let remaining = buf.len() - len;
+            // The first hole that ends after the position, which the position is either in or
+            // before.
+            let next = self.holes.partition_point(|hole| hole.end <= position);
+            match self.holes.get(next).cloned() {
+                Some(hole) if hole.start <= position => {
+                    let count = ((hole.end - position) as usize).min(remaining);
+                    buf[len..len + count].iter_mut().for_each(|byte| *byte = 0);
+                    len += count;
+                },
+                hole => {
+                    let count = hole.map_or(remaining, |hole| ((hole.start - position) as usize).min(remaining));
+                    let read = ByteSource::read_at(&mut self.file, position, &mut buf[len..len + count])?;
+                    len += read;
+                    if read < count {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(len)
+    }
+
+    fn length(&mut self) -> Result<u64> {
+        self.refresh()?;
+        Ok(self.length)
+    }
+
+    fn modified(&mut self) -> Result<Option<SystemTime>> {
+        self.refresh()?;
+        Ok(self.modified)
+    }
+
+    fn tile_config(&self) -> TileConfig {
+        self.file.tile_config()
+    }
+
+    fn holes(&self) -> &[Range<u64>] {
+        &self.holes
+    }
+}
+
+/// The holes of the first `length` bytes of the file, in order. File systems that do not keep
+/// track of holes have none.
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "solaris",
+          target_os = "illumos"))]
+fn find_holes(file: &File, length: u64) -> Result<Vec<Range<u64>>> {
+    use std::os::unix::io::AsRawFd;
+
+    use nix::errno::Errno;
+    use nix::libc::off_t;
+    use nix::unistd::{lseek, Whence};
+
+    let fd = file.as_raw_fd();
+    let mut holes = Vec::new();
+    let mut offset = 0;
+    while offset < length && holes.len() < MAX_HOLES {
+        // File systems that cannot look up holes refuse to.
+        let start = match lseek(fd, offset as off_t, Whence::SeekHole) {
+            Ok(start) => start as u64,
+            Err(Errno::ENXIO) | Err(Errno::EINVAL) => break,
+            Err(errno) => return Err(errno.into())
+        };
+        // There is always a hole at the end of the file, which is not a real one.
+        if start >= length {
+            break;
+        }
+        let end = match lseek(fd, start as off_t, Whence::SeekData) {
+            Ok(end) => (end as u64).min(length),
+            Err(Errno::ENXIO) => length,
+            Err(errno) => return Err(errno.into())
+        };
+        holes.push(start..end);
+        offset = end;
+    }
+    Ok(holes)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "solaris",
+              target_os = "illumos")))]
+fn find_holes(_file: &File, _length: u64) -> Result<Vec<Range<u64>>> {
+    Ok(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Seek, SeekFrom, Write};
+
+    use super::*;
+
+    #[test]
+    fn reading_around_the_holes_of_a_sparse_file() {
+        let mut tmpf = tempfile::tempfile().unwrap();
+        tmpf.write_all(b"start").unwrap();
+        tmpf.seek(SeekFrom::Start(4 * 1024 * 1024)).unwrap();
+        tmpf.write_all(b"end").unwrap();
+        let mut sparse = SparseFile::open(tmpf).unwrap();
+        assert_eq!(sparse.length().unwrap(), 4 * 1024 * 1024 + 3);
+
+        // Not every file system keeps the hole, but the bytes are the same either way.
+        for hole in sparse.holes() {
+            assert!(hole.start >= 5 && hole.end <= 4 * 1024 * 1024);
+        }
+        let mut buf = [0xFF; 8];
+        assert_eq!(sparse.read_at(0, &mut buf).unwrap(), 8);
+        assert_eq!(&buf, b"start\0\0\0");
+        assert_eq!(sparse.read_at(4 * 1024 * 1024 - 5, &mut buf).unwrap(), 8);
+        assert_eq!(&buf, b"\0\0\0\0\0end");
+        assert_eq!(sparse.read_at(4 * 1024 * 1024 + 1, &mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"nd");
+    }
+}
@@ -0,0 +1,63 @@
+//! A second hex view, below the first, of another file, which can scroll along with the first at
+//! a set distance, for comparing files that have the same structure at different offsets.
+
+use std::path::Path;
+
+use cursive::Cursive;
+use cursive::traits::Identifiable;
+use cursive::views::LinearLayout;
+use xv::byte_reader::TilingByteReader;
+use xv::error::{Result, XvError};
+use xv::hex_reader::HexReader;
+
+use crate::hex_view::HexView;
+use crate::xv_state::XvState;
+
+/// Show the file below the hex view, with the same line width, and lock their scrolling together,
+/// or show it instead of the file that was shown there before.
+pub fn open_split_view(s: &mut Cursive, path: &Path) -> Result<()> {
+    let reader = HexReader::new(TilingByteReader::new(path)?)?;
+    let (leader, line_width, panes) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_published_offset(), v.get_line_width(), v.get_panes())
+    }).unwrap();
+    let display_profile = s.with_user_data(|state: &mut XvState| state.display_profile()).unwrap();
+
+    let mut split_view = HexView::new(reader);
+    split_view.set_display_profile(display_profile);
+    split_view.set_panes(panes);
+    split_view.set_line_width(line_width);
+    split_view.set_scroll_lock(Some((leader, 0)));
+    close_split_view(s);
+    s.call_on_id("split_row", |row: &mut LinearLayout| row.add_child(split_view.with_id("split_view")));
+    Ok(())
+}
+
+/// Hide the view below the hex view, if it is shown.
+pub fn close_split_view(s: &mut Cursive) {
+    s.call_on_id("split_row", |row: &mut LinearLayout| {
+        if row.len() > 0 {
+            row.remove_child(0);
+        }
+    });
+}
+
+/// Keep the window of the view below `delta` bytes after the window of the hex view, or as far
+/// after it as it is now without a delta.
+pub fn lock_split_view(s: &mut Cursive, delta: Option<i64>) -> Result<()> {
+    let (leader, offset) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_published_offset(), v.get_offset())
+    }).unwrap();
+    s.call_on_id("split_view", |v: &mut HexView| {
+        let delta = delta.unwrap_or_else(|| (i128::from(v.get_offset()) - i128::from(offset)) as i64);
+        v.set_scroll_lock(Some((leader, delta)));
+    }).ok_or_else(no_split_view)
+}
+
+/// Stop scrolling the view below along with the hex view.
+pub fn unlock_split_view(s: &mut Cursive) -> Result<()> {
+    s.call_on_id("split_view", |v: &mut HexView| v.set_scroll_lock(None)).ok_or_else(no_split_view)
+}
+
+fn no_split_view() -> XvError {
+    XvError::Input(String::from("There is no split view. Open one with the split command."))
+}
@@ -0,0 +1,451 @@
+//! Sources that grow while they are viewed, like a serial port or a network socket, that are read
+//! into a spool on another thread, so that the bytes that have arrived so far can be viewed at any
+//! offset. Streams that may be larger than memory, like named pipes, are spooled to a temporary
+//! file instead, and so are the rest of the bytes of a spool in memory that no longer fit in the
+//! memory budget.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::byte_reader::ByteSource;
+use crate::memory::{self, Reservation, Subsystem};
+
+/// How much is read from the stream at a time, which is enough for the largest UDP datagram.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// How long a read from a socket or a serial port waits for bytes, before the spool checks whether
+/// it has been stopped.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Where the spooled bytes are kept.
+enum Storage {
+    /// The bytes, and the memory they count for towards the budget.
+    Memory(Vec<u8>, Reservation),
+    /// A temporary file, that is removed when the spool is dropped.
+    TempFile { file: File, path: PathBuf, length: u64 }
+}
+
+/// Tells the temporary files of the spools of a process apart.
+static TEMP_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+impl Storage {
+    fn temp_file() -> Result<Storage> {
+        let count = TEMP_FILE_COUNT.fetch_add(1, Ordering::SeqCst);
+        let path = std::env::temp_dir().join(format!("xv-spool-{}-{}", process::id(), count));
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        Ok(Storage::TempFile { file, path, length: 0 })
+    }
+
+    fn len(&self) -> u64 {
+        match self {
+            Storage::Memory(bytes, _) => bytes.len() as u64,
+            Storage::TempFile { length, .. } => *length
+        }
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> Result<()> {
+        if let Storage::Memory(spooled, _) = self {
+            if memory::available() < bytes.len() as u64 {
+                let mut storage = Storage::temp_file()?;
+                storage.append(spooled)?;
+                *self = storage;
+            }
+        }
+        match self {
+            Storage::Memory(spooled, memory) => {
+                spooled.extend_from_slice(bytes);
+                memory.resize(spooled.capacity() as u64);
+            },
+            Storage::TempFile { file, length, .. } => {
+                file.seek(SeekFrom::End(0))?;
+                file.write_all(bytes)?;
+                *length += bytes.len() as u64;
+            }
+        }
+        Ok(())
+    }
+
+    /// Another handle to the temporary file, if the bytes are kept in one, that bytes can be
+    /// appended through without the spool being locked.
+    fn appender(&self) -> Result<Option<File>> {
+        match self {
+            Storage::Memory(..) => Ok(None),
+            Storage::TempFile { path, .. } => Ok(Some(OpenOptions::new().append(true).open(path)?))
+        }
+    }
+
+    /// Count the bytes that were appended through another handle to the temporary file.
+    fn appended(&mut self, count: u64) {
+        if let Storage::TempFile { length, .. } = self {
+            *length += count;
+        }
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        match self {
+            Storage::Memory(bytes, _) => (&bytes[..]).read_at(offset, buf),
+            Storage::TempFile { file, .. } => file.read_at(offset, buf)
+        }
+    }
+}
+
+impl Drop for Storage {
+    fn drop(&mut self) {
+        if let Storage::TempFile { path, .. } = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+struct Spooled {
+    storage: Storage,
+    /// The offset of every chunk that was read, and how long after the spool was started it
+    /// arrived.
+    arrivals: Vec<(u64, Duration)>,
+    started: Instant,
+    /// Why the stream could not be read to the end, if it could not.
+    error: Option<String>,
+    finished: bool
+}
+
+/// Stops the thread of a spool when it is dropped, which is when the last clone of the spool is.
+struct Stopper(Arc<AtomicBool>);
+
+impl Drop for Stopper {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// The bytes that have been read from a stream so far. Clones share the same bytes. Once every
+/// clone has been dropped, the stream is no longer read, and the temporary file is removed.
+#[derive(Clone)]
+pub struct Spool {
+    spooled: Arc<Mutex<Spooled>>,
+    stopper: Arc<Stopper>
+}
+
+impl fmt::Debug for Spool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Spool({} bytes)", self.spooled.lock().unwrap().storage.len())
+    }
+}
+
+impl Spool {
+    /// Read the stream into a spool on another thread, until the stream ends or fails.
+    pub fn start<R: Read + Send + 'static>(stream: R) -> Spool {
+        Spool::start_from(move |_: &Fn() -> bool| Ok(stream))
+    }
+
+    /// Open a stream on another thread, like by waiting for a connection, and read it into the
+    /// spool until it ends or fails. Opening is given a function that tells whether the spool
+    /// has been stopped, so that it can stop waiting.
+    ///
+    /// Reads that time out are tried again, after checking whether the spool has been stopped,
+    /// so streams that are given a read timeout stop soon after the spool does.
+    pub fn start_from<F, R>(open: F) -> Spool
+        where F: FnOnce(&Fn() -> bool) -> Result<R> + Send + 'static,
+              R: Read {
+        Spool::spawn(Storage::Memory(Vec::new(), Reservation::new(Subsystem::Captures, 0)), open)
+    }
+
+    /// Like `start_from`, but keep the bytes in a temporary file rather than in memory.
+    pub fn start_in_temp_file<F, R>(open: F) -> Result<Spool>
+        where F: FnOnce(&Fn() -> bool) -> Result<R> + Send + 'static,
+              R: Read {
+        Ok(Spool::spawn(Storage::temp_file()?, open))
+    }
+
+    fn spawn<F, R>(storage: Storage, open: F) -> Spool
+        where F: FnOnce(&Fn() -> bool) -> Result<R> + Send + 'static,
+              R: Read {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let spool = Spool {
+            spooled: Arc::new(Mutex::new(Spooled {
+                storage,
+                arrivals: Vec::new(),
+                started: Instant::now(),
+                error: None,
+                finished: false
+            })),
+            stopper: Arc::new(Stopper(Arc::clone(&stopped)))
+        };
+        let spooled = Arc::clone(&spool.spooled);
+        thread::spawn(move || {
+            let is_stopped = || stopped.load(Ordering::SeqCst);
+            let mut stream = match open(&is_stopped) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let mut spooled = spooled.lock().unwrap();
+                    spooled.error = Some(e.to_string());
+                    spooled.finished = true;
+                    return;
+                }
+            };
+            let mut chunk = vec![0; CHUNK_SIZE];
+            // Once the bytes are kept in a temporary file, they are written to it before the spool
+            // is locked, so that reading the bytes that have arrived does not wait for the writing.
+            // The bytes past the length of the storage are not read, and the length is only
+            // grown, under the lock, once they have been written.
+            let mut appender: Option<File> = None;
+            while !is_stopped() {
+                let result = stream.read(&mut chunk);
+                let written = match (&result, &mut appender) {
+                    (Ok(n), Some(file)) if *n > 0 => Some(file.write_all(&chunk[0..*n])),
+                    _ => None
+                };
+                let mut spooled = spooled.lock().unwrap();
+                match result {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        let arrival = (spooled.storage.len(), spooled.started.elapsed());
+                        let appended = match written {
+                            Some(written) => written.map(|_| spooled.storage.appended(n as u64)),
+                            None => spooled.storage.append(&chunk[0..n]).and_then(|_| {
+                                appender = spooled.storage.appender()?;
+                                Ok(())
+                            })
+                        };
+                        if let Err(e) = appended {
+                            spooled.error = Some(e.to_string());
+                            break;
+                        }
+                        spooled.arrivals.push(arrival);
+                    },
+                    Err(ref e) if is_retried(e.kind()) => {},
+                    Err(e) => {
+                        spooled.error = Some(e.to_string());
+                        break;
+                    }
+                }
+            }
+            spooled.lock().unwrap().finished = true;
+        });
+        spool
+    }
+
+    /// Stop reading the stream, like when every clone of the spool is dropped.
+    pub fn stop(&self) {
+        self.stopper.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the stream has ended, and no more bytes will arrive.
+    pub fn is_finished(&self) -> bool {
+        self.spooled.lock().unwrap().finished
+    }
+
+    /// Why the stream could not be read to the end, once it has ended, if it could not.
+    pub fn error(&self) -> Option<String> {
+        self.spooled.lock().unwrap().error.clone()
+    }
+
+    /// How long after the spool was started the byte at the offset arrived, if it has arrived.
+    pub fn arrival_of(&self, offset: u64) -> Option<Duration> {
+        let spooled = self.spooled.lock().unwrap();
+        if offset >= spooled.storage.len() {
+            return None;
+        }
+        let index = match spooled.arrivals.binary_search_by_key(&offset, |&(start, _)| start) {
+            Ok(index) => index,
+            Err(index) => index.checked_sub(1)?
+        };
+        Some(spooled.arrivals[index].1)
+    }
+}
+
+/// Whether a read that failed this way is tried again, like one that timed out.
+fn is_retried(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// A socket address from an address, or from only a port, which is then listened to on all
+/// interfaces.
+pub fn socket_address(address: &str) -> String {
+    match address.parse::<u16>() {
+        Ok(port) => format!("0.0.0.0:{}", port),
+        Err(_) => address.to_string()
+    }
+}
+
+/// Wait for a TCP connection on the address, and spool what arrives on it.
+pub fn listen_tcp(address: &str) -> Result<Spool> {
+    let listener = TcpListener::bind(socket_address(address))?;
+    // The listener does not wait for a connection, so that it can be closed when the spool is
+    // stopped before one arrives.
+    listener.set_nonblocking(true)?;
+    Ok(Spool::start_from(move |is_stopped: &Fn() -> bool| loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(STOP_CHECK_INTERVAL))?;
+                return Ok(stream);
+            },
+            Err(ref e) if is_retried(e.kind()) && !is_stopped() => thread::sleep(STOP_CHECK_INTERVAL),
+            Err(e) => return Err(e)
+        }
+    }))
+}
+
+/// Connect to a TCP server, and spool what it sends.
+pub fn connect_tcp(address: &str) -> Result<Spool> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(STOP_CHECK_INTERVAL))?;
+    Ok(Spool::start(stream))
+}
+
+/// Spool the datagrams that arrive on a UDP address, one after the other.
+pub fn listen_udp(address: &str) -> Result<Spool> {
+    let socket = UdpSocket::bind(socket_address(address))?;
+    socket.set_read_timeout(Some(STOP_CHECK_INTERVAL))?;
+    Ok(Spool::start(Datagrams(socket)))
+}
+
+/// Reads the datagrams that arrive on a socket as a stream.
+struct Datagrams(UdpSocket);
+
+impl Read for Datagrams {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.0.recv(buf)
+    }
+}
+
+/// Spool what arrives on a serial port. The port is set to the baud rate and to raw mode with
+/// `stty` first, since the standard library has no way to do that.
+///
+/// The port is also set to ignore the modem control lines, so that opening it does not wait for
+/// a carrier, and for reads to give up after a while without bytes, so that the spool can be
+/// stopped while nothing arrives.
+pub fn open_serial_port<P: AsRef<Path>>(path: P, baud: u32) -> Result<Spool> {
+    let path = path.as_ref();
+    let device_option = if cfg!(any(target_os = "macos", target_os = "freebsd")) { "-f" } else { "-F" };
+    // The time that a read waits for is in tenths of a second.
+    let read_time = (STOP_CHECK_INTERVAL.as_millis() / 100).max(1);
+    let status = Command::new("stty")
+        .arg(device_option)
+        .arg(path)
+        .arg(baud.to_string())
+        .arg("raw")
+        .arg("-echo")
+        .arg("clocal")
+        .args(["min", "0", "time", &read_time.to_string()])
+        .status()?;
+    if !status.success() {
+        return Err(Error::other(format!(
+            "Could not set {} to {} baud with stty.", path.display(), baud)));
+    }
+    Ok(Spool::start(SerialPort(File::open(path)?)))
+}
+
+/// Reads a serial port as a stream that does not end. A read that gives up without bytes is a
+/// timeout, rather than the end of the stream.
+struct SerialPort(File);
+
+impl Read for SerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self.0.read(buf)? {
+            0 if !buf.is_empty() => Err(Error::new(ErrorKind::TimedOut, "No bytes arrived.")),
+            n => Ok(n)
+        }
+    }
+}
+
+impl ByteSource for Spool {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        self.spooled.lock().unwrap().storage.read_at(offset, buf)
+    }
+
+    fn length(&mut self) -> Result<u64> {
+        Ok(self.spooled.lock().unwrap().storage.len())
+    }
+
+    fn try_clone(&self) -> Option<Box<ByteSource + Send>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn spooling_a_stream() {
+        let mut spool = Spool::start(Cursor::new(b"Hello, World!".to_vec()));
+        while !spool.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(spool.length().unwrap(), 13);
+        let mut buf = [0; 5];
+        assert_eq!(spool.read_at(7, &mut buf).unwrap(), 5);
+        assert_eq!(&buf, b"World");
+        assert_eq!(spool.error(), None);
+        assert!(spool.arrival_of(12).is_some());
+        assert_eq!(spool.arrival_of(13), None);
+        assert_eq!(socket_address("9000"), "0.0.0.0:9000");
+        assert_eq!(socket_address("localhost:9000"), "localhost:9000");
+    }
+
+    #[test]
+    fn spooling_to_a_temp_file() {
+        let mut spool = Spool::start_in_temp_file(|_: &Fn() -> bool| Ok(Cursor::new(b"Hello, World!".to_vec())))
+            .unwrap();
+        while !spool.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let path = match &spool.spooled.lock().unwrap().storage {
+            Storage::TempFile { path, .. } => path.clone(),
+            Storage::Memory(..) => panic!("The bytes should be in a temporary file.")
+        };
+        assert_eq!(spool.length().unwrap(), 13);
+        let mut buf = [0; 5];
+        assert_eq!(spool.read_at(0, &mut buf).unwrap(), 5);
+        assert_eq!(&buf, b"Hello");
+        drop(spool);
+        // The file is removed once the spooling thread has let go of it as well.
+        for _ in 0..1000 {
+            if !path.exists() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn spooling_chunks_to_a_temp_file() {
+        let bytes: Vec<u8> = (0..5 * CHUNK_SIZE).map(|i| (i % 251) as u8).collect();
+        let stream = Cursor::new(bytes.clone());
+        let mut spool = Spool::start_in_temp_file(move |_: &Fn() -> bool| Ok(stream)).unwrap();
+        while !spool.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(spool.error(), None);
+        assert_eq!(spool.length().unwrap(), bytes.len() as u64);
+        let mut buf = vec![0; bytes.len()];
+        assert_eq!(spool.read_at(0, &mut buf).unwrap(), bytes.len());
+        assert!(buf == bytes);
+    }
+
+    #[test]
+    fn stopping_a_spool() {
+        let listener = listen_tcp("127.0.0.1:0").unwrap();
+        listener.stop();
+        for _ in 0..1000 {
+            if listener.is_finished() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(listener.is_finished());
+    }
+}
@@ -0,0 +1,101 @@
+//! The dialog that shows the statistics of the selection or the view, and of the whole file.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::views::{Dialog, LinearLayout, OnEventView, TextView};
+use xv::byte_reader::ByteSource;
+use xv::byte_stats::ByteStats;
+use xv::error::Result;
+use xv::progress::Progress;
+
+use crate::hex_view::HexView;
+use crate::progress_dialog::run_with_progress;
+use crate::xv_tui::ShowError;
+
+/// The number of bytes of a selection that are read at a time.
+const STATS_CHUNK: usize = 1024 * 1024;
+
+/// Show the statistics of the selection, if there is one, and otherwise of the bytes in view,
+/// along with those of the whole file once it has been indexed. The selection is read in the
+/// background, since it can be large.
+pub fn open_stats_dialog(s: &mut Cursive) {
+    let (selection, stats, file_stats) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_selection(), v.get_window_stats(), v.get_block_index().map(|index| index.stats()))
+    }).unwrap();
+
+    let (start, end) = match selection {
+        Some(selection) => selection,
+        None => return show_stats(s, "In view", &stats, file_stats)
+    };
+    let source = s.call_on_id("hex_view", |v: &mut HexView| {
+        v.open_background_source(start, end - start)
+    }).unwrap();
+    let (source, offset) = match source {
+        Ok(source) => source,
+        Err(error) => return s.show_error(error)
+    };
+    let length = end - start;
+    let compute = move |progress: &Progress| selection_stats(source, offset, length, progress);
+    run_with_progress(s, "Computing statistics", length, compute, move |s, stats| {
+        show_stats(s, "Selection", &stats, file_stats)
+    });
+}
+
+fn selection_stats(mut source: Box<ByteSource + Send>, offset: u64, length: u64, progress: &Progress)
+    -> Result<ByteStats> {
+    let mut stats = ByteStats::new();
+    let mut buf = vec![0; STATS_CHUNK];
+    let mut done = 0;
+    while done < length {
+        let len = (length - done).min(STATS_CHUNK as u64) as usize;
+        let read = source.read_at(offset + done, &mut buf[0..len])?;
+        if read == 0 {
+            break;
+        }
+        stats.add(&buf[0..read]);
+        done += read as u64;
+        progress.advance(read as u64)?;
+    }
+    Ok(stats)
+}
+
+fn show_stats(s: &mut Cursive, title: &str, stats: &ByteStats, file_stats: Option<ByteStats>) {
+    let labels = "Bytes:  \nMinimum:  \nMaximum:  \nMean:  \nEntropy:  \nPrintable:  \nMost common:  ";
+    let mut layout = LinearLayout::horizontal()
+        .child(TextView::new(format!("\n{}", labels)))
+        .child(TextView::new(format!("{}\n{}", title, describe(stats))));
+    // The statistics of the whole file are known once it has been indexed.
+    if let Some(file_stats) = file_stats {
+        layout.add_child(TextView::new("    "));
+        layout.add_child(TextView::new(format!("Whole file\n{}", describe(&file_stats))));
+    }
+
+    let dialog = Dialog::around(layout)
+        .title("Statistics")
+        .dismiss_button("Ok");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+fn describe(stats: &ByteStats) -> String {
+    let min = stats.min().map_or(String::from("-"), |b| format!("0x{:02x} ({})", b, b));
+    let max = stats.max().map_or(String::from("-"), |b| format!("0x{:02x} ({})", b, b));
+    let count = stats.count().max(1) as f64;
+    let most_common: Vec<String> = stats.most_common(5).iter()
+        .map(|(b, n)| format!("0x{:02x} ({:.1}%)", b, 100.0 * *n as f64 / count))
+        .collect();
+
+    format!("{}\n{}\n{}\n{:.2}\n{:.3} bits per byte\n{:.1}%\n{}",
+            stats.count(),
+            min,
+            max,
+            stats.mean(),
+            stats.entropy(),
+            100.0 * stats.printable_ratio(),
+            most_common.join(", "))
+}
@@ -1,9 +1,13 @@
+use cursive::Cursive;
 use cursive::theme::{ColorStyle, ColorType, Effect, PaletteColor, Style};
-use cursive::traits::Boxable;
+use cursive::traits::{Boxable, Identifiable};
 use cursive::utils::markup::StyledString;
-use cursive::views::{LinearLayout, PaddedView, TextView};
+use cursive::views::{Dialog, LinearLayout, PaddedView, TextContent, TextView};
 
-pub fn new_status_bar() -> PaddedView<LinearLayout> {
+use crate::hex_view::HexView;
+use crate::xv_tui::ShowError;
+
+pub fn new_status_bar(position: TextContent) -> PaddedView<LinearLayout> {
     let hints_style = ColorStyle::new(
         ColorType::Palette(PaletteColor::Tertiary),
         ColorType::Palette(PaletteColor::Background));
@@ -26,5 +30,123 @@ pub fn new_status_bar() -> PaddedView<LinearLayout> {
     let hints_bar = TextView::new(hints_bar_string);
 
     PaddedView::new((1, 1, 0, 0), LinearLayout::horizontal()
-        .child(hints_bar.full_width()))
+        .child(hints_bar.full_width())
+        .child(TextView::new("").with_id("status_measure"))
+        .child(TextView::new_with_content(position)))
+}
+
+/// Describe where a window of `height` lines, starting at `top_line`, is in a file of `lines`
+/// lines, as a percentage of the file that is above the window.
+pub fn describe_position(top_line: u64, height: u16, lines: u64) -> String {
+    let at_end = top_line + u64::from(height) >= lines;
+    match (top_line == 0, at_end) {
+        (true, true) => String::from("   All"),
+        (true, false) => String::from("   Top"),
+        (false, true) => String::from("   End"),
+        (false, false) => format!("   {:.1}%", 100.0 * top_line as f64 / lines as f64)
+    }
+}
+
+/// Describe the byte at the position in words, the same way every time, so that screen readers can
+/// read it out.
+pub fn describe_byte(address: u64, byte: Option<u8>) -> String {
+    match byte {
+        Some(byte) => match byte_name(byte) {
+            Some(name) => format!("Offset 0x{:08X}, byte 0x{:02X}, {}, {}", address, byte, byte, name),
+            None => format!("Offset 0x{:08X}, byte 0x{:02X}, {}", address, byte, byte)
+        },
+        None => format!("Offset 0x{:08X}, end of file", address)
+    }
+}
+
+/// Describe a line of bytes in words, with its text, so that screen readers can read it out.
+pub fn describe_line(address: u64, bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return format!("Line at 0x{:08X} is past the end of the file.", address);
+    }
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    let text: String = bytes.iter()
+        .map(|b| if b.is_ascii_graphic() || *b == b' ' { char::from(*b) } else { '.' })
+        .collect();
+    format!("Line at 0x{:08X}, {} bytes: {}.\nText: {}", address, bytes.len(), hex.join(" "), text)
+}
+
+/// What a byte is as a character, when it is one that can be read out.
+fn byte_name(byte: u8) -> Option<String> {
+    match byte {
+        0 => Some(String::from("null")),
+        b'\t' => Some(String::from("tab")),
+        b'\n' => Some(String::from("line feed")),
+        b'\r' => Some(String::from("carriage return")),
+        b' ' => Some(String::from("space")),
+        _ if byte.is_ascii_graphic() => Some(format!("'{}'", char::from(byte))),
+        _ => None
+    }
+}
+
+/// Show the text of the top line of the hex view in a dialog, for screen readers.
+pub fn speak_line(s: &mut Cursive) {
+    let line = s.call_on_id("hex_view", |v: &mut HexView| v.describe_top_line()).unwrap();
+    match line {
+        Ok(line) => s.add_layer(Dialog::info(line).title("Line")),
+        Err(error) => s.show_error(error)
+    }
+}
+
+/// Show the marks, and the distance between them, in the status bar.
+pub fn update_measure(s: &mut Cursive) {
+    let (marks, columns) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_marks(), v.get_column_selection())
+    }).unwrap();
+    let measure = match columns {
+        Some(columns) => format!("Columns: {} of {} rows   {}", columns.columns, columns.rows, describe_marks(marks)),
+        None => describe_marks(marks)
+    };
+    s.call_on_id("status_measure", |v: &mut TextView| v.set_content(measure));
+}
+
+fn describe_marks(marks: (Option<u64>, Option<u64>)) -> String {
+    match marks {
+        (Some(a), Some(b)) => {
+            let distance = b.abs_diff(a);
+            let sign = if b >= a { "" } else { "-" };
+            format!("B-A: {}0x{:X} ({}{})   mod 16: {}   mod 512: {}   mod 4096: {}",
+                    sign, distance, sign, distance, distance % 16, distance % 512, distance % 4096)
+        },
+        (Some(a), None) => format!("A: 0x{:X}", a),
+        (None, Some(b)) => format!("B: 0x{:X}", b),
+        (None, None) => String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describing_marks() {
+        assert_eq!(describe_marks((None, None)), "");
+        assert_eq!(describe_marks((Some(0x10), None)), "A: 0x10");
+        assert_eq!(describe_marks((Some(0x10), Some(0x1210))),
+                   "B-A: 0x1200 (4608)   mod 16: 0   mod 512: 0   mod 4096: 512");
+        assert_eq!(describe_marks((Some(0x20), Some(0x10))),
+                   "B-A: -0x10 (-16)   mod 16: 0   mod 512: 16   mod 4096: 16");
+    }
+
+    #[test]
+    fn describing_bytes() {
+        assert_eq!(describe_byte(0x1230, Some(b'O')), "Offset 0x00001230, byte 0x4F, 79, 'O'");
+        assert_eq!(describe_byte(0x10, Some(b'\n')), "Offset 0x00000010, byte 0x0A, 10, line feed");
+        assert_eq!(describe_byte(0x10, Some(0x80)), "Offset 0x00000010, byte 0x80, 128");
+        assert_eq!(describe_byte(0x20, None), "Offset 0x00000020, end of file");
+        assert_eq!(describe_line(0x10, b"Hi\n"), "Line at 0x00000010, 3 bytes: 48 69 0A.\nText: Hi.");
+    }
+
+    #[test]
+    fn describing_position() {
+        assert_eq!(describe_position(0, 20, 10), "   All");
+        assert_eq!(describe_position(0, 20, 100), "   Top");
+        assert_eq!(describe_position(80, 20, 100), "   End");
+        assert_eq!(describe_position(25, 20, 100), "   25.0%");
+    }
 }
@@ -0,0 +1,161 @@
+//! Finding the runs of printable text in any `ByteSource`, and keeping those around a moving
+//! window.
+
+use serde_derive::Serialize;
+
+use crate::byte_reader::ByteSource;
+use crate::error::Result;
+use crate::progress::Progress;
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The shortest string that is found, unless another length is asked for.
+pub const DEFAULT_MIN_LENGTH: usize = 4;
+
+/// A run of printable ASCII characters.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FoundString {
+    pub offset: u64,
+    pub text: String
+}
+
+/// Find the runs of at least `min_length` printable ASCII characters, or tabs, in the given range
+/// of the source, like the `strings` program does.
+pub fn find_strings(source: &mut ByteSource, offset: u64, length: u64, min_length: usize,
+                    progress: &Progress) -> Result<Vec<FoundString>> {
+    let mut found = Vec::new();
+    let mut current = FoundString { offset, text: String::new() };
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut done = 0;
+    while done < length {
+        let len = (length - done).min(CHUNK_SIZE as u64) as usize;
+        let read = source.read_at(offset + done, &mut buf[0..len])?;
+        if read == 0 {
+            break;
+        }
+        for (i, b) in buf[0..read].iter().enumerate() {
+            if is_string_byte(*b) {
+                current.text.push(char::from(*b));
+            } else {
+                if current.text.len() >= min_length.max(1) {
+                    found.push(current.clone());
+                }
+                current.offset = offset + done + i as u64 + 1;
+                current.text.clear();
+            }
+        }
+        done += read as u64;
+        progress.advance(read as u64)?;
+    }
+    if current.text.len() >= min_length.max(1) {
+        found.push(current);
+    }
+    Ok(found)
+}
+
+/// The strings in a region around a window of a source, which are only found again when the
+/// window moves near the edges of the region, so that following the window as it scrolls through
+/// a large source only reads a little of it at a time.
+#[derive(Debug, Clone)]
+pub struct WindowStrings {
+    min_length: usize,
+    /// The number of bytes before and after the window that are searched.
+    margin: u64,
+    /// The start and the end of the region that the strings were found in.
+    region: Option<(u64, u64)>,
+    strings: Vec<FoundString>
+}
+
+impl WindowStrings {
+    pub fn new(min_length: usize, margin: u64) -> WindowStrings {
+        WindowStrings { min_length, margin, region: None, strings: Vec::new() }
+    }
+
+    /// Find the strings around the window from `start` to `end` again, unless those that were
+    /// found before are of a region that still has half the margin around it. The bytes are read
+    /// with `read`, from an offset for a length. Returns whether they were found again.
+    pub fn update<F>(&mut self, start: u64, end: u64, length: u64, read: F) -> Result<bool>
+        where F: FnOnce(u64, usize) -> Result<Vec<u8>> {
+        let needed = (start.saturating_sub(self.margin / 2), (end + self.margin / 2).min(length));
+        if let Some((first, last)) = self.region {
+            if first <= needed.0 && needed.1 <= last {
+                return Ok(false);
+            }
+        }
+        let region = (start.saturating_sub(self.margin), (end + self.margin).min(length));
+        let bytes = read(region.0, (region.1 - region.0) as usize)?;
+        let len = bytes.len() as u64;
+        self.strings = find_strings(&mut bytes.as_slice(), 0, len, self.min_length, &Progress::new(len))?;
+        for string in self.strings.iter_mut() {
+            string.offset += region.0;
+        }
+        self.region = Some((region.0, region.0 + len));
+        Ok(true)
+    }
+
+    /// Forget the strings, so that they are found again, like when the bytes changed.
+    pub fn clear(&mut self) {
+        self.region = None;
+        self.strings.clear();
+    }
+
+    /// The strings that end after the offset, in the order they are in.
+    pub fn from(&self, offset: u64) -> &[FoundString] {
+        let first = self.strings.iter()
+            .position(|string| string.offset + string.text.len() as u64 > offset)
+            .unwrap_or(self.strings.len());
+        &self.strings[first..]
+    }
+}
+
+fn is_string_byte(b: u8) -> bool {
+    (0x20..0x7F).contains(&b) || b == b'\t'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finding_strings() {
+        let mut bytes: &[u8] = b"\x00\x01Hello, world!\xFFab\x00tab\there\n1234";
+        let length = bytes.len() as u64;
+        let found = find_strings(&mut bytes, 0, length, 4, &Progress::new(length)).unwrap();
+        assert_eq!(found, vec![
+            FoundString { offset: 2, text: String::from("Hello, world!") },
+            FoundString { offset: 19, text: String::from("tab\there") },
+            FoundString { offset: 28, text: String::from("1234") }
+        ]);
+        let found = find_strings(&mut bytes, 4, 10, 4, &Progress::new(10)).unwrap();
+        assert_eq!(found, vec![FoundString { offset: 4, text: String::from("llo, world") }]);
+    }
+
+    #[test]
+    fn finding_strings_around_a_window() {
+        let mut bytes = vec![0; 1000];
+        for &offset in [10, 300, 520, 850].iter() {
+            bytes[offset..offset + 6].copy_from_slice(b"string");
+        }
+        let length = bytes.len() as u64;
+        let mut reads = Vec::new();
+        let mut read = |offset: u64, len: usize| {
+            reads.push((offset, len));
+            Ok(bytes[offset as usize..offset as usize + len].to_vec())
+        };
+        let mut strings = WindowStrings::new(4, 200);
+        assert!(strings.update(400, 500, length, &mut read).unwrap());
+        assert_eq!(strings.from(0).iter().map(|string| string.offset).collect::<Vec<_>>(), vec![300, 520]);
+        assert_eq!(strings.from(303)[0].offset, 300);
+        assert_eq!(strings.from(306)[0].offset, 520);
+
+        // Moving the window a little keeps the strings that were found.
+        assert!(!strings.update(450, 550, length, &mut read).unwrap());
+        assert!(strings.update(600, 700, length, &mut read).unwrap());
+        assert_eq!(strings.from(0).iter().map(|string| string.offset).collect::<Vec<_>>(), vec![520, 850]);
+
+        strings.clear();
+        assert!(strings.from(0).is_empty());
+        assert!(strings.update(600, 700, length, &mut read).unwrap());
+        assert_eq!(reads, vec![(200, 500), (400, 500), (400, 500)]);
+    }
+}
@@ -0,0 +1,33 @@
+//! The panel next to the hex view, that lists the strings around the window.
+
+use cursive::Cursive;
+use cursive::theme::Effect;
+use cursive::traits::Boxable;
+use cursive::views::{BoxView, LinearLayout, PaddedView, TextContent, TextView};
+
+use crate::hex_view::HexView;
+
+/// The width of the strings panel, with an offset and the start of a string.
+const STRINGS_PANEL_WIDTH: usize = 34;
+
+/// A panel of the strings around the window, that follows it as it moves.
+pub fn new_strings_panel(content: TextContent) -> BoxView<PaddedView<LinearLayout>> {
+    PaddedView::new((1, 1, 0, 0), LinearLayout::vertical()
+        .child(TextView::new("Strings").effect(Effect::Bold))
+        .child(TextView::new_with_content(content).no_wrap()))
+        .fixed_width(STRINGS_PANEL_WIDTH)
+}
+
+/// Show the strings panel next to the hex view, or hide it.
+pub fn toggle_strings_panel(s: &mut Cursive) {
+    let (shown, content) = s.call_on_id("hex_view", |v: &mut HexView| {
+        let shown = !v.has_strings_panel();
+        v.set_strings_panel(shown);
+        (shown, v.get_strings_content())
+    }).unwrap();
+    s.call_on_id("main_row", |row: &mut LinearLayout| if shown {
+        row.add_child(new_strings_panel(content));
+    } else {
+        row.remove_child(1);
+    });
+}
@@ -7,8 +7,9 @@ use cursive::traits::{Boxable, Identifiable};
 use cursive::views::{Dialog, LinearLayout, OnEventView, ScrollView, SelectView};
 
 use crate::hex_view::HexView;
+use crate::utilities::{height_left, width_left};
 use crate::xv_state::XvState;
-use crate::xv_tui::ShowError;
+use crate::xv_tui::{watch_spool, ShowError};
 
 pub fn switch_file_dialog(s: &mut Cursive) {
     let mut file_selector: SelectView<OsString> = SelectView::new().autojump();
@@ -24,8 +25,8 @@ pub fn switch_file_dialog(s: &mut Cursive) {
     let layout = LinearLayout::vertical()
         .child(ScrollView::new(file_selector.with_id("file_selector"))
             .scroll_x(true))
-        .max_height((s.screen_size().y - 11).min(50))
-        .max_width((s.screen_size().x - 20).min(80));
+        .max_height(height_left(s, 11).min(50))
+        .max_width(width_left(s, 20).min(80));
     
     let file_switcher = Dialog::new()
         .title("Switch file")
@@ -60,14 +61,12 @@ fn do_switch_file(s: &mut Cursive) {
             result
         }) {
             match reader_result {
-                Ok(reader) => s.call_on_id("hex_view", |view: &mut HexView| {
-                    view.switch_reader(reader);
-                }),
-                Err(error) => {
-                    s.show_error(error);
-                    None
-                }
-            };
+                Ok(reader) => {
+                    s.call_on_id("hex_view", |view: &mut HexView| view.switch_reader(reader));
+                    watch_spool(s);
+                },
+                Err(error) => s.show_error(error)
+            }
         }
     }
 }
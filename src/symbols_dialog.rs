@@ -0,0 +1,147 @@
+//! The dialogs that import the symbols of a map file, and jump to a symbol by name.
+
+use std::fs;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, ScrollView, SelectView, TextView};
+use xv::error::XvError;
+
+use crate::hex_view::HexView;
+use crate::map_files::{find_symbols, parse_symbols};
+use crate::utilities::{expand_path, get_content, height_left};
+use crate::xv_state::XvState;
+use crate::xv_tui::ShowError;
+
+/// The most symbols that the jump to symbol dialog lists.
+const MAX_LISTED_SYMBOLS: usize = 200;
+
+pub fn open_symbols_dialog(s: &mut Cursive) {
+    let current_dir = s.with_user_data(|state: &mut XvState| {
+        state.current_directory().to_path_buf()
+    }).unwrap();
+    let symbol_count = s.call_on_id("hex_view", |v: &mut HexView| v.get_symbols().len()).unwrap();
+
+    let summary = match symbol_count {
+        0 => String::from("There are no symbols."),
+        1 => String::from("There is 1 symbol."),
+        n => format!("There are {} symbols.", n)
+    };
+
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Load from:  "))
+            .child(EditView::new()
+                .content(format!("{}", current_dir.display()))
+                .with_id("symbols_path")
+                .min_width(40)))
+        .child(TextView::new(
+            "\nA GNU ld or MSVC map file, or lines with an\n\
+             address and a name, like 0x08000188,main.\n"))
+        .child(TextView::new(summary));
+
+    let dialog = Dialog::around(layout)
+        .title("Symbols")
+        .dismiss_button("Close")
+        .button("Clear", do_clear_symbols)
+        .button("Load", do_load_symbols);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_load_symbols);
+
+    s.add_layer(event_view)
+}
+
+fn do_load_symbols(s: &mut Cursive) {
+    let path_str = s.call_on_id("symbols_path", get_content).unwrap();
+
+    s.pop_layer();
+
+    let symbols = fs::read_to_string(expand_path(&path_str))
+        .map_err(|error| error.into())
+        .and_then(|text| parse_symbols(&text));
+    match symbols {
+        Ok(symbols) => {
+            let count = symbols.len();
+            s.call_on_id("hex_view", |v: &mut HexView| v.set_symbols(symbols));
+            s.add_layer(Dialog::info(format!("Loaded {} symbols.", count)));
+        },
+        Err(error) => s.show_error(error)
+    }
+}
+
+fn do_clear_symbols(s: &mut Cursive) {
+    s.pop_layer();
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_symbols(Vec::new()));
+}
+
+pub fn open_jump_to_symbol_dialog(s: &mut Cursive) {
+    let symbol_count = s.call_on_id("hex_view", |v: &mut HexView| v.get_symbols().len()).unwrap();
+    if symbol_count == 0 {
+        return s.show_error(XvError::Input(String::from("There are no symbols. Press Y to load some.")));
+    }
+
+    let layout = LinearLayout::vertical()
+        .child(EditView::new()
+            .on_edit(|s, pattern, _| update_symbol_list(s, pattern))
+            .on_submit(|s, _| do_jump_to_symbol(s))
+            .with_id("symbol_pattern")
+            .min_width(40))
+        .child(ScrollView::new(SelectView::<u64>::new()
+            .on_submit(|s, _: &u64| do_jump_to_symbol(s))
+            .with_id("symbol_list"))
+            .max_height(height_left(s, 11).min(20)));
+
+    let dialog = Dialog::around(layout)
+        .title("Jump to symbol")
+        .dismiss_button("Cancel")
+        .button("Go", do_jump_to_symbol);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view);
+    update_symbol_list(s, "");
+}
+
+/// List the symbols that match the pattern, best first.
+fn update_symbol_list(s: &mut Cursive, pattern: &str) {
+    let items: Vec<(String, u64)> = s.call_on_id("hex_view", |v: &mut HexView| {
+        find_symbols(v.get_symbols(), pattern, MAX_LISTED_SYMBOLS).iter()
+            .map(|symbol| (format!("0x{:08X}  {}", symbol.address, symbol.name), symbol.address))
+            .collect()
+    }).unwrap();
+    s.call_on_id("symbol_list", |v: &mut SelectView<u64>| {
+        v.clear();
+        v.add_all(items);
+    });
+}
+
+fn do_jump_to_symbol(s: &mut Cursive) {
+    let address = s.call_on_id("symbol_list", |v: &mut SelectView<u64>| v.selection()).unwrap();
+
+    s.pop_layer();
+
+    let address = match address {
+        Some(address) => *address,
+        None => return
+    };
+    let result = s.call_on_id("hex_view", |v: &mut HexView| {
+        match v.get_address_map().offset_of(address) {
+            Some(offset) if offset < v.get_length() => {
+                v.go_to_offset(offset);
+                Ok(())
+            },
+            _ => Err(XvError::Input(format!("No byte of the file is at the address 0x{:X}.", address)))
+        }
+    }).unwrap();
+    if let Err(error) = result {
+        s.show_error(error);
+    }
+}
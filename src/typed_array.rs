@@ -0,0 +1,147 @@
+//! Decoding bytes as arrays of integers and floats, and describing them in the data inspector.
+
+use xv::inspector::{Inspector, InspectorRow};
+
+use crate::bitfield::read_integer;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ElementType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64
+}
+
+pub const ELEMENT_TYPES: [ElementType; 10] = [
+    ElementType::U8, ElementType::I8, ElementType::U16, ElementType::I16, ElementType::U32,
+    ElementType::I32, ElementType::U64, ElementType::I64, ElementType::F32, ElementType::F64];
+
+impl ElementType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ElementType::U8 => "u8",
+            ElementType::I8 => "i8",
+            ElementType::U16 => "u16",
+            ElementType::I16 => "i16",
+            ElementType::U32 => "u32",
+            ElementType::I32 => "i32",
+            ElementType::U64 => "u64",
+            ElementType::I64 => "i64",
+            ElementType::F32 => "f32",
+            ElementType::F64 => "f64"
+        }
+    }
+
+    /// The size of an element in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            ElementType::U8 | ElementType::I8 => 1,
+            ElementType::U16 | ElementType::I16 => 2,
+            ElementType::U32 | ElementType::I32 | ElementType::F32 => 4,
+            ElementType::U64 | ElementType::I64 | ElementType::F64 => 8
+        }
+    }
+
+    /// Decode one element from the start of `bytes`, which must hold at least `size()` bytes.
+    pub fn decode(self, bytes: &[u8], big_endian: bool) -> String {
+        let size = self.size();
+        let value = read_integer(&bytes[0..size], big_endian);
+        let bits = 8 * size as u32;
+        match self {
+            ElementType::U8 | ElementType::U16 | ElementType::U32 | ElementType::U64 =>
+                format!("{}", value),
+            ElementType::I8 | ElementType::I16 | ElementType::I32 | ElementType::I64 =>
+                format!("{}", ((value << (64 - bits)) as i64) >> (64 - bits)),
+            ElementType::F32 => format!("{}", f32::from_bits(value as u32)),
+            ElementType::F64 => format!("{}", f64::from_bits(value))
+        }
+    }
+}
+
+/// Decode the elements found every `stride` bytes in `bytes`, returning the position of each
+/// element relative to the start of `bytes`, and its decoded value. A trailing partial element
+/// is left out.
+pub fn decode_array(bytes: &[u8], element_type: ElementType, big_endian: bool, stride: usize)
+    -> Vec<(usize, String)> {
+    let size = element_type.size();
+    let stride = stride.max(1);
+    let mut elements = Vec::new();
+    let mut pos = 0;
+    while pos + size <= bytes.len() {
+        elements.push((pos, element_type.decode(&bytes[pos..], big_endian)));
+        pos += stride;
+    }
+    elements
+}
+
+/// Shows the bytes as each of the element types, for the data inspector.
+pub struct NumberInspector {
+    big_endian: bool
+}
+
+impl NumberInspector {
+    pub fn new(big_endian: bool) -> NumberInspector {
+        NumberInspector { big_endian }
+    }
+}
+
+impl Inspector for NumberInspector {
+    fn name(&self) -> &str {
+        if self.big_endian { "Big endian" } else { "Little endian" }
+    }
+
+    fn size(&self) -> usize {
+        8
+    }
+
+    fn inspect(&self, bytes: &[u8], rows: &mut Vec<InspectorRow>) {
+        for element_type in ELEMENT_TYPES.iter().filter(|t| t.size() <= bytes.len()) {
+            rows.push(InspectorRow::new(element_type.name(), element_type.decode(bytes, self.big_endian)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoding_elements() {
+        let bytes = [0xFE, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x80, 0x3F];
+        assert_eq!(ElementType::U8.decode(&bytes, false), "254");
+        assert_eq!(ElementType::I8.decode(&bytes, false), "-2");
+        assert_eq!(ElementType::U16.decode(&bytes, true), "65279");
+        assert_eq!(ElementType::I16.decode(&bytes, false), "-2");
+        assert_eq!(ElementType::I32.decode(&bytes, false), "-2");
+        assert_eq!(ElementType::U32.decode(&bytes, false), "4294967294");
+        assert_eq!(ElementType::F32.decode(&bytes[4..], false), "1");
+        assert_eq!(ElementType::F32.decode(&[0x3F, 0xC0, 0x00, 0x00], true), "1.5");
+        assert_eq!(ElementType::I64.decode(&[0xFF; 8], true), "-1");
+        assert_eq!(ElementType::F64.decode(&[0, 0, 0, 0, 0, 0, 0xF0, 0xBF], false), "-1");
+    }
+
+    #[test]
+    fn decoding_arrays_with_stride() {
+        let bytes = [1, 0, 9, 9, 2, 0, 9, 9, 3, 0, 9];
+        let elements = decode_array(&bytes, ElementType::U16, false, 4);
+        assert_eq!(elements, vec![
+            (0, String::from("1")), (4, String::from("2")), (8, String::from("3"))]);
+        let elements = decode_array(&bytes[0..5], ElementType::U16, false, 2);
+        assert_eq!(elements, vec![(0, String::from("1")), (2, String::from("2313"))]);
+    }
+
+    #[test]
+    fn inspecting_numbers() {
+        let mut rows = Vec::new();
+        NumberInspector::new(true).inspect(&[0x01, 0x02], &mut rows);
+        assert_eq!(rows, vec![
+            InspectorRow::new("u8", "1"), InspectorRow::new("i8", "1"),
+            InspectorRow::new("u16", "258"), InspectorRow::new("i16", "258")]);
+    }
+}
@@ -0,0 +1,122 @@
+//! The dialog that shows the bytes from an offset as an array of numbers of a chosen type.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Checkbox, Dialog, EditView, LinearLayout, OnEventView, ScrollView, SelectView, TextView};
+use xv::error::XvError;
+
+use crate::hex_view::HexView;
+use crate::typed_array::{decode_array, ElementType, ELEMENT_TYPES};
+use crate::utilities::{get_content, height_left, parse_number, parse_offset};
+use crate::xv_tui::ShowError;
+
+const MAX_ELEMENTS: u64 = 10_000;
+
+pub fn open_typed_array_dialog(s: &mut Cursive) {
+    let offset = s.call_on_id("hex_view", |v: &mut HexView| v.get_offset()).unwrap();
+
+    let mut type_selector: SelectView<ElementType> = SelectView::new().popup();
+    for element_type in ELEMENT_TYPES.iter() {
+        type_selector.add_item(element_type.name(), *element_type);
+    }
+
+    let editors = LinearLayout::vertical()
+        .child(type_selector.with_id("array_type"))
+        .child(Checkbox::new().with_id("array_big_endian"))
+        .child(EditView::new()
+            .content(format!("0x{:X}", offset))
+            .with_id("array_offset")
+            .min_width(18))
+        .child(EditView::new()
+            .with_id("array_stride")
+            .min_width(18))
+        .child(EditView::new()
+            .content("256")
+            .with_id("array_count")
+            .min_width(18));
+
+    let layout = LinearLayout::horizontal()
+        .child(TextView::new("Element type:  \nBig endian:  \nOffset:  \nStride:  \nCount:  "))
+        .child(editors);
+
+    let dialog = Dialog::around(layout)
+        .title("View as array")
+        .dismiss_button("Cancel")
+        .button("Show", do_show_array);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_show_array);
+
+    s.add_layer(event_view)
+}
+
+fn do_show_array(s: &mut Cursive) {
+    let element_type = s.call_on_id("array_type", |v: &mut SelectView<ElementType>| v.selection())
+        .unwrap()
+        .map_or(ElementType::U8, |t| *t);
+    let big_endian = s.call_on_id("array_big_endian", |c: &mut Checkbox| c.is_checked()).unwrap();
+    let offset_str = s.call_on_id("array_offset", get_content).unwrap();
+    let stride_str = s.call_on_id("array_stride", get_content).unwrap();
+    let count_str = s.call_on_id("array_count", get_content).unwrap();
+
+    s.pop_layer();
+
+    let length = s.call_on_id("hex_view", |v: &mut HexView| v.get_length()).unwrap();
+    let offset = match parse_offset(&offset_str, length) {
+        Ok(offset) => offset,
+        Err(error) => return s.show_error(error)
+    };
+    // An empty stride packs the elements back to back.
+    let stride = match parse_number(&stride_str) {
+        Ok(stride) if stride > 0 => stride as usize,
+        _ => element_type.size()
+    };
+    let count = match parse_number(&count_str) {
+        Ok(count) if count > 0 => count.min(MAX_ELEMENTS),
+        Ok(_) => return s.show_error(XvError::Input(String::from("The count must be more than zero."))),
+        Err(error) => return s.show_error(error)
+    };
+
+    let length = (count as usize - 1) * stride + element_type.size();
+    let result = s.call_on_id("hex_view", |v: &mut HexView| v.read_bytes(offset, length)).unwrap();
+    let bytes = match result {
+        Ok(bytes) => bytes,
+        Err(error) => return s.show_error(error)
+    };
+
+    let mut element_selector: SelectView<u64> = SelectView::new().on_submit(go_to_element);
+    for (index, (pos, value)) in decode_array(&bytes, element_type, big_endian, stride).into_iter().enumerate() {
+        let element_offset = offset + pos as u64;
+        element_selector.add_item(
+            format!("{:>6}  0x{:016X}  {}", index, element_offset, value), element_offset);
+    }
+
+    let title = format!("{} x {} at 0x{:X}", element_selector.len(), element_type.name(), offset);
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(format!("{:>6}  {:<18}  Value", "Index", "Offset")))
+        .child(ScrollView::new(element_selector))
+        .max_height(height_left(s, 10).min(50));
+
+    let dialog = Dialog::around(layout)
+        .title(title)
+        .dismiss_button("Close");
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+
+    s.add_layer(event_view)
+}
+
+fn go_to_element(s: &mut Cursive, offset: &u64) {
+    let target = *offset;
+    s.pop_layer();
+    s.call_on_id("hex_view", |view: &mut HexView| {
+        view.go_to_offset(target);
+    });
+}
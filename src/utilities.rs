@@ -1,8 +1,13 @@
 use std::num::ParseIntError;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::rc::Rc;
 
+use cursive::Cursive;
 use cursive::views::EditView;
-use directories::ProjectDirs;
+use directories::{BaseDirs, ProjectDirs};
+use xv::address_map::AddressMap;
+use xv::error::{Result as XvResult, XvError};
 
 pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 pub const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -26,6 +31,115 @@ pub fn parse_number_or_zero(number_str: &str) -> u64 {
     }
 }
 
+/// Parse an offset into a file of the given length. A negative offset, like `-0x200`, counts back
+/// from the end of the file, and a percentage, like `50%`, is that part of the way into the file.
+pub fn parse_offset(offset_str: &str, length: u64) -> XvResult<u64> {
+    let offset_str = offset_str.trim();
+    if let Some(percentage) = offset_str.strip_suffix('%') {
+        let percentage = percentage.trim().parse::<f64>()
+            .map_err(|_| XvError::Parse(format!("Not a percentage: {}.", offset_str)))?;
+        Ok((length as f64 * percentage.clamp(0.0, 100.0) / 100.0) as u64)
+    } else if let Some(from_end) = offset_str.strip_prefix('-') {
+        let from_end = parse_number(from_end.trim())?;
+        length.checked_sub(from_end).ok_or_else(|| XvError::Input(
+            format!("The offset {} is before the start of the file.", offset_str)))
+    } else {
+        Ok(parse_number(offset_str)?)
+    }
+}
+
+/// Parse an address that the address map maps a byte of the file to, and return the offset of that
+/// byte. Percentages and negative offsets are within the file, as for `parse_offset`.
+pub fn parse_address(address_str: &str, length: u64, address_map: &AddressMap) -> XvResult<u64> {
+    let address_str = address_str.trim();
+    if address_str.ends_with('%') || address_str.starts_with('-') {
+        return parse_offset(address_str, length);
+    }
+    let address = parse_number(address_str)?;
+    address_map.offset_of(address).ok_or_else(|| XvError::Input(
+        format!("No byte of the file is at the address 0x{:X}.", address)))
+}
+
+pub fn parse_offset_or_zero(offset_str: &str, length: u64) -> u64 {
+    parse_offset(offset_str, length).unwrap_or(0)
+}
+
+/// Parse bytes written in hex, like "DE AD be ef" or "deadbeef". Spaces between the bytes are
+/// optional.
+pub fn parse_hex_bytes(hex_str: &str) -> XvResult<Vec<u8>> {
+    let digits: Vec<char> = hex_str.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(XvError::Parse(String::from("Every byte must be two hex digits.")));
+    }
+    digits.chunks(2)
+        .map(|pair| {
+            let byte: String = pair.iter().collect();
+            u8::from_str_radix(&byte, 16)
+                .map_err(|_| XvError::Parse(format!("Not a hex byte: {}.", byte)))
+        })
+        .collect()
+}
+
+/// Turn a path that was typed in into a `PathBuf`. A leading `~` stands for the home directory.
+pub fn expand_path(path_str: &str) -> PathBuf {
+    let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    expand_home(path_str.trim(), home)
+}
+
+fn expand_home(path_str: &str, home: Option<PathBuf>) -> PathBuf {
+    match home {
+        Some(mut home) if path_str == "~" || path_str.starts_with("~/") => {
+            let rest = path_str[1..].trim_start_matches('/');
+            if !rest.is_empty() {
+                home.push(rest);
+            }
+            home
+        },
+        _ => PathBuf::from(path_str)
+    }
+}
+
+/// Describe a number of bytes in the largest binary unit that it is at least one of, like `1.5 MiB`.
+pub fn describe_size(bytes: f64) -> String {
+    let units = ["bytes", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", size, units[unit])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// Describe a point in time as a date and time in UTC, like `2019-04-18 02:45:55 UTC`.
+pub fn describe_time(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_secs() as i64,
+        Err(before) => {
+            let before = before.duration();
+            -(before.as_secs() as i64) - if before.subsec_nanos() > 0 { 1 } else { 0 }
+        }
+    };
+    let (days, time_of_day) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    // Convert the days since the epoch to a civil date, the way Howard Hinnant's days_from_civil
+    // algorithm does it in reverse.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day,
+            time_of_day / 3600, time_of_day % 3600 / 60, time_of_day % 60)
+}
+
 pub fn project_dirs() -> Option<ProjectDirs> {
     ProjectDirs::from("io.github.chrisvest", "", "xv")
 }
@@ -33,3 +147,77 @@ pub fn project_dirs() -> Option<ProjectDirs> {
 pub fn get_content(ev: &mut EditView) -> Rc<String> {
     ev.get_content()
 }
+
+/// The FNV-1a hash of the bytes. Unlike the hash of the standard library, it is the same from one
+/// Rust release to the next.
+pub fn fnv_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ u64::from(b)).wrapping_mul(0x100_0000_01b3))
+}
+
+/// The height of the screen that is left when the given number of lines are taken. Terminals can
+/// be shorter than that, so this is zero rather than negative.
+pub fn height_left(s: &Cursive, taken: usize) -> usize {
+    s.screen_size().y.saturating_sub(taken)
+}
+
+/// The width of the screen that is left when the given number of columns are taken.
+pub fn width_left(s: &Cursive, taken: usize) -> usize {
+    s.screen_size().x.saturating_sub(taken)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn parsing_offsets() {
+        assert_eq!(parse_offset("0x10", 1000).unwrap(), 16);
+        assert_eq!(parse_offset(" 010 ", 1000).unwrap(), 8);
+        assert_eq!(parse_offset("-0x200", 0x1000).unwrap(), 0xE00);
+        assert_eq!(parse_offset("-1", 1000).unwrap(), 999);
+        assert_eq!(parse_offset("50%", 1000).unwrap(), 500);
+        assert_eq!(parse_offset("150 %", 1000).unwrap(), 1000);
+        assert!(parse_offset("-2000", 1000).is_err());
+        assert!(parse_offset("x%", 1000).is_err());
+        assert_eq!(parse_offset_or_zero("nope", 1000), 0);
+    }
+
+    #[test]
+    fn parsing_addresses() {
+        let map = AddressMap::new(0x0800_0000, Vec::new());
+        assert_eq!(parse_address("0x08000010", 1000, &map).unwrap(), 16);
+        assert_eq!(parse_address("50%", 1000, &map).unwrap(), 500);
+        assert_eq!(parse_address("-1", 1000, &map).unwrap(), 999);
+        assert!(parse_address("0x10", 1000, &map).is_err());
+    }
+
+    #[test]
+    fn parsing_hex_bytes() {
+        assert_eq!(parse_hex_bytes("DE AD be ef").unwrap(), vec![0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(parse_hex_bytes("0a0B").unwrap(), vec![0x0A, 0x0B]);
+        assert!(parse_hex_bytes("abc").is_err());
+        assert!(parse_hex_bytes("zz").is_err());
+    }
+
+    #[test]
+    fn describing_sizes_and_times() {
+        assert_eq!(describe_size(1023.0), "1023 bytes");
+        assert_eq!(describe_size(5.0 * 1024.0 * 1024.0 * 1024.0), "5.0 GiB");
+        assert_eq!(describe_time(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+        assert_eq!(describe_time(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000-02-29 00:00:00 UTC");
+        assert_eq!(describe_time(UNIX_EPOCH + Duration::from_secs(1_555_555_555)), "2019-04-18 02:45:55 UTC");
+        assert_eq!(describe_time(UNIX_EPOCH - Duration::from_secs(1)), "1969-12-31 23:59:59 UTC");
+    }
+
+    #[test]
+    fn expanding_home() {
+        let home = Some(PathBuf::from("/home/xv"));
+        assert_eq!(expand_home("~", home.clone()), PathBuf::from("/home/xv"));
+        assert_eq!(expand_home("~/data/dump.bin", home.clone()), PathBuf::from("/home/xv/data/dump.bin"));
+        assert_eq!(expand_home("~other/file", home.clone()), PathBuf::from("~other/file"));
+        assert_eq!(expand_home("/tmp/~", home), PathBuf::from("/tmp/~"));
+        assert_eq!(expand_home("~/file", None), PathBuf::from("~/file"));
+    }
+}
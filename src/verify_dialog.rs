@@ -0,0 +1,126 @@
+//! The dialog that checks the file, or the selection, against an expected checksum.
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
+use xv::checksum::{compute_checksums, to_hex, ChecksumKind, CHECKSUM_KINDS};
+use xv::error::Result;
+use xv::progress::Progress;
+
+use crate::hex_view::HexView;
+use crate::progress_dialog::run_with_progress;
+use crate::utilities::{get_content, parse_hex_bytes, parse_number, parse_offset};
+use crate::xv_tui::ShowError;
+
+pub fn open_verify_dialog(s: &mut Cursive) {
+    let (selection, length) = s.call_on_id("hex_view", |v: &mut HexView| {
+        (v.get_selection(), v.get_length())
+    }).unwrap();
+    // Verify the selected bytes, if there are any, and otherwise the whole file.
+    let (start, end) = selection.unwrap_or((0, length));
+
+    let editors = LinearLayout::vertical()
+        .child(EditView::new()
+            .content(format!("0x{:X}", start))
+            .with_id("verify_offset")
+            .min_width(18))
+        .child(EditView::new()
+            .content(format!("{}", end - start))
+            .with_id("verify_length")
+            .min_width(18))
+        .child(EditView::new()
+            .with_id("verify_expected")
+            .min_width(66));
+
+    let layout = LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Offset:  \nLength:  \nExpected:  "))
+            .child(editors))
+        .child(TextView::new(
+            "\nPaste a CRC-32, MD5 or SHA-256 checksum, or leave it empty to compute all three."));
+
+    let dialog = Dialog::around(layout)
+        .title("Verify checksum")
+        .dismiss_button("Cancel")
+        .button("Verify", do_verify);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, do_verify);
+
+    s.add_layer(event_view)
+}
+
+fn do_verify(s: &mut Cursive) {
+    let offset_str = s.call_on_id("verify_offset", get_content).unwrap();
+    let length_str = s.call_on_id("verify_length", get_content).unwrap();
+    let expected_str = s.call_on_id("verify_expected", get_content).unwrap();
+
+    s.pop_layer();
+
+    let file_length = s.call_on_id("hex_view", |v: &mut HexView| v.get_length()).unwrap();
+    let offset = match parse_offset(&offset_str, file_length) {
+        Ok(offset) => offset,
+        Err(error) => return s.show_error(error)
+    };
+    let length = match parse_number(&length_str) {
+        Ok(length) => length.min(file_length.saturating_sub(offset)),
+        Err(error) => return s.show_error(error)
+    };
+    let expected = match parse_expected(&expected_str) {
+        Ok(expected) => expected,
+        Err(error) => return s.show_error(error)
+    };
+    let source = s.call_on_id("hex_view", |v: &mut HexView| {
+        v.open_background_source(offset, length)
+    }).unwrap();
+    let (mut source, source_offset) = match source {
+        Ok(source) => source,
+        Err(error) => return s.show_error(error)
+    };
+
+    let kinds = match expected {
+        Some((kind, _)) => vec![kind],
+        None => CHECKSUM_KINDS.to_vec()
+    };
+    let work_kinds = kinds.clone();
+    let checksum = move |progress: &Progress| {
+        compute_checksums(&work_kinds, source.as_mut(), source_offset, length, progress)
+    };
+    run_with_progress(s, "Computing checksums", length, checksum, move |s, checksums| {
+        show_result(s, &kinds, &checksums, expected, offset, length)
+    });
+}
+
+/// The kind and bytes of the expected checksum, or `None` if no checksum was given.
+fn parse_expected(expected: &str) -> Result<Option<(ChecksumKind, Vec<u8>)>> {
+    let expected = expected.trim();
+    let expected = expected.strip_prefix("0x").unwrap_or(expected);
+    if expected.is_empty() {
+        return Ok(None);
+    }
+    let bytes = parse_hex_bytes(expected)?;
+    Ok(Some((ChecksumKind::from_size(bytes.len())?, bytes)))
+}
+
+fn show_result(s: &mut Cursive, kinds: &[ChecksumKind], checksums: &[Vec<u8>],
+               expected: Option<(ChecksumKind, Vec<u8>)>, offset: u64, length: u64) {
+    let range = format!("{} bytes from offset 0x{:X}.\n\n", length, offset);
+    let (title, text) = match expected {
+        Some((kind, expected)) => {
+            let title = if expected == checksums[0] { "Checksum matches" } else { "Checksum does NOT match" };
+            (title, format!("{}Expected {}:  {}\nComputed {}:  {}", range,
+                            kind.name(), to_hex(&expected), kind.name(), to_hex(&checksums[0])))
+        },
+        None => {
+            let lines: Vec<String> = kinds.iter().zip(checksums)
+                .map(|(kind, checksum)| format!("{:<8} {}", format!("{}:", kind.name()), to_hex(checksum)))
+                .collect();
+            ("Checksums", format!("{}{}", range, lines.join("\n")))
+        }
+    };
+    s.add_layer(Dialog::info(text).title(title));
+}
@@ -0,0 +1,87 @@
+//! The dialogs that save the open files to a workspace, and open them again.
+
+use std::path::PathBuf;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Boxable, Identifiable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
+
+use crate::hex_view::HexView;
+use crate::utilities::{expand_path, get_content};
+use crate::xv_state::XvState;
+use crate::xv_tui::{watch_spool, ShowError};
+
+const WORKSPACE_FILE: &str = "xv-workspace.json";
+
+pub fn open_save_workspace_dialog(s: &mut Cursive) {
+    open_workspace_path_dialog(s, "Save workspace", "Save", do_save_workspace);
+}
+
+pub fn open_load_workspace_dialog(s: &mut Cursive) {
+    open_workspace_path_dialog(s, "Open workspace", "Open", do_load_workspace);
+}
+
+fn open_workspace_path_dialog(s: &mut Cursive, title: &str, button: &str, action: fn(&mut Cursive)) {
+    let default_path = s.with_user_data(|state: &mut XvState| {
+        state.resolve_path(WORKSPACE_FILE.as_ref())
+    }).unwrap();
+
+    let layout = LinearLayout::horizontal()
+        .child(TextView::new("Workspace file:  "))
+        .child(EditView::new()
+            .content(format!("{}", default_path.display()))
+            .with_id("workspace_path")
+            .min_width(40));
+
+    let dialog = Dialog::around(layout)
+        .title(title)
+        .dismiss_button("Cancel")
+        .button(button, action);
+
+    let event_view = OnEventView::new(dialog)
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        })
+        .on_event(Key::Enter, action);
+
+    s.add_layer(event_view)
+}
+
+fn get_workspace_path(s: &mut Cursive) -> PathBuf {
+    let path_str = s.call_on_id("workspace_path", get_content).unwrap();
+    s.pop_layer();
+    expand_path(&path_str)
+}
+
+fn do_save_workspace(s: &mut Cursive) {
+    let path = get_workspace_path(s);
+    let current_file = s.call_on_id("hex_view", |view: &mut HexView| {
+        view.get_reader_state()
+    }).unwrap();
+    let result = s.with_user_data(|state: &mut XvState| {
+        state.save_workspace(current_file, &path)
+    }).unwrap();
+    if let Err(error) = result {
+        s.show_error(error);
+    }
+}
+
+fn do_load_workspace(s: &mut Cursive) {
+    let path = get_workspace_path(s);
+    let current_file = s.call_on_id("hex_view", |view: &mut HexView| {
+        view.get_reader_state()
+    }).unwrap();
+    let result = s.with_user_data(|state: &mut XvState| {
+        state.open_workspace(current_file, &path)
+    }).unwrap();
+    match result {
+        Ok(reader) => {
+            s.call_on_id("hex_view", |view: &mut HexView| {
+                view.switch_reader(reader);
+            });
+            watch_spool(s);
+        },
+        Err(error) => s.show_error(error)
+    }
+}
@@ -2,29 +2,63 @@ use std::env;
 use std::ffi::OsStr;
 use std::fs;
 use std::fs::{create_dir_all, File, OpenOptions};
-use std::io::Result;
 use std::path::{Path, PathBuf};
 
-use cursive::theme::{Palette, Theme};
+use cursive::theme::{Color, Palette, Theme};
 use cursive::theme::BaseColor::*;
 use cursive::theme::Color::*;
 use directories::BaseDirs;
 use rmp_serde::Serializer;
 use serde::ser::Serialize;
 use serde_derive::{Deserialize, Serialize};
+use xv::address_map::{AddressMap, Segment};
+use xv::byte_reader::{TileConfig, TilingByteReader};
+use xv::error::{Result, XvError};
+use xv::hex_reader::{HexMode, HexReader, SearchOptions, TextRenderings, VisualMode};
+use xv::inspector::Inspectors;
+use xv::memory::DEFAULT_BUDGET;
 
-use crate::byte_reader::TilingByteReader;
-use crate::hex_reader::{HexReader, VisualMode};
+use crate::group_separators::GroupSeparators;
+use crate::hex_view::{Panes, DEFAULT_MAX_FPS};
+use crate::path_serde;
 use crate::utilities;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// How a file was being viewed, so it can be viewed the same way when it is opened again.
+///
+/// The state is stored as a msgpack array, so new fields must be added at the end, with a default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReaderState {
+    #[serde(with = "path_serde")]
     path: PathBuf,
     line_width: u64,
     group: u16,
     window_pos: (u64,u64),
     window_size: (u16,u16),
-    vis_mode: String
+    vis_mode: VisualMode,
+    #[serde(default)]
+    line_skip: u64,
+    #[serde(default)]
+    line_shown: Option<u64>,
+    #[serde(default)]
+    record_mode: bool,
+    #[serde(default)]
+    field_guides: Vec<u64>,
+    #[serde(default)]
+    hex_mode: HexMode,
+    #[serde(default)]
+    offset_digits: Option<u8>,
+    #[serde(default)]
+    base_address: u64,
+    #[serde(default)]
+    segments: Vec<Segment>,
+    #[serde(default)]
+    tile_config: Option<TileConfig>,
+    #[serde(default)]
+    text_rendering: TextRenderings,
+    /// Whether the bytes are in memory, like the output of a filter command, rather than in a
+    /// file. This is not stored.
+    #[serde(skip)]
+    in_memory: bool
 }
 
 impl ReaderState {
@@ -35,10 +69,40 @@ impl ReaderState {
             group: reader.group,
             window_pos: reader.window_pos,
             window_size: reader.window_size,
-            vis_mode: reader.vis_mode.into(),
+            vis_mode: reader.vis_mode,
+            line_skip: reader.line_skip,
+            line_shown: reader.line_shown,
+            record_mode: reader.record_mode,
+            field_guides: reader.field_guides.clone(),
+            hex_mode: reader.hex_mode,
+            offset_digits: reader.offset_digits,
+            base_address: reader.address_map.base_address,
+            segments: reader.address_map.segments().to_vec(),
+            tile_config: reader.get_chosen_tile_config(),
+            text_rendering: reader.text_rendering,
+            in_memory: !reader.is_file()
         }
     }
     
+    /// Set up the reader to view its file the way this state describes.
+    pub fn apply_to(&self, reader: &mut HexReader) {
+        reader.line_width = self.line_width;
+        reader.line_skip = self.line_skip;
+        reader.line_shown = self.line_shown;
+        reader.record_mode = self.record_mode;
+        reader.field_guides = self.field_guides.clone();
+        reader.group = self.group;
+        reader.window_pos = self.window_pos;
+        reader.window_size = self.window_size;
+        reader.vis_mode = self.vis_mode;
+        reader.text_rendering = self.text_rendering;
+        reader.hex_mode = self.hex_mode;
+        reader.offset_digits = self.offset_digits;
+        reader.address_map = AddressMap::new(self.base_address, self.segments.clone());
+        reader.set_tile_config(self.tile_config);
+        reader.clamp_window();
+    }
+    
     pub fn path(&self) -> &Path {
         self.path.as_path()
     }
@@ -50,33 +114,118 @@ impl PartialEq for ReaderState {
     }
 }
 
-impl From<VisualMode> for String {
-    fn from(mode: VisualMode) -> Self {
-        match mode {
-            VisualMode::Unicode => String::from("Unicode"),
-            VisualMode::Ascii => String::from("Ascii"),
-            VisualMode::Off => String::from("Off"),
-        }
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitfieldLayout {
+    pub name: String,
+    pub size: u64,
+    pub big_endian: bool,
+    pub fields: String
+}
+
+/// The settings of the search dialog, that are remembered between searches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchSettings {
+    /// Search for bytes written in hex, instead of for text.
+    pub hex: bool,
+    pub options: SearchOptions
+}
+
+/// A search that was done, for the history of the search dialog.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub pattern: String,
+    pub hex: bool
+}
+
+/// A script that runs when a function key is pressed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptBinding {
+    pub key: String,
+    #[serde(with = "path_serde")]
+    pub path: PathBuf
+}
+
+/// How the bytes and the dialogs are told apart, for terminals and eyes that cannot tell all
+/// colors apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum DisplayProfile {
+    #[default]
+    Color,
+    /// Blue, cyan, magenta and yellow, instead of the reds and greens that are hard to tell apart
+    /// with the most common color blindness.
+    Colorblind,
+    /// No colors, only bold, underlined and reversed text.
+    Monochrome
 }
 
-impl From<String> for VisualMode {
-    fn from(mode: String) -> Self {
-        match mode.as_str() {
-            "Unicode" => VisualMode::Unicode,
-            "Ascii" => VisualMode::Ascii,
-            "Off" => VisualMode::Off,
-            _ => VisualMode::Unicode
+pub const DISPLAY_PROFILES: [DisplayProfile; 3] =
+    [DisplayProfile::Color, DisplayProfile::Colorblind, DisplayProfile::Monochrome];
+
+impl DisplayProfile {
+    pub fn name(self) -> &'static str {
+        match self {
+            DisplayProfile::Color => "color",
+            DisplayProfile::Colorblind => "colorblind",
+            DisplayProfile::Monochrome => "monochrome"
         }
     }
 }
 
+const MAX_SEARCH_HISTORY: usize = 50;
+
+/// The files of an investigation, with the file that was being viewed first, saved as JSON so
+/// the work can be picked up again later.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Workspace {
+    files: Vec<ReaderState>
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct XvState {
     theme: bool,
+    #[serde(with = "path_serde")]
     current_dir: PathBuf,
     max_recent_files: usize,
     recent_files: Vec<ReaderState>,
+    #[serde(default)]
+    bitfield_layouts: Vec<BitfieldLayout>,
+    #[serde(default)]
+    search_settings: SearchSettings,
+    /// The most recent searches, the newest first.
+    #[serde(default)]
+    search_history: Vec<SearchQuery>,
+    /// Directories that are pinned to the open file dialog.
+    #[serde(default, with = "path_serde::vec")]
+    favorite_dirs: Vec<PathBuf>,
+    #[serde(default)]
+    display_profile: DisplayProfile,
+    /// Whether the position is described in words in the status bar, for screen readers.
+    #[serde(default)]
+    screen_reader: bool,
+    /// Whether every other row is shaded.
+    #[serde(default)]
+    stripes: bool,
+    /// The number of bytes between the guide lines in the hex column, or 0 for no guide lines.
+    #[serde(default)]
+    column_guides: u64,
+    #[serde(default)]
+    group_separators: GroupSeparators,
+    /// The memory budget in bytes, or `None` for the default budget.
+    #[serde(default)]
+    memory_budget: Option<u64>,
+    /// The most frames per second, 0 for no limit, or `None` for the default.
+    #[serde(default)]
+    max_fps: Option<u64>,
+    #[serde(default)]
+    panes: Panes,
+    /// The most pages that are shown side by side, or `None` for one.
+    #[serde(default)]
+    pages: Option<u16>,
+    #[serde(default)]
+    script_bindings: Vec<ScriptBinding>,
+    /// The inspectors are registered when the program starts, and are not stored.
+    #[serde(skip)]
+    inspectors: Inspectors
 }
 
 impl XvState {
@@ -106,7 +255,22 @@ impl XvState {
             theme: true,
             current_dir,
             max_recent_files: 50,
-            recent_files: Vec::new()
+            recent_files: Vec::new(),
+            bitfield_layouts: Vec::new(),
+            search_settings: SearchSettings::default(),
+            search_history: Vec::new(),
+            favorite_dirs: Vec::new(),
+            display_profile: DisplayProfile::Color,
+            screen_reader: false,
+            stripes: false,
+            column_guides: 0,
+            group_separators: GroupSeparators::default(),
+            memory_budget: None,
+            max_fps: None,
+            panes: Panes::default(),
+            pages: None,
+            script_bindings: Vec::new(),
+            inspectors: Inspectors::new()
         }
     }
     
@@ -131,11 +295,7 @@ impl XvState {
             Ok(mut reader) => {
                 let lookup_state = ReaderState::new(&reader);
                 if let Some(index) = self.index_of(&lookup_state) {
-                    let state = &self.recent_files[index];
-                    reader.line_width = state.line_width;
-                    reader.group = state.group;
-                    reader.window_pos = state.window_pos;
-                    reader.window_size = state.window_size;
+                    self.recent_files[index].apply_to(&mut reader);
                     self.recent_files.remove(index);
                 };
                 Ok(reader)
@@ -144,7 +304,12 @@ impl XvState {
         }
     }
     
+    /// Remember how a file was being viewed. Readers of in-memory data, like the output of a
+    /// filter command, have no file to remember and are left out.
     pub fn close_reader(&mut self, reader: ReaderState) {
+        if reader.in_memory {
+            return;
+        }
         if let Some(index) = self.index_of(&reader) {
             self.recent_files.remove(index);
             self.recent_files.insert(0, reader);
@@ -156,6 +321,37 @@ impl XvState {
         }
     }
     
+    /// Save the current file and the recent files to a workspace file.
+    pub fn save_workspace(&mut self, current: ReaderState, path: &Path) -> Result<()> {
+        let mut files: Vec<ReaderState> = self.recent_files().iter()
+            .filter(|file| **file != current)
+            .cloned()
+            .collect();
+        files.insert(0, current);
+        let workspace = Workspace { files };
+        let json = serde_json::to_string_pretty(&workspace)
+            .map_err(|e| XvError::Input(format!("Could not save the workspace: {}.", e)))?;
+        Ok(fs::write(path, json)?)
+    }
+    
+    /// Open the first file of a workspace file, and put the other files at the top of the recent
+    /// files, in the order they were saved.
+    pub fn open_workspace(&mut self, current: ReaderState, path: &Path) -> Result<HexReader> {
+        let json = fs::read_to_string(path)?;
+        let workspace: Workspace = serde_json::from_str(&json)
+            .map_err(|e| XvError::Parse(format!("Not a workspace file: {}.", e)))?;
+        let first = match workspace.files.first() {
+            Some(file) => file.path.clone(),
+            None => return Err(XvError::Input(String::from("The workspace has no files.")))
+        };
+        
+        self.close_reader(current);
+        for file in workspace.files.into_iter().rev() {
+            self.close_reader(file);
+        }
+        self.open_reader(first)
+    }
+    
     fn index_of(&self, reader: &ReaderState) -> Option<usize> {
         for i in 0..self.recent_files.len() {
             if self.recent_files[i].eq(reader) {
@@ -198,7 +394,7 @@ impl XvState {
     }
     
     pub fn list_directory(&mut self) -> Result<fs::ReadDir> {
-        fs::read_dir(&self.current_dir)
+        Ok(fs::read_dir(&self.current_dir)?)
     }
     
     pub fn reset_current_directory(&mut self) -> Result<()> {
@@ -216,12 +412,162 @@ impl XvState {
         cloned_buf
     }
     
+    pub fn bitfield_layouts(&self) -> &[BitfieldLayout] {
+        &self.bitfield_layouts
+    }
+    
+    /// Save a bitfield layout, replacing any existing layout with the same name.
+    pub fn save_bitfield_layout(&mut self, layout: BitfieldLayout) {
+        self.bitfield_layouts.retain(|l| l.name != layout.name);
+        self.bitfield_layouts.push(layout);
+        self.bitfield_layouts.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    
+    pub fn search_settings(&self) -> &SearchSettings {
+        &self.search_settings
+    }
+    
+    pub fn set_search_settings(&mut self, settings: SearchSettings) {
+        self.search_settings = settings;
+    }
+    
+    pub fn search_history(&self) -> &[SearchQuery] {
+        &self.search_history
+    }
+    
+    /// Put a search at the top of the history, removing any earlier copy of it.
+    pub fn add_search_to_history(&mut self, query: SearchQuery) {
+        self.search_history.retain(|q| *q != query);
+        self.search_history.insert(0, query);
+        self.search_history.truncate(MAX_SEARCH_HISTORY);
+    }
+    
+    pub fn favorite_directories(&self) -> &[PathBuf] {
+        &self.favorite_dirs
+    }
+    
+    /// Pin the current directory as a favorite, or unpin it if it already is one. Returns whether
+    /// it is a favorite now.
+    pub fn toggle_favorite_directory(&mut self) -> bool {
+        if let Some(index) = self.favorite_dirs.iter().position(|dir| *dir == self.current_dir) {
+            self.favorite_dirs.remove(index);
+            false
+        } else {
+            self.favorite_dirs.push(self.current_dir.clone());
+            self.favorite_dirs.sort();
+            true
+        }
+    }
+    
+    pub fn remove_favorite_directory(&mut self, index: usize) {
+        self.favorite_dirs.remove(index);
+    }
+    
+    pub fn inspectors(&self) -> &Inspectors {
+        &self.inspectors
+    }
+    
+    pub fn inspectors_mut(&mut self) -> &mut Inspectors {
+        &mut self.inspectors
+    }
+    
     pub fn toggle_theme(&mut self) {
         self.theme = !self.theme;
     }
     
+    pub fn display_profile(&self) -> DisplayProfile {
+        self.display_profile
+    }
+    
+    pub fn set_display_profile(&mut self, profile: DisplayProfile) {
+        self.display_profile = profile;
+    }
+    
+    pub fn screen_reader(&self) -> bool {
+        self.screen_reader
+    }
+    
+    pub fn set_screen_reader(&mut self, screen_reader: bool) {
+        self.screen_reader = screen_reader;
+    }
+    
+    pub fn set_stripes(&mut self, stripes: bool) {
+        self.stripes = stripes;
+    }
+    
+    /// The background of every other row, which is a little lighter or darker than the background
+    /// of the theme. Without colors, the rows are not shaded.
+    pub fn stripe_color(&self) -> Option<Color> {
+        match self.display_profile {
+            _ if !self.stripes => None,
+            DisplayProfile::Monochrome => None,
+            _ if self.theme => Some(Light(White)),
+            _ => Some(Light(Black))
+        }
+    }
+    
+    pub fn column_guides(&self) -> u64 {
+        self.column_guides
+    }
+    
+    pub fn set_column_guides(&mut self, every: u64) {
+        self.column_guides = every;
+    }
+    
+    pub fn memory_budget(&self) -> u64 {
+        self.memory_budget.unwrap_or(DEFAULT_BUDGET)
+    }
+    
+    pub fn set_memory_budget(&mut self, budget: u64) {
+        self.memory_budget = Some(budget);
+    }
+    
+    pub fn max_fps(&self) -> u64 {
+        self.max_fps.unwrap_or(DEFAULT_MAX_FPS)
+    }
+    
+    pub fn set_max_fps(&mut self, fps: u64) {
+        self.max_fps = Some(fps);
+    }
+    
+    pub fn panes(&self) -> Panes {
+        self.panes
+    }
+    
+    pub fn set_panes(&mut self, panes: Panes) {
+        self.panes = panes;
+    }
+    
+    pub fn pages(&self) -> u16 {
+        self.pages.unwrap_or(1)
+    }
+    
+    pub fn set_pages(&mut self, pages: u16) {
+        self.pages = Some(pages);
+    }
+    
+    pub fn script_binding(&self, key: &str) -> Option<&Path> {
+        self.script_bindings.iter().find(|binding| binding.key == key).map(|binding| binding.path.as_path())
+    }
+    
+    /// Bind the script to the key, or remove the binding of the key when there is no script.
+    pub fn bind_script(&mut self, key: &str, path: Option<PathBuf>) {
+        self.script_bindings.retain(|binding| binding.key != key);
+        if let Some(path) = path {
+            self.script_bindings.push(ScriptBinding { key: key.to_string(), path });
+        }
+    }
+    
+    pub fn group_separators(&self) -> &GroupSeparators {
+        &self.group_separators
+    }
+    
+    pub fn group_separators_mut(&mut self) -> &mut GroupSeparators {
+        &mut self.group_separators
+    }
+    
     pub fn current_theme(&self) -> Theme {
-        if self.theme {
+        let mut theme = if self.theme {
             Theme::default()
         } else {
             let mut palette = Palette::default();
@@ -236,6 +582,32 @@ impl XvState {
             palette.set_color("highlight", Dark(Red));
             palette.set_color("highlight_inactive", Dark(Blue));
             Theme { shadow: false, palette, ..Theme::default() }
+        };
+        let palette = &mut theme.palette;
+        match self.display_profile {
+            DisplayProfile::Color => {},
+            DisplayProfile::Colorblind if self.theme => {
+                palette.set_color("secondary", Dark(Cyan));
+                palette.set_color("title_primary", Dark(Blue));
+                palette.set_color("title_secondary", Dark(Magenta));
+                palette.set_color("highlight", Dark(Blue));
+            },
+            DisplayProfile::Colorblind => {
+                palette.set_color("secondary", Light(Cyan));
+                palette.set_color("title_primary", Light(Blue));
+                palette.set_color("title_secondary", Light(Yellow));
+                palette.set_color("highlight", Dark(Blue));
+            },
+            DisplayProfile::Monochrome => {
+                theme.shadow = false;
+                for name in &["background", "view", "primary", "secondary", "tertiary", "title_primary",
+                              "title_secondary"] {
+                    palette.set_color(name, TerminalDefault);
+                }
+                palette.set_color("highlight", Dark(White));
+                palette.set_color("highlight_inactive", Dark(White));
+            }
         }
+        theme
     }
 }
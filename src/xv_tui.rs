@@ -1,54 +1,203 @@
-use std::io::Error;
 use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 
 use cursive::Cursive;
 use cursive::event::Key;
 use cursive::traits::{Boxable, Identifiable};
 use cursive::views::{Dialog, LinearLayout, TextView};
+use xv::error::XvError;
+use xv::hex_reader::HexReader;
+use xv::memory;
 
+use crate::address_map_dialog::open_address_map_dialog;
+use crate::annotations_dialog::open_annotations_dialog;
+use crate::bitfield_dialog::open_bitfield_dialog;
+use crate::calculator_dialog::open_calculator_dialog;
+use crate::command_line::{open_command_line, run_commands};
+use crate::compare_dialog::open_compare_dialog;
+use crate::disassembler_dialog::open_disassembler_dialog;
+use crate::duplicates_dialog::open_duplicates_dialog;
+use crate::export_dialog::open_export_dialog;
+use crate::file_info_dialog::open_file_info_dialog;
+use crate::filter_dialog::open_filter_dialog;
 use crate::goto_dialog::open_goto_dialog;
+use crate::group_separators::GroupSeparators;
 use crate::help_text::show_help;
-use crate::hex_reader::HexReader;
-use crate::hex_view::HexView;
+use crate::hex_view::{HexView, Panes, ScreenMode};
+use crate::inspector_dialog::open_inspector_dialog;
 use crate::open_file_dialog::open_file_dialog;
+use crate::overview_view::open_overview;
 use crate::panic_hook::archive_last_crash;
+use crate::points_dialog::open_points_dialog;
+use crate::record_dialog::open_record_dialog;
+use crate::scripts::{run_bound_script, SCRIPT_KEYS};
+use crate::search_dialog::open_search_dialog;
 use crate::set_width_dialog::open_set_width_dialog;
-use crate::status_bar::new_status_bar;
+use crate::stats_dialog::open_stats_dialog;
+use crate::status_bar::{new_status_bar, update_measure};
 use crate::switch_file_dialog::switch_file_dialog;
+use crate::symbols_dialog::{open_jump_to_symbol_dialog, open_symbols_dialog};
+use crate::typed_array::NumberInspector;
+use crate::typed_array_dialog::open_typed_array_dialog;
 use crate::utilities::PKG_REPOSITORY;
-use crate::xv_state::XvState;
+use crate::verify_dialog::open_verify_dialog;
+use crate::workspace_dialog::{open_load_workspace_dialog, open_save_workspace_dialog};
+use crate::xv_state::{DisplayProfile, XvState};
 
-pub fn run_tui(reader: HexReader, state: XvState) {
+/// How often a growing source is looked at for the bytes that have arrived.
+const LIVE_REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Run the user interface. The reader can read from a spool, that grows while it is viewed.
+pub fn run_tui(reader: HexReader, mut state: XvState, commands: Vec<String>) {
+    register_inspectors(&mut state);
+    memory::set_budget(state.memory_budget());
     let mut tui = Cursive::default();
     tui.set_theme(state.current_theme());
+    let display_profile = state.display_profile();
+    let screen_reader = state.screen_reader();
+    let (stripe_color, column_guides) = (state.stripe_color(), state.column_guides());
+    let group_separators = state.group_separators().clone();
+    let (max_fps, panes, pages) = (state.max_fps(), state.panes(), state.pages());
     tui.set_user_data(state);
     
     tui.add_global_callback('q', quit);
     tui.add_global_callback(Key::Esc, quit);
     tui.add_global_callback('?', show_help);
     tui.add_global_callback(Key::F1, show_help);
+    tui.add_global_callback(Key::F11, toggle_full_screen);
     tui.add_global_callback('w', open_set_width_dialog);
     tui.add_global_callback('g', open_goto_dialog);
     tui.add_global_callback('t', change_theme);
     tui.add_global_callback('o', open_file_dialog);
     tui.add_global_callback('s', switch_file_dialog);
+    tui.add_global_callback('d', open_duplicates_dialog);
+    tui.add_global_callback('i', open_stats_dialog);
+    tui.add_global_callback('b', open_overview);
+    tui.add_global_callback('f', open_bitfield_dialog);
+    tui.add_global_callback('a', open_typed_array_dialog);
+    tui.add_global_callback('m', open_record_dialog);
+    tui.add_global_callback('S', open_save_workspace_dialog);
+    tui.add_global_callback('O', open_load_workspace_dialog);
+    tui.add_global_callback('I', open_inspector_dialog);
+    tui.add_global_callback('|', open_filter_dialog);
+    tui.add_global_callback('c', open_calculator_dialog);
+    tui.add_global_callback('/', open_search_dialog);
+    tui.add_global_callback('C', open_compare_dialog);
+    tui.add_global_callback('V', open_verify_dialog);
+    tui.add_global_callback('F', open_file_info_dialog);
+    tui.add_global_callback(':', open_command_line);
+    tui.add_global_callback('X', open_export_dialog);
+    tui.add_global_callback('A', open_annotations_dialog);
+    tui.add_global_callback('M', open_address_map_dialog);
+    tui.add_global_callback('Y', open_symbols_dialog);
+    tui.add_global_callback('y', open_jump_to_symbol_dialog);
+    tui.add_global_callback('D', open_disassembler_dialog);
+    tui.add_global_callback('P', open_points_dialog);
+    for &(name, key) in SCRIPT_KEYS.iter() {
+        tui.add_global_callback(key, move |s| run_bound_script(s, name));
+    }
 
-    let hex_view = HexView::new(reader).with_id("hex_view");
-    let status_bar = new_status_bar();
+    let mut hex_view = HexView::new(reader);
+    hex_view.set_display_profile(display_profile);
+    hex_view.set_screen_reader(screen_reader);
+    hex_view.set_stripe_color(stripe_color);
+    hex_view.set_column_guides(column_guides);
+    hex_view.set_group_separators(group_separators);
+    hex_view.set_max_fps(max_fps);
+    hex_view.set_panes(panes);
+    hex_view.set_pages(pages);
+    let status_bar = new_status_bar(hex_view.get_position_content());
+    let hex_view = hex_view.with_id("hex_view");
 
     tui.screen_mut().add_transparent_layer(LinearLayout::vertical()
-        .child(hex_view)
+        .child(LinearLayout::horizontal().child(hex_view).with_id("main_row"))
+        .child(LinearLayout::horizontal().with_id("split_row"))
         .child(status_bar)
+        .with_id("main_column")
         .full_screen());
     
+    watch_spool(&mut tui);
+    wake_up_for_frames(&mut tui);
+    
     if let Some(archived_crash_log) = archive_last_crash() {
         show_crash_dialog(&mut tui, archived_crash_log);
     }
 
+    // The commands are run once the hex view has been laid out, so they know how big it is.
+    if !commands.is_empty() {
+        tui.cb_sink().send(Box::new(move |s: &mut Cursive| {
+            for command in &commands {
+                if let Err(error) = run_commands(s, command) {
+                    s.show_error(error);
+                    break;
+                }
+            }
+        })).unwrap();
+    }
+
     tui.run();
 }
 
-fn quit(s: &mut Cursive) {
+/// Wake up the event loop when the hex view asks for it, so that a move of the window that was
+/// put off until the next frame is captured then, even when no other event comes.
+fn wake_up_for_frames(s: &mut Cursive) {
+    let (wakeup, delays) = mpsc::channel::<Duration>();
+    let cb_sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        // This ends when the hex view lets go of the sender.
+        for delay in delays {
+            thread::sleep(delay);
+            // Any callback makes the view be laid out again.
+            if cb_sink.send(Box::new(|_: &mut Cursive| {})).is_err() {
+                break;
+            }
+        }
+    });
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_frame_wakeup(wakeup));
+}
+
+/// Show the bytes that arrive in the spool that is viewed, if one is, while they arrive, until the
+/// stream ends, as long as it is viewed. Once it is no longer viewed, it is stopped.
+pub fn watch_spool(s: &mut Cursive) {
+    let (spool, path) = match s.call_on_id("hex_view", |v: &mut HexView| (v.get_spool(), v.get_path())) {
+        Some((Some(spool), path)) => (spool, path),
+        _ => return
+    };
+    let cb_sink = s.cb_sink().clone();
+    thread::spawn(move || loop {
+        thread::sleep(LIVE_REFRESH_INTERVAL);
+        let finished = spool.is_finished();
+        let notice = match spool.error() {
+            Some(error) => format!("The stream failed: {}", error),
+            None => String::from("The stream ended")
+        };
+        let path = path.clone();
+        let viewed_spool = spool.clone();
+        let sent = cb_sink.send(Box::new(move |s: &mut Cursive| {
+            s.call_on_id("hex_view", |v: &mut HexView| if v.get_path() == path {
+                v.refresh();
+                if finished {
+                    v.set_notice(notice.clone());
+                }
+            } else {
+                viewed_spool.stop();
+            });
+        }));
+        if finished || sent.is_err() {
+            break;
+        }
+    });
+}
+
+fn register_inspectors(state: &mut XvState) {
+    let inspectors = state.inspectors_mut();
+    inspectors.register(NumberInspector::new(false));
+    inspectors.register(NumberInspector::new(true));
+}
+
+pub fn quit(s: &mut Cursive) {
     let reader_state = s.call_on_id("hex_view", |view: &mut HexView| {
         view.get_reader_state()
     }).unwrap();
@@ -59,7 +208,7 @@ fn quit(s: &mut Cursive) {
     s.quit()
 }
 
-fn change_theme(s: &mut Cursive) {
+pub fn change_theme(s: &mut Cursive) {
     let new_theme = s.with_user_data(|state: &mut XvState| {
         state.toggle_theme();
         state.current_theme()
@@ -67,6 +216,109 @@ fn change_theme(s: &mut Cursive) {
     if let Some(t) = new_theme {
         s.set_theme(t);
     }
+    update_stripes(s);
+}
+
+/// Use the display profile for the dialogs and the bytes, and remember it across restarts.
+pub fn set_display_profile(s: &mut Cursive, profile: DisplayProfile) {
+    let new_theme = s.with_user_data(|state: &mut XvState| {
+        state.set_display_profile(profile);
+        state.current_theme()
+    });
+    if let Some(t) = new_theme {
+        s.set_theme(t);
+    }
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_display_profile(profile));
+    update_stripes(s);
+}
+
+/// Shade every other row, or stop shading them, and remember it across restarts.
+pub fn set_stripes(s: &mut Cursive, stripes: bool) {
+    s.with_user_data(|state: &mut XvState| state.set_stripes(stripes));
+    update_stripes(s);
+}
+
+/// Shade the rows in the color of the theme.
+fn update_stripes(s: &mut Cursive) {
+    let color = s.with_user_data(|state: &mut XvState| state.stripe_color()).unwrap();
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_stripe_color(color));
+}
+
+/// Draw guide lines in the hex column every `every` bytes, and remember it across restarts.
+pub fn set_column_guides(s: &mut Cursive, every: u64) {
+    s.with_user_data(|state: &mut XvState| state.set_column_guides(every));
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_column_guides(every));
+}
+
+/// Share a memory budget of this many bytes, and remember it across restarts.
+pub fn set_memory_budget(s: &mut Cursive, budget: u64) {
+    s.with_user_data(|state: &mut XvState| state.set_memory_budget(budget));
+    memory::set_budget(budget);
+}
+
+/// Show and hide the columns of the hex view, and remember it across restarts.
+pub fn set_panes(s: &mut Cursive, panes: Panes) {
+    s.with_user_data(|state: &mut XvState| state.set_panes(panes));
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_panes(panes));
+}
+
+/// Show the bytes with or without the border, the title and the status bar around them.
+pub fn set_screen_mode(s: &mut Cursive, mode: ScreenMode) {
+    let (had_status_bar, position) = s.call_on_id("hex_view", |v: &mut HexView| {
+        let had_status_bar = v.get_screen_mode() == ScreenMode::Normal;
+        v.set_screen_mode(mode);
+        (had_status_bar, v.get_position_content())
+    }).unwrap();
+    let has_status_bar = mode == ScreenMode::Normal;
+    if has_status_bar == had_status_bar {
+        return;
+    }
+    s.call_on_id("main_column", |column: &mut LinearLayout| if has_status_bar {
+        column.add_child(new_status_bar(position));
+    } else {
+        column.remove_child(1);
+    });
+    update_measure(s);
+}
+
+/// Show only the bytes, in all of the screen, or everything around them again.
+pub fn toggle_full_screen(s: &mut Cursive) {
+    let mode = s.call_on_id("hex_view", |v: &mut HexView| v.get_screen_mode()).unwrap();
+    set_screen_mode(s, if mode == ScreenMode::Normal { ScreenMode::FullScreen } else { ScreenMode::Normal });
+}
+
+/// Show only the bytes, with the groups set further apart, or everything around them again.
+pub fn toggle_presentation(s: &mut Cursive) {
+    let mode = s.call_on_id("hex_view", |v: &mut HexView| v.get_screen_mode()).unwrap();
+    set_screen_mode(s, if mode == ScreenMode::Presentation { ScreenMode::Normal } else { ScreenMode::Presentation });
+}
+
+/// Show up to this many pages of consecutive rows side by side, and remember it across restarts.
+pub fn set_pages(s: &mut Cursive, pages: u16) {
+    s.with_user_data(|state: &mut XvState| state.set_pages(pages));
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_pages(pages));
+}
+
+/// Capture the window at most this many times a second, or as often as it moves with 0, and
+/// remember it across restarts.
+pub fn set_max_fps(s: &mut Cursive, fps: u64) {
+    s.with_user_data(|state: &mut XvState| state.set_max_fps(fps));
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_max_fps(fps));
+}
+
+/// Describe the position in words in the status bar, and remember it across restarts.
+pub fn set_screen_reader(s: &mut Cursive, screen_reader: bool) {
+    s.with_user_data(|state: &mut XvState| state.set_screen_reader(screen_reader));
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_screen_reader(screen_reader));
+}
+
+/// Change what the group boundaries are shown as, and remember it across restarts.
+pub fn change_group_separators<F: FnOnce(&mut GroupSeparators)>(s: &mut Cursive, change: F) {
+    let separators = s.with_user_data(|state: &mut XvState| {
+        change(state.group_separators_mut());
+        state.group_separators().clone()
+    }).unwrap();
+    s.call_on_id("hex_view", |v: &mut HexView| v.set_group_separators(separators));
 }
 
 fn show_crash_dialog(s: &mut Cursive, archived_crash_log: PathBuf) {
@@ -76,12 +328,12 @@ fn show_crash_dialog(s: &mut Cursive, archived_crash_log: PathBuf) {
 }
 
 pub trait ShowError {
-    fn show_error(&mut self, error: Error);
+    fn show_error<E: Into<XvError>>(&mut self, error: E);
 }
 
 impl ShowError for Cursive {
-    fn show_error(&mut self, error: Error) {
+    fn show_error<E: Into<XvError>>(&mut self, error: E) {
         self.add_layer(Dialog::info("Error").content(
-            TextView::new(format!("{}", error))));
+            TextView::new(format!("{}", error.into()))));
     }
 }